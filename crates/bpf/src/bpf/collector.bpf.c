@@ -39,6 +39,12 @@ struct {
 // Subscriber ID assigned by userspace for the collector handler
 const volatile __u64 collector_sync_timer_id;
 
+// CPU selection set by userspace: when cpu_filter_enabled is set, only CPUs whose
+// bit is set in enabled_cpus (bit i%64 of word i/64) are measured
+#define MAX_FILTER_CPUS 4096
+const volatile __u8 cpu_filter_enabled;
+const volatile __u64 enabled_cpus[MAX_FILTER_CPUS / 64];
+
 // Declare the perf event arrays for hardware counters
 struct {
     __uint(type, BPF_MAP_TYPE_PERF_EVENT_ARRAY);
@@ -94,6 +100,16 @@ static const __u64 TASK_METADATA_INIT = 0;  // 0 = not reported yet
 // Value to store in the exited_leaders map
 static const __u8 LEADER_PRESENT = 1;
 
+// Whether events on the given CPU should be measured
+static __always_inline int cpu_enabled(__u32 cpu)
+{
+    if (!cpu_filter_enabled)
+        return 1;
+    if (cpu >= MAX_FILTER_CPUS)
+        return 0;
+    return (enabled_cpus[cpu / 64] >> (cpu % 64)) & 1;
+}
+
 // Helper function to check if a task is a kernel thread
 static __always_inline int is_kernel_thread(struct task_struct *task)
 {
@@ -123,6 +139,9 @@ static __always_inline int send_task_metadata(void *ctx, struct task_struct *tas
     // Get cgroup ID for the current task
     msg.cgroup_id = bpf_get_current_cgroup_id();
 
+    // Nice value from the static priority (DEFAULT_PRIO = 120 is nice 0)
+    msg.nice = task->static_prio - 120;
+
     msg.header.timestamp = bpf_ktime_get_ns();
     
     // Skip the size field (first 4 bytes) when sending
@@ -153,7 +172,7 @@ static __always_inline __u64 compute_delta(__u64 current, __u64 previous) {
 }
 
 // Send perf measurement event to userspace
-static __always_inline int send_perf_measurement(void *ctx, __u32 pid, __u64 cycles_delta, 
+static __always_inline int send_perf_measurement(void *ctx, __u32 pid, __u32 tid, __u64 cycles_delta, 
                                                __u64 instructions_delta, __u64 llc_misses_delta,
                                                __u64 cache_references_delta, __u64 time_delta_ns, __u64 timestamp,
                                                __u32 is_context_switch, __u32 next_tgid)
@@ -164,6 +183,7 @@ static __always_inline int send_perf_measurement(void *ctx, __u32 pid, __u64 cyc
     msg.header.type = MSG_TYPE_PERF_MEASUREMENT;
     // size field is filled by the kernel
     msg.pid = pid;
+    msg.tid = tid;
     msg.cycles_delta = cycles_delta;
     msg.instructions_delta = instructions_delta;
     msg.llc_misses_delta = llc_misses_delta;
@@ -236,6 +256,7 @@ static __always_inline int collect_and_send_perf_measurements(void *ctx, struct
         return 0;
     
     __u32 pid = task->tgid;
+    __u32 tid = task->pid;
     
     // Get previous counters
     __u32 zero = 0;
@@ -287,7 +308,7 @@ static __always_inline int collect_and_send_perf_measurements(void *ctx, struct
 
         if (prev->timestamp != 0) {
             time_delta_ns = compute_delta(now, prev->timestamp);
-            send_perf_measurement(ctx, pid, cycles_delta, instructions_delta, 
+            send_perf_measurement(ctx, pid, tid, cycles_delta, instructions_delta, 
                                 llc_misses_delta, cache_references_delta, time_delta_ns, now,
                                 is_context_switch, next_tgid);
         }
@@ -302,6 +323,9 @@ int handle_sched_switch(u64 *ctx)
 {
     struct task_struct *prev = (struct task_struct *)ctx[1];
     struct task_struct *next = (struct task_struct *)ctx[2];
+
+    if (!cpu_enabled(bpf_get_smp_processor_id()))
+        return 0;
     
     // Get current task (simpler approach as requested)
     struct task_struct *current_task = bpf_get_current_task_btf();
@@ -396,10 +420,13 @@ int handle_hrtimer_expire_exit(void *ctx)
         return 0;
     }
 
-    struct task_struct *current_task = bpf_get_current_task_btf();
+    // Unselected CPUs still report the tick so timeslots keep advancing
+    if (cpu_enabled(cpu)) {
+        struct task_struct *current_task = bpf_get_current_task_btf();
 
-    check_and_send_metadata(ctx, current_task);
-    collect_and_send_perf_measurements(ctx, current_task, 0, 0);
+        check_and_send_metadata(ctx, current_task);
+        collect_and_send_perf_measurements(ctx, current_task, 0, 0);
+    }
     send_timer_finished_processing(ctx);
 
     return 0;
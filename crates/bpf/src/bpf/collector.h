@@ -26,6 +26,7 @@ struct task_metadata_msg {
     __u32 pid;                   // Process ID
     __u8 comm[TASK_COMM_LEN];    // Process command name
     __u64 cgroup_id;             // Cgroup ID (inode number in cgroup filesystem)
+    __s32 nice;                  // Nice value (-20..19) when the task was first seen
 };
 
 // Structure for task free messages
@@ -43,7 +44,8 @@ struct timer_finished_processing_msg {
 // Structure for performance measurement messages
 struct perf_measurement_msg {
     struct sample_header header; // Common header
-    __u32 pid;                   // Process ID
+    __u32 pid;                   // Process ID (thread group ID)
+    __u32 tid;                   // Thread ID, equal to pid for the main thread
     __u64 cycles_delta;          // CPU cycles delta
     __u64 instructions_delta;    // Instructions delta
     __u64 llc_misses_delta;      // LLC misses delta
@@ -51,12 +51,40 @@ pub struct BpfLoader {
     skel: bpf::CollectorSkel<'static>,
     dispatcher: Dispatcher,
     perf_map_reader: PerfMapReader,
+    sync_interval_ns: u64,
     _perf_timing_grace_ns: u64,
 }
 
 impl BpfLoader {
-    /// Create a new BPF loader with initialized skeleton
+    /// Create a new BPF loader with initialized skeleton, opening all hardware counters
     pub fn new(perf_ring_pages: u32, sync_timer: &mut SyncTimer) -> Result<Self> {
+        Self::with_counters(
+            perf_ring_pages,
+            sync_timer,
+            &[
+                HardwareCounter::Cycles,
+                HardwareCounter::Instructions,
+                HardwareCounter::LLCMisses,
+                HardwareCounter::CacheReferences,
+            ],
+            None,
+        )
+    }
+
+    /// Create a new BPF loader that opens only the given hardware counters.
+    ///
+    /// Counters that are not opened read as zero deltas in the BPF program, so
+    /// hardware without them can still be monitored.
+    ///
+    /// With `cpus`, only those CPUs are measured: counters are opened on them
+    /// alone and the BPF programs skip events on other CPUs, whose timers still
+    /// report ticks so timeslots keep advancing. `None` measures every CPU.
+    pub fn with_counters(
+        perf_ring_pages: u32,
+        sync_timer: &mut SyncTimer,
+        counters: &[HardwareCounter],
+        cpus: Option<&[usize]>,
+    ) -> Result<Self> {
         fn print_to_log(level: PrintLevel, msg: String) {
             match level {
                 PrintLevel::Debug => log::debug!("{}", msg),
@@ -68,43 +96,35 @@ impl BpfLoader {
         set_print(Some((PrintLevel::Debug, print_to_log)));
 
         // Load BPF program (non-verbose, use the log crate to print errors)
-        let mut skel = match Self::load_skel(false, sync_timer) {
+        let mut skel = match Self::load_skel(false, sync_timer, cpus) {
             Ok(skel) => skel,
             Err(e) => {
                 log::error!("Failed to load BPF program: {}", e);
                 log::error!("Reloading with debug flag, for more information");
 
                 // Reload with debug flag (verbose, to always print the error to stderr)
-                let _ = Self::load_skel(true, sync_timer);
+                let _ = Self::load_skel(true, sync_timer, cpus);
                 return Err(e);
             }
         };
 
-        // Initialize perf event rings for the hardware counters
-        if let Err(e) =
-            perf_events::open_perf_counter(&mut skel.maps.cycles, HardwareCounter::Cycles)
-        {
-            return Err(anyhow!("Failed to open cycles counter: {:?}", e));
-        }
-
-        if let Err(e) = perf_events::open_perf_counter(
-            &mut skel.maps.instructions,
-            HardwareCounter::Instructions,
-        ) {
-            return Err(anyhow!("Failed to open instructions counter: {:?}", e));
-        }
-
-        if let Err(e) =
-            perf_events::open_perf_counter(&mut skel.maps.llc_misses, HardwareCounter::LLCMisses)
-        {
-            return Err(anyhow!("Failed to open LLC misses counter: {:?}", e));
-        }
-
-        if let Err(e) = perf_events::open_perf_counter(
-            &mut skel.maps.cache_references,
-            HardwareCounter::CacheReferences,
-        ) {
-            return Err(anyhow!("Failed to open cache references counter: {:?}", e));
+        // Initialize perf event rings for the selected hardware counters
+        for &counter in counters {
+            let (map, name) = match counter {
+                HardwareCounter::Cycles => (&mut skel.maps.cycles, "cycles"),
+                HardwareCounter::Instructions => (&mut skel.maps.instructions, "instructions"),
+                HardwareCounter::LLCMisses => (&mut skel.maps.llc_misses, "LLC misses"),
+                HardwareCounter::CacheReferences => {
+                    (&mut skel.maps.cache_references, "cache references")
+                }
+            };
+            let opened = match cpus {
+                Some(cpus) => perf_events::open_perf_counter_on_cpus(map, counter, cpus),
+                None => perf_events::open_perf_counter(map, counter),
+            };
+            if let Err(e) = opened {
+                return Err(anyhow!("Failed to open {} counter: {:?}", name, e));
+            }
         }
 
         // Set up the perf map reader for the events map
@@ -120,11 +140,16 @@ impl BpfLoader {
             skel,
             dispatcher,
             perf_map_reader,
+            sync_interval_ns: sync_timer.interval_ns(),
             _perf_timing_grace_ns: 100_000, // 100 microseconds grace period for timing
         })
     }
 
-    fn load_skel(verbose: bool, sync_timer: &mut SyncTimer) -> Result<bpf::CollectorSkel<'static>> {
+    fn load_skel(
+        verbose: bool,
+        sync_timer: &mut SyncTimer,
+        cpus: Option<&[usize]>,
+    ) -> Result<bpf::CollectorSkel<'static>> {
         let mut skel_builder = bpf::CollectorSkelBuilder::default();
         if verbose {
             skel_builder.obj_builder.debug(true);
@@ -153,6 +178,23 @@ impl BpfLoader {
             .map_err(|e| anyhow!("failed to assign sync timer subscriber id: {}", e))?;
         open_skel.maps.rodata_data.collector_sync_timer_id = subscriber_id as u64;
 
+        // Restrict measurements to the selected CPUs
+        if let Some(cpus) = cpus {
+            let rodata = &mut open_skel.maps.rodata_data;
+            let max_cpus = rodata.enabled_cpus.len() * 64;
+            for &cpu in cpus {
+                if cpu >= max_cpus {
+                    return Err(anyhow!(
+                        "CPU {} cannot be selected (at most {} CPUs are supported)",
+                        cpu,
+                        max_cpus
+                    ));
+                }
+                rodata.enabled_cpus[cpu / 64] |= 1 << (cpu % 64);
+            }
+            rodata.cpu_filter_enabled = 1;
+        }
+
         let skel = open_skel
             .load()
             .with_context(|| "Failed to load BPF program")?;
@@ -165,6 +207,11 @@ impl BpfLoader {
         &self.dispatcher
     }
 
+    /// Period of the sync timer driving the collector's timer events, in nanoseconds
+    pub fn sync_interval_ns(&self) -> u64 {
+        self.sync_interval_ns
+    }
+
     /// Get a mutable reference to the perf events dispatcher
     pub fn dispatcher_mut(&mut self) -> &mut Dispatcher {
         &mut self.dispatcher
@@ -202,6 +249,15 @@ impl BpfLoader {
         Ok(())
     }
 
+    /// Samples the kernel dropped because a perf ring was full, per ring (one
+    /// ring per CPU), as reported by the lost records read so far. Growing
+    /// counts mean userspace is not keeping up with the BPF program.
+    pub fn lost_samples(&self) -> Vec<u64> {
+        let mut lost = self.dispatcher.lost_samples().to_vec();
+        lost.resize(self.perf_map_reader.reader().num_rings(), 0);
+        lost
+    }
+
     /// Get a reference to the BPF skeleton
     pub fn skel(&self) -> &bpf::CollectorSkel<'static> {
         &self.skel
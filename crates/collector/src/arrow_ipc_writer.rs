@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use arrow_array::RecordBatch;
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::SchemaRef;
+use async_trait::async_trait;
+use log::{debug, info};
+use object_store::ObjectStore;
+
+use crate::error::{CollectorError, Result};
+use crate::parquet_writer_task::BatchWriter;
+
+/// Sink name selecting standard output
+pub const STDOUT_SINK: &str = "-";
+
+/// Writes record batches as a single Arrow IPC stream to a file or stdout.
+///
+/// Implements the same `BatchWriter` interface as `ParquetWriter`, so the writer
+/// task drives either.
+/// A stream has no files, so rotation only flushes the sink, and a failed
+/// write cannot be recovered by starting over.
+pub struct ArrowIpcWriter {
+    writer: Option<StreamWriter<Box<dyn Write + Send>>>,
+    sink_name: String,
+    rows_written: u64,
+    shutdown_drops: u64,
+}
+
+impl ArrowIpcWriter {
+    /// Create a writer streaming to `sink`: a file path, or `-` for stdout
+    pub fn open(sink: &str, schema: SchemaRef) -> Result<Self> {
+        let output: Box<dyn Write + Send> = if sink == STDOUT_SINK {
+            Box::new(BufWriter::new(std::io::stdout()))
+        } else {
+            let file = File::create(sink).map_err(|e| {
+                CollectorError::Storage(anyhow!(
+                    "failed to create Arrow IPC sink '{}': {}",
+                    sink,
+                    e
+                ))
+            })?;
+            Box::new(BufWriter::new(file))
+        };
+        Self::new(output, sink, schema)
+    }
+
+    /// Create a writer streaming to `output`, named `sink_name` in logs
+    pub fn new(output: Box<dyn Write + Send>, sink_name: &str, schema: SchemaRef) -> Result<Self> {
+        let writer = StreamWriter::try_new(output, &schema)?;
+        debug!("Created Arrow IPC stream writer for sink: {}", sink_name);
+        Ok(Self {
+            writer: Some(writer),
+            sink_name: sink_name.to_string(),
+            rows_written: 0,
+            shutdown_drops: 0,
+        })
+    }
+
+    fn stream(&mut self) -> Result<&mut StreamWriter<Box<dyn Write + Send>>> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| CollectorError::Storage(anyhow!("Arrow IPC stream already closed")))
+    }
+}
+
+#[async_trait]
+impl BatchWriter for ArrowIpcWriter {
+    /// Append a record batch to the stream, flushing it to the sink so consumers
+    /// see it right away
+    async fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        let stream = self.stream()?;
+        stream.write(&batch)?;
+        stream.flush()?;
+        self.rows_written += batch.num_rows() as u64;
+        Ok(())
+    }
+
+    /// A stream has no files to rotate; flush the sink instead
+    async fn rotate(&mut self) -> Result<()> {
+        debug!("Rotation requested on Arrow IPC stream, flushing");
+        self.stream()?.flush()?;
+        Ok(())
+    }
+
+    /// Finish the stream with its end-of-stream marker and flush the sink
+    async fn close(mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.finish()?;
+            writer.into_inner()?.flush().map_err(|e| {
+                CollectorError::Storage(anyhow!(
+                    "failed to flush Arrow IPC sink '{}': {}",
+                    self.sink_name,
+                    e
+                ))
+            })?;
+        }
+        info!(
+            "Closed Arrow IPC stream '{}' after {} rows ({} rows dropped on shutdown)",
+            self.sink_name, self.rows_written, self.shutdown_drops
+        );
+        Ok(())
+    }
+
+    /// Streams are flushed on every write, so there is no interval
+    fn flush_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Every written batch has already been flushed to the sink
+    fn has_buffered_rows(&self) -> bool {
+        false
+    }
+
+    /// A stream never closes files, so no success ends a run of write failures
+    fn files_closed(&self) -> u64 {
+        0
+    }
+
+    fn record_shutdown_drops(&mut self, rows: u64) {
+        self.shutdown_drops += rows;
+    }
+
+    /// A partially written message leaves the stream unreadable, so there is no
+    /// file to discard and continue from
+    fn discard_current_file(&mut self) -> Result<()> {
+        Err(CollectorError::Storage(anyhow!(
+            "cannot recover Arrow IPC stream '{}' after a failed write",
+            self.sink_name
+        )))
+    }
+
+    /// Streams are not written to an object store, so they cannot spill to one
+    fn set_store(&mut self, _store: Arc<dyn ObjectStore>) -> Result<()> {
+        Err(CollectorError::Storage(anyhow!(
+            "Arrow IPC stream '{}' cannot spill to an object store",
+            self.sink_name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_ipc::reader::StreamReader;
+
+    use super::*;
+    use crate::metrics::{Metric, PerfEvents};
+    use crate::task_metadata::TaskMetadata;
+    use crate::test_utils::SharedBuffer;
+    use crate::timeslot_data::{Granularity, TimeslotData};
+    use crate::timeslot_to_recordbatch_task::{create_timeslot_schema, timeslot_to_batch};
+
+    #[tokio::test]
+    async fn test_timeslot_batch_round_trips_through_stream() {
+        let events = PerfEvents::default();
+        let schema = create_timeslot_schema(&events, Granularity::Process);
+        let mut timeslot = TimeslotData::new(1_500_000);
+        let mut comm = [0u8; 16];
+        comm[..3].copy_from_slice(b"app");
+        let metadata = Some(TaskMetadata::new(42, comm, 4242));
+        timeslot.update(
+            42,
+            metadata,
+            Metric::from_deltas(1000, 2000, 30, 500, 100000),
+        );
+        timeslot.update(43, None, Metric::from_deltas(10, 20, 3, 5, 1000));
+        let batch =
+            timeslot_to_batch(timeslot, schema.clone(), &events, Granularity::Process).unwrap();
+
+        let buffer = SharedBuffer::default();
+        let mut writer =
+            ArrowIpcWriter::new(Box::new(buffer.clone()), "memory", schema.clone()).unwrap();
+        writer.write(batch.clone()).await.unwrap();
+        // Rotation keeps the stream open
+        writer.rotate().await.unwrap();
+        writer.write(batch.clone()).await.unwrap();
+        writer.close().await.unwrap();
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        let reader = StreamReader::try_new(bytes.as_slice(), None).unwrap();
+        assert_eq!(reader.schema(), schema);
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batches, vec![batch.clone(), batch]);
+    }
+
+    #[test]
+    fn test_failed_stream_cannot_be_recovered() {
+        let schema = create_timeslot_schema(&PerfEvents::default(), Granularity::Process);
+        let mut writer =
+            ArrowIpcWriter::new(Box::new(SharedBuffer::default()), "memory", schema).unwrap();
+        assert!(matches!(
+            writer.discard_current_file(),
+            Err(CollectorError::Storage(_))
+        ));
+        let store = Arc::new(object_store::memory::InMemory::new());
+        assert!(writer.set_store(store).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -11,13 +12,54 @@ use bpf::{msg_type, BpfLoader, TimerMigrationMsg};
 /// Event types that can be sent to the error reporting channel
 #[derive(Debug)]
 pub enum ErrorEvent {
-    LostEvents,
+    /// The kernel dropped `samples` samples because perf ring `ring_index` was full
+    LostEvents { ring_index: usize, samples: u64 },
+}
+
+/// Lost records and samples accumulated between two reports
+#[derive(Debug, Default)]
+struct LostSamples {
+    records: usize,
+    /// Dropped samples by ring index
+    samples_by_ring: BTreeMap<usize, u64>,
+    /// Dropped samples since the collector started
+    total_samples: u64,
+}
+
+impl LostSamples {
+    fn add(&mut self, ring_index: usize, samples: u64) {
+        self.records += 1;
+        *self.samples_by_ring.entry(ring_index).or_default() += samples;
+        self.total_samples += samples;
+    }
+
+    /// Log the losses since the last report, then start a new interval
+    fn report(&mut self) {
+        if self.records == 0 {
+            return;
+        }
+        let samples: u64 = self.samples_by_ring.values().sum();
+        let rings = self
+            .samples_by_ring
+            .iter()
+            .map(|(ring, n)| format!("{}={}", ring, n))
+            .collect::<Vec<_>>()
+            .join(", ");
+        error!(
+            "Lost {} samples ({} lost events) in the last second, by ring: {} ({} samples lost since start)",
+            samples, self.records, rings, self.total_samples
+        );
+        self.records = 0;
+        self.samples_by_ring.clear();
+    }
 }
 
 /// BPF Error Handler manages error-related BPF events like timer migration and lost samples
 pub struct BpfErrorHandler {
     error_sender: Option<mpsc::Sender<ErrorEvent>>,
     error_receiver: Option<mpsc::Receiver<ErrorEvent>>,
+    // Samples the perf rings reported lost since the handler was created
+    lost_samples: u64,
 }
 
 impl BpfErrorHandler {
@@ -27,6 +69,7 @@ impl BpfErrorHandler {
         let handler = Rc::new(RefCell::new(Self {
             error_sender: Some(sender),
             error_receiver: Some(receiver),
+            lost_samples: 0,
         }));
 
         // Subscribe to timer migration events
@@ -40,7 +83,9 @@ impl BpfErrorHandler {
         // Subscribe to lost samples events
         let handler_clone = handler.clone();
         dispatcher.subscribe_lost_samples(move |ring_index, data| {
-            handler_clone.borrow().handle_lost_events(ring_index, data);
+            handler_clone
+                .borrow_mut()
+                .handle_lost_events(ring_index, data);
         });
 
         handler
@@ -51,34 +96,34 @@ impl BpfErrorHandler {
         self.error_receiver.take()
     }
 
+    /// Samples the perf rings reported lost so far, over all rings
+    pub fn lost_samples(&self) -> u64 {
+        self.lost_samples
+    }
+
     /// Run the error reporting task that batches and reports errors
     pub async fn run_error_reporting(mut receiver: mpsc::Receiver<ErrorEvent>) {
         let mut interval = time::interval(Duration::from_secs(1));
-        let mut lost_events_count = 0;
+        let mut lost = LostSamples::default();
 
         loop {
             tokio::select! {
                 // Check for new error events
                 event = receiver.recv() => {
                     match event {
-                        Some(ErrorEvent::LostEvents) => {
-                            lost_events_count += 1;
+                        Some(ErrorEvent::LostEvents { ring_index, samples }) => {
+                            lost.add(ring_index, samples);
                         }
                         None => {
                             // Channel closed, shutdown gracefully
-                            if lost_events_count > 0 {
-                                error!("Lost {} events in the last second", lost_events_count);
-                            }
+                            lost.report();
                             break;
                         }
                     }
                 }
                 // Timer tick every second
                 _ = interval.tick() => {
-                    if lost_events_count > 0 {
-                        error!("Lost {} events in the last second", lost_events_count);
-                        lost_events_count = 0;
-                    }
+                    lost.report();
                 }
             }
         }
@@ -118,9 +163,14 @@ Exiting to prevent incorrect performance measurements."#,
     }
 
     /// Handle lost events
-    fn handle_lost_events(&self, ring_index: usize, _data: &[u8]) {
+    fn handle_lost_events(&mut self, ring_index: usize, data: &[u8]) {
+        let samples = perf_events::lost_record_count(data);
+        self.lost_samples += samples;
         if let Some(sender) = &self.error_sender {
-            let event = ErrorEvent::LostEvents;
+            let event = ErrorEvent::LostEvents {
+                ring_index,
+                samples,
+            };
 
             // If we're inside a Tokio runtime, avoid blocking the runtime thread.
             // Use an async send via spawn; otherwise, fall back to a blocking send.
@@ -1,5 +1,7 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use log::error;
 use tokio::sync::mpsc;
@@ -8,8 +10,9 @@ use bpf::{msg_type, BpfLoader, PerfMeasurementMsg};
 
 use crate::bpf_task_tracker::BpfTaskTracker;
 use crate::bpf_timeslot_tracker::BpfTimeslotTracker;
+use crate::control_socket::CollectorStatus;
 use crate::metrics::Metric;
-use crate::timeslot_data::TimeslotData;
+use crate::timeslot_data::{Granularity, TimeslotData};
 
 /// Handles BPF performance measurements and composes them into timeslots
 pub struct BpfPerfToTimeslot {
@@ -19,8 +22,31 @@ pub struct BpfPerfToTimeslot {
     // Error tracking for batched reporting
     error_counter: u64,
     last_error_report: std::time::Instant,
+    // Samples the perf rings reported lost while the current timeslot was open
+    lost_samples: u64,
+    // Timeslots completed with lost samples, and those samples, since the last
+    // error report
+    lossy_timeslots: u64,
+    lossy_samples: u64,
+    // Status counting dropped timeslots, if reported on the control socket
+    status: Option<Arc<CollectorStatus>>,
     // Task tracker for metadata lookup
     task_tracker: Rc<RefCell<BpfTaskTracker>>,
+    // Tasks that exit with a shorter lifetime are aggregated into ephemeral buckets
+    min_task_lifetime_ns: Option<u64>,
+    // Number of completed timeslots merged into each emitted timeslot
+    downsample_factor: u32,
+    // Whether tasks are recorded per process or per thread
+    granularity: Granularity,
+    // Completed timeslots merged so far, with their count
+    pending: Option<(TimeslotData, u32)>,
+    // Completed timeslot with tasks that had no metadata yet (metrics can arrive
+    // before the task's metadata message), held until the next timeslot completes
+    // so the metadata can still be filled in
+    awaiting_metadata: Option<TimeslotData>,
+    // PIDs that went a whole timeslot without metadata (e.g. kernel threads),
+    // whose timeslots are no longer held back
+    without_metadata: HashSet<u32>,
 }
 
 impl BpfPerfToTimeslot {
@@ -30,14 +56,18 @@ impl BpfPerfToTimeslot {
         timeslot_tracker: Rc<RefCell<BpfTimeslotTracker>>,
         task_tracker: Rc<RefCell<BpfTaskTracker>>,
         timeslot_tx: mpsc::Sender<TimeslotData>,
+        min_task_lifetime_ns: Option<u64>,
+        downsample_factor: u32,
+        granularity: Granularity,
     ) -> Rc<RefCell<Self>> {
-        let processor = Rc::new(RefCell::new(Self {
-            current_timeslot: TimeslotData::new(0), // Start with timestamp 0
-            timeslot_tx: Some(timeslot_tx),
-            error_counter: 0u64,
-            last_error_report: std::time::Instant::now(),
+        let mut processor = Self::with_task_tracker(
             task_tracker,
-        }));
+            timeslot_tx,
+            min_task_lifetime_ns,
+            downsample_factor,
+        );
+        processor.granularity = granularity;
+        let processor = Rc::new(RefCell::new(processor));
 
         // Set up timeslot event subscription using subscribe_method
         timeslot_tracker
@@ -53,11 +83,50 @@ impl BpfPerfToTimeslot {
                 processor.clone(),
                 BpfPerfToTimeslot::handle_perf_measurement,
             );
+
+            // Timeslots open while the rings dropped samples are incomplete
+            let processor_clone = processor.clone();
+            dispatcher.subscribe_lost_samples(move |_ring_index, data| {
+                processor_clone
+                    .borrow_mut()
+                    .record_lost_samples(perf_events::lost_record_count(data));
+            });
         }
 
         processor
     }
 
+    /// Create a processor that is not subscribed to any events
+    fn with_task_tracker(
+        task_tracker: Rc<RefCell<BpfTaskTracker>>,
+        timeslot_tx: mpsc::Sender<TimeslotData>,
+        min_task_lifetime_ns: Option<u64>,
+        downsample_factor: u32,
+    ) -> Self {
+        Self {
+            current_timeslot: TimeslotData::new(0), // Start with timestamp 0
+            timeslot_tx: Some(timeslot_tx),
+            error_counter: 0u64,
+            last_error_report: std::time::Instant::now(),
+            lost_samples: 0,
+            lossy_timeslots: 0,
+            lossy_samples: 0,
+            status: None,
+            task_tracker,
+            min_task_lifetime_ns,
+            downsample_factor: downsample_factor.max(1),
+            granularity: Granularity::default(),
+            pending: None,
+            awaiting_metadata: None,
+            without_metadata: HashSet::new(),
+        }
+    }
+
+    /// Count timeslots dropped on a full channel in `status`
+    pub fn set_status(&mut self, status: Arc<CollectorStatus>) {
+        self.status = Some(status);
+    }
+
     /// Handle performance measurement events
     fn handle_perf_measurement(&mut self, _ring_index: usize, data: &[u8]) {
         let event: &PerfMeasurementMsg = match plain::from_bytes(data) {
@@ -77,46 +146,323 @@ impl BpfPerfToTimeslot {
             event.time_delta_ns,
         );
 
+        self.record_metric(event.pid, event.tid, metric);
+    }
+
+    /// Count samples the perf rings dropped while the current timeslot is open
+    fn record_lost_samples(&mut self, samples: u64) {
+        self.lost_samples += samples;
+    }
+
+    /// Add the metric of thread `tid` of process `pid` to the current timeslot
+    fn record_metric(&mut self, pid: u32, tid: u32, metric: Metric) {
         // Look up task metadata and update timeslot data
-        let pid = event.pid;
-        let metadata = self.task_tracker.borrow().lookup(pid).cloned();
-        self.current_timeslot.update(pid, metadata, metric);
+        let task_tracker = self.task_tracker.borrow();
+        if task_tracker.is_filtered(pid) {
+            return;
+        }
+        let metadata = task_tracker.lookup(pid).cloned();
+        match self.granularity {
+            Granularity::Process => self.current_timeslot.update(pid, metadata, metric),
+            Granularity::Thread => self
+                .current_timeslot
+                .update_thread(pid, tid, metadata, metric),
+        }
     }
 
     /// Handle new timeslot events
     fn on_new_timeslot(&mut self, _old_timeslot: u64, new_timeslot: u64) {
+        if self.lost_samples > 0 {
+            self.lossy_timeslots += 1;
+            self.lossy_samples += std::mem::take(&mut self.lost_samples);
+        }
+
         // Create a new empty timeslot with the new timestamp
         let new_timeslot_data = TimeslotData::new(new_timeslot);
 
         // Take ownership of the current timeslot, replacing it with the new one
-        let completed_timeslot = std::mem::replace(&mut self.current_timeslot, new_timeslot_data);
+        let mut completed_timeslot =
+            std::mem::replace(&mut self.current_timeslot, new_timeslot_data);
+        self.resolve_metadata(&mut completed_timeslot);
+        self.bucket_short_lived(&mut completed_timeslot);
+
+        // The timeslot held back at the previous boundary has waited long enough;
+        // it is sent with whatever metadata arrived since, ahead of this one
+        self.release_awaiting_metadata();
+        if self.has_unresolved(&completed_timeslot) {
+            self.awaiting_metadata = Some(completed_timeslot);
+        } else {
+            self.complete_timeslot(completed_timeslot);
+        }
+        self.report_errors();
+    }
+
+    /// Fill in metadata that arrived after the task's metrics, including that of
+    /// tasks freed since. Tasks the metadata revealed to be rejected by the comm
+    /// filter are dropped.
+    fn resolve_metadata(&self, timeslot: &mut TimeslotData) {
+        let task_tracker = self.task_tracker.borrow();
+        timeslot
+            .tasks
+            .retain(|_, task| !task_tracker.is_filtered(task.pid));
+        timeslot.resolve_metadata(|pid| task_tracker.lookup_recent(pid).cloned());
+    }
+
+    /// Send the timeslot held back waiting for metadata, with the metadata that
+    /// arrived since. Its tasks still without metadata no longer hold back later
+    /// timeslots.
+    fn release_awaiting_metadata(&mut self) {
+        let Some(mut timeslot) = self.awaiting_metadata.take() else {
+            return;
+        };
+        self.resolve_metadata(&mut timeslot);
+        self.without_metadata.extend(
+            timeslot
+                .tasks
+                .values()
+                .filter(|task| task.metadata.is_none())
+                .map(|task| task.pid),
+        );
+        // Tasks resolved since may have exited after a short lifetime
+        self.bucket_short_lived(&mut timeslot);
+        self.complete_timeslot(timeslot);
+    }
+
+    /// Whether `timeslot` has tasks without metadata that may still receive it,
+    /// i.e. that did not already go a timeslot without metadata
+    fn has_unresolved(&mut self, timeslot: &TimeslotData) -> bool {
+        let task_tracker = self.task_tracker.borrow();
+        self.without_metadata
+            .retain(|pid| task_tracker.lookup(*pid).is_none());
+        timeslot
+            .tasks
+            .values()
+            .any(|task| task.metadata.is_none() && !self.without_metadata.contains(&task.pid))
+    }
+
+    /// Fold tasks that already exited after a short lifetime into ephemeral buckets
+    fn bucket_short_lived(&self, timeslot: &mut TimeslotData) {
+        if let Some(min_lifetime_ns) = self.min_task_lifetime_ns {
+            let task_tracker = self.task_tracker.borrow();
+            timeslot.bucket_short_lived(min_lifetime_ns, |pid| task_tracker.lifetime_ns(pid));
+        }
+    }
+
+    /// Merge a completed timeslot into the downsampled output, sending it once
+    /// `downsample_factor` are collected
+    fn complete_timeslot(&mut self, completed_timeslot: TimeslotData) {
+        // Merge consecutive timeslots until `downsample_factor` are collected
+        let (merged, count) = match self.pending.take() {
+            Some((mut merged, count)) => {
+                merged.merge(completed_timeslot);
+                (merged, count + 1)
+            }
+            None => (completed_timeslot, 1),
+        };
+        if count < self.downsample_factor {
+            self.pending = Some((merged, count));
+            return;
+        }
+
+        self.send_timeslot(merged);
+    }
 
+    /// Send a completed timeslot to the writer, counting failures for the
+    /// batched report
+    fn send_timeslot(&mut self, completed_timeslot: TimeslotData) {
         // Try to send the completed timeslot to the writer
         if let Some(ref sender) = self.timeslot_tx {
             if sender.try_send(completed_timeslot).is_err() {
                 // Increment error count instead of printing immediately
                 self.error_counter += 1;
-
-                // Check if it's time to report errors (every 1 second)
-                let now = std::time::Instant::now();
-                if now.duration_since(self.last_error_report).as_secs() >= 1 {
-                    // Report accumulated errors
-                    if self.error_counter > 0 {
-                        error!(
-                            "Error sending timeslots to writer: {} errors in the last 1 second",
-                            self.error_counter
-                        );
-                        self.error_counter = 0;
-                    }
-                    self.last_error_report = now;
+                if let Some(status) = &self.status {
+                    status.record_dropped_timeslot();
                 }
             }
         }
     }
 
+    /// Report send failures and timeslots missing lost samples, at most once
+    /// per second
+    fn report_errors(&mut self) {
+        let now = std::time::Instant::now();
+        if now.duration_since(self.last_error_report).as_secs() < 1 {
+            return;
+        }
+        if self.error_counter > 0 {
+            error!(
+                "Error sending timeslots to writer: {} errors in the last 1 second",
+                self.error_counter
+            );
+            self.error_counter = 0;
+        }
+        if self.lossy_timeslots > 0 {
+            error!(
+                "{} timeslots in the last 1 second are missing {} samples lost by the perf rings",
+                self.lossy_timeslots, self.lossy_samples
+            );
+            self.lossy_timeslots = 0;
+            self.lossy_samples = 0;
+        }
+        self.last_error_report = now;
+    }
+
     /// Shutdown the processor and close the timeslot channel
     pub fn shutdown(&mut self) {
+        // Emit the timeslot still waiting for metadata and the partial timeslot in
+        // progress, then the timeslots merged so far, even if fewer than
+        // `downsample_factor`
+        self.release_awaiting_metadata();
+        let start = self.current_timeslot.start_timestamp;
+        let mut partial = std::mem::replace(&mut self.current_timeslot, TimeslotData::new(start));
+        self.resolve_metadata(&mut partial);
+        if partial.task_count() > 0 {
+            self.bucket_short_lived(&mut partial);
+            self.complete_timeslot(partial);
+        }
+        if let Some((merged, _)) = self.pending.take() {
+            self.send_timeslot(merged);
+        }
+
         // Extract and drop the sender to close the channel
         self.timeslot_tx.take();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comm_filter::CommFilter;
+    use crate::task_metadata::TaskMetadata;
+    use tokio::sync::mpsc::error::TryRecvError;
+
+    fn processor(timeslot_tx: mpsc::Sender<TimeslotData>) -> BpfPerfToTimeslot {
+        let task_tracker = Rc::new(RefCell::new(BpfTaskTracker::with_max_tasks(0)));
+        BpfPerfToTimeslot::with_task_tracker(task_tracker, timeslot_tx, None, 1)
+    }
+
+    #[test]
+    fn test_shutdown_sends_partial_timeslot() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut processor = processor(tx);
+
+        processor.record_metric(42, 42, Metric::from_deltas(1000, 2000, 30, 500, 100_000));
+        processor.shutdown();
+
+        let partial = rx.try_recv().expect("partial timeslot should be sent");
+        assert_eq!(partial.task_count(), 1);
+        assert_eq!(partial.tasks[&42].metrics.cycles, 1000);
+        assert_eq!(partial.tasks[&42].metrics.instructions, 2000);
+        // The sender is dropped after the partial timeslot
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn test_filtered_tasks_are_dropped() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let filter = CommFilter::parse("nginx").unwrap();
+        let task_tracker = Rc::new(RefCell::new(
+            BpfTaskTracker::with_max_tasks(0).with_comm_filter(Some(filter)),
+        ));
+        let mut processor = BpfPerfToTimeslot::with_task_tracker(task_tracker.clone(), tx, None, 1);
+        let task = |pid: u32, name: &str| {
+            let mut comm = [0u8; 16];
+            comm[..name.len()].copy_from_slice(name.as_bytes());
+            TaskMetadata::new(pid, comm, 1)
+        };
+        let metric = || Metric::from_deltas(1000, 2000, 30, 500, 100_000);
+
+        task_tracker.borrow_mut().add_task(task(1, "nginx"));
+        task_tracker.borrow_mut().add_task(task(2, "bash"));
+        processor.record_metric(1, 1, metric());
+        processor.record_metric(2, 2, metric());
+        // Metrics arriving before the task's metadata are dropped once it is known
+        processor.record_metric(3, 3, metric());
+        task_tracker.borrow_mut().add_task(task(3, "sshd"));
+        processor.shutdown();
+
+        let timeslot = rx.try_recv().expect("timeslot should be sent");
+        assert_eq!(timeslot.tasks.keys().copied().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_metrics_before_metadata_wait_one_timeslot() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let task_tracker = Rc::new(RefCell::new(BpfTaskTracker::with_max_tasks(0)));
+        let mut processor =
+            BpfPerfToTimeslot::with_task_tracker(task_tracker.clone(), tx, Some(1_000), 1);
+        let task = |pid: u32, cgroup_id: u64| TaskMetadata::new(pid, [0; 16], cgroup_id);
+        let metric = |cycles: u64| Metric::from_deltas(cycles, 0, 0, 0, 0);
+        // Timeslot boundary as dispatched: the task tracker is subscribed first.
+        // Returns the timeslots sent at the boundary.
+        let mut next_timeslot = |processor: &mut BpfPerfToTimeslot, timeslot: u64| {
+            task_tracker.borrow_mut().on_new_timeslot(0, timeslot);
+            processor.on_new_timeslot(0, timeslot);
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<_>>()
+        };
+
+        // Task 1 exits after a short lifetime; task 2's metadata arrives late and
+        // task 3 never gets any (e.g. a kernel thread)
+        task_tracker
+            .borrow_mut()
+            .add_task(task(1, 10).with_start_ns(100));
+        processor.record_metric(1, 1, metric(1));
+        task_tracker.borrow_mut().free_task(1, 600);
+        processor.record_metric(2, 2, metric(2));
+        processor.record_metric(3, 3, metric(3));
+
+        // The timeslot with tasks lacking metadata is held back
+        assert!(next_timeslot(&mut processor, 1000).is_empty());
+
+        task_tracker.borrow_mut().add_task(task(2, 20));
+        processor.record_metric(2, 2, metric(20));
+        let sent = next_timeslot(&mut processor, 2000);
+        assert_eq!(sent.len(), 2);
+
+        // The held timeslot keeps its own timestamp and metrics, with the metadata
+        // that arrived since; the short-lived task is bucketed with the lifetimes
+        // of its own timeslot
+        let first = &sent[0];
+        assert_eq!(first.start_timestamp, 0);
+        assert_eq!(first.ephemeral[&10].cycles, 1);
+        assert_eq!(first.tasks[&2].metrics.cycles, 2);
+        assert_eq!(first.tasks[&2].metadata.as_ref().unwrap().cgroup_id, 20);
+        assert_eq!(first.tasks[&3].metrics.cycles, 3);
+        assert!(first.tasks[&3].metadata.is_none());
+
+        // The next timeslot has its metadata and is sent right away
+        let second = &sent[1];
+        assert_eq!(second.start_timestamp, 1000);
+        assert_eq!(second.tasks[&2].metrics.cycles, 20);
+
+        // A task that went a timeslot without metadata no longer holds one back
+        processor.record_metric(3, 3, metric(30));
+        let third = next_timeslot(&mut processor, 3000);
+        assert_eq!(third.len(), 1);
+        assert_eq!(third[0].start_timestamp, 2000);
+        assert_eq!(third[0].tasks[&3].metrics.cycles, 30);
+    }
+
+    #[test]
+    fn test_lost_samples_mark_open_timeslot() {
+        let (tx, _rx) = mpsc::channel(8);
+        let mut processor = processor(tx);
+
+        processor.record_lost_samples(5);
+        processor.record_lost_samples(2);
+        processor.on_new_timeslot(0, 1_000_000);
+        // A timeslot without losses is not counted
+        processor.on_new_timeslot(1_000_000, 2_000_000);
+
+        assert_eq!(processor.lost_samples, 0);
+        assert_eq!(processor.lossy_timeslots, 1);
+        assert_eq!(processor.lossy_samples, 7);
+    }
+
+    #[test]
+    fn test_shutdown_skips_empty_timeslot() {
+        let (tx, mut rx) = mpsc::channel(4);
+        processor(tx).shutdown();
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+}
@@ -3,7 +3,6 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Instant;
 
-use anyhow::{anyhow, Result};
 use arrow_array::builder::{BooleanBuilder, Int32Builder, Int64Builder, StringBuilder};
 use arrow_array::{ArrayRef, RecordBatch};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
@@ -14,28 +13,32 @@ use bpf::{msg_type, BpfLoader, PerfMeasurementMsg};
 
 use crate::bpf_task_tracker::BpfTaskTracker;
 use crate::bpf_timeslot_tracker::BpfTimeslotTracker;
+use crate::error::Result;
+use crate::metrics::{Metric, PerfEvents};
 
 /// Create the schema for trace record batches
-pub fn create_schema() -> SchemaRef {
-    Arc::new(Schema::new(vec![
+pub fn create_schema(events: &PerfEvents) -> SchemaRef {
+    let mut fields = vec![
         Field::new("timestamp", DataType::Int64, false),
         Field::new("pid", DataType::Int32, false),
         Field::new("process_name", DataType::Utf8, true),
         Field::new("cgroup_id", DataType::Int64, false),
         Field::new("cpu_id", DataType::Int32, false),
-        Field::new("cycles", DataType::Int64, false),
-        Field::new("instructions", DataType::Int64, false),
-        Field::new("llc_misses", DataType::Int64, false),
-        Field::new("cache_references", DataType::Int64, false),
-        Field::new("is_context_switch", DataType::Boolean, false),
-        Field::new("next_tgid", DataType::Int32, true),
-    ]))
+    ];
+    fields.extend(events.fields());
+    fields.push(Field::new("is_context_switch", DataType::Boolean, false));
+    fields.push(Field::new("next_tgid", DataType::Int32, true));
+    // Appended so existing column positions are unchanged
+    fields.push(Field::new("nice", DataType::Int32, true));
+    Arc::new(Schema::new(fields))
 }
 
 /// Handles BPF performance measurements and outputs individual trace events
 pub struct BpfPerfToTrace {
     // Schema for trace records
     schema: SchemaRef,
+    // Counters collected; the others are written as nulls
+    events: PerfEvents,
     // Array builders for each column
     timestamp_builder: Int64Builder,
     pid_builder: Int32Builder,
@@ -48,6 +51,7 @@ pub struct BpfPerfToTrace {
     cache_references_builder: Int64Builder,
     is_context_switch_builder: BooleanBuilder,
     next_tgid_builder: Int32Builder,
+    nice_builder: Int32Builder,
     // Channel for sending completed record batches
     batch_tx: Option<mpsc::Sender<RecordBatch>>,
     // Task tracker for metadata lookup
@@ -67,11 +71,13 @@ impl BpfPerfToTrace {
         task_tracker: Rc<RefCell<BpfTaskTracker>>,
         batch_tx: mpsc::Sender<RecordBatch>,
         capacity: usize,
+        events: PerfEvents,
     ) -> Rc<RefCell<Self>> {
-        let schema = create_schema();
+        let schema = create_schema(&events);
 
         let processor = Rc::new(RefCell::new(Self {
             schema: schema.clone(),
+            events,
             timestamp_builder: Int64Builder::with_capacity(capacity),
             pid_builder: Int32Builder::with_capacity(capacity),
             process_name_builder: StringBuilder::with_capacity(capacity, capacity * 16),
@@ -83,6 +89,7 @@ impl BpfPerfToTrace {
             cache_references_builder: Int64Builder::with_capacity(capacity),
             is_context_switch_builder: BooleanBuilder::with_capacity(capacity),
             next_tgid_builder: Int32Builder::with_capacity(capacity),
+            nice_builder: Int32Builder::with_capacity(capacity),
             batch_tx: Some(batch_tx),
             task_tracker,
             last_flush: Instant::now(),
@@ -119,6 +126,12 @@ impl BpfPerfToTrace {
             }
         };
 
+        // Tasks rejected by the comm filter are not traced. Trace rows are written
+        // as they arrive, so measurements preceding a task's metadata are kept
+        if self.task_tracker.borrow().is_filtered(event.pid) {
+            return;
+        }
+
         // Add event data to builders
         self.timestamp_builder
             .append_value(event.header.timestamp as i64);
@@ -134,22 +147,30 @@ impl BpfPerfToTrace {
             self.process_name_builder.append_value(comm);
             self.cgroup_id_builder
                 .append_value(metadata.cgroup_id as i64);
+            self.nice_builder.append_value(metadata.nice);
         } else {
             self.process_name_builder.append_null();
             self.cgroup_id_builder.append_value(0); // Default value when no metadata available
+            self.nice_builder.append_null();
         }
 
         // Add CPU ID from ring index (ring index corresponds to CPU ID)
         self.cpu_id_builder.append_value(ring_index as i32);
 
-        // Add performance counter deltas
-        self.cycles_builder.append_value(event.cycles_delta as i64);
-        self.instructions_builder
-            .append_value(event.instructions_delta as i64);
-        self.llc_misses_builder
-            .append_value(event.llc_misses_delta as i64);
+        // Add performance counter deltas (null for counters not collected)
+        let [cycles, instructions, llc_misses, cache_references] = Metric::from_deltas(
+            event.cycles_delta,
+            event.instructions_delta,
+            event.llc_misses_delta,
+            event.cache_references_delta,
+            0,
+        )
+        .counters(&self.events);
+        self.cycles_builder.append_option(cycles);
+        self.instructions_builder.append_option(instructions);
+        self.llc_misses_builder.append_option(llc_misses);
         self.cache_references_builder
-            .append_value(event.cache_references_delta as i64);
+            .append_option(cache_references);
 
         // Add event type indication from BPF message
         self.is_context_switch_builder
@@ -191,11 +212,11 @@ impl BpfPerfToTrace {
             Arc::new(self.cache_references_builder.finish()),
             Arc::new(self.is_context_switch_builder.finish()),
             Arc::new(self.next_tgid_builder.finish()),
+            Arc::new(self.nice_builder.finish()),
         ];
 
         // Create record batch
-        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
-            .map_err(|e| anyhow!("Failed to create trace RecordBatch: {}", e))?;
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
 
         // Send the batch
         if let Some(ref sender) = self.batch_tx {
@@ -216,6 +237,7 @@ impl BpfPerfToTrace {
         self.cache_references_builder = Int64Builder::with_capacity(self.capacity);
         self.is_context_switch_builder = BooleanBuilder::with_capacity(self.capacity);
         self.next_tgid_builder = Int32Builder::with_capacity(self.capacity);
+        self.nice_builder = Int32Builder::with_capacity(self.capacity);
         self.current_rows = 0;
         self.last_flush = Instant::now();
 
@@ -1,26 +1,39 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use log::error;
 
 use crate::bpf_timeslot_tracker::BpfTimeslotTracker;
+use crate::comm_filter::CommFilter;
 use crate::task_metadata::{TaskCollection, TaskMetadata};
 use bpf::{msg_type, BpfLoader, TaskFreeMsg, TaskMetadataMsg};
 
 /// BPF Task Tracker manages task metadata and task free events
 pub struct BpfTaskTracker {
     task_collection: TaskCollection,
+    // Tasks whose comm must match to be tracked (None = all tasks)
+    comm_filter: Option<CommFilter>,
+    // Tasks rejected by the filter, whose measurements are dropped
+    filtered: HashSet<u32>,
+    // Filtered tasks freed during the current and the previous timeslot; kept
+    // until their last measurements have been dropped
+    filtered_freed: Vec<u32>,
+    filtered_freed_previous: Vec<u32>,
 }
 
 impl BpfTaskTracker {
-    /// Create a new BpfTaskTracker and subscribe to task events
+    /// Create a new BpfTaskTracker and subscribe to task events, tracking at most
+    /// `max_tasks` tasks (0 = unbounded) whose comm matches `comm_filter`
     pub fn new(
         bpf_loader: &mut BpfLoader,
         timeslot_tracker: Rc<RefCell<BpfTimeslotTracker>>,
+        max_tasks: usize,
+        comm_filter: Option<CommFilter>,
     ) -> Rc<RefCell<Self>> {
-        let tracker = Rc::new(RefCell::new(Self {
-            task_collection: TaskCollection::new(),
-        }));
+        let tracker = Rc::new(RefCell::new(
+            Self::with_max_tasks(max_tasks).with_comm_filter(comm_filter),
+        ));
 
         // Subscribe to task events
         let dispatcher = bpf_loader.dispatcher_mut();
@@ -47,14 +60,68 @@ impl BpfTaskTracker {
         tracker
     }
 
+    /// Create a tracker of at most `max_tasks` tasks (0 = unbounded) that is not
+    /// subscribed to any events
+    pub(crate) fn with_max_tasks(max_tasks: usize) -> Self {
+        Self {
+            task_collection: TaskCollection::with_max_entries(max_tasks),
+            comm_filter: None,
+            filtered: HashSet::new(),
+            filtered_freed: Vec::new(),
+            filtered_freed_previous: Vec::new(),
+        }
+    }
+
+    /// Only track tasks whose comm matches `comm_filter`
+    pub(crate) fn with_comm_filter(mut self, comm_filter: Option<CommFilter>) -> Self {
+        self.comm_filter = comm_filter;
+        self
+    }
+
     /// Look up task metadata by PID
     pub fn lookup(&self, pid: u32) -> Option<&TaskMetadata> {
         self.task_collection.lookup(pid)
     }
 
+    /// Look up task metadata by PID, including tasks freed during the last timeslot
+    pub fn lookup_recent(&self, pid: u32) -> Option<&TaskMetadata> {
+        self.task_collection.lookup_recent(pid)
+    }
+
+    /// Lifetime of a recently freed task, if known
+    pub fn lifetime_ns(&self, pid: u32) -> Option<u64> {
+        self.task_collection.lifetime_ns(pid)
+    }
+
+    /// Whether the task's comm was rejected by the comm filter, so its
+    /// measurements should be dropped
+    pub fn is_filtered(&self, pid: u32) -> bool {
+        self.filtered.contains(&pid)
+    }
+
     /// Handle new timeslot events - triggers flush_removals maintenance
-    fn on_new_timeslot(&mut self, _old_timeslot: u64, _new_timeslot: u64) {
+    pub(crate) fn on_new_timeslot(&mut self, _old_timeslot: u64, _new_timeslot: u64) {
         self.task_collection.flush_removals();
+
+        // Metrics can be held back a full timeslot waiting for metadata, so
+        // filtered tasks are forgotten one timeslot after the one they were freed in
+        for pid in self.filtered_freed_previous.drain(..) {
+            self.filtered.remove(&pid);
+        }
+        std::mem::swap(&mut self.filtered_freed, &mut self.filtered_freed_previous);
+    }
+
+    /// Track a task, unless its comm is rejected by the comm filter
+    pub(crate) fn add_task(&mut self, metadata: TaskMetadata) {
+        if let Some(filter) = &self.comm_filter {
+            if !filter.matches(&metadata.comm) {
+                self.filtered.insert(metadata.pid);
+                return;
+            }
+            // A reused PID may now belong to a matching task
+            self.filtered.remove(&metadata.pid);
+        }
+        self.task_collection.add(metadata);
     }
 
     /// Handle task metadata events
@@ -68,8 +135,10 @@ impl BpfTaskTracker {
         };
 
         // Create task metadata and add to collection
-        let metadata = TaskMetadata::new(event.pid, event.comm, event.cgroup_id);
-        self.task_collection.add(metadata);
+        let metadata = TaskMetadata::new(event.pid, event.comm, event.cgroup_id)
+            .with_nice(event.nice)
+            .with_start_ns(event.header.timestamp);
+        self.add_task(metadata);
     }
 
     /// Handle task free events
@@ -82,7 +151,62 @@ impl BpfTaskTracker {
             }
         };
 
-        // Queue the task for removal
-        self.task_collection.queue_removal(event.pid);
+        self.free_task(event.pid, event.header.timestamp);
+    }
+
+    /// Queue a task freed at `timestamp` for removal at the next timeslot
+    pub(crate) fn free_task(&mut self, pid: u32, timestamp: u64) {
+        self.task_collection.queue_removal(pid, timestamp);
+        if self.filtered.contains(&pid) {
+            self.filtered_freed.push(pid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(pid: u32, name: &str) -> TaskMetadata {
+        let mut comm = [0u8; 16];
+        comm[..name.len()].copy_from_slice(name.as_bytes());
+        TaskMetadata::new(pid, comm, 1)
+    }
+
+    #[test]
+    fn test_comm_filter_skips_non_matching_tasks() {
+        let filter = CommFilter::parse("nginx,!nginx-debug").unwrap();
+        let mut tracker = BpfTaskTracker::with_max_tasks(0).with_comm_filter(Some(filter));
+
+        tracker.add_task(task(1, "nginx"));
+        tracker.add_task(task(2, "bash"));
+        tracker.add_task(task(3, "nginx-debug"));
+
+        assert_eq!(tracker.lookup(1).map(|t| t.pid), Some(1));
+        assert!(!tracker.is_filtered(1));
+        for pid in [2, 3] {
+            assert!(tracker.lookup(pid).is_none());
+            assert!(tracker.is_filtered(pid));
+        }
+
+        // A freed filtered task is forgotten a timeslot after the one it was freed in
+        tracker.filtered_freed.push(2);
+        tracker.on_new_timeslot(0, 1);
+        assert!(tracker.is_filtered(2));
+        tracker.on_new_timeslot(1, 2);
+        assert!(!tracker.is_filtered(2));
+
+        // Its PID can be reused by a matching task
+        tracker.add_task(task(3, "nginx"));
+        assert!(!tracker.is_filtered(3));
+        assert!(tracker.lookup(3).is_some());
+    }
+
+    #[test]
+    fn test_without_filter_all_tasks_are_tracked() {
+        let mut tracker = BpfTaskTracker::with_max_tasks(0);
+        tracker.add_task(task(2, "bash"));
+        assert!(tracker.lookup(2).is_some());
+        assert!(!tracker.is_filtered(2));
     }
 }
@@ -18,13 +18,13 @@ pub struct BpfTimeslotTracker {
 }
 
 impl BpfTimeslotTracker {
-    /// Create a new BpfTimeslotTracker and subscribe to timer events
+    /// Create a new BpfTimeslotTracker and subscribe to timer events.
+    /// Timeslots follow the period of the sync timer the loader is attached to.
     pub fn new(bpf_loader: &mut BpfLoader, num_cpus: usize) -> Rc<RefCell<Self>> {
-        let tracker = Rc::new(RefCell::new(Self {
-            min_tracker: MinTracker::new(1_000_000, num_cpus),
-            last_min_slot: None,
-            subscribers: Vec::new(),
-        }));
+        let tracker = Rc::new(RefCell::new(Self::with_interval(
+            bpf_loader.sync_interval_ns(),
+            num_cpus,
+        )));
 
         // Subscribe to timer finished processing events
         let dispatcher = bpf_loader.dispatcher_mut();
@@ -37,6 +37,15 @@ impl BpfTimeslotTracker {
         tracker
     }
 
+    /// Create a tracker with timeslots of `interval_ns`, without subscribing to timer events
+    fn with_interval(interval_ns: u64, num_cpus: usize) -> Self {
+        Self {
+            min_tracker: MinTracker::new(interval_ns, num_cpus),
+            last_min_slot: None,
+            subscribers: Vec::new(),
+        }
+    }
+
     /// Subscribe to new timeslot events
     /// Callback receives (old_timeslot, new_timeslot) timestamps
     pub fn subscribe(&mut self, callback: impl Fn(u64, u64) + 'static) {
@@ -66,9 +75,13 @@ impl BpfTimeslotTracker {
             }
         };
 
-        // Update the min tracker with the CPU ID and timestamp
-        let timestamp = event.header.timestamp;
+        self.record_timer_tick(ring_index, event.header.timestamp);
+    }
 
+    /// Record that the timer on `cpu` finished processing at `timestamp`, notifying
+    /// subscribers when the minimum completed timeslot across CPUs advances
+    fn record_timer_tick(&mut self, ring_index: usize, timestamp: u64) {
+        // Update the min tracker with the CPU ID and timestamp
         if let Err(e) = self.min_tracker.update(ring_index, timestamp) {
             error!("Failed to update min tracker: {:?}", e);
             return;
@@ -93,3 +106,30 @@ impl BpfTimeslotTracker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeslots_follow_configured_interval() {
+        const INTERVAL_NS: u64 = 5_000_000;
+        let mut tracker = BpfTimeslotTracker::with_interval(INTERVAL_NS, 2);
+        let transitions = Rc::new(RefCell::new(Vec::new()));
+        let seen = transitions.clone();
+        tracker.subscribe(move |old, new| seen.borrow_mut().push((old, new)));
+
+        tracker.record_timer_tick(0, 10_000_100);
+        tracker.record_timer_tick(1, 10_000_200);
+        // Ticks within the same 5ms period do not start a new timeslot
+        tracker.record_timer_tick(0, 14_000_000);
+        tracker.record_timer_tick(1, 14_900_000);
+        tracker.record_timer_tick(0, 15_000_100);
+        tracker.record_timer_tick(1, 15_000_200);
+
+        assert_eq!(
+            *transitions.borrow(),
+            vec![(0, 10_000_000), (10_000_000, 15_000_000)]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::anyhow;
+
+use crate::error::{CollectorError, Result};
+
+/// Selects tasks by their command name (`comm`).
+///
+/// A spec is a comma-separated list of patterns; patterns starting with `!`
+/// deny, the others allow. Patterns containing `*` or `?` are globs over the
+/// whole name, others match anywhere in it. A task is kept if it matches no
+/// deny pattern and, when any allow pattern is given, matches one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl CommFilter {
+    /// Parse a filter such as `nginx,redis*,!redis-cli`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = |reason: String| {
+            CollectorError::Config(anyhow!("invalid comm filter '{}': {}", spec, reason))
+        };
+
+        let mut filter = Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+        };
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (list, pattern) = match entry.strip_prefix('!') {
+                Some(pattern) => (&mut filter.deny, pattern.trim()),
+                None => (&mut filter.allow, entry),
+            };
+            if pattern.is_empty() {
+                return Err(invalid(format!("'{}' has an empty pattern", entry)));
+            }
+            // The kernel truncates comm to 15 bytes, so longer literals never match
+            if pattern.len() > 15 && !pattern.contains(['*', '?']) {
+                return Err(invalid(format!(
+                    "'{}' is longer than the 15 bytes of a task name",
+                    pattern
+                )));
+            }
+            list.push(pattern.to_string());
+        }
+        if filter.allow.is_empty() && filter.deny.is_empty() {
+            return Err(invalid("no patterns given".to_string()));
+        }
+        Ok(filter)
+    }
+
+    /// Whether a task with this NUL-padded `comm` is kept
+    pub fn matches(&self, comm: &[u8; 16]) -> bool {
+        let len = comm.iter().position(|&b| b == 0).unwrap_or(comm.len());
+        let name = &comm[..len];
+        if self
+            .deny
+            .iter()
+            .any(|p| pattern_matches(p.as_bytes(), name))
+        {
+            return false;
+        }
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|p| pattern_matches(p.as_bytes(), name))
+    }
+}
+
+fn pattern_matches(pattern: &[u8], name: &[u8]) -> bool {
+    if pattern.iter().any(|&b| b == b'*' || b == b'?') {
+        glob_matches(pattern, name)
+    } else {
+        name.windows(pattern.len()).any(|w| w == pattern)
+    }
+}
+
+/// Match `*` (any run of bytes) and `?` (any single byte) over the whole name
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some((&p, rest)) => match name.split_first() {
+            Some((&c, name_rest)) => (p == b'?' || p == c) && glob_matches(rest, name_rest),
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comm(name: &str) -> [u8; 16] {
+        let mut comm = [0u8; 16];
+        comm[..name.len()].copy_from_slice(name.as_bytes());
+        comm
+    }
+
+    #[test]
+    fn test_allow_and_deny_patterns() {
+        let filter = CommFilter::parse("nginx, redis*, !redis-cli").unwrap();
+        assert!(filter.matches(&comm("nginx")));
+        assert!(filter.matches(&comm("nginx: worker")));
+        assert!(filter.matches(&comm("redis-server")));
+        assert!(!filter.matches(&comm("redis-cli")));
+        assert!(!filter.matches(&comm("kworker/0:1")));
+        // Globs cover the whole name
+        assert!(!filter.matches(&comm("my-redis")));
+
+        let deny_only = CommFilter::parse("!kworker/*,!ksoftirqd?").unwrap();
+        assert!(deny_only.matches(&comm("python3")));
+        assert!(!deny_only.matches(&comm("kworker/3:0H")));
+        assert!(!deny_only.matches(&comm("ksoftirqd7")));
+        assert!(deny_only.matches(&comm("ksoftirqd/12")));
+    }
+
+    #[test]
+    fn test_invalid_filters_are_rejected() {
+        let cases = [
+            ("", "no patterns given"),
+            (" , ", "no patterns given"),
+            ("nginx,!", "'!' has an empty pattern"),
+            ("a-very-long-process-name", "longer than the 15 bytes"),
+        ];
+        for (spec, expected) in cases {
+            let err = CommFilter::parse(spec).unwrap_err();
+            assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+            assert!(
+                err.to_string().contains(expected),
+                "{}: expected '{}' in '{}'",
+                spec,
+                expected,
+                err
+            );
+        }
+    }
+}
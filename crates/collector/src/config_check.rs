@@ -0,0 +1,596 @@
+use std::fs::{self, OpenOptions};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use bpf::BpfLoader;
+use bpf_sync_timer::SyncTimer;
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use uuid::Uuid;
+
+use crate::nri_endpoint::NriEndpoint;
+use crate::Command;
+
+/// Environment variables accepted by `AmazonS3Builder::from_env` for the bucket name
+const S3_BUCKET_VARS: &[&str] = &["AWS_BUCKET", "AWS_BUCKET_NAME"];
+
+/// Smallest part S3 accepts in a multipart upload (except for the last part)
+const S3_MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Validate the buffer and file sizes, which the collector also checks at startup.
+///
+/// Zero sizes would make empty row groups or channels, and a Parquet buffer
+/// larger than the file size limit only rotates files at buffer flushes.
+pub fn check_sizes(opts: &Command) -> Vec<String> {
+    let mut problems = Vec::new();
+    let nonzero = [
+        ("--parquet-buffer-size", opts.parquet_buffer_size),
+        ("--parquet-file-size", opts.parquet_file_size),
+        ("--max-row-group-size", opts.max_row_group_size),
+        ("--timeslot-buffer", opts.timeslot_buffer),
+    ];
+    for (flag, value) in nonzero {
+        if value == 0 {
+            problems.push(format!("{} must be greater than 0", flag));
+        }
+    }
+    if opts.parquet_buffer_size > opts.parquet_file_size && opts.parquet_file_size > 0 {
+        problems.push(format!(
+            "--parquet-buffer-size ({} bytes) must not exceed --parquet-file-size ({} bytes)",
+            opts.parquet_buffer_size, opts.parquet_file_size
+        ));
+    }
+    problems
+}
+
+/// Validate the S3 request tuning, which the collector also checks at startup.
+///
+/// Without permits every upload would wait forever, and S3 rejects the parts of
+/// a multipart upload below its minimum part size.
+pub fn check_s3_tuning(opts: &Command) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(threshold) = opts.s3_multipart_threshold {
+        if threshold < S3_MIN_PART_SIZE {
+            problems.push(format!(
+                "--s3-multipart-threshold ({} bytes) is below the {} bytes S3 requires for upload parts",
+                threshold, S3_MIN_PART_SIZE
+            ));
+        }
+    }
+    if opts.s3_max_concurrency == Some(0) {
+        problems.push("--s3-max-concurrency must be greater than 0".to_string());
+    }
+    problems
+}
+
+/// Validate the collector configuration without starting collection.
+///
+/// Returns every problem found (empty when the configuration looks usable). `env`
+/// looks up environment variables so tests can inject their own.
+pub fn check_config<F>(opts: &Command, node_id: &str, env: F) -> Vec<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut problems = Vec::new();
+    let env_set = |key: &str| env(key).is_some_and(|v| !v.is_empty());
+
+    // Storage backend
+    match opts.storage_type.to_lowercase().as_str() {
+        "s3" => {
+            if !S3_BUCKET_VARS.iter().any(|k| env_set(k)) {
+                problems.push(format!(
+                    "s3 storage: bucket not configured (set one of {})",
+                    S3_BUCKET_VARS.join(", ")
+                ));
+            }
+            let has_key_id = env_set("AWS_ACCESS_KEY_ID");
+            let has_secret = env_set("AWS_SECRET_ACCESS_KEY");
+            if has_key_id != has_secret {
+                problems.push(
+                    "s3 storage: AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set together"
+                        .to_string(),
+                );
+            }
+            // Without keys the default chain (web identity, container or instance
+            // metadata) supplies credentials; the write probe in `check_runtime`
+            // tells whether it works
+        }
+        "local" => {
+            for prefix in output_prefixes(opts, node_id) {
+                if let Err(e) = check_local_writable(&prefix) {
+                    problems.push(format!("local storage: {}", e));
+                }
+            }
+        }
+        "stdout" => {
+            if opts.enable_resctrl {
+                problems.push(
+                    "stdout storage: --enable-resctrl writes Parquet files and needs 'local' or 's3' storage"
+                        .to_string(),
+                );
+            }
+            if !opts.output_format.eq_ignore_ascii_case("parquet") {
+                problems.push(
+                    "stdout storage: rows are printed as JSON lines, so --output-format does not apply"
+                        .to_string(),
+                );
+            }
+        }
+        other => problems.push(format!(
+            "unknown storage type '{}' (expected 'local', 's3' or 'stdout')",
+            other
+        )),
+    }
+
+    match opts.output_format.to_lowercase().as_str() {
+        "parquet" | "arrow-ipc" => {}
+        other => problems.push(format!(
+            "unknown --output-format '{}' (expected 'parquet' or 'arrow-ipc')",
+            other
+        )),
+    }
+
+    problems.extend(check_sizes(opts));
+    if let Err(e) = crate::metrics::PerfEvents::parse(&opts.perf_events) {
+        problems.push(format!("--perf-events: {}", e));
+    }
+    match crate::timeslot_data::Granularity::parse(&opts.granularity) {
+        Ok(crate::timeslot_data::Granularity::Thread) if opts.trace => problems.push(
+            "--granularity thread only applies to timeslot mode; trace rows are per process"
+                .to_string(),
+        ),
+        Ok(_) => {}
+        Err(e) => problems.push(format!("--granularity: {}", e)),
+    }
+    if let Some(Err(e)) = opts
+        .comm_filter
+        .as_deref()
+        .map(crate::comm_filter::CommFilter::parse)
+    {
+        problems.push(format!("--comm-filter: {}", e));
+    }
+    if let Some(quota) = opts.storage_quota {
+        if quota < opts.parquet_file_size && opts.duration == 0 {
+            problems.push(format!(
+                "--storage-quota ({} bytes) is smaller than --parquet-file-size ({} bytes) \
+                 with unlimited duration; collection will stop once the quota is reached",
+                quota, opts.parquet_file_size
+            ));
+        }
+    }
+
+    match opts.storage_failure_policy.to_lowercase().as_str() {
+        "exit" => {}
+        "spill" => {
+            // The spill directory is created on demand, so its parent must be writable
+            if let Err(e) = check_local_writable(opts.spill_dir.trim_start_matches('/')) {
+                problems.push(format!("--spill-dir: {}", e));
+            }
+        }
+        other => problems.push(format!(
+            "unknown --storage-failure-policy '{}' (expected 'exit' or 'spill')",
+            other
+        )),
+    }
+
+    // Tee stores
+    for spec in &opts.tee {
+        match spec.split_once(':') {
+            Some(("local", dir)) if !dir.is_empty() => {
+                if let Err(e) = check_local_writable(dir.trim_start_matches('/')) {
+                    problems.push(format!("--tee {}: {}", spec, e));
+                }
+            }
+            None if spec.eq_ignore_ascii_case("s3") => {
+                if !S3_BUCKET_VARS.iter().any(|k| env_set(k)) {
+                    problems.push(format!(
+                        "--tee s3: bucket not configured (set one of {})",
+                        S3_BUCKET_VARS.join(", ")
+                    ));
+                }
+            }
+            _ => problems.push(format!(
+                "invalid --tee '{}' (expected 's3' or 'local:<DIR>')",
+                spec
+            )),
+        }
+    }
+    if let Err(e) = crate::tee::TeeFailurePolicy::parse(&opts.tee_failure_policy) {
+        problems.push(format!("--tee-failure-policy: {}", e));
+    }
+
+    problems.extend(check_s3_tuning(opts));
+
+    // Health server address
+    if opts.health_addr.parse::<SocketAddr>().is_err() {
+        problems.push(format!(
+            "--health-addr '{}' is not a valid socket address",
+            opts.health_addr
+        ));
+    }
+
+    // Control socket directory
+    if let Some(path) = &opts.control_socket {
+        let dir = Path::new(path)
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if !dir.is_dir() {
+            problems.push(format!(
+                "--control-socket: directory {} does not exist",
+                dir.display()
+            ));
+        }
+    }
+
+    // Feature prerequisites
+    if opts.enable_resctrl {
+        let mount = env("RESCTRL_MOUNT")
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| "/sys/fs/resctrl".to_string());
+        if !Path::new(&mount).is_dir() {
+            problems.push(format!(
+                "--enable-resctrl: resctrl mountpoint {} does not exist (kernel lacks resctrl?)",
+                mount
+            ));
+        }
+        let endpoint = NriEndpoint::resolve(
+            opts.nri_socket_path.as_deref(),
+            opts.nri_plugin_idx.as_deref(),
+        );
+        if !Path::new(&endpoint.socket_path).exists() {
+            problems.push(format!(
+                "--enable-resctrl: NRI socket {} not found",
+                endpoint.socket_path
+            ));
+        }
+    }
+
+    if opts.resctrl_metrics && !opts.enable_resctrl {
+        problems.push("--resctrl-metrics requires --enable-resctrl".to_string());
+    }
+    if opts.resctrl_cleanup_dry_run && !opts.enable_resctrl {
+        problems.push("--resctrl-cleanup-dry-run requires --enable-resctrl".to_string());
+    }
+
+    problems
+}
+
+/// Validate that the node can run the collector, without collecting.
+///
+/// Opens the configured object store and writes and deletes a marker object
+/// under every output prefix, then loads the BPF skeleton (and its sync timer)
+/// without attaching any probes. Returns every problem found.
+pub async fn check_runtime(opts: &Command, node_id: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // Stdout output writes nothing to storage
+    match crate::create_object_storage(&opts.storage_type, opts.s3_max_concurrency) {
+        _ if opts.storage_type.eq_ignore_ascii_case("stdout") => {}
+        Ok(store) => {
+            for prefix in output_prefixes(opts, node_id) {
+                if let Err(e) = check_store_writable(store.as_ref(), &prefix).await {
+                    problems.push(format!("{} storage: {}", opts.storage_type, e));
+                }
+            }
+        }
+        Err(e) => problems.push(format!(
+            "{} storage: failed to open object store: {}",
+            opts.storage_type, e
+        )),
+    }
+
+    match SyncTimer::start(opts.sync_interval_us.saturating_mul(1_000)) {
+        Ok(mut sync_timer) => {
+            let perf_ring_pages = if opts.trace {
+                crate::TRACE_PERF_RING_PAGES
+            } else {
+                crate::TIMESLOT_PERF_RING_PAGES
+            };
+            // Loading the skeleton verifies the programs and opens the selected
+            // counters; nothing is attached
+            let counters = crate::metrics::PerfEvents::parse(&opts.perf_events)
+                .unwrap_or_default()
+                .hardware_counters();
+            let cpus = match (opts.cpu_list.as_deref(), libbpf_rs::num_possible_cpus()) {
+                (Some(spec), Ok(num_cpus)) => {
+                    crate::cpu_list::parse_cpu_list(spec, num_cpus).map(Some)
+                }
+                _ => Ok(None),
+            };
+            match cpus {
+                Ok(cpus) => {
+                    if let Err(e) = BpfLoader::with_counters(
+                        perf_ring_pages,
+                        &mut sync_timer,
+                        &counters,
+                        cpus.as_deref(),
+                    ) {
+                        problems.push(format!("bpf: failed to load collector programs: {}", e));
+                    }
+                }
+                Err(e) => problems.push(format!("--cpu-list: {}", e)),
+            }
+        }
+        Err(e) => problems.push(format!("bpf: failed to start sync timer: {}", e)),
+    }
+
+    problems
+}
+
+/// Put and delete a small marker object under `prefix` to confirm the store
+/// accepts writes with the current credentials.
+async fn check_store_writable(store: &dyn ObjectStore, prefix: &str) -> Result<(), String> {
+    let marker = ObjectPath::from(format!("{}.collector-check-{}", prefix, Uuid::new_v4()));
+    store
+        .put(&marker, b"ok".to_vec().into())
+        .await
+        .map_err(|e| format!("cannot write marker object '{}': {}", marker, e))?;
+    store
+        .delete(&marker)
+        .await
+        .map_err(|e| format!("cannot delete marker object '{}': {}", marker, e))
+}
+
+/// Storage prefixes the collector will write under
+fn output_prefixes(opts: &Command, node_id: &str) -> Vec<String> {
+    let mut prefixes = vec![format!("{}{}", opts.prefix, node_id)];
+    if opts.enable_resctrl {
+        prefixes.push(format!("{}{}", opts.resctrl_prefix, node_id));
+    }
+    prefixes
+}
+
+/// Check that the directory a local storage prefix resolves to accepts new files.
+/// Local storage paths are rooted at `/`.
+fn check_local_writable(prefix: &str) -> Result<(), String> {
+    let full = Path::new("/").join(prefix);
+    let dir = full
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("/"));
+    if !dir.is_dir() {
+        return Err(format!("output directory {} does not exist", dir.display()));
+    }
+    let probe = dir.join(format!(".collector-check-{}", Uuid::new_v4()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => Err(format!(
+            "output directory {} is not writable: {}",
+            dir.display(),
+            e
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::env_from;
+    use clap::Parser;
+
+    #[test]
+    fn test_s3_missing_env_is_reported() {
+        let opts = Command::parse_from(["collector", "--check", "--storage-type", "s3"]);
+        let problems = check_config(&opts, "node", env_from(&[]));
+        assert!(problems.iter().any(|p| p.contains("bucket not configured")));
+    }
+
+    #[test]
+    fn test_s3_without_keys_uses_default_credential_chain() {
+        // Instance profiles and other default-chain setups set no credential variables
+        let opts = Command::parse_from(["collector", "--check", "--storage-type", "s3"]);
+        let problems = check_config(&opts, "node", env_from(&[("AWS_BUCKET_NAME", "metrics")]));
+        assert!(problems.is_empty(), "unexpected problems: {:?}", problems);
+
+        let problems = check_config(
+            &opts,
+            "node",
+            env_from(&[("AWS_BUCKET_NAME", "metrics"), ("AWS_ACCESS_KEY_ID", "id")]),
+        );
+        assert_eq!(problems.len(), 1, "problems: {:?}", problems);
+        assert!(problems[0].contains("must be set together"));
+    }
+
+    #[test]
+    fn test_s3_with_env_passes() {
+        let opts = Command::parse_from(["collector", "--check", "--storage-type", "s3"]);
+        let problems = check_config(
+            &opts,
+            "node",
+            env_from(&[
+                ("AWS_BUCKET_NAME", "metrics"),
+                ("AWS_ACCESS_KEY_ID", "id"),
+                ("AWS_SECRET_ACCESS_KEY", "secret"),
+            ]),
+        );
+        assert!(problems.is_empty(), "unexpected problems: {:?}", problems);
+    }
+
+    #[test]
+    fn test_invalid_s3_tuning_is_reported() {
+        let opts = Command::parse_from([
+            "collector",
+            "--check",
+            "--storage-type",
+            "s3",
+            "--s3-multipart-threshold",
+            "1048576",
+            "--s3-max-concurrency",
+            "0",
+        ]);
+        let problems = check_config(
+            &opts,
+            "node",
+            env_from(&[
+                ("AWS_BUCKET_NAME", "metrics"),
+                ("AWS_ACCESS_KEY_ID", "id"),
+                ("AWS_SECRET_ACCESS_KEY", "secret"),
+            ]),
+        );
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].contains("--s3-multipart-threshold (1048576 bytes)"));
+        assert!(problems[1].contains("--s3-max-concurrency must be greater than 0"));
+    }
+
+    #[test]
+    fn test_s3_tuning_checks() {
+        let tuning = |args: &[&str]| {
+            let opts = Command::parse_from(["collector"].iter().chain(args));
+            check_s3_tuning(&opts)
+        };
+        assert!(tuning(&[]).is_empty());
+        assert!(tuning(&[
+            "--s3-multipart-threshold",
+            "5242880",
+            "--s3-max-concurrency",
+            "1"
+        ])
+        .is_empty());
+        assert_eq!(tuning(&["--s3-max-concurrency", "0"]).len(), 1);
+        assert_eq!(tuning(&["--s3-multipart-threshold", "5242879"]).len(), 1);
+    }
+
+    #[test]
+    fn test_stdout_storage_conflicts_are_reported() {
+        let opts = Command::parse_from(["collector", "--check", "--storage-type", "stdout"]);
+        assert!(check_config(&opts, "node", env_from(&[])).is_empty());
+
+        let opts = Command::parse_from([
+            "collector",
+            "--check",
+            "--storage-type",
+            "stdout",
+            "--output-format",
+            "arrow-ipc",
+            "--enable-resctrl",
+        ]);
+        let problems = check_config(&opts, "node", env_from(&[]));
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("--output-format does not apply")));
+        assert!(problems.iter().any(|p| p.contains("--enable-resctrl")));
+    }
+
+    #[test]
+    fn test_invalid_tee_is_reported() {
+        let opts = Command::parse_from([
+            "collector",
+            "--check",
+            "--storage-type",
+            "s3",
+            "--tee",
+            "gcs",
+            "--tee",
+            "s3",
+            "--tee-failure-policy",
+            "some",
+        ]);
+        let problems = check_config(
+            &opts,
+            "node",
+            env_from(&[
+                ("AWS_BUCKET_NAME", "metrics"),
+                ("AWS_ACCESS_KEY_ID", "id"),
+                ("AWS_SECRET_ACCESS_KEY", "secret"),
+            ]),
+        );
+        assert_eq!(problems.len(), 2, "problems: {:?}", problems);
+        assert!(problems[0].contains("invalid --tee 'gcs'"));
+        assert!(problems[1].contains("--tee-failure-policy"));
+    }
+
+    #[test]
+    fn test_size_checks() {
+        let sizes = |args: &[&str]| {
+            let opts = Command::parse_from(["collector"].iter().chain(args));
+            check_sizes(&opts)
+        };
+        assert!(sizes(&[]).is_empty(), "defaults: {:?}", sizes(&[]));
+        assert!(sizes(&[
+            "--parquet-buffer-size",
+            "1000",
+            "--parquet-file-size",
+            "1000"
+        ])
+        .is_empty());
+
+        let problems = sizes(&["--max-row-group-size", "0", "--timeslot-buffer", "0"]);
+        assert_eq!(
+            problems,
+            vec![
+                "--max-row-group-size must be greater than 0",
+                "--timeslot-buffer must be greater than 0",
+            ]
+        );
+
+        let problems = sizes(&[
+            "--parquet-buffer-size",
+            "2048",
+            "--parquet-file-size",
+            "1024",
+        ]);
+        assert_eq!(
+            problems,
+            vec!["--parquet-buffer-size (2048 bytes) must not exceed --parquet-file-size (1024 bytes)"]
+        );
+        // A zero file size is reported once, not also as smaller than the buffer
+        assert_eq!(sizes(&["--parquet-file-size", "0"]).len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_perf_event_is_reported() {
+        let opts =
+            Command::parse_from(["collector", "--check", "--perf-events", "cycles,branches"]);
+        let problems = check_config(&opts, "node", env_from(&[]));
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("--perf-events") && p.contains("branches")),
+            "problems: {:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn test_problems_are_reported_together() {
+        let opts = Command::parse_from([
+            "collector",
+            "--check",
+            "--storage-type",
+            "gcs",
+            "--max-row-group-size",
+            "0",
+            "--timeslot-buffer",
+            "0",
+            "--health-addr",
+            "not-an-addr",
+        ]);
+        let problems = check_config(&opts, "node", env_from(&[]));
+        assert_eq!(problems.len(), 4, "problems: {:?}", problems);
+    }
+
+    #[tokio::test]
+    async fn test_store_marker_is_written_and_removed() {
+        use futures::StreamExt;
+        use object_store::memory::InMemory;
+
+        let store = InMemory::new();
+        check_store_writable(&store, "metrics-node").await.unwrap();
+        let remaining: Vec<_> = store.list(None).collect().await;
+        assert!(remaining.is_empty(), "marker left behind: {:?}", remaining);
+    }
+
+    #[tokio::test]
+    async fn test_unwritable_store_is_reported() {
+        // A prefix pointing at a regular file cannot hold objects
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let store = object_store::local::LocalFileSystem::new_with_prefix(file.path()).unwrap();
+        let err = check_store_writable(&store, "metrics-node")
+            .await
+            .unwrap_err();
+        assert!(err.contains("cannot write marker object"), "{}", err);
+    }
+}
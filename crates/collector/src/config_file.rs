@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Parser};
+use serde::Deserialize;
+
+use crate::error::{CollectorError, Result};
+
+/// Name of the option pointing at the config file
+const CONFIG_OPTION: &str = "config";
+
+/// A value in the config file: a scalar, or a list for repeatable options
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+}
+
+/// Parse `args` into `P`, taking options that are not given on the command line
+/// from the file named by `--config`.
+///
+/// The file is a YAML (`.yaml`/`.yml`) or TOML (`.toml`) mapping from long option
+/// names (`storage-type` or `storage_type`) to values. Flags take `true`/`false`
+/// and repeatable options take a list. Unknown options and values the option does
+/// not accept are reported as `CollectorError::Config`. Errors on the command
+/// line itself exit like `Parser::parse`. When a subcommand is given, `--config`
+/// and the file's options belong to that subcommand.
+pub fn parse_with_config<P, I, T>(args: I) -> Result<P>
+where
+    P: Parser,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let cmd = P::command();
+    let matches = cmd.clone().get_matches_from(&args);
+
+    // With a subcommand, the file sets that subcommand's options and its
+    // arguments go right after the subcommand name
+    let (sub_cmd, sub_matches, insert_at) = match matches.subcommand() {
+        Some((name, sub_matches)) => (
+            cmd.find_subcommand(name)
+                .expect("matched subcommand is defined"),
+            sub_matches,
+            args.iter()
+                .skip(1)
+                .position(|a| a == name)
+                .map_or(1, |i| i + 2),
+        ),
+        None => (&cmd, &matches, 1),
+    };
+
+    let config = sub_matches
+        .try_get_one::<String>(CONFIG_OPTION)
+        .ok()
+        .flatten();
+    let merged = match config {
+        Some(path) => {
+            let file_args = file_args(sub_cmd, sub_matches, Path::new(path))
+                .with_context(|| format!("config file {}", path))
+                .map_err(CollectorError::Config)?;
+            let mut merged = args[..insert_at].to_vec();
+            merged.extend(file_args);
+            merged.extend_from_slice(&args[insert_at..]);
+            cmd.try_get_matches_from(merged)
+                .map_err(|e| CollectorError::Config(anyhow!("config file {}: {}", path, e)))?
+        }
+        None => matches,
+    };
+    P::from_arg_matches(&merged).map_err(|e| CollectorError::Config(e.into()))
+}
+
+/// Command line arguments for the options set in the file at `path`, skipping
+/// those already given on the command line
+fn file_args(
+    cmd: &clap::Command,
+    matches: &ArgMatches,
+    path: &Path,
+) -> anyhow::Result<Vec<OsString>> {
+    let values = load(path)?;
+
+    let mut args = Vec::new();
+    for (key, value) in values {
+        let name = key.replace('_', "-");
+        let arg = cmd
+            .get_arguments()
+            .find(|a| a.get_long() == Some(name.as_str()) && name != CONFIG_OPTION)
+            .filter(|a| {
+                matches!(
+                    a.get_action(),
+                    ArgAction::Set | ArgAction::Append | ArgAction::SetTrue
+                )
+            })
+            .ok_or_else(|| anyhow!("unknown option '{}'", key))?;
+
+        // Options given on the command line take precedence
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let option_args = match (arg.get_action(), value) {
+            (ArgAction::SetTrue, Value::Bool(true)) => vec![format!("--{}", name)],
+            (ArgAction::SetTrue, Value::Bool(false)) => vec![],
+            (ArgAction::SetTrue, _) => bail!("'{}' must be true or false", key),
+            (ArgAction::Append, Value::List(items)) => items
+                .into_iter()
+                .map(|item| Ok(format!("--{}={}", name, scalar(&key, item)?)))
+                .collect::<anyhow::Result<_>>()?,
+            (_, value) => vec![format!("--{}={}", name, scalar(&key, value)?)],
+        };
+
+        // Check each option on its own so errors name the offending key
+        for option_arg in &option_args {
+            if cmd
+                .clone()
+                .try_get_matches_from([cmd.get_name(), option_arg.as_str()])
+                .is_err()
+            {
+                let value = option_arg.split_once('=').map_or("", |(_, v)| v);
+                bail!("invalid value '{}' for '{}'", value, key);
+            }
+        }
+        args.extend(option_args.into_iter().map(OsString::from));
+    }
+    Ok(args)
+}
+
+/// Render a single value as it would be written on the command line
+fn scalar(key: &str, value: Value) -> anyhow::Result<String> {
+    match value {
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Int(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::String(s) => Ok(s),
+        Value::List(_) => bail!("'{}' takes a single value", key),
+    }
+}
+
+/// Read the file as a mapping from option names to values, by extension
+fn load(path: &Path) -> anyhow::Result<BTreeMap<String, Value>> {
+    let text = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(toml::from_str(&text)?),
+        Some("yaml" | "yml") => Ok(serde_yaml::from_str(&text)?),
+        _ => bail!("unsupported format (expected a .toml, .yaml or .yml file)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Command;
+    use std::io::Write;
+
+    fn write_config(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    fn parse(args: &[&str]) -> Result<Command> {
+        parse_with_config(std::iter::once("collector").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_toml_values_are_applied() {
+        let file = write_config(
+            ".toml",
+            "storage-type = \"s3\"\nduration = 30\ntrace = true\ntee = [\"s3\", \"local:/tmp/copy\"]\n",
+        );
+        let opts = parse(&["--config", file.path().to_str().unwrap()]).unwrap();
+        assert_eq!(opts.storage_type, "s3");
+        assert_eq!(opts.duration, 30);
+        assert!(opts.trace);
+        assert_eq!(opts.tee, vec!["s3", "local:/tmp/copy"]);
+    }
+
+    #[test]
+    fn test_yaml_values_are_applied() {
+        let file = write_config(
+            ".yaml",
+            "storage_type: s3\nprefix: -metrics-\nverbose: true\n",
+        );
+        let opts = parse(&["--config", file.path().to_str().unwrap()]).unwrap();
+        assert_eq!(opts.storage_type, "s3");
+        assert_eq!(opts.prefix, "-metrics-");
+        assert!(opts.verbose);
+        // Options not in the file keep their defaults
+        assert_eq!(opts.duration, 0);
+    }
+
+    #[test]
+    fn test_command_line_overrides_file() {
+        let file = write_config(
+            ".toml",
+            "storage-type = \"s3\"\nduration = 30\ntee = [\"s3\"]\n",
+        );
+        let opts = parse(&[
+            "--duration",
+            "10",
+            "--config",
+            file.path().to_str().unwrap(),
+            "--tee",
+            "local:/tmp/copy",
+        ])
+        .unwrap();
+        assert_eq!(opts.duration, 10);
+        assert_eq!(opts.tee, vec!["local:/tmp/copy"]);
+        assert_eq!(opts.storage_type, "s3");
+    }
+
+    #[test]
+    fn test_file_applies_to_subcommand() {
+        #[derive(Debug, clap::Parser)]
+        #[command(args_conflicts_with_subcommands = true)]
+        struct Cli {
+            #[command(subcommand)]
+            subcommand: Option<Subcommand>,
+            #[command(flatten)]
+            collect: Command,
+        }
+
+        #[derive(Debug, clap::Subcommand)]
+        enum Subcommand {
+            Collect(Command),
+        }
+
+        let file = write_config(".toml", "storage-type = \"s3\"\nduration = 30\n");
+        let path = file.path().to_str().unwrap();
+
+        let cli: Cli =
+            parse_with_config(["collector", "collect", "--duration", "10", "--config", path])
+                .unwrap();
+        let Some(Subcommand::Collect(opts)) = cli.subcommand else {
+            panic!("expected the collect subcommand");
+        };
+        assert_eq!(opts.storage_type, "s3");
+        assert_eq!(opts.duration, 10);
+
+        // Without a subcommand the file applies to the top-level options
+        let cli: Cli = parse_with_config(["collector", "--config", path]).unwrap();
+        assert!(cli.subcommand.is_none());
+        assert_eq!(cli.collect.storage_type, "s3");
+        assert_eq!(cli.collect.duration, 30);
+    }
+
+    #[test]
+    fn test_invalid_files_are_rejected() {
+        let cases = [
+            (
+                ".toml",
+                "no-such-option = 1\n",
+                "unknown option 'no-such-option'",
+            ),
+            (
+                ".toml",
+                "config = \"other.toml\"\n",
+                "unknown option 'config'",
+            ),
+            (
+                ".toml",
+                "duration = \"soon\"\n",
+                "invalid value 'soon' for 'duration'",
+            ),
+            (
+                ".toml",
+                "trace = \"yes\"\n",
+                "'trace' must be true or false",
+            ),
+            (
+                ".toml",
+                "storage-type = [\"s3\"]\n",
+                "'storage-type' takes a single value",
+            ),
+            (".json", "{}", "unsupported format"),
+        ];
+        for (suffix, contents, expected) in cases {
+            let file = write_config(suffix, contents);
+            let err = parse(&["--config", file.path().to_str().unwrap()]).unwrap_err();
+            assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+            assert!(
+                err.to_string().contains(expected),
+                "{}: expected '{}' in '{}'",
+                contents,
+                expected,
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::io::ErrorKind;
+use std::os::unix::fs::FileTypeExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::CollectorError;
+
+/// Counters of the main data stream reported by the `status` command, updated by
+/// the pipeline as it runs
+#[derive(Debug)]
+pub struct CollectorStatus {
+    run_id: String,
+    started: Instant,
+    files_written: AtomicU64,
+    bytes_written: AtomicU64,
+    rows_dropped: AtomicU64,
+    timeslots_dropped: AtomicU64,
+}
+
+impl CollectorStatus {
+    pub fn new(run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            started: Instant::now(),
+            files_written: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            rows_dropped: AtomicU64::new(0),
+            timeslots_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a file closed in the object store, of `bytes` compressed bytes
+    pub fn record_file(&self, bytes: u64) {
+        self.files_written.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Count rows the writer accepted that will not reach a closed file
+    pub fn record_dropped_rows(&self, rows: u64) {
+        self.rows_dropped.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// Count a timeslot dropped because the conversion queue was full
+    pub fn record_dropped_timeslot(&self) {
+        self.timeslots_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render the status as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "run_id": self.run_id,
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "files_written": self.files_written.load(Ordering::Relaxed),
+            "bytes_written": self.bytes_written.load(Ordering::Relaxed),
+            "rows_dropped": self.rows_dropped.load(Ordering::Relaxed),
+            "timeslots_dropped": self.timeslots_dropped.load(Ordering::Relaxed),
+        })
+        .to_string()
+    }
+}
+
+/// Answer the commands of one connection, one reply line per command line
+async fn handle_connection(
+    stream: UnixStream,
+    status: Arc<CollectorStatus>,
+    rotate_sender: mpsc::Sender<()>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let reply = match line.trim() {
+            "" => continue,
+            "status" => status.to_json(),
+            "rotate" => {
+                debug!("Rotation requested on control socket");
+                match rotate_sender.send(()).await {
+                    Ok(()) => "ok".to_string(),
+                    Err(e) => format!("error: {}", e),
+                }
+            }
+            "shutdown" => {
+                info!("Shutdown requested on control socket");
+                shutdown.cancel();
+                "ok".to_string()
+            }
+            other => format!(
+                "error: unknown command '{}' (expected 'status', 'rotate' or 'shutdown')",
+                other
+            ),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+    }
+    Ok(())
+}
+
+/// Serve line commands on a Unix socket at `path`: `status` replies with the
+/// status as JSON, `rotate` rotates the main data stream like SIGUSR1, and
+/// `shutdown` stops the collector like SIGTERM. A stale socket file left at
+/// `path` is replaced, and the socket is removed on shutdown.
+pub async fn run(
+    path: String,
+    status: Arc<CollectorStatus>,
+    rotate_sender: mpsc::Sender<()>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    remove_stale_socket(&path)?;
+    let listener = UnixListener::bind(&path)?;
+    info!("Control socket listening on {}", path);
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                debug!("Control socket shutting down");
+                break;
+            }
+            accept_res = listener.accept() => {
+                match accept_res {
+                    Ok((stream, _peer)) => {
+                        let status = status.clone();
+                        let rotate_sender = rotate_sender.clone();
+                        let shutdown = shutdown.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) =
+                                handle_connection(stream, status, rotate_sender, shutdown).await
+                            {
+                                warn!("Control socket connection error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        error!("Control socket accept error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Remove a socket left at `path` by an earlier run. Anything else at `path` is
+/// left alone and reported as a configuration error.
+fn remove_stale_socket(path: &str) -> crate::error::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)
+            .map_err(|e| CollectorError::Config(anyhow!("control socket {}: {}", path, e))),
+        Ok(_) => Err(CollectorError::Config(anyhow!(
+            "control socket path {} exists and is not a socket",
+            path
+        ))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(CollectorError::Config(anyhow!(
+            "control socket {}: {}",
+            path,
+            e
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_rotate_and_shutdown_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        let path_str = path.to_str().unwrap().to_string();
+
+        let status = Arc::new(CollectorStatus::new("run-1"));
+        status.record_file(1000);
+        status.record_file(24);
+        status.record_dropped_rows(5);
+        status.record_dropped_timeslot();
+        let (rotate_tx, mut rotate_rx) = mpsc::channel::<()>(1);
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(run(path_str, status.clone(), rotate_tx, shutdown.clone()));
+
+        // Wait for the listener to bind
+        let stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let (reader, mut writer) = stream.into_split();
+        let mut replies = BufReader::new(reader).lines();
+
+        writer.write_all(b"status\n").await.unwrap();
+        let reply = replies.next_line().await.unwrap().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(json["run_id"], "run-1");
+        assert_eq!(json["files_written"], 2);
+        assert_eq!(json["bytes_written"], 1024);
+        assert_eq!(json["rows_dropped"], 5);
+        assert_eq!(json["timeslots_dropped"], 1);
+
+        writer.write_all(b"rotate\n").await.unwrap();
+        assert_eq!(replies.next_line().await.unwrap().unwrap(), "ok");
+        assert!(rotate_rx.try_recv().is_ok());
+
+        writer.write_all(b"bogus\n").await.unwrap();
+        let reply = replies.next_line().await.unwrap().unwrap();
+        assert!(
+            reply.starts_with("error: unknown command 'bogus'"),
+            "{}",
+            reply
+        );
+
+        writer.write_all(b"shutdown\n").await.unwrap();
+        assert_eq!(replies.next_line().await.unwrap().unwrap(), "ok");
+        assert!(shutdown.is_cancelled());
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_replace_non_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        std::fs::write(&path, b"keep me").unwrap();
+
+        let status = Arc::new(CollectorStatus::new("run-1"));
+        let (rotate_tx, _rotate_rx) = mpsc::channel::<()>(1);
+        let err = run(
+            path.to_str().unwrap().to_string(),
+            status,
+            rotate_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(err.downcast_ref(), Some(CollectorError::Config(_))),
+            "{:?}",
+            err
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"keep me");
+    }
+
+    #[tokio::test]
+    async fn test_replaces_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        // A socket left behind by an earlier run
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        remove_stale_socket(path.to_str().unwrap()).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::anyhow;
+
+use crate::error::{CollectorError, Result};
+
+/// Parse a CPU list such as `0-15,32` into sorted, deduplicated CPU IDs.
+///
+/// Entries are single CPUs or inclusive `START-END` ranges separated by commas.
+/// Every CPU must be below `num_cpus`.
+pub fn parse_cpu_list(spec: &str, num_cpus: usize) -> Result<Vec<usize>> {
+    let invalid =
+        |reason: String| CollectorError::Config(anyhow!("invalid CPU list '{}': {}", spec, reason));
+    let parse_cpu = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| invalid(format!("'{}' is not a CPU number", s.trim())))
+    };
+
+    let mut cpus = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (start, end) = match entry.split_once('-') {
+            Some((start, end)) => (parse_cpu(start)?, parse_cpu(end)?),
+            None => {
+                let cpu = parse_cpu(entry)?;
+                (cpu, cpu)
+            }
+        };
+        if start > end {
+            return Err(invalid(format!("range '{}' is reversed", entry)));
+        }
+        if end >= num_cpus {
+            return Err(invalid(format!(
+                "CPU {} does not exist (this machine has CPUs 0-{})",
+                end,
+                num_cpus.saturating_sub(1)
+            )));
+        }
+        cpus.extend(start..=end);
+    }
+    if cpus.is_empty() {
+        return Err(invalid("no CPUs selected".to_string()));
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges_and_single_cpus() {
+        assert_eq!(
+            parse_cpu_list("0-3, 8,2-4", 16).unwrap(),
+            vec![0, 1, 2, 3, 4, 8]
+        );
+        assert_eq!(parse_cpu_list("15", 16).unwrap(), vec![15]);
+    }
+
+    #[test]
+    fn test_invalid_lists_are_rejected() {
+        let cases = [
+            ("16", "CPU 16 does not exist"),
+            ("0-16", "CPU 16 does not exist"),
+            ("4-2", "range '4-2' is reversed"),
+            ("a", "'a' is not a CPU number"),
+            ("1-", "'' is not a CPU number"),
+            (",", "no CPUs selected"),
+        ];
+        for (spec, expected) in cases {
+            let err = parse_cpu_list(spec, 16).unwrap_err();
+            assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+            assert!(
+                err.to_string().contains(expected),
+                "{}: expected '{}' in '{}'",
+                spec,
+                expected,
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use arrow_schema::ArrowError;
+use parquet::errors::ParquetError;
+
+pub type Result<T, E = CollectorError> = std::result::Result<T, E>;
+
+/// Failure categories of the collector pipeline.
+///
+/// Variants wrap an `anyhow::Error` so the underlying cause and its context are
+/// kept while callers can still match on the category.
+#[derive(thiserror::Error, Debug)]
+pub enum CollectorError {
+    /// Invalid command line options or configuration
+    #[error("{0:#}")]
+    Config(anyhow::Error),
+
+    /// Loading, attaching or polling the BPF programs
+    #[error("bpf: {0:#}")]
+    Bpf(anyhow::Error),
+
+    /// Encoding output files or writing them to an object store
+    #[error("storage: {0:#}")]
+    Storage(anyhow::Error),
+
+    /// Building Arrow record batches
+    #[error("record batch: {0:#}")]
+    Arrow(anyhow::Error),
+
+    /// Connecting to the container runtime over NRI
+    #[error("nri: {0:#}")]
+    Nri(anyhow::Error),
+
+    /// A pipeline channel closed while the pipeline was still running
+    #[error("{0} channel closed")]
+    ChannelClosed(&'static str),
+}
+
+impl CollectorError {
+    /// Wrap the cause with additional context, keeping the category
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            CollectorError::Config(e) => CollectorError::Config(e.context(context)),
+            CollectorError::Bpf(e) => CollectorError::Bpf(e.context(context)),
+            CollectorError::Storage(e) => CollectorError::Storage(e.context(context)),
+            CollectorError::Arrow(e) => CollectorError::Arrow(e.context(context)),
+            CollectorError::Nri(e) => CollectorError::Nri(e.context(context)),
+            e @ CollectorError::ChannelClosed(_) => e,
+        }
+    }
+}
+
+impl From<object_store::Error> for CollectorError {
+    fn from(e: object_store::Error) -> Self {
+        CollectorError::Storage(e.into())
+    }
+}
+
+impl From<ParquetError> for CollectorError {
+    fn from(e: ParquetError) -> Self {
+        CollectorError::Storage(e.into())
+    }
+}
+
+impl From<serde_json::Error> for CollectorError {
+    fn from(e: serde_json::Error) -> Self {
+        CollectorError::Storage(e.into())
+    }
+}
+
+impl From<ArrowError> for CollectorError {
+    fn from(e: ArrowError) -> Self {
+        CollectorError::Arrow(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use arrow_array::{Int64Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use object_store::{memory::InMemory, path::Path, ObjectStore};
+
+    use crate::metrics::PerfEvents;
+    use crate::tee::TeeFailurePolicy;
+
+    #[test]
+    fn test_config_errors() {
+        let err = PerfEvents::parse("cycles,branches").unwrap_err();
+        assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("unknown perf event 'branches'"));
+
+        let err = TeeFailurePolicy::parse("some").unwrap_err();
+        assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+
+        let err = crate::logging::init("yaml").unwrap_err();
+        assert!(matches!(err, CollectorError::Config(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_storage_errors() {
+        let store = InMemory::new();
+        let err: CollectorError = store
+            .get(&Path::from("missing.parquet"))
+            .await
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, CollectorError::Storage(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("storage: "));
+
+        let err: CollectorError = ParquetError::General("bad footer".into()).into();
+        assert!(matches!(err, CollectorError::Storage(_)), "{:?}", err);
+
+        let err = err.context("closing file");
+        assert!(matches!(err, CollectorError::Storage(_)), "{:?}", err);
+        assert_eq!(
+            err.to_string(),
+            "storage: closing file: Parquet error: bad footer"
+        );
+    }
+
+    #[test]
+    fn test_arrow_errors() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Utf8, false)]));
+        let res: Result<RecordBatch> = (|| {
+            Ok(RecordBatch::try_new(
+                schema,
+                vec![Arc::new(Int64Array::from(vec![1]))],
+            )?)
+        })();
+        let err = res.unwrap_err();
+        assert!(matches!(err, CollectorError::Arrow(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("record batch: "));
+    }
+
+    #[test]
+    fn test_channel_closed_keeps_category() {
+        let err = CollectorError::ChannelClosed("metadata").context("ignored");
+        assert!(matches!(err, CollectorError::ChannelClosed("metadata")));
+        assert_eq!(err.to_string(), "metadata channel closed");
+    }
+}
@@ -9,7 +9,16 @@ use tokio_util::sync::CancellationToken;
 
 type ReadyFn = Arc<dyn Fn() -> bool + Send + Sync + 'static>;
 
-async fn handle_connection(mut stream: TcpStream, ready_fn: ReadyFn) -> Result<()> {
+/// Renders the body served on `/metrics` (OpenMetrics text format)
+pub type MetricsFn = Arc<dyn Fn() -> String + Send + Sync + 'static>;
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    ready_fn: ReadyFn,
+    metrics_fn: Option<MetricsFn>,
+) -> Result<()> {
     let mut buf = [0u8; 1024];
     let n = stream.read(&mut buf).await.unwrap_or(0);
     let req = String::from_utf8_lossy(&buf[..n]);
@@ -23,21 +32,30 @@ async fn handle_connection(mut stream: TcpStream, ready_fn: ReadyFn) -> Result<(
         }
     }
 
-    let (status_line, body) = match path {
-        "/live" => ("HTTP/1.1 200 OK\r\n", "live"),
-        "/ready" => {
+    let mut content_type = "text/plain";
+    let (status_line, body) = match (path, metrics_fn.as_ref()) {
+        ("/live", _) => ("HTTP/1.1 200 OK\r\n", "live".to_string()),
+        ("/ready", _) => {
             if (ready_fn)() {
-                ("HTTP/1.1 200 OK\r\n", "ready")
+                ("HTTP/1.1 200 OK\r\n", "ready".to_string())
             } else {
-                ("HTTP/1.1 503 Service Unavailable\r\n", "not ready")
+                (
+                    "HTTP/1.1 503 Service Unavailable\r\n",
+                    "not ready".to_string(),
+                )
             }
         }
-        _ => ("HTTP/1.1 404 Not Found\r\n", "not found"),
+        ("/metrics", Some(metrics_fn)) => {
+            content_type = OPENMETRICS_CONTENT_TYPE;
+            ("HTTP/1.1 200 OK\r\n", (metrics_fn)())
+        }
+        _ => ("HTTP/1.1 404 Not Found\r\n", "not found".to_string()),
     };
 
     let headers = format!(
-        "{}Content-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        "{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
         status_line,
+        content_type,
         body.len()
     );
     if let Err(e) = stream.write_all(headers.as_bytes()).await {
@@ -51,7 +69,13 @@ async fn handle_connection(mut stream: TcpStream, ready_fn: ReadyFn) -> Result<(
     Ok(())
 }
 
-pub async fn run(addr: String, ready_fn: ReadyFn, shutdown: CancellationToken) -> Result<()> {
+/// Serve `/live`, `/ready` and, when `metrics_fn` is provided, `/metrics`.
+pub async fn run(
+    addr: String,
+    ready_fn: ReadyFn,
+    metrics_fn: Option<MetricsFn>,
+    shutdown: CancellationToken,
+) -> Result<()> {
     let addr: SocketAddr = addr.parse()?;
     let listener = TcpListener::bind(addr).await?;
     info!("Health server listening on {}", addr);
@@ -66,8 +90,9 @@ pub async fn run(addr: String, ready_fn: ReadyFn, shutdown: CancellationToken) -
                 match accept_res {
                     Ok((stream, _peer)) => {
                         let ready_fn = ready_fn.clone();
+                        let metrics_fn = metrics_fn.clone();
                         tokio::spawn(async move {
-                            let _ = handle_connection(stream, ready_fn).await;
+                            let _ = handle_connection(stream, ready_fn, metrics_fn).await;
                         });
                     }
                     Err(e) => {
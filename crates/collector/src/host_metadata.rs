@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+
+use parquet::file::metadata::KeyValue;
+
+/// Location of the CPU description read at startup
+pub const CPUINFO_PATH: &str = "/proc/cpuinfo";
+
+/// Cache hierarchy of the first CPU (assumed representative of the node)
+pub const CPU0_CACHE_DIR: &str = "/sys/devices/system/cpu/cpu0/cache";
+
+/// Running kernel release
+pub const KERNEL_RELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+/// Memory statistics of the node
+pub const MEMINFO_PATH: &str = "/proc/meminfo";
+
+/// One level of the CPU cache hierarchy as reported by sysfs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheInfo {
+    /// Cache level (1, 2, 3, ...)
+    pub level: u32,
+    /// "Data", "Instruction" or "Unified"
+    pub cache_type: String,
+    /// Size as reported by the kernel, e.g. "32K"
+    pub size: String,
+}
+
+impl CacheInfo {
+    /// Metadata key for this cache, e.g. `cache_l1d_size` or `cache_l3_size`
+    fn key(&self) -> String {
+        let suffix = match self.cache_type.as_str() {
+            "Data" => "d",
+            "Instruction" => "i",
+            _ => "",
+        };
+        format!("cache_l{}{}_size", self.level, suffix)
+    }
+}
+
+/// Hardware and kernel description of the node, embedded in Parquet metadata so
+/// that files from heterogeneous nodes can be compared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostMetadata {
+    pub cpu_model: Option<String>,
+    pub kernel_version: Option<String>,
+    pub caches: Vec<CacheInfo>,
+}
+
+impl HostMetadata {
+    /// Read host metadata from the standard procfs/sysfs locations.
+    /// Missing or unreadable sources leave the corresponding fields empty.
+    pub fn collect() -> Self {
+        Self::from_paths(
+            Path::new(CPUINFO_PATH),
+            Path::new(CPU0_CACHE_DIR),
+            Path::new(KERNEL_RELEASE_PATH),
+        )
+    }
+
+    /// Same as [`HostMetadata::collect`] with explicit source paths
+    pub fn from_paths(cpuinfo_path: &Path, cache_dir: &Path, kernel_release_path: &Path) -> Self {
+        let cpu_model = fs::read_to_string(cpuinfo_path)
+            .ok()
+            .and_then(|s| parse_cpu_model(&s));
+        let kernel_version = fs::read_to_string(kernel_release_path)
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        Self {
+            cpu_model,
+            kernel_version,
+            caches: read_caches(cache_dir),
+        }
+    }
+
+    /// Key-value pairs to append to Parquet file metadata
+    pub fn to_key_values(&self) -> Vec<KeyValue> {
+        let mut kv = Vec::new();
+        if let Some(model) = &self.cpu_model {
+            kv.push(KeyValue {
+                key: "cpu_model".to_string(),
+                value: Some(model.clone()),
+            });
+        }
+        if let Some(kernel) = &self.kernel_version {
+            kv.push(KeyValue {
+                key: "kernel_version".to_string(),
+                value: Some(kernel.clone()),
+            });
+        }
+        for cache in &self.caches {
+            kv.push(KeyValue {
+                key: cache.key(),
+                value: Some(cache.size.clone()),
+            });
+        }
+        kv
+    }
+}
+
+/// Extract the CPU model from `/proc/cpuinfo` contents.
+///
+/// Uses the first `model name` entry (x86), falling back to `Hardware` or
+/// `cpu model` which some ARM and MIPS kernels report instead.
+pub fn parse_cpu_model(cpuinfo: &str) -> Option<String> {
+    let field = |name: &str| {
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name)
+                .then(|| value.trim().to_string())
+                .filter(|v| !v.is_empty())
+        })
+    };
+    field("model name")
+        .or_else(|| field("Hardware"))
+        .or_else(|| field("cpu model"))
+}
+
+/// Memory available for new allocations without swapping, in bytes, or None if
+/// `/proc/meminfo` cannot be read
+pub fn available_memory() -> Option<u64> {
+    parse_mem_available(&fs::read_to_string(MEMINFO_PATH).ok()?)
+}
+
+/// Extract `MemAvailable` from `/proc/meminfo` contents, in bytes
+pub fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix("MemAvailable:")?.trim();
+        let kib = value.strip_suffix("kB").unwrap_or(value).trim();
+        kib.parse::<u64>().ok().map(|kib| kib * 1024)
+    })
+}
+
+/// Read `index*/{level,type,size}` entries from a sysfs cache directory, sorted
+/// by level and type. Incomplete entries are skipped.
+fn read_caches(cache_dir: &Path) -> Vec<CacheInfo> {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return Vec::new();
+    };
+    let read = |dir: &Path, name: &str| {
+        fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+
+    let mut caches: Vec<CacheInfo> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("index"))
+        .filter_map(|e| {
+            let dir = e.path();
+            Some(CacheInfo {
+                level: read(&dir, "level")?.parse().ok()?,
+                cache_type: read(&dir, "type")?,
+                size: read(&dir, "size")?,
+            })
+        })
+        .collect();
+    caches.sort_by(|a, b| (a.level, &a.cache_type).cmp(&(b.level, &b.cache_type)));
+    caches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const SAMPLE_CPUINFO: &str = "processor\t: 0\n\
+vendor_id\t: GenuineIntel\n\
+cpu family\t: 6\n\
+model\t\t: 106\n\
+model name\t: Intel(R) Xeon(R) Platinum 8375C CPU @ 2.90GHz\n\
+\n\
+processor\t: 1\n\
+model name\t: Intel(R) Xeon(R) Platinum 8375C CPU @ 2.90GHz\n";
+
+    fn add_cache(dir: &Path, index: u32, level: &str, cache_type: &str, size: &str) {
+        let index_dir = dir.join(format!("index{}", index));
+        fs::create_dir_all(&index_dir).unwrap();
+        fs::write(index_dir.join("level"), format!("{}\n", level)).unwrap();
+        fs::write(index_dir.join("type"), format!("{}\n", cache_type)).unwrap();
+        fs::write(index_dir.join("size"), format!("{}\n", size)).unwrap();
+    }
+
+    #[test]
+    fn test_parse_mem_available() {
+        let meminfo = "MemTotal:       16318588 kB\nMemFree:         1234567 kB\nMemAvailable:    8000000 kB\n";
+        assert_eq!(parse_mem_available(meminfo), Some(8_000_000 * 1024));
+        assert_eq!(parse_mem_available("MemTotal: 16318588 kB\n"), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_model() {
+        assert_eq!(
+            parse_cpu_model(SAMPLE_CPUINFO).as_deref(),
+            Some("Intel(R) Xeon(R) Platinum 8375C CPU @ 2.90GHz")
+        );
+        assert_eq!(
+            parse_cpu_model("processor\t: 0\nHardware\t: BCM2835\n").as_deref(),
+            Some("BCM2835")
+        );
+        assert_eq!(parse_cpu_model("processor\t: 0\n"), None);
+    }
+
+    #[test]
+    fn test_from_paths_builds_metadata() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cpuinfo = tmp.path().join("cpuinfo");
+        fs::write(&cpuinfo, SAMPLE_CPUINFO).unwrap();
+        let osrelease = tmp.path().join("osrelease");
+        fs::write(&osrelease, "6.8.0-1015-aws\n").unwrap();
+
+        let cache_dir = tmp.path().join("cache");
+        add_cache(&cache_dir, 0, "1", "Data", "48K");
+        add_cache(&cache_dir, 1, "1", "Instruction", "32K");
+        add_cache(&cache_dir, 2, "2", "Unified", "1280K");
+        add_cache(&cache_dir, 3, "3", "Unified", "55296K");
+        // Non-index entries such as uevent are ignored
+        fs::write(cache_dir.join("uevent"), "").unwrap();
+
+        let meta = HostMetadata::from_paths(&cpuinfo, &cache_dir, &osrelease);
+        assert_eq!(meta.kernel_version.as_deref(), Some("6.8.0-1015-aws"));
+        assert_eq!(meta.caches.len(), 4);
+
+        let kv: HashMap<String, Option<String>> = meta
+            .to_key_values()
+            .into_iter()
+            .map(|kv| (kv.key, kv.value))
+            .collect();
+        assert_eq!(
+            kv["cpu_model"].as_deref(),
+            Some("Intel(R) Xeon(R) Platinum 8375C CPU @ 2.90GHz")
+        );
+        assert_eq!(kv["kernel_version"].as_deref(), Some("6.8.0-1015-aws"));
+        assert_eq!(kv["cache_l1d_size"].as_deref(), Some("48K"));
+        assert_eq!(kv["cache_l1i_size"].as_deref(), Some("32K"));
+        assert_eq!(kv["cache_l2_size"].as_deref(), Some("1280K"));
+        assert_eq!(kv["cache_l3_size"].as_deref(), Some("55296K"));
+    }
+
+    #[test]
+    fn test_missing_sources_yield_empty_metadata() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("missing");
+        let meta = HostMetadata::from_paths(&missing, &missing, &missing);
+        assert_eq!(meta, HostMetadata::default());
+        assert!(meta.to_key_values().is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::Write;
+
+use anyhow::anyhow;
+use chrono::{SecondsFormat, Utc};
+
+use crate::error::{CollectorError, Result};
+
+/// Initialize `env_logger` with the requested output format.
+///
+/// `text` keeps env_logger's default human-readable format; `json` emits one
+/// JSON object per line with `timestamp`, `level`, `target` and `message`.
+/// Log filtering is controlled by `RUST_LOG` in both cases.
+pub fn init(format: &str) -> Result<()> {
+    let mut builder = env_logger::Builder::from_default_env();
+    match format.to_lowercase().as_str() {
+        "text" => {}
+        "json" => {
+            builder.format(|buf, record| {
+                let line = json_line(
+                    &Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                    record.level().as_str(),
+                    record.target(),
+                    &record.args().to_string(),
+                );
+                writeln!(buf, "{}", line)
+            });
+        }
+        other => {
+            return Err(CollectorError::Config(anyhow!(
+                "unknown log format '{}' (expected 'text' or 'json')",
+                other
+            )))
+        }
+    }
+    builder
+        .try_init()
+        .map_err(|e| CollectorError::Config(e.into()))?;
+    Ok(())
+}
+
+/// Render a single log record as a JSON object
+fn json_line(timestamp: &str, level: &str, target: &str, message: &str) -> String {
+    serde_json::json!({
+        "timestamp": timestamp,
+        "level": level,
+        "target": target,
+        "message": message,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_line_fields_and_escaping() {
+        let line = json_line(
+            "2024-01-01T00:00:00.000Z",
+            "INFO",
+            "collector::parquet_writer",
+            "wrote \"file\"\nnext",
+        );
+        assert!(!line.contains('\n'));
+        let v: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(v["timestamp"], "2024-01-01T00:00:00.000Z");
+        assert_eq!(v["level"], "INFO");
+        assert_eq!(v["target"], "collector::parquet_writer");
+        assert_eq!(v["message"], "wrote \"file\"\nnext");
+    }
+}
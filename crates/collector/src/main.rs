@@ -3,10 +3,13 @@ use arrow_array::RecordBatch;
 use bpf::BpfLoader;
 use bpf_sync_timer::SyncTimer;
 use clap::Parser;
-use log::{debug, error, info};
-use object_store::ObjectStore;
+use log::{debug, error, info, warn};
+use nri::reconnect::ReconnectConfig;
+use nri::send_policy::SendPolicy;
+use object_store::{limit::LimitStore, ObjectStore};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -14,26 +17,49 @@ use tokio_util::task::TaskTracker;
 use uuid::Uuid;
 
 // Import local modules
+mod arrow_ipc_writer;
 mod bpf_error_handler;
 mod bpf_perf_to_timeslot;
 mod bpf_perf_to_trace;
 mod bpf_task_tracker;
 mod bpf_timeslot_tracker;
+mod comm_filter;
+mod config_check;
+mod config_file;
+mod control_socket;
+mod cpu_list;
+mod error;
 mod health_server;
+mod host_metadata;
+mod logging;
 mod metrics;
+mod ndjson_writer;
+mod nri_endpoint;
 mod nri_enrich_recordbatch_task;
 mod parquet_writer;
 mod parquet_writer_task;
 mod perf_event_processor;
+mod retry_store;
 mod task_metadata;
+mod tee;
+#[cfg(test)]
+mod test_utils;
 mod timeslot_data;
 mod timeslot_to_recordbatch_task;
 
+use arrow_ipc_writer::ArrowIpcWriter;
+use control_socket::CollectorStatus;
+use error::CollectorError;
+use metrics::PerfEvents;
+use ndjson_writer::NdjsonWriter;
+use nri_endpoint::NriEndpoint;
 use nri_enrich_recordbatch_task::NRIEnrichRecordBatchTask;
 use parquet_writer::{ParquetWriter, ParquetWriterConfig};
-use parquet_writer_task::ParquetWriterTask;
+use parquet_writer_task::{ParquetWriterTask, WriteFailurePolicy};
 use perf_event_processor::{PerfEventProcessor, ProcessorMode};
-use timeslot_data::TimeslotData;
+use retry_store::WriteRetry;
+use tee::{TeeConfig, TeeFailurePolicy, TeeStore};
+use timeslot_data::{Granularity, TimeslotData};
 use timeslot_to_recordbatch_task::TimeslotToRecordBatchTask;
 use tokio_helpers::task_completion_handler;
 
@@ -42,21 +68,78 @@ const TIMESLOT_PERF_RING_PAGES: u32 = 32;
 
 /// Number of perf ring buffer pages for trace mode (needs more buffering)
 const TRACE_PERF_RING_PAGES: u32 = 256;
-/// Interval for sync timer ticks (1 ms)
-const SYNC_TIMER_INTERVAL_NS: u64 = 1_000_000;
+
+/// Extra time after --drain-timeout for tasks to finish before the collector exits
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
 
 /// Linux process monitoring tool
 #[derive(Debug, Parser)]
+#[command(name = "collector", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    subcommand: Option<Subcommand>,
+
+    /// Collection options, used when no subcommand is given
+    #[command(flatten)]
+    collect: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Subcommand {
+    /// Collect performance data (the default when no subcommand is given)
+    Collect(Command),
+    /// Run an analysis over a collected Parquet trace file
+    Analyze(AnalyzeArgs),
+}
+
+/// Options for `collector analyze`
+#[derive(Debug, clap::Args)]
+struct AnalyzeArgs {
+    /// Input Parquet trace file, or a directory whose Parquet files are analyzed
+    /// in parallel
+    input: PathBuf,
+
+    /// Analysis to run: concurrency, hyperthread, monotonicity, cache
+    /// (cache-efficiency) or migration
+    #[arg(long, default_value = "hyperthread")]
+    analysis: String,
+
+    /// Output file prefix (defaults to the input file name without extension;
+    /// not allowed for a directory)
+    #[arg(long)]
+    output_prefix: Option<String>,
+
+    /// Restrict hyperthread peer analysis to a CPU range, e.g. '0-15'
+    #[arg(long)]
+    cpu_range: Option<String>,
+
+    /// Read hyperthread siblings from a sysfs CPU directory, e.g.
+    /// '/sys/devices/system/cpu'
+    #[arg(long)]
+    cpu_topology: Option<PathBuf>,
+}
+
+/// Options for collecting performance data
+#[derive(Debug, Parser)]
 struct Command {
+    /// Read options from this YAML (.yaml/.yml) or TOML (.toml) file; options
+    /// given on the command line take precedence
+    #[arg(long)]
+    config: Option<String>,
+
     /// Verbose debug output
     #[arg(short, long)]
     verbose: bool,
 
+    /// Log output format: "text" (human-readable) or "json" (one object per line)
+    #[arg(long, default_value = "text")]
+    log_format: String,
+
     /// Track duration in seconds (0 = unlimited)
     #[arg(short, long, default_value = "0")]
     duration: u64,
 
-    /// Storage type (local or s3)
+    /// Storage type (local, s3, or stdout to print rows as JSON lines)
     #[arg(long, default_value = "local")]
     storage_type: String,
 
@@ -64,6 +147,15 @@ struct Command {
     #[arg(short, long, default_value = "unvariance-metrics-")]
     prefix: String,
 
+    /// Output format: "parquet" files in the configured storage, or an "arrow-ipc"
+    /// stream written to --ipc-output (resctrl data is always written as Parquet)
+    #[arg(long, default_value = "parquet")]
+    output_format: String,
+
+    /// Destination of the Arrow IPC stream: a file path, or '-' for stdout
+    #[arg(long, default_value = "-")]
+    ipc_output: String,
+
     /// Maximum memory buffer size before flushing (bytes)
     #[arg(long, default_value = "104857600")] // 100MB
     parquet_buffer_size: usize,
@@ -76,25 +168,185 @@ struct Command {
     #[arg(long, default_value = "1048576")]
     max_row_group_size: usize,
 
+    /// Rotate a Parquet file holding data after this many seconds, even if it is below
+    /// the size limit (0 = rotate on size only)
+    #[arg(long, default_value = "0")]
+    parquet_flush_interval: u64,
+
+    /// Write Parquet files under hourly `dt=<YYYY-MM-DD>/hr=<HH>/` partitions (UTC),
+    /// from the first timestamp in each file
+    #[arg(long, default_value = "false")]
+    partition_by_time: bool,
+
+    /// Seconds the Parquet writers may take to finish after a shutdown signal: queued
+    /// data is written during the first half, the rest is left to close the current
+    /// file, which is abandoned if that does not complete in time. Keep it below the
+    /// pod's termination grace period
+    #[arg(long, default_value = "10")]
+    drain_timeout: u64,
+
     /// Maximum total bytes to write to object store
     #[arg(long)]
     storage_quota: Option<usize>,
 
+    /// Seconds of continuous Parquet write failures tolerated before applying
+    /// --storage-failure-policy (0 = apply on the first failure)
+    #[arg(long, default_value = "60")]
+    storage_failure_timeout: u64,
+
+    /// What to do after sustained write failures: "exit" with an error, or "spill"
+    /// subsequent files to --spill-dir on local disk
+    #[arg(long, default_value = "exit")]
+    storage_failure_policy: String,
+
+    /// Local directory used by the "spill" storage failure policy
+    #[arg(long, default_value = "/var/tmp/collector-spill")]
+    spill_dir: String,
+
+    /// Also copy every closed Parquet file to this store: "s3" (configured from the
+    /// environment) or "local:<DIR>". May be repeated
+    #[arg(long = "tee")]
+    tee: Vec<String>,
+
+    /// When copying to a --tee store fails: "all" fails the write (and applies
+    /// --storage-failure-policy), "any" continues since the primary store has the file
+    #[arg(long, default_value = "any")]
+    tee_failure_policy: String,
+
+    /// Copy attempts per --tee store and file
+    #[arg(long, default_value = "3")]
+    tee_max_attempts: u32,
+
+    /// S3 only: upload Parquet files larger than this many bytes in parts of this
+    /// size (at least 5 MiB; default 10 MiB)
+    #[arg(long)]
+    s3_multipart_threshold: Option<usize>,
+
+    /// S3 only: maximum concurrent requests to each S3 store, including the parts
+    /// of multipart uploads (default: unlimited)
+    #[arg(long)]
+    s3_max_concurrency: Option<usize>,
+
     /// Enable trace mode (outputs individual events instead of aggregated timeslots)
     #[arg(long, default_value = "false")]
     trace: bool,
 
-    /// Enable resctrl LLC occupancy collection (1 Hz)
+    /// Period of the BPF sync timer in microseconds, which is also the timeslot
+    /// duration; longer periods lower overhead at the cost of time resolution
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    sync_interval_us: u64,
+
+    /// Aggregate tasks that exit within this many microseconds into one "<ephemeral>"
+    /// row per cgroup instead of per-PID rows (timeslot mode only, 0 = disabled)
+    #[arg(long, default_value = "0")]
+    min_task_lifetime_us: u64,
+
+    /// Merge this many consecutive timeslots into each emitted timeslot, summing
+    /// per-PID metrics (timeslot mode only, 1 = no downsampling)
+    #[arg(long, default_value = "1")]
+    downsample_factor: u32,
+
+    /// Timeslots queued between the BPF processor and Parquet conversion before
+    /// new ones are dropped (timeslot mode only). Each queued timeslot holds a row
+    /// per active task, so memory grows with this times the task count
+    #[arg(long, default_value = "1000")]
+    timeslot_buffer: usize,
+
+    /// Maximum number of tasks whose metadata is kept; the tasks added longest
+    /// ago are evicted beyond this, bounding memory if task exit events are lost
+    /// (0 = unbounded)
+    #[arg(long, default_value = "1048576")]
+    max_tracked_tasks: usize,
+
+    /// Record timeslot rows per 'process' or per 'thread'. Thread rows add a 'tid'
+    /// column; 'pid' is the thread's process either way
+    #[arg(long, default_value = "process")]
+    granularity: String,
+
+    /// Only record tasks whose command name matches, e.g. 'nginx,redis*,!redis-cli'.
+    /// '!' denies; globs match the whole name, other patterns any part of it
+    #[arg(long)]
+    comm_filter: Option<String>,
+
+    /// Comma-separated hardware counters to collect (cycles, instructions, llc_misses,
+    /// cache_references); the others are written as nulls
+    #[arg(
+        long,
+        default_value = "cycles,instructions,llc_misses,cache_references"
+    )]
+    perf_events: String,
+
+    /// Only measure these CPUs, e.g. '0-15,32' (default: all CPUs). Other CPUs
+    /// produce no rows
+    #[arg(long)]
+    cpu_list: Option<String>,
+
+    /// On clean shutdown, write a JSON summary of the run (files, row and drop
+    /// counts, start and end times) next to the Parquet files
+    #[arg(long, default_value = "false")]
+    run_summary: bool,
+
+    /// Embed the node's CPU model, cache sizes and kernel version in Parquet file metadata
+    #[arg(long, default_value = "false")]
+    include_host_metadata: bool,
+
+    /// Enable per-pod resctrl monitoring (LLC occupancy and memory bandwidth, 1 Hz)
     #[arg(long, default_value = "false")]
     enable_resctrl: bool,
 
+    /// Serve per-pod resctrl gauges (LLC occupancy, memory bandwidth) in OpenMetrics
+    /// format on the health server's /metrics endpoint (requires --enable-resctrl)
+    #[arg(long, default_value = "false")]
+    resctrl_metrics: bool,
+
     /// Storage filename prefix for resctrl occupancy parquet files
     #[arg(long, default_value = "resctrl-occupancy-")]
     resctrl_prefix: String,
 
+    /// Only log which stale resctrl groups startup cleanup would remove, without
+    /// removing them (requires --enable-resctrl)
+    #[arg(long, default_value = "false")]
+    resctrl_cleanup_dry_run: bool,
+
     /// Address to bind the health HTTP server (for readiness/liveness)
     #[arg(long, default_value = "0.0.0.0:8080")]
     health_addr: String,
+
+    /// Unix socket accepting line commands: 'status' (JSON counters of the main
+    /// data stream), 'rotate' (like SIGUSR1) and 'shutdown' (like SIGTERM)
+    #[arg(long)]
+    control_socket: Option<String>,
+
+    /// Node name used in storage paths and file metadata (falls back to $NODE_NAME,
+    /// then the hostname)
+    #[arg(long)]
+    node_name: Option<String>,
+
+    /// NRI runtime socket path (falls back to $NRI_SOCKET_PATH, then /var/run/nri/nri.sock)
+    #[arg(long)]
+    nri_socket_path: Option<String>,
+
+    /// NRI plugin index for ordering among plugins (falls back to $NRI_PLUGIN_IDX, then 10)
+    #[arg(long)]
+    nri_plugin_idx: Option<String>,
+
+    /// Maximum consecutive NRI reconnection attempts before exiting (0 = exit on disconnect)
+    #[arg(long, default_value = "5")]
+    nri_max_reconnect_attempts: u32,
+
+    /// Maximum delay between NRI reconnection attempts (seconds)
+    #[arg(long, default_value = "30")]
+    nri_max_reconnect_backoff: u64,
+
+    /// How long NRI metadata events may wait for a full channel before being
+    /// dropped (milliseconds; 0 = drop immediately)
+    #[arg(long, default_value = "0")]
+    nri_event_send_timeout_ms: u64,
+
+    /// Validate the configuration, storage access and BPF loading, report all
+    /// problems, and exit without attaching probes or collecting
+    #[arg(long, default_value = "false")]
+    check: bool,
 }
 
 /// Duration timeout handler - exits when duration completes or cancellation token is triggered
@@ -162,12 +414,18 @@ async fn rotation_handler(
 }
 
 // Create object store based on storage type
-fn create_object_storage(storage_type: &str) -> Result<Arc<dyn ObjectStore>> {
+fn create_object_storage(
+    storage_type: &str,
+    s3_max_concurrency: Option<usize>,
+) -> error::Result<Arc<dyn ObjectStore>> {
     match storage_type.to_lowercase().as_str() {
         "s3" => {
             debug!("Creating S3 object store from environment variables");
             let s3 = object_store::aws::AmazonS3Builder::from_env().build()?;
-            Ok(Arc::new(s3))
+            match s3_max_concurrency {
+                Some(max) => Ok(Arc::new(LimitStore::new(s3, max))),
+                None => Ok(Arc::new(s3)),
+            }
         }
         _ => {
             debug!("Creating local filesystem object store");
@@ -177,47 +435,229 @@ fn create_object_storage(storage_type: &str) -> Result<Arc<dyn ObjectStore>> {
     }
 }
 
-/// Find node identity for file path construction
-fn get_node_identity() -> String {
-    // Try to get hostname
-    if let Ok(name) = hostname::get() {
-        if let Ok(name_str) = name.into_string() {
-            return name_str;
+/// Create the store for one `--tee` spec ("s3" or "local:<DIR>")
+fn create_tee_store(
+    spec: &str,
+    s3_max_concurrency: Option<usize>,
+) -> error::Result<Arc<dyn ObjectStore>> {
+    match spec.split_once(':') {
+        Some(("local", dir)) if !dir.is_empty() => {
+            std::fs::create_dir_all(dir).map_err(|e| CollectorError::Storage(e.into()))?;
+            Ok(Arc::new(
+                object_store::local::LocalFileSystem::new_with_prefix(dir)?,
+            ))
         }
+        None if spec.eq_ignore_ascii_case("s3") => create_object_storage("s3", s3_max_concurrency),
+        _ => Err(CollectorError::Config(anyhow!(
+            "invalid --tee '{}' (expected 's3' or 'local:<DIR>')",
+            spec
+        ))),
     }
+}
 
-    // Fallback to a UUID if hostname is not available
-    Uuid::new_v4().to_string().chars().take(8).collect()
+/// Build the tee configuration from the command line, if any --tee store is given
+fn create_tee_config(opts: &Command) -> error::Result<Option<Arc<TeeConfig>>> {
+    if opts.tee.is_empty() {
+        return Ok(None);
+    }
+    let stores = opts
+        .tee
+        .iter()
+        .map(|spec| {
+            Ok(TeeStore {
+                name: spec.clone(),
+                store: create_tee_store(spec, opts.s3_max_concurrency)?,
+            })
+        })
+        .collect::<error::Result<Vec<_>>>()?;
+    Ok(Some(Arc::new(TeeConfig {
+        stores,
+        policy: TeeFailurePolicy::parse(&opts.tee_failure_policy)?,
+        max_attempts: opts.tee_max_attempts,
+        retry_backoff: Duration::from_secs(1),
+    })))
+}
+
+/// Build the writer failure policy from the command line
+fn create_write_failure_policy(policy: &str, spill_dir: &str) -> error::Result<WriteFailurePolicy> {
+    match policy.to_lowercase().as_str() {
+        "exit" => Ok(WriteFailurePolicy::Terminate),
+        "spill" => {
+            std::fs::create_dir_all(spill_dir).map_err(|e| CollectorError::Storage(e.into()))?;
+            let local = object_store::local::LocalFileSystem::new_with_prefix(spill_dir)?;
+            Ok(WriteFailurePolicy::Spill(Arc::new(local)))
+        }
+        other => Err(CollectorError::Config(anyhow!(
+            "unknown storage failure policy '{}' (expected 'exit' or 'spill')",
+            other
+        ))),
+    }
+}
+
+/// Offset from the monotonic clock of BPF timestamps to the Unix epoch, in nanoseconds
+fn monotonic_to_epoch_offset_ns() -> i64 {
+    let epoch_ns = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as i64;
+    epoch_ns - bpf::now_monotonic_ns() as i64
+}
+
+/// Environment variable consulted for the node name, as set from `spec.nodeName`
+/// through the Kubernetes downward API
+const NODE_NAME_ENV: &str = "NODE_NAME";
+
+/// Find node identity for file path construction: the `--node-name` flag, then
+/// `$NODE_NAME`, then the hostname. Empty values are treated as unset.
+fn get_node_identity(cli_node_name: Option<&str>) -> String {
+    let env_node_name = std::env::var(NODE_NAME_ENV).ok();
+    let hostname = hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok());
+    select_node_identity(cli_node_name, env_node_name.as_deref(), hostname.as_deref())
+        // Fallback to a UUID if hostname is not available
+        .unwrap_or_else(|| Uuid::new_v4().to_string().chars().take(8).collect())
+}
+
+/// Pick the first non-empty name of the flag and `$NODE_NAME`, else the hostname
+fn select_node_identity(
+    cli_node_name: Option<&str>,
+    env_node_name: Option<&str>,
+    hostname: Option<&str>,
+) -> Option<String> {
+    [cli_node_name, env_node_name]
+        .into_iter()
+        .flatten()
+        .find(|name| !name.is_empty())
+        .or(hostname)
+        .map(str::to_string)
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize env_logger
-    env_logger::init();
+    let cli: Cli = config_file::parse_with_config(std::env::args_os())?;
+    let opts = match cli.subcommand {
+        Some(Subcommand::Analyze(args)) => {
+            return trace_analysis::run(&trace_analysis::AnalyzeOptions {
+                filename: args.input,
+                output_prefix: args.output_prefix,
+                analysis_type: args.analysis,
+                cpu_range: args.cpu_range,
+                cpu_topology: args.cpu_topology,
+            });
+        }
+        Some(Subcommand::Collect(opts)) => opts,
+        None => cli.collect,
+    };
 
-    let opts = Command::parse();
+    // Initialize env_logger in the requested format
+    logging::init(&opts.log_format)?;
 
     debug!("Starting collector with options: {:?}", opts);
 
     // Get node identity for file path
-    let node_id = get_node_identity();
+    let node_id = get_node_identity(opts.node_name.as_deref());
+
+    // Identify this run, so files from overlapping runs on a node can be told apart
+    let run_id = Uuid::new_v4().to_string();
+
+    // Dry-run: validate configuration, storage access and BPF loading, then exit
+    if opts.check {
+        let mut problems =
+            config_check::check_config(&opts, &node_id, |key| std::env::var(key).ok());
+        if problems.is_empty() {
+            problems = config_check::check_runtime(&opts, &node_id).await;
+        }
+        if problems.is_empty() {
+            println!("Configuration OK");
+            return Ok(());
+        }
+        for problem in &problems {
+            eprintln!("config error: {}", problem);
+        }
+        return Err(anyhow!("{} configuration problem(s) found", problems.len()));
+    }
+
+    let s3_problems = config_check::check_s3_tuning(&opts);
+    if !s3_problems.is_empty() {
+        return Err(CollectorError::Config(anyhow!(s3_problems.join("; "))).into());
+    }
 
     // Create object store based on storage type
-    let store = create_object_storage(&opts.storage_type)?;
+    let store = create_object_storage(&opts.storage_type, opts.s3_max_concurrency)?;
+    // The multipart threshold is tuned for S3 only
+    let multipart_threshold = opts
+        .s3_multipart_threshold
+        .filter(|_| opts.storage_type.eq_ignore_ascii_case("s3"));
+
+    // Hardware counters to collect
+    let perf_events = PerfEvents::parse(&opts.perf_events)?;
 
     // Determine the number of available CPUs
     let num_cpus = libbpf_rs::num_possible_cpus()?;
 
+    // CPUs to measure (None = all)
+    let cpu_list = opts
+        .cpu_list
+        .as_deref()
+        .map(|spec| cpu_list::parse_cpu_list(spec, num_cpus))
+        .transpose()?;
+
+    let granularity = Granularity::parse(&opts.granularity)?;
+
+    // Tasks to record (None = all)
+    let comm_filter = opts
+        .comm_filter
+        .as_deref()
+        .map(comm_filter::CommFilter::parse)
+        .transpose()?;
+
+    let size_problems = config_check::check_sizes(&opts);
+    if !size_problems.is_empty() {
+        return Err(CollectorError::Config(anyhow!(size_problems.join("; "))).into());
+    }
+    if let Some(available) = host_metadata::available_memory() {
+        if opts.parquet_buffer_size as u64 > available {
+            warn!(
+                "--parquet-buffer-size ({} bytes) exceeds the memory currently available ({} bytes); \
+                 buffered rows may be lost if the collector runs out of memory",
+                opts.parquet_buffer_size, available
+            );
+        }
+    }
+
     // Compose storage prefix with node identity for main stream
     let storage_prefix = format!("{}{}", opts.prefix, node_id);
 
     // Create CPU count metadata for parquet files
-    let cpu_metadata = vec![parquet::file::metadata::KeyValue {
+    let mut cpu_metadata = vec![parquet::file::metadata::KeyValue {
         key: "num_cpus".to_string(),
         value: Some(num_cpus.to_string()),
     }];
+    if let Some(spec) = &opts.cpu_list {
+        cpu_metadata.push(parquet::file::metadata::KeyValue {
+            key: "cpu_list".to_string(),
+            value: Some(spec.clone()),
+        });
+    }
+    if opts.include_host_metadata {
+        let host = host_metadata::HostMetadata::collect();
+        info!("Including host metadata in parquet files: {:?}", host);
+        cpu_metadata.extend(host.to_key_values());
+    }
+
+    // Additional stores receiving a copy of every closed file
+    let tee = create_tee_config(&opts)?;
+
+    // Counters reported on the control socket
+    let status = opts
+        .control_socket
+        .as_ref()
+        .map(|_| Arc::new(CollectorStatus::new(&run_id)));
 
     // Create ParquetWriterConfig with the storage prefix and metadata
+    let flush_interval =
+        (opts.parquet_flush_interval > 0).then(|| Duration::from_secs(opts.parquet_flush_interval));
     let config = ParquetWriterConfig {
         storage_prefix,
         buffer_size: opts.parquet_buffer_size,
@@ -225,6 +665,17 @@ async fn main() -> Result<()> {
         max_row_group_size: opts.max_row_group_size,
         storage_quota: opts.storage_quota,
         key_value_metadata: Some(cpu_metadata.clone()),
+        flush_interval,
+        node_id: Some(node_id.clone()),
+        write_manifest: true,
+        write_run_summary: opts.run_summary,
+        tee: tee.clone(),
+        partition_by_time: opts.partition_by_time,
+        time_offset_ns: monotonic_to_epoch_offset_ns(),
+        run_id: Some(run_id.clone()),
+        write_retry: WriteRetry::default(),
+        multipart_threshold,
+        status: status.clone(),
     };
 
     // Create channels for the pipeline
@@ -241,14 +692,17 @@ async fn main() -> Result<()> {
     // Configure processor mode and schema based on trace flag
     let (processor_mode, input_schema) = if opts.trace {
         // Trace mode: direct RecordBatch output
-        let schema = crate::bpf_perf_to_trace::create_schema();
+        let schema = crate::bpf_perf_to_trace::create_schema(&perf_events);
         (ProcessorMode::Trace(pre_enrich_sender), schema)
     } else {
         // Timeslot mode: aggregated output with conversion
-        let (timeslot_sender, timeslot_receiver) = mpsc::channel::<TimeslotData>(1000);
+        let (timeslot_sender, timeslot_receiver) =
+            mpsc::channel::<TimeslotData>(opts.timeslot_buffer);
 
         // Create the conversion task and get schema
-        let conversion_task = TimeslotToRecordBatchTask::new(timeslot_receiver, pre_enrich_sender);
+        let conversion_task = TimeslotToRecordBatchTask::new(timeslot_receiver, pre_enrich_sender)
+            .with_perf_events(perf_events)
+            .with_granularity(granularity);
         let schema = conversion_task.schema();
 
         // Spawn the conversion task
@@ -261,8 +715,24 @@ async fn main() -> Result<()> {
         (ProcessorMode::Timeslot(timeslot_sender), schema)
     };
 
+    // Resolve NRI connection settings (CLI flag > env > default)
+    let nri_endpoint = NriEndpoint::resolve(
+        opts.nri_socket_path.as_deref(),
+        opts.nri_plugin_idx.as_deref(),
+    );
+    debug!("Using NRI endpoint: {:?}", nri_endpoint);
+
     // Create the NRI enrichment task between conversion/trace and the writer
-    let enrich_task = NRIEnrichRecordBatchTask::new(input_schema.clone());
+    let enrich_task = NRIEnrichRecordBatchTask::new(input_schema.clone(), nri_endpoint.clone())
+        .with_reconnect(ReconnectConfig {
+            max_attempts: opts.nri_max_reconnect_attempts,
+            max_backoff: Duration::from_secs(opts.nri_max_reconnect_backoff),
+            ..Default::default()
+        })
+        .with_send_policy(match opts.nri_event_send_timeout_ms {
+            0 => SendPolicy::Drop,
+            ms => SendPolicy::BlockWithTimeout(Duration::from_millis(ms)),
+        });
     let schema = enrich_task.schema();
 
     // Spawn the enrichment task
@@ -272,29 +742,96 @@ async fn main() -> Result<()> {
         "NRIEnrichRecordBatchTask",
     ));
 
-    // Create the ParquetWriter with the appropriate schema
-    debug!(
-        "Writing {} data to {} storage with prefix: {}",
-        if opts.trace { "trace" } else { "timeslot" },
-        &opts.storage_type,
-        &config.storage_prefix
-    );
-    let writer = ParquetWriter::new(store.clone(), schema, config)?;
-
-    // Create ParquetWriterTask with pre-configured channels
-    let writer_task = ParquetWriterTask::new(writer, batch_receiver, rotate_receiver);
-
-    // Spawn the writer task with completion handler using task tracker
-    task_tracker.spawn(task_completion_handler(
-        writer_task.run(),
-        shutdown_token.clone(),
-        "ParquetWriterTask",
-    ));
+    let drain_timeout = Duration::from_secs(opts.drain_timeout);
+    let storage_failure_timeout = Duration::from_secs(opts.storage_failure_timeout);
+    match opts.output_format.to_lowercase().as_str() {
+        format if opts.storage_type.eq_ignore_ascii_case("stdout") => {
+            if format != "parquet" {
+                return Err(CollectorError::Config(anyhow!(
+                    "'stdout' storage prints JSON lines and cannot write output format '{}'",
+                    format
+                ))
+                .into());
+            }
+            if opts.enable_resctrl {
+                return Err(CollectorError::Config(anyhow!(
+                    "--enable-resctrl writes Parquet files and needs 'local' or 's3' storage"
+                ))
+                .into());
+            }
+            debug!(
+                "Printing {} data to stdout as JSON lines",
+                if opts.trace { "trace" } else { "timeslot" }
+            );
+
+            // Like a stream, stdout cannot recover from a failed write
+            let writer_task =
+                ParquetWriterTask::new(NdjsonWriter::stdout(), batch_receiver, rotate_receiver)
+                    .with_drain(shutdown_token.clone(), drain_timeout);
+
+            task_tracker.spawn(task_completion_handler(
+                writer_task.run(),
+                shutdown_token.clone(),
+                "NdjsonWriterTask",
+            ));
+        }
+        "parquet" => {
+            // Create the ParquetWriter with the appropriate schema
+            debug!(
+                "Writing {} data to {} storage with prefix: {}",
+                if opts.trace { "trace" } else { "timeslot" },
+                &opts.storage_type,
+                &config.storage_prefix
+            );
+            let writer = ParquetWriter::new(store.clone(), schema, config)?;
+
+            // Create ParquetWriterTask with pre-configured channels
+            let writer_task = ParquetWriterTask::new(writer, batch_receiver, rotate_receiver)
+                .with_drain(shutdown_token.clone(), drain_timeout)
+                .with_failure_policy(
+                    create_write_failure_policy(&opts.storage_failure_policy, &opts.spill_dir)?,
+                    storage_failure_timeout,
+                );
+
+            // Spawn the writer task with completion handler using task tracker
+            task_tracker.spawn(task_completion_handler(
+                writer_task.run(),
+                shutdown_token.clone(),
+                "ParquetWriterTask",
+            ));
+        }
+        "arrow-ipc" => {
+            debug!(
+                "Streaming {} data in Arrow IPC format to {}",
+                if opts.trace { "trace" } else { "timeslot" },
+                &opts.ipc_output
+            );
+            let writer = ArrowIpcWriter::open(&opts.ipc_output, schema)?;
+
+            // A stream cannot recover from a failed write, so the first one ends the task
+            let writer_task = ParquetWriterTask::new(writer, batch_receiver, rotate_receiver)
+                .with_drain(shutdown_token.clone(), drain_timeout);
+
+            task_tracker.spawn(task_completion_handler(
+                writer_task.run(),
+                shutdown_token.clone(),
+                "ArrowIpcWriterTask",
+            ));
+        }
+        other => {
+            return Err(CollectorError::Config(anyhow!(
+                "unknown output format '{}' (expected 'parquet' or 'arrow-ipc')",
+                other
+            ))
+            .into())
+        }
+    }
 
-    debug!("Parquet writer task initialized and ready to receive data");
+    debug!("Writer task initialized and ready to receive data");
 
     // Readiness provider for health server
     let mut ready_provider: Option<Arc<dyn Fn() -> bool + Send + Sync>> = None;
+    let mut metrics_provider: Option<health_server::MetricsFn> = None;
 
     // Optionally enable resctrl occupancy collection with a dedicated writer
     if opts.enable_resctrl {
@@ -304,7 +841,10 @@ async fn main() -> Result<()> {
         // (delayed) writer + channel setup follows after config parsing
 
         // Spawn the resctrl-collector loop with config from env
-        let occupancy_cfg = resctrl_collector::ResctrlCollectorConfig::from_env();
+        let mut occupancy_cfg = resctrl_collector::ResctrlCollectorConfig::from_env();
+        occupancy_cfg.nri_socket_path = nri_endpoint.socket_path.clone();
+        occupancy_cfg.nri_plugin_idx = nri_endpoint.plugin_idx.clone();
+        occupancy_cfg.cleanup_dry_run = opts.resctrl_cleanup_dry_run;
         // Create writer and channels for occupancy using parsed config
         // Use a separate prefix for resctrl outputs to avoid mixing files
         let occupancy_prefix = format!("{}{}", opts.resctrl_prefix, node_id);
@@ -315,13 +855,31 @@ async fn main() -> Result<()> {
             max_row_group_size: opts.max_row_group_size,
             storage_quota: opts.storage_quota,
             key_value_metadata: Some(cpu_metadata.clone()),
+            flush_interval,
+            node_id: Some(node_id.clone()),
+            write_manifest: true,
+            write_run_summary: opts.run_summary,
+            tee: tee.clone(),
+            partition_by_time: opts.partition_by_time,
+            // Occupancy timestamps are already wall-clock
+            time_offset_ns: 0,
+            run_id: Some(run_id.clone()),
+            write_retry: WriteRetry::default(),
+            multipart_threshold,
+            // The control socket reports the main data stream only
+            status: None,
         };
         let (occupancy_sender, occupancy_receiver) = mpsc::channel::<RecordBatch>(64);
         let (occupancy_rotate_tx, occupancy_rotate_rx) = mpsc::channel::<()>(1);
         let occupancy_writer =
             ParquetWriter::new(store.clone(), occupancy_schema, occupancy_config)?;
         let occupancy_writer_task =
-            ParquetWriterTask::new(occupancy_writer, occupancy_receiver, occupancy_rotate_rx);
+            ParquetWriterTask::new(occupancy_writer, occupancy_receiver, occupancy_rotate_rx)
+                .with_drain(shutdown_token.clone(), drain_timeout)
+                .with_failure_policy(
+                    create_write_failure_policy(&opts.storage_failure_policy, &opts.spill_dir)?,
+                    storage_failure_timeout,
+                );
 
         // Spawn writer task
         task_tracker.spawn(task_completion_handler(
@@ -343,6 +901,10 @@ async fn main() -> Result<()> {
             let occupancy_clone = occupancy_instance.clone();
             Arc::new(move || occupancy_clone.ready())
         });
+        if opts.resctrl_metrics {
+            let occupancy_clone = occupancy_instance.clone();
+            metrics_provider = Some(Arc::new(move || occupancy_clone.gauges().render()));
+        }
         task_tracker.spawn(task_completion_handler(
             resctrl_collector::run(
                 occupancy_instance,
@@ -384,12 +946,21 @@ async fn main() -> Result<()> {
         "RotationHandler",
     ));
 
+    // Spawn control socket for rotation, status and shutdown commands
+    if let (Some(path), Some(status)) = (opts.control_socket.clone(), status.clone()) {
+        task_tracker.spawn(task_completion_handler(
+            control_socket::run(path, status, rotate_sender.clone(), shutdown_token.clone()),
+            shutdown_token.clone(),
+            "ControlSocket",
+        ));
+    }
+
     // Spawn health HTTP server (readiness/liveness)
     {
         let addr = opts.health_addr.clone();
         let ready_fn = ready_provider.expect("ready provider");
         task_tracker.spawn(task_completion_handler(
-            health_server::run(addr, ready_fn, shutdown_token.clone()),
+            health_server::run(addr, ready_fn, metrics_provider, shutdown_token.clone()),
             shutdown_token.clone(),
             "HealthServer",
         ));
@@ -401,13 +972,35 @@ async fn main() -> Result<()> {
     } else {
         TIMESLOT_PERF_RING_PAGES
     };
-    let mut sync_timer = SyncTimer::start(SYNC_TIMER_INTERVAL_NS)
-        .map_err(|e| anyhow!("failed to start sync timer: {}", e))?;
+    let mut sync_timer = SyncTimer::start(opts.sync_interval_us.saturating_mul(1_000))
+        .map_err(|e| CollectorError::Bpf(anyhow!("failed to start sync timer: {}", e)))?;
 
-    let mut bpf_loader = BpfLoader::new(perf_ring_pages, &mut sync_timer)?;
+    let mut bpf_loader = BpfLoader::with_counters(
+        perf_ring_pages,
+        &mut sync_timer,
+        &perf_events.hardware_counters(),
+        cpu_list.as_deref(),
+    )
+    .map_err(CollectorError::Bpf)?;
 
     // Create PerfEventProcessor with the appropriate mode
-    let processor = PerfEventProcessor::new(&mut bpf_loader, num_cpus, processor_mode);
+    let min_task_lifetime_ns =
+        (opts.min_task_lifetime_us > 0).then(|| opts.min_task_lifetime_us * 1_000);
+    let processor = PerfEventProcessor::new(
+        &mut bpf_loader,
+        num_cpus,
+        processor_mode,
+        min_task_lifetime_ns,
+        opts.downsample_factor,
+        granularity,
+        perf_events,
+        opts.max_tracked_tasks,
+        comm_filter,
+    );
+
+    if let Some(status) = &status {
+        processor.borrow_mut().set_status(status.clone());
+    }
 
     // Spawn error reporting task
     let error_receiver = processor
@@ -423,9 +1016,9 @@ async fn main() -> Result<()> {
     task_tracker.close();
 
     // Attach BPF programs
-    bpf_loader.attach()?;
+    bpf_loader.attach().map_err(CollectorError::Bpf)?;
 
-    info!("Collection started.");
+    info!("Collection started (run id {}).", run_id);
 
     // Run BPF polling in the main thread until signaled to stop
     loop {
@@ -449,10 +1042,64 @@ async fn main() -> Result<()> {
     // Clean up: shutdown the processor
     processor.borrow_mut().shutdown();
 
-    // Clean up: wait for all tasks to complete
+    let lost_samples = processor.borrow().lost_samples();
+    if lost_samples > 0 {
+        error!(
+            "Perf rings dropped {} samples during collection; the collector could not keep up",
+            lost_samples
+        );
+    }
+
+    // Clean up: wait for all tasks to complete. The writers bound their own drain,
+    // so this only expires if a task ignores the shutdown
     debug!("Waiting for all tasks to complete...");
-    task_tracker.wait().await;
+    if tokio::time::timeout(drain_timeout + SHUTDOWN_GRACE, task_tracker.wait())
+        .await
+        .is_err()
+    {
+        return Err(anyhow!(
+            "tasks still running {:?} after shutdown, exiting",
+            drain_timeout + SHUTDOWN_GRACE
+        ));
+    }
 
     info!("Shutdown complete");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_identity_precedence() {
+        let pick = select_node_identity;
+        assert_eq!(
+            pick(Some("flag"), Some("env"), Some("host")).as_deref(),
+            Some("flag")
+        );
+        assert_eq!(
+            pick(None, Some("env"), Some("host")).as_deref(),
+            Some("env")
+        );
+        assert_eq!(pick(None, None, Some("host")).as_deref(), Some("host"));
+        assert_eq!(pick(None, None, None), None);
+
+        // Empty values fall through to the next source
+        assert_eq!(
+            pick(Some(""), Some("env"), Some("host")).as_deref(),
+            Some("env")
+        );
+        assert_eq!(
+            pick(Some(""), Some(""), Some("host")).as_deref(),
+            Some("host")
+        );
+    }
+
+    #[test]
+    fn test_zero_sync_interval_is_rejected() {
+        assert!(Cli::try_parse_from(["collector", "--sync-interval-us", "0"]).is_err());
+        let cli = Cli::try_parse_from(["collector", "--sync-interval-us", "500"]).unwrap();
+        assert_eq!(cli.collect.sync_interval_us, 500);
+    }
+}
@@ -1,3 +1,9 @@
+use anyhow::anyhow;
+use arrow_schema::{DataType, Field};
+use perf_events::HardwareCounter;
+
+use crate::error::{CollectorError, Result};
+
 /// Metrics structure to hold performance measurements collected from eBPF
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Metric {
@@ -39,4 +45,130 @@ impl Metric {
             time_ns,
         }
     }
+
+    /// Counter values in column order (cycles, instructions, llc_misses,
+    /// cache_references), `None` for counters that are not collected
+    pub fn counters(&self, events: &PerfEvents) -> [Option<i64>; 4] {
+        let value = |enabled: bool, v: u64| enabled.then_some(v as i64);
+        [
+            value(events.cycles, self.cycles),
+            value(events.instructions, self.instructions),
+            value(events.llc_misses, self.llc_misses),
+            value(events.cache_references, self.cache_references),
+        ]
+    }
+}
+
+/// Hardware counters selected for collection. Counters that are not collected
+/// are written as nulls, so their columns stay in the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerfEvents {
+    pub cycles: bool,
+    pub instructions: bool,
+    pub llc_misses: bool,
+    pub cache_references: bool,
+}
+
+impl Default for PerfEvents {
+    fn default() -> Self {
+        Self {
+            cycles: true,
+            instructions: true,
+            llc_misses: true,
+            cache_references: true,
+        }
+    }
+}
+
+impl PerfEvents {
+    /// Event names accepted by `parse`, in column order
+    pub const NAMES: [&'static str; 4] =
+        ["cycles", "instructions", "llc_misses", "cache_references"];
+
+    /// Parse a comma-separated list of event names (e.g. "cycles,instructions")
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut events = Self {
+            cycles: false,
+            instructions: false,
+            llc_misses: false,
+            cache_references: false,
+        };
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match name {
+                "cycles" => events.cycles = true,
+                "instructions" => events.instructions = true,
+                "llc_misses" => events.llc_misses = true,
+                "cache_references" => events.cache_references = true,
+                other => {
+                    return Err(CollectorError::Config(anyhow!(
+                        "unknown perf event '{}' (expected one of {})",
+                        other,
+                        Self::NAMES.join(", ")
+                    )))
+                }
+            }
+        }
+        if events.hardware_counters().is_empty() {
+            return Err(CollectorError::Config(anyhow!("no perf events selected")));
+        }
+        Ok(events)
+    }
+
+    /// Counters to open in the BPF loader
+    pub fn hardware_counters(&self) -> Vec<HardwareCounter> {
+        [
+            (self.cycles, HardwareCounter::Cycles),
+            (self.instructions, HardwareCounter::Instructions),
+            (self.llc_misses, HardwareCounter::LLCMisses),
+            (self.cache_references, HardwareCounter::CacheReferences),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, counter)| enabled.then_some(counter))
+        .collect()
+    }
+
+    /// Schema fields for the counter columns; only disabled counters are nullable
+    pub fn fields(&self) -> Vec<Field> {
+        let enabled = [
+            self.cycles,
+            self.instructions,
+            self.llc_misses,
+            self.cache_references,
+        ];
+        Self::NAMES
+            .iter()
+            .zip(enabled)
+            .map(|(name, enabled)| Field::new(*name, DataType::Int64, !enabled))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_perf_events() {
+        let events = PerfEvents::parse("cycles, instructions").unwrap();
+        assert!(events.cycles && events.instructions);
+        assert!(!events.llc_misses && !events.cache_references);
+        assert_eq!(events.hardware_counters().len(), 2);
+
+        let nullable: Vec<_> = events.fields().iter().map(|f| f.is_nullable()).collect();
+        assert_eq!(nullable, [false, false, true, true]);
+
+        assert!(PerfEvents::parse("cycles,branches").is_err());
+        assert!(PerfEvents::parse(" , ").is_err());
+        assert_eq!(
+            PerfEvents::parse(&PerfEvents::NAMES.join(",")).unwrap(),
+            PerfEvents::default()
+        );
+    }
+
+    #[test]
+    fn test_disabled_counters_are_none() {
+        let metric = Metric::from_deltas(1, 2, 3, 4, 5);
+        let events = PerfEvents::parse("instructions,cache_references").unwrap();
+        assert_eq!(metric.counters(&events), [None, Some(2), None, Some(4)]);
+    }
 }
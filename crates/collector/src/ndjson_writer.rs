@@ -0,0 +1,196 @@
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use arrow_array::RecordBatch;
+use arrow_json::writer::{LineDelimited, WriterBuilder};
+use async_trait::async_trait;
+use log::{debug, info};
+use object_store::ObjectStore;
+
+use crate::error::{CollectorError, Result};
+use crate::parquet_writer_task::BatchWriter;
+
+/// Writes record batches to stdout as newline-delimited JSON, one object per row.
+///
+/// Rows are built by the same conversion as the Parquet output, so each object
+/// has the fields of the Parquet schema; null values are written explicitly so
+/// every line has all of them. Each batch (one timeslot in timeslot mode) is
+/// encoded and flushed on its own, so nothing is buffered between batches.
+pub struct NdjsonWriter {
+    output: Box<dyn Write + Send>,
+    sink_name: String,
+    rows_written: u64,
+    shutdown_drops: u64,
+}
+
+impl NdjsonWriter {
+    /// Create a writer printing to stdout
+    pub fn stdout() -> Self {
+        Self::new(Box::new(BufWriter::new(std::io::stdout())), "stdout")
+    }
+
+    /// Create a writer printing to `output`, named `sink_name` in logs
+    pub fn new(output: Box<dyn Write + Send>, sink_name: &str) -> Self {
+        debug!("Created NDJSON writer for sink: {}", sink_name);
+        Self {
+            output,
+            sink_name: sink_name.to_string(),
+            rows_written: 0,
+            shutdown_drops: 0,
+        }
+    }
+
+    fn flush_sink(&mut self) -> Result<()> {
+        self.output.flush().map_err(|e| {
+            CollectorError::Storage(anyhow!(
+                "failed to flush NDJSON sink '{}': {}",
+                self.sink_name,
+                e
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl BatchWriter for NdjsonWriter {
+    /// Print one JSON object per row of the batch and flush the sink
+    async fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        let mut writer = WriterBuilder::new()
+            .with_explicit_nulls(true)
+            .build::<_, LineDelimited>(&mut self.output);
+        writer.write(&batch)?;
+        writer.finish()?;
+        self.flush_sink()?;
+        self.rows_written += batch.num_rows() as u64;
+        Ok(())
+    }
+
+    /// There are no files to rotate; flush the sink instead
+    async fn rotate(&mut self) -> Result<()> {
+        debug!("Rotation requested on NDJSON output, flushing");
+        self.flush_sink()
+    }
+
+    async fn close(mut self) -> Result<()> {
+        self.flush_sink()?;
+        info!(
+            "Closed NDJSON output '{}' after {} rows ({} rows dropped on shutdown)",
+            self.sink_name, self.rows_written, self.shutdown_drops
+        );
+        Ok(())
+    }
+
+    /// Output is flushed on every write, so there is no interval
+    fn flush_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Every written batch has already been flushed to the sink
+    fn has_buffered_rows(&self) -> bool {
+        false
+    }
+
+    /// No files are closed, so no success ends a run of write failures
+    fn files_closed(&self) -> u64 {
+        0
+    }
+
+    fn record_shutdown_drops(&mut self, rows: u64) {
+        self.shutdown_drops += rows;
+    }
+
+    /// A batch may have been printed in part, so there is no file to discard
+    /// and continue from
+    fn discard_current_file(&mut self) -> Result<()> {
+        Err(CollectorError::Storage(anyhow!(
+            "cannot recover NDJSON output '{}' after a failed write",
+            self.sink_name
+        )))
+    }
+
+    /// Output is not written to an object store, so it cannot spill to one
+    fn set_store(&mut self, _store: Arc<dyn ObjectStore>) -> Result<()> {
+        Err(CollectorError::Storage(anyhow!(
+            "NDJSON output '{}' cannot spill to an object store",
+            self.sink_name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{Metric, PerfEvents};
+    use crate::task_metadata::TaskMetadata;
+    use crate::test_utils::SharedBuffer;
+    use crate::timeslot_data::{Granularity, TimeslotData};
+    use crate::timeslot_to_recordbatch_task::{create_timeslot_schema, timeslot_to_batch};
+
+    #[tokio::test]
+    async fn test_timeslots_are_printed_as_json_lines() {
+        let events = PerfEvents::default();
+        let schema = create_timeslot_schema(&events, Granularity::Process);
+
+        let mut first = TimeslotData::new(1_000_000);
+        let mut comm = [0u8; 16];
+        comm[..3].copy_from_slice(b"app");
+        first.update(
+            42,
+            Some(TaskMetadata::new(42, comm, 4242)),
+            Metric::from_deltas(1000, 2000, 30, 500, 100000),
+        );
+        first.update(43, None, Metric::from_deltas(10, 20, 3, 5, 1000));
+        let mut second = TimeslotData::new(2_000_000);
+        second.update(42, None, Metric::from_deltas(7, 8, 9, 10, 11));
+
+        let buffer = SharedBuffer::default();
+        let mut writer = NdjsonWriter::new(Box::new(buffer.clone()), "memory");
+        for timeslot in [first, second] {
+            let batch =
+                timeslot_to_batch(timeslot, schema.clone(), &events, Granularity::Process).unwrap();
+            writer.write(batch).await.unwrap();
+        }
+        writer.close().await.unwrap();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+
+        // Every row has exactly the fields of the Parquet schema
+        let fields: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        for row in &rows {
+            let mut keys: Vec<&str> = row.keys().map(String::as_str).collect();
+            let mut expected = fields.clone();
+            keys.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(keys, expected);
+        }
+
+        // Rows of a timeslot are in no particular order, but timeslots are in order
+        let row = |start_time: u64, pid: i32| {
+            rows.iter()
+                .position(|r| r["start_time"] == start_time && r["pid"] == pid)
+                .unwrap()
+        };
+        assert!(row(1_000_000, 42) < 2 && row(1_000_000, 43) < 2);
+        assert_eq!(rows[row(1_000_000, 42)]["process_name"], "app");
+        assert!(rows[row(1_000_000, 43)]["process_name"].is_null());
+        assert_eq!(rows[row(2_000_000, 42)]["instructions"], 8);
+    }
+
+    #[test]
+    fn test_failed_output_cannot_be_recovered() {
+        let mut writer = NdjsonWriter::new(Box::new(SharedBuffer::default()), "memory");
+        assert!(matches!(
+            writer.discard_current_file(),
+            Err(CollectorError::Storage(_))
+        ));
+        let store = Arc::new(object_store::memory::InMemory::new());
+        assert!(writer.set_store(store).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+/// Default NRI runtime socket path
+pub const DEFAULT_NRI_SOCKET_PATH: &str = "/var/run/nri/nri.sock";
+
+/// Default NRI plugin index (controls plugin ordering in the runtime)
+pub const DEFAULT_NRI_PLUGIN_IDX: &str = "10";
+
+/// Environment variable consulted for the NRI socket path
+pub const NRI_SOCKET_PATH_ENV: &str = "NRI_SOCKET_PATH";
+
+/// Environment variable consulted for the NRI plugin index
+pub const NRI_PLUGIN_IDX_ENV: &str = "NRI_PLUGIN_IDX";
+
+/// Where and how the collector's NRI plugins connect to the runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NriEndpoint {
+    /// Path to the NRI runtime unix socket
+    pub socket_path: String,
+    /// Plugin index passed to `NRI::new`
+    pub plugin_idx: String,
+}
+
+impl Default for NriEndpoint {
+    fn default() -> Self {
+        Self {
+            socket_path: DEFAULT_NRI_SOCKET_PATH.to_string(),
+            plugin_idx: DEFAULT_NRI_PLUGIN_IDX.to_string(),
+        }
+    }
+}
+
+impl NriEndpoint {
+    /// Resolve the endpoint with precedence: CLI flag > environment > default.
+    pub fn resolve(cli_socket_path: Option<&str>, cli_plugin_idx: Option<&str>) -> Self {
+        Self::resolve_with(cli_socket_path, cli_plugin_idx, |key| {
+            std::env::var(key).ok()
+        })
+    }
+
+    /// Same as [`NriEndpoint::resolve`] but with an injectable environment lookup.
+    /// Empty values (from either source) are treated as unset.
+    fn resolve_with<F>(cli_socket_path: Option<&str>, cli_plugin_idx: Option<&str>, env: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let pick = |cli: Option<&str>, key: &str, default: &str| -> String {
+            cli.filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .or_else(|| env(key).filter(|v| !v.is_empty()))
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        Self {
+            socket_path: pick(
+                cli_socket_path,
+                NRI_SOCKET_PATH_ENV,
+                DEFAULT_NRI_SOCKET_PATH,
+            ),
+            plugin_idx: pick(cli_plugin_idx, NRI_PLUGIN_IDX_ENV, DEFAULT_NRI_PLUGIN_IDX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::env_from;
+
+    #[test]
+    fn test_defaults_when_nothing_set() {
+        let ep = NriEndpoint::resolve_with(None, None, env_from(&[]));
+        assert_eq!(ep, NriEndpoint::default());
+    }
+
+    #[test]
+    fn test_env_overrides_default() {
+        let ep = NriEndpoint::resolve_with(
+            None,
+            None,
+            env_from(&[
+                (NRI_SOCKET_PATH_ENV, "/run/custom/nri.sock"),
+                (NRI_PLUGIN_IDX_ENV, "42"),
+            ]),
+        );
+        assert_eq!(ep.socket_path, "/run/custom/nri.sock");
+        assert_eq!(ep.plugin_idx, "42");
+    }
+
+    #[test]
+    fn test_cli_overrides_env() {
+        let ep = NriEndpoint::resolve_with(
+            Some("/cli/nri.sock"),
+            Some("05"),
+            env_from(&[
+                (NRI_SOCKET_PATH_ENV, "/run/custom/nri.sock"),
+                (NRI_PLUGIN_IDX_ENV, "42"),
+            ]),
+        );
+        assert_eq!(ep.socket_path, "/cli/nri.sock");
+        assert_eq!(ep.plugin_idx, "05");
+    }
+
+    #[test]
+    fn test_empty_values_are_ignored() {
+        let ep = NriEndpoint::resolve_with(
+            Some(""),
+            None,
+            env_from(&[(NRI_SOCKET_PATH_ENV, ""), (NRI_PLUGIN_IDX_ENV, "")]),
+        );
+        assert_eq!(ep, NriEndpoint::default());
+    }
+}
@@ -1,21 +1,25 @@
 use std::collections::HashMap;
-use std::fs;
-use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Context};
 use arrow_array::builder::StringBuilder;
 use arrow_array::{ArrayRef, Int64Array, RecordBatch};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use log::{debug, info, warn};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
 
 use nri::metadata::{ContainerMetadata, MetadataMessage, MetadataPlugin};
+use nri::reconnect::{ReconnectConfig, ReconnectingNRI};
+use nri::send_policy::SendPolicy;
 use nri::NRI;
 
+use crate::error::{CollectorError, Result};
+use crate::nri_endpoint::NriEndpoint;
+
 /// Fields appended by the NRI enrichment task
 const ENRICH_FIELDS: &[(&str, DataType)] = &[
     ("pod_name", DataType::Utf8),
@@ -29,11 +33,10 @@ const ENRICH_FIELDS: &[(&str, DataType)] = &[
 ///
 /// Assumes `cgroup_path` is an absolute path under `/sys/fs/cgroup` and attempts to
 /// resolve its inode number via `stat`.
-fn resolve_cgroup_inode(cgroup_path: &str) -> Result<u64> {
+fn resolve_cgroup_inode(cgroup_path: &str) -> anyhow::Result<u64> {
     let path = Path::new(cgroup_path);
-    let metadata = fs::metadata(path)
-        .with_context(|| format!("Failed to get metadata for cgroup path: {:?}", path))?;
-    Ok(metadata.ino())
+    nri::cgroup_path::cgroup_inode(cgroup_path)
+        .with_context(|| format!("Failed to get metadata for cgroup path: {:?}", path))
 }
 
 /// Task that enriches incoming RecordBatches with container metadata based on cgroup_id
@@ -41,14 +44,19 @@ pub struct NRIEnrichRecordBatchTask {
     // Schemas
     output_schema: SchemaRef,
 
+    // NRI connection settings
+    nri_endpoint: NriEndpoint,
+    reconnect: ReconnectConfig,
+    send_policy: SendPolicy,
+
     // Mapping structures
     container_to_inode: HashMap<String, u64>,
     inode_to_metadata: HashMap<u64, ContainerMetadata>,
 }
 
 impl NRIEnrichRecordBatchTask {
-    /// Create a new enrichment task with channels and input schema
-    pub fn new(input_schema: SchemaRef) -> Self {
+    /// Create a new enrichment task with the input schema and NRI connection settings
+    pub fn new(input_schema: SchemaRef, nri_endpoint: NriEndpoint) -> Self {
         // Build output schema (input + appended nullable columns)
         let mut fields: Vec<Field> = input_schema
             .fields()
@@ -62,52 +70,84 @@ impl NRIEnrichRecordBatchTask {
 
         Self {
             output_schema,
+            nri_endpoint,
+            reconnect: ReconnectConfig::default(),
+            send_policy: SendPolicy::Drop,
             container_to_inode: HashMap::new(),
             inode_to_metadata: HashMap::new(),
         }
     }
 
+    /// Limit how often, and for how long, a dropped NRI connection is re-established
+    /// before the task fails.
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// How the metadata plugin handles a full metadata channel (default: drop).
+    pub fn with_send_policy(mut self, send_policy: SendPolicy) -> Self {
+        self.send_policy = send_policy;
+        self
+    }
+
     /// Return the output schema (input + enrichment columns)
     pub fn schema(&self) -> SchemaRef {
         self.output_schema.clone()
     }
 
+    /// Connect to the NRI socket and register the metadata plugin, re-dialing per
+    /// `reconnect` whenever the connection drops. Fails if the socket is unavailable
+    /// or registration is rejected.
+    async fn connect_nri(
+        endpoint: &NriEndpoint,
+        reconnect: ReconnectConfig,
+        metadata_tx: mpsc::Sender<MetadataMessage>,
+        send_policy: SendPolicy,
+    ) -> anyhow::Result<(ReconnectingNRI, JoinHandle<anyhow::Result<()>>)> {
+        let plugin =
+            std::sync::Arc::new(MetadataPlugin::with_send_policy(metadata_tx, send_policy));
+        let socket_path = endpoint.socket_path.clone();
+        let dial = move || {
+            let socket_path = socket_path.clone();
+            async move {
+                tokio::net::UnixStream::connect(&socket_path)
+                    .await
+                    .with_context(|| format!("NRI socket not available at {}", socket_path))
+            }
+        };
+
+        info!(
+            "Connecting to NRI socket at {} (plugin index {})",
+            endpoint.socket_path, endpoint.plugin_idx
+        );
+        let connected = NRI::new_with_reconnect(
+            dial,
+            plugin,
+            "collector-metadata",
+            &endpoint.plugin_idx,
+            reconnect,
+        )
+        .await?;
+
+        info!("NRI plugin registered successfully");
+        Ok(connected)
+    }
+
     /// Initialize NRI plugin and connection using the provided metadata sender. Returns an
-    /// active NRI instance and join handle when connected, or Ok(None) when best-effort disabled.
+    /// active NRI handle and the join handle of its supervisor when connected, or Ok(None)
+    /// when best-effort disabled.
     async fn init_nri_with_sender(
+        endpoint: &NriEndpoint,
+        reconnect: ReconnectConfig,
         metadata_tx: mpsc::Sender<MetadataMessage>,
-    ) -> Result<Option<(NRI, tokio::task::JoinHandle<Result<()>>)>> {
-        let plugin = std::sync::Arc::new(MetadataPlugin::new(metadata_tx));
-
-        // Determine socket path
-        let socket_path = std::env::var("NRI_SOCKET_PATH")
-            .unwrap_or_else(|_| "/var/run/nri/nri.sock".to_string());
-
-        // Try to connect
-        match tokio::net::UnixStream::connect(&socket_path).await {
-            Ok(stream) => {
-                info!("Connecting to NRI socket at {}", socket_path);
-                let (nri, join_handle) =
-                    NRI::new(stream, plugin, "collector-metadata", "10").await?;
-
-                // Register plugin
-                if let Err(e) = nri.register().await {
-                    warn!(
-                        "NRI registration failed (best-effort mode, continuing without enrichment): {}",
-                        e
-                    );
-                    return Ok(None); // Best-effort: remain without an active NRI
-                }
-
-                info!("NRI plugin registered successfully");
-                Ok(Some((nri, join_handle)))
-            }
+        send_policy: SendPolicy,
+    ) -> Result<Option<(ReconnectingNRI, JoinHandle<anyhow::Result<()>>)>> {
+        match Self::connect_nri(endpoint, reconnect, metadata_tx, send_policy).await {
+            Ok(connected) => Ok(Some(connected)),
             Err(e) => {
-                warn!(
-                    "NRI socket not available at {} (best-effort mode, continuing without enrichment): {}",
-                    socket_path, e
-                );
                 // Best-effort: keep nri as None; enrichment will produce nulls
+                warn!("{:#} (best-effort mode, continuing without enrichment)", e);
                 Ok(None)
             }
         }
@@ -124,7 +164,11 @@ impl NRIEnrichRecordBatchTask {
                     );
                     return;
                 }
-                match resolve_cgroup_inode(&metadata.cgroup_path) {
+                let inode = match metadata.cgroup_id {
+                    Some(id) => Ok(id),
+                    None => resolve_cgroup_inode(&metadata.cgroup_path),
+                };
+                match inode {
                     Ok(inode) => {
                         // Update both maps
                         self.container_to_inode.insert(container_id.clone(), inode);
@@ -154,13 +198,15 @@ impl NRIEnrichRecordBatchTask {
             .fields()
             .iter()
             .position(|f| f.name() == "cgroup_id")
-            .ok_or_else(|| anyhow!("cgroup_id column not found in input batch schema"))?;
+            .ok_or_else(|| {
+                CollectorError::Arrow(anyhow!("cgroup_id column not found in input batch schema"))
+            })?;
 
         let cgroup_array = batch.column(cgroup_idx);
         let cgroup_ids = cgroup_array
             .as_any()
             .downcast_ref::<Int64Array>()
-            .ok_or_else(|| anyhow!("cgroup_id column is not Int64"))?;
+            .ok_or_else(|| CollectorError::Arrow(anyhow!("cgroup_id column is not Int64")))?;
 
         let num_rows = batch.num_rows();
 
@@ -199,8 +245,7 @@ impl NRIEnrichRecordBatchTask {
         arrays.push(Arc::new(container_name_b.finish()));
         arrays.push(Arc::new(container_id_b.finish()));
 
-        RecordBatch::try_new(self.output_schema.clone(), arrays)
-            .map_err(|e| anyhow!("Failed to create enriched RecordBatch: {}", e))
+        Ok(RecordBatch::try_new(self.output_schema.clone(), arrays)?)
     }
 
     /// Run the enrichment task: read metadata and batches, output enriched batches
@@ -215,18 +260,38 @@ impl NRIEnrichRecordBatchTask {
         // Metadata channel for NRI plugin
         let (metadata_tx, mut metadata_rx) = mpsc::channel::<MetadataMessage>(1000);
 
+        // Stops the NRI supervisor when this task exits
+        let nri_stop = CancellationToken::new();
+
         // Try initializing NRI (best-effort)
-        let mut nri_opt: Option<NRI> = None;
         let mut nri_active = false;
-        match Self::init_nri_with_sender(metadata_tx).await {
-            Ok(Some((nri, join_handle))) => {
-                // Monitor NRI lifecycle using the common task completion handler
+        match Self::init_nri_with_sender(
+            &self.nri_endpoint,
+            self.reconnect.clone(),
+            metadata_tx,
+            self.send_policy,
+        )
+        .await
+        {
+            Ok(Some((nri, mut join_handle))) => {
+                // The library supervisor reconnects on drop; its final failure
+                // cancels the shutdown token via the common task completion handler
                 nri_active = true;
-                nri_opt = Some(nri);
-                let token = shutdown_token.clone();
-                task_tracker.spawn(tokio_helpers::join_handle_completion_handler(
-                    join_handle,
-                    token,
+                let stop = nri_stop.clone();
+                task_tracker.spawn(tokio_helpers::task_completion_handler(
+                    async move {
+                        let res = tokio::select! {
+                            res = &mut join_handle => res,
+                            _ = stop.cancelled() => {
+                                let _ = nri.close().await;
+                                join_handle.await
+                            }
+                        };
+                        res.map_err(|e| anyhow!("NRI supervisor panicked or was cancelled: {}", e))
+                            .and_then(|res| res)
+                            .map_err(CollectorError::Nri)
+                    },
+                    shutdown_token.clone(),
                     "NRIPlugin",
                 ));
             }
@@ -256,7 +321,7 @@ impl NRIEnrichRecordBatchTask {
                                     if let Err(e) = batch_sender.send(enriched).await {
                                         // Shutdown should initiate from upstream; downstream closed is an error
                                         debug!("Downstream batch channel closed unexpectedly: {}", e);
-                                        res = Err(anyhow!("downstream batch channel closed: {}", e));
+                                        res = Err(CollectorError::ChannelClosed("downstream batch"));
                                         break;
                                     }
                                 },
@@ -284,7 +349,7 @@ impl NRIEnrichRecordBatchTask {
                     } else {
                         // If the metadata channel closed unexpectedly, treat as error
                         debug!("NRI metadata channel closed unexpectedly");
-                        res = Err(anyhow!("nri metadata channel closed"));
+                        res = Err(CollectorError::ChannelClosed("NRI metadata"));
                         break;
                     }
                 }
@@ -292,9 +357,7 @@ impl NRIEnrichRecordBatchTask {
         }
 
         // Cleanup NRI on any exit path
-        if let Some(nri) = &nri_opt {
-            let _ = nri.close().await;
-        }
+        nri_stop.cancel();
         // Wait for internal tasks (e.g., NRI plugin) to complete
         task_tracker.wait().await;
         // Output channel closes when sender is dropped (self goes out of scope here)
@@ -309,6 +372,8 @@ mod tests {
     use arrow_array::builder::{Int32Builder, Int64Builder};
     use arrow_array::Array;
     use arrow_schema::Field;
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
 
     fn make_input_schema() -> SchemaRef {
         Arc::new(Schema::new(vec![
@@ -342,7 +407,7 @@ mod tests {
     #[test]
     fn test_schema_appended_fields() {
         let schema = make_input_schema();
-        let task = NRIEnrichRecordBatchTask::new(schema.clone());
+        let task = NRIEnrichRecordBatchTask::new(schema.clone(), NriEndpoint::default());
         let out = task.schema();
         assert_eq!(
             out.fields().len(),
@@ -374,7 +439,7 @@ mod tests {
     #[test]
     fn test_process_metadata_map_updates() {
         let schema = make_input_schema();
-        let mut task = NRIEnrichRecordBatchTask::new(schema);
+        let mut task = NRIEnrichRecordBatchTask::new(schema, NriEndpoint::default());
 
         // Use a known directory for inode: root "/" should exist
         let inode = fs::metadata("/").unwrap().ino();
@@ -388,6 +453,7 @@ mod tests {
             pid: None,
             labels: HashMap::new(),
             annotations: HashMap::new(),
+            ..Default::default()
         };
 
         task.process_metadata_message(MetadataMessage::Add("abc".into(), Box::new(meta.clone())));
@@ -402,7 +468,7 @@ mod tests {
     #[test]
     fn test_enrich_batch_known_unknown() {
         let schema = make_input_schema();
-        let mut task = NRIEnrichRecordBatchTask::new(schema.clone());
+        let mut task = NRIEnrichRecordBatchTask::new(schema.clone(), NriEndpoint::default());
 
         // Prepare mapping for inode 42
         let cm = ContainerMetadata {
@@ -415,6 +481,7 @@ mod tests {
             pid: None,
             labels: HashMap::new(),
             annotations: HashMap::new(),
+            ..Default::default()
         };
         task.inode_to_metadata.insert(42, cm);
 
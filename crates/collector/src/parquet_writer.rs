@@ -1,18 +1,43 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{anyhow, Result};
-use arrow_array::RecordBatch;
+use anyhow::anyhow;
+use arrow_array::{Array, Int64Array, RecordBatch};
 use arrow_schema::SchemaRef;
-use chrono::Utc;
-use log::{debug, info};
-use object_store::{path::Path, ObjectStore};
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use futures::future::BoxFuture;
+use log::{debug, info, warn};
+use object_store::{buffered::BufWriter, path::Path, ObjectStore};
 use parquet::arrow::arrow_writer::ArrowWriterOptions;
-use parquet::arrow::async_writer::{AsyncArrowWriter, ParquetObjectWriter};
+use parquet::arrow::async_writer::{AsyncArrowWriter, AsyncFileWriter, ParquetObjectWriter};
 use parquet::basic::Compression;
 use parquet::file::metadata::KeyValue;
 use parquet::file::properties::WriterProperties;
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::control_socket::CollectorStatus;
+use crate::error::{CollectorError, Result};
+use crate::retry_store::{RetryStore, WriteRetry};
+use crate::tee::TeeConfig;
+
+/// Name of the run manifest object, appended to the storage prefix
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// Most recent closed files listed in the run manifest and run summary; older
+/// ones are only counted, so long runs keep a bounded list
+const MANIFEST_MAX_FILES: usize = 10_000;
+
+/// Key-value metadata key holding the run id in every Parquet file
+pub const RUN_ID_KEY: &str = "run_id";
+
+/// Int64 columns holding row timestamps in nanoseconds, in order of preference,
+/// used to partition files by time
+const TIME_COLUMNS: [&str; 2] = ["start_time", "timestamp"];
+
 /// Configuration for the parquet writer
 pub struct ParquetWriterConfig {
     /// Path prefix to use within the storage location
@@ -29,6 +54,38 @@ pub struct ParquetWriterConfig {
     pub storage_quota: Option<usize>,
     /// Optional key-value metadata to include in parquet files
     pub key_value_metadata: Option<Vec<KeyValue>>,
+    /// Optional wall-clock interval after which a file holding data is rotated
+    pub flush_interval: Option<Duration>,
+    /// Node identity recorded in the run manifest and run summary
+    pub node_id: Option<String>,
+    /// Keep a JSON manifest describing the run at `<storage_prefix>manifest.json`,
+    /// rewritten every time a file is closed
+    pub write_manifest: bool,
+    /// On clean close, write a JSON summary of the run to
+    /// `<storage_prefix>run-<run id>.json`
+    pub write_run_summary: bool,
+    /// Additional stores that receive a copy of every closed Parquet file
+    pub tee: Option<Arc<TeeConfig>>,
+    /// Place each file under `dt=<YYYY-MM-DD>/hr=<HH>/` (UTC), inserted after the
+    /// last `/` of `storage_prefix`, from the first timestamp written to it. Files
+    /// are then opened on their first write instead of when the previous one closes
+    pub partition_by_time: bool,
+    /// Added to timestamps to get nanoseconds since the Unix epoch, for data
+    /// timestamped with another clock (e.g. the monotonic clock used by BPF)
+    pub time_offset_ns: i64,
+    /// Identifier of the collector run, recorded in every file's key-value metadata
+    /// under `run_id` and in the manifest and run summary. Writers of one run
+    /// should share it; a random one is generated if unset
+    pub run_id: Option<String>,
+    /// Retries of uploads that fail with a transient error (timeouts, server
+    /// errors) before the write fails
+    pub write_retry: WriteRetry,
+    /// Files larger than this many bytes are uploaded in parts of this size
+    /// (multipart upload); `None` uses the object store's default (10 MiB)
+    pub multipart_threshold: Option<usize>,
+    /// Status reported on the control socket, updated as files are closed and
+    /// rows dropped
+    pub status: Option<Arc<CollectorStatus>>,
 }
 
 impl Default for ParquetWriterConfig {
@@ -40,22 +97,90 @@ impl Default for ParquetWriterConfig {
             max_row_group_size: 1024 * 1024,     // Default max row group size
             storage_quota: None,
             key_value_metadata: None,
+            flush_interval: None,
+            node_id: None,
+            write_manifest: false,
+            write_run_summary: false,
+            tee: None,
+            partition_by_time: false,
+            time_offset_ns: 0,
+            run_id: None,
+            write_retry: WriteRetry::default(),
+            multipart_threshold: None,
+            status: None,
         }
     }
 }
 
+/// Rows accepted by the writer that did not end up in a closed file, by cause
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedRows {
+    /// Buffered in a file that was discarded after a failed write
+    pub write_failure: u64,
+    /// Skipped because the storage quota was reached
+    pub storage_quota: u64,
+    /// Still queued when the shutdown drain timed out
+    pub shutdown: u64,
+}
+
+/// Uploads a Parquet file while computing the SHA-256 of its bytes, so the
+/// checksum is known once the file is closed without holding it in memory
+struct HashingWriter {
+    inner: ParquetObjectWriter,
+    hasher: Arc<Mutex<Sha256>>,
+}
+
+impl AsyncFileWriter for HashingWriter {
+    fn write(&mut self, bs: Bytes) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        self.hasher.lock().unwrap().update(&bs);
+        self.inner.write(bs)
+    }
+
+    fn complete(&mut self) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        self.inner.complete()
+    }
+}
+
+/// A file closed in the object store, as listed in the manifest and run summary
+struct ClosedFile {
+    path: Path,
+    rows: i64,
+    /// Hex-encoded SHA-256 of the file's bytes
+    sha256: String,
+}
+
 /// Handles writing record batches to parquet files in object storage
 pub struct ParquetWriter {
     store: Arc<dyn ObjectStore>,
     schema: SchemaRef,
-    current_writer: Option<AsyncArrowWriter<ParquetObjectWriter>>,
+    current_writer: Option<AsyncArrowWriter<HashingWriter>>,
     current_file_path: Option<Path>,
+    // Checksum of the bytes written to the current file so far
+    current_file_hasher: Arc<Mutex<Sha256>>,
+    // With time partitioning, the next file is opened by the next write
+    open_pending: bool,
 
     // Size tracking
     closed_files_size: usize,
     flushed_row_groups_size: usize,
     flushed_row_groups_count: usize,
     in_memory_size: usize,
+    // Rows written to the current file
+    current_file_rows: usize,
+    // Files closed successfully in the object store
+    files_closed: u64,
+
+    // Run manifest state: the most recent closed files with their row counts and
+    // checksums, totals over all closed files, and run bounds
+    run_id: String,
+    manifest_files: VecDeque<ClosedFile>,
+    manifest_max_files: usize,
+    manifest_files_omitted: u64,
+    manifest_total_rows: i64,
+    run_start: DateTime<Utc>,
+    run_end: Option<DateTime<Utc>>,
+    // Rows that never reached a closed file
+    dropped: DroppedRows,
 
     config: ParquetWriterConfig,
 }
@@ -68,14 +193,29 @@ impl ParquetWriter {
         config: ParquetWriterConfig,
     ) -> Result<Self> {
         let mut writer = Self {
-            store,
+            store: RetryStore::wrap(store, config.write_retry),
             schema,
             current_writer: None,
             current_file_path: None,
+            current_file_hasher: Arc::default(),
+            open_pending: false,
             closed_files_size: 0,
             flushed_row_groups_size: 0,
             flushed_row_groups_count: 0,
             in_memory_size: 0,
+            current_file_rows: 0,
+            files_closed: 0,
+            run_id: config
+                .run_id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            manifest_files: VecDeque::new(),
+            manifest_max_files: MANIFEST_MAX_FILES,
+            manifest_files_omitted: 0,
+            manifest_total_rows: 0,
+            run_start: Utc::now(),
+            run_end: None,
+            dropped: DroppedRows::default(),
             config,
         };
 
@@ -85,8 +225,9 @@ impl ParquetWriter {
         Ok(writer)
     }
 
-    /// Generate a new file path with timestamp and UUID
-    fn generate_file_path(&self) -> Path {
+    /// Generate a new file path with timestamp and UUID, inside the partition for
+    /// `partition_time` if given
+    fn generate_file_path(&self, partition_time: Option<DateTime<Utc>>) -> Path {
         let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
         let uuid = Uuid::new_v4()
             .to_string()
@@ -95,22 +236,48 @@ impl ParquetWriter {
             .collect::<String>();
 
         // Include the prefix from config directly in the filename
-        let filename = format!(
-            "{}{}-{}.parquet",
-            self.config.storage_prefix, timestamp, uuid
-        );
+        let prefix = &self.config.storage_prefix;
+        let filename = match partition_time {
+            Some(time) => {
+                let (dir, name) = prefix.split_at(prefix.rfind('/').map_or(0, |i| i + 1));
+                format!(
+                    "{}dt={}/hr={}/{}{}-{}.parquet",
+                    dir,
+                    time.format("%Y-%m-%d"),
+                    time.format("%H"),
+                    name,
+                    timestamp,
+                    uuid
+                )
+            }
+            None => format!("{}{}-{}.parquet", prefix, timestamp, uuid),
+        };
 
         Path::from(filename)
     }
 
-    /// Create a new file and writer
+    /// Time of the batch's first row, or the current time without a time column
+    fn first_timestamp(&self, batch: &RecordBatch) -> DateTime<Utc> {
+        TIME_COLUMNS
+            .iter()
+            .find_map(|name| batch.column_by_name(name))
+            .and_then(|column| column.as_any().downcast_ref::<Int64Array>())
+            .filter(|column| !column.is_empty())
+            .map(|column| {
+                Utc.timestamp_nanos(column.value(0).saturating_add(self.config.time_offset_ns))
+            })
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Create a new file and writer, or with time partitioning, have the next
+    /// write open it
     fn create_new_file(&mut self) -> Result<()> {
         // Close the current writer if it exists
         if self.current_writer.is_some() {
             // error if we try to create a new file while there is an open writer
-            return Err(anyhow!(
+            return Err(CollectorError::Storage(anyhow!(
                 "Cannot create new file while there is an open writer"
-            ));
+            )));
         }
 
         // Check quota before creating a new file
@@ -119,17 +286,38 @@ impl ParquetWriter {
             return Ok(());
         }
 
-        // Generate new file path
-        let path = self.generate_file_path();
+        if self.config.partition_by_time {
+            self.open_pending = true;
+            return Ok(());
+        }
+        self.open_file(self.generate_file_path(None))
+    }
+
+    /// Open a writer for a new file at `path`
+    fn open_file(&mut self, path: Path) -> Result<()> {
+        // Tag every file with the run it belongs to
+        let mut key_value_metadata = self.config.key_value_metadata.clone().unwrap_or_default();
+        key_value_metadata.push(KeyValue::new(RUN_ID_KEY.to_string(), self.run_id.clone()));
 
         // Create writer properties with Snappy compression
         let props = WriterProperties::builder()
             .set_compression(Compression::SNAPPY)
             .set_max_row_group_size(self.config.max_row_group_size)
-            .set_key_value_metadata(self.config.key_value_metadata.clone())
+            .set_key_value_metadata(Some(key_value_metadata))
             .build();
 
-        let object_writer = ParquetObjectWriter::new(self.store.clone(), path.clone());
+        self.current_file_hasher = Arc::default();
+        let object_writer = HashingWriter {
+            inner: match self.config.multipart_threshold {
+                Some(size) => ParquetObjectWriter::from_buf_writer(BufWriter::with_capacity(
+                    self.store.clone(),
+                    path.clone(),
+                    size,
+                )),
+                None => ParquetObjectWriter::new(self.store.clone(), path.clone()),
+            },
+            hasher: self.current_file_hasher.clone(),
+        };
 
         let options = ArrowWriterOptions::new().with_properties(props);
         let writer =
@@ -138,6 +326,7 @@ impl ParquetWriter {
         // Store the writer and path
         self.current_writer = Some(writer);
         self.current_file_path = Some(path.clone());
+        self.current_file_rows = 0;
 
         debug!("Created new parquet writer for path: {}", path);
 
@@ -214,12 +403,21 @@ impl ParquetWriter {
     pub async fn write(&mut self, batch: RecordBatch) -> Result<()> {
         // Skip writing if we've exceeded quota
         if !self.is_below_quota() {
+            self.dropped.storage_quota += batch.num_rows() as u64;
+            self.report_dropped_rows(batch.num_rows() as u64);
             return Ok(());
         }
 
+        if self.open_pending {
+            self.open_pending = false;
+            let time = self.first_timestamp(&batch);
+            self.open_file(self.generate_file_path(Some(time)))?;
+        }
+
         if let Some(writer) = &mut self.current_writer {
             // Write the batch
             writer.write(&batch).await?;
+            self.current_file_rows += batch.num_rows();
 
             // Update size tracking
             self.update_current_writer_size()?;
@@ -247,12 +445,22 @@ impl ParquetWriter {
             // Check if we need to rotate the file
             self.maybe_rotate_file().await?;
         } else {
-            return Err(anyhow!("No writer available"));
+            return Err(CollectorError::Storage(anyhow!("No writer available")));
         }
 
         Ok(())
     }
 
+    /// Interval after which the current file should be rotated if it holds data
+    pub fn flush_interval(&self) -> Option<Duration> {
+        self.config.flush_interval
+    }
+
+    /// Whether the current file has rows that are not yet in a closed file
+    pub fn has_buffered_rows(&self) -> bool {
+        self.current_writer.is_some() && self.current_file_rows > 0
+    }
+
     /// Flush any pending data
     pub async fn flush(&mut self) -> Result<()> {
         if let Some(writer) = &mut self.current_writer {
@@ -262,38 +470,71 @@ impl ParquetWriter {
         Ok(())
     }
 
-    /// Close the writer, finishing the Parquet file
+    /// Close the writer, finishing the Parquet file, the run manifest and the
+    /// run summary
     pub async fn close(mut self) -> Result<()> {
         debug!("Closing ParquetWriter instance");
-        self.close_writer().await
+        self.run_end = Some(Utc::now());
+        let has_open_file = self.current_writer.is_some();
+        self.close_writer().await?;
+        if !has_open_file {
+            // close_writer only refreshes the manifest when it closes a file
+            self.write_manifest().await;
+        }
+        self.write_run_summary().await
     }
 
     /// Close the writer, finishing the Parquet file
     async fn close_writer(&mut self) -> Result<()> {
         if let Some(writer) = self.current_writer.take() {
             let metadata = writer.close().await?;
+            let num_rows: i64 = metadata.row_groups.iter().map(|rg| rg.num_rows).sum();
+            let sha256 = format!(
+                "{:x}",
+                std::mem::take(&mut *self.current_file_hasher.lock().unwrap()).finalize()
+            );
 
             // Log the metadata details
             debug!(
-                "Closed parquet file at path '{}' with {} row groups, {} rows",
+                "Closed parquet file at path '{}' with {} row groups, {} rows, sha256 {}",
                 self.current_file_path
                     .as_ref()
                     .map(|p| p.to_string())
                     .unwrap_or_default(),
                 metadata.row_groups.len(),
-                metadata
-                    .row_groups
-                    .iter()
-                    .map(|rg| rg.num_rows)
-                    .sum::<i64>()
+                num_rows,
+                sha256
             );
 
             // Update closed files size from the metadata
-            for row_group in &metadata.row_groups {
-                if let Some(size) = row_group.total_compressed_size {
-                    self.closed_files_size += size as usize;
+            let file_size: usize = metadata
+                .row_groups
+                .iter()
+                .filter_map(|row_group| row_group.total_compressed_size)
+                .map(|size| size as usize)
+                .sum();
+            self.closed_files_size += file_size;
+            self.files_closed += 1;
+            if let Some(status) = &self.config.status {
+                status.record_file(file_size as u64);
+            }
+
+            // The file is in the primary store whatever happens to its copies, so
+            // it is recorded before a failed copy is reported
+            let mut tee_result = Ok(());
+            if let Some(path) = self.current_file_path.clone() {
+                self.record_closed_file(ClosedFile {
+                    path: path.clone(),
+                    rows: num_rows,
+                    sha256,
+                });
+                if let Some(tee) = &self.config.tee {
+                    tee_result = tee.replicate(&self.store, &path).await;
                 }
             }
+            self.write_manifest().await;
+            self.update_current_writer_size()?;
+            return tee_result;
         }
 
         self.update_current_writer_size()?;
@@ -301,6 +542,187 @@ impl ParquetWriter {
         Ok(())
     }
 
+    /// Path of the run manifest object
+    pub fn manifest_path(&self) -> Path {
+        Path::from(format!("{}{}", self.config.storage_prefix, MANIFEST_NAME))
+    }
+
+    /// Path of the run summary object
+    pub fn run_summary_path(&self) -> Path {
+        Path::from(format!(
+            "{}run-{}.json",
+            self.config.storage_prefix, self.run_id
+        ))
+    }
+
+    /// Identifier of this run, unique per writer
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Record rows that were dropped before reaching the writer during shutdown
+    pub fn record_shutdown_drops(&mut self, rows: u64) {
+        self.dropped.shutdown += rows;
+        self.report_dropped_rows(rows);
+    }
+
+    fn report_dropped_rows(&self, rows: u64) {
+        if let Some(status) = &self.config.status {
+            status.record_dropped_rows(rows);
+        }
+    }
+
+    /// Rows dropped so far, by cause
+    pub fn dropped_rows(&self) -> DroppedRows {
+        self.dropped
+    }
+
+    /// Keep a closed file for the manifest and run summary, if either is written
+    fn record_closed_file(&mut self, file: ClosedFile) {
+        if !self.config.write_manifest && !self.config.write_run_summary {
+            return;
+        }
+        self.manifest_total_rows += file.rows;
+        self.manifest_files.push_back(file);
+        if self.manifest_files.len() > self.manifest_max_files {
+            self.manifest_files.pop_front();
+            self.manifest_files_omitted += 1;
+        }
+    }
+
+    /// The most recent closed files with their row counts and checksums, and the
+    /// total row count over all closed files
+    fn files_json(&self) -> (Vec<serde_json::Value>, i64) {
+        let files = self
+            .manifest_files
+            .iter()
+            .map(|file| {
+                json!({
+                    "path": file.path.to_string(),
+                    "rows": file.rows,
+                    "sha256": file.sha256,
+                })
+            })
+            .collect();
+        (files, self.manifest_total_rows)
+    }
+
+    fn format_time(time: DateTime<Utc>) -> String {
+        time.to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    /// JSON summary of a cleanly finished run
+    fn run_summary_json(&self) -> serde_json::Value {
+        let (files, total_rows) = self.files_json();
+        json!({
+            "run_id": self.run_id,
+            "node_id": self.config.node_id,
+            "collector_version": env!("CARGO_PKG_VERSION"),
+            "run_start": Self::format_time(self.run_start),
+            "run_end": self.run_end.map(Self::format_time),
+            "files": files,
+            "files_omitted": self.manifest_files_omitted,
+            "total_rows": total_rows,
+            "dropped_rows": {
+                "write_failure": self.dropped.write_failure,
+                "storage_quota": self.dropped.storage_quota,
+                "shutdown": self.dropped.shutdown,
+            },
+        })
+    }
+
+    /// Write the run summary, if enabled
+    async fn write_run_summary(&mut self) -> Result<()> {
+        if !self.config.write_run_summary {
+            return Ok(());
+        }
+        let body = serde_json::to_vec_pretty(&self.run_summary_json())?;
+        let path = self.run_summary_path();
+        self.store.put(&path, body.into()).await?;
+        info!("Wrote run summary to '{}'", path);
+        Ok(())
+    }
+
+    /// JSON manifest describing the files written so far in this run
+    fn manifest_json(&self) -> serde_json::Value {
+        let schema: Vec<_> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| {
+                json!({
+                    "name": f.name(),
+                    "data_type": f.data_type().to_string(),
+                    "nullable": f.is_nullable(),
+                })
+            })
+            .collect();
+        let (files, total_rows) = self.files_json();
+
+        json!({
+            "collector_version": env!("CARGO_PKG_VERSION"),
+            "node_id": self.config.node_id,
+            "run_id": self.run_id,
+            "run_start": Self::format_time(self.run_start),
+            "run_end": self.run_end.map(Self::format_time),
+            "schema": schema,
+            "files": files,
+            "files_omitted": self.manifest_files_omitted,
+            "total_rows": total_rows,
+        })
+    }
+
+    /// Write (or overwrite) the run manifest, if enabled. The data files are
+    /// already stored, so a failure is only logged; the next closed file retries.
+    async fn write_manifest(&mut self) {
+        if !self.config.write_manifest {
+            return;
+        }
+        let body = match serde_json::to_vec_pretty(&self.manifest_json()) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to encode run manifest: {}", e);
+                return;
+            }
+        };
+        let path = self.manifest_path();
+        match self.store.put(&path, body.into()).await {
+            Ok(_) => debug!("Wrote run manifest to '{}'", path),
+            Err(e) => warn!("Failed to write run manifest to '{}': {}", path, e),
+        }
+    }
+
+    /// Number of files closed successfully, i.e. confirmed written to the store
+    pub fn files_closed(&self) -> u64 {
+        self.files_closed
+    }
+
+    /// Drop the current file without finishing it (e.g. after a failed write left
+    /// it unusable) and start a new one. Rows buffered in the dropped file are lost.
+    pub fn discard_current_file(&mut self) -> Result<()> {
+        if let Some(path) = self.current_file_path.take() {
+            debug!("Discarding parquet file '{}'", path);
+        }
+        if self.current_writer.take().is_some() {
+            self.dropped.write_failure += self.current_file_rows as u64;
+            self.report_dropped_rows(self.current_file_rows as u64);
+        }
+        self.update_current_writer_size()?;
+        self.create_new_file()
+    }
+
+    /// Write subsequent files to a different object store, discarding the current file
+    pub fn set_store(&mut self, store: Arc<dyn ObjectStore>) -> Result<()> {
+        if self.current_writer.take().is_some() {
+            self.dropped.write_failure += self.current_file_rows as u64;
+            self.report_dropped_rows(self.current_file_rows as u64);
+        }
+        self.current_file_path = None;
+        self.store = RetryStore::wrap(store, self.config.write_retry);
+        self.update_current_writer_size()?;
+        self.create_new_file()
+    }
+
     /// Rotate the current parquet file, closing the current one and creating a new one
     pub async fn rotate(&mut self) -> Result<()> {
         debug!("Rotating parquet file");
@@ -336,7 +758,7 @@ mod tests {
     }
 
     /// Create a test record batch with known data
-    fn create_test_batch(schema: SchemaRef) -> Result<RecordBatch> {
+    fn create_test_batch(schema: SchemaRef) -> anyhow::Result<RecordBatch> {
         let mut id_builder = Int32Builder::with_capacity(2);
         let mut name_builder = StringBuilder::with_capacity(2, 20);
         let mut value_builder = Float64Builder::with_capacity(2);
@@ -470,6 +892,17 @@ mod tests {
             max_row_group_size: 10,  // Small row group size
             storage_quota: None,
             key_value_metadata: None,
+            flush_interval: None,
+            node_id: None,
+            write_manifest: false,
+            write_run_summary: false,
+            tee: None,
+            partition_by_time: false,
+            time_offset_ns: 0,
+            run_id: None,
+            write_retry: WriteRetry::default(),
+            multipart_threshold: None,
+            status: None,
         };
 
         let mut writer =
@@ -562,6 +995,17 @@ mod tests {
             max_row_group_size: 1024 * 1024,
             storage_quota: None,
             key_value_metadata: Some(metadata.clone()),
+            flush_interval: None,
+            node_id: None,
+            write_manifest: false,
+            write_run_summary: false,
+            tee: None,
+            partition_by_time: false,
+            time_offset_ns: 0,
+            run_id: None,
+            write_retry: WriteRetry::default(),
+            multipart_threshold: None,
+            status: None,
         };
 
         let mut writer =
@@ -620,4 +1064,466 @@ mod tests {
             "collection_version value should match"
         );
     }
+
+    #[tokio::test]
+    async fn test_run_id_tags_every_file() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        // Two writers of the same run, e.g. the main and resctrl streams
+        let memory_storage = Arc::new(InMemory::new());
+        for prefix in ["main-", "resctrl-"] {
+            let config = ParquetWriterConfig {
+                storage_prefix: prefix.to_string(),
+                write_manifest: true,
+                run_id: Some("run-1".to_string()),
+                ..Default::default()
+            };
+            let mut writer =
+                ParquetWriter::new(memory_storage.clone(), schema.clone(), config).unwrap();
+            assert_eq!(writer.run_id(), "run-1");
+            writer.write(test_batch.clone()).await.unwrap();
+            writer.rotate().await.unwrap();
+            writer.write(test_batch.clone()).await.unwrap();
+            writer.close().await.unwrap();
+        }
+
+        let files: Vec<_> = memory_storage
+            .list(None)
+            .map(|meta| meta.unwrap().location)
+            .collect()
+            .await;
+        let mut parquet_files = 0;
+        for path in files {
+            let bytes = memory_storage
+                .get(&path)
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            if path.as_ref().ends_with("manifest.json") {
+                let manifest: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                assert_eq!(manifest["run_id"], "run-1");
+                continue;
+            }
+            let builder = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+            let run_id = builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|kvs| kvs.iter().find(|kv| kv.key == RUN_ID_KEY))
+                .and_then(|kv| kv.value.clone());
+            assert_eq!(run_id.as_deref(), Some("run-1"), "{}", path);
+            parquet_files += 1;
+        }
+        assert_eq!(parquet_files, 4);
+
+        // Without a configured run id, each writer generates its own
+        let writer =
+            ParquetWriter::new(memory_storage, schema, ParquetWriterConfig::default()).unwrap();
+        assert!(Uuid::parse_str(writer.run_id()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_manifest() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let memory_storage = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            storage_prefix: "node-a/".to_string(),
+            node_id: Some("node-a".to_string()),
+            write_manifest: true,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new(memory_storage.clone(), schema.clone(), config).unwrap();
+        let manifest_path = writer.manifest_path();
+        assert_eq!(manifest_path.as_ref(), "node-a/manifest.json");
+
+        let read_manifest = || async {
+            let bytes = memory_storage
+                .get(&manifest_path)
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+
+        // Rotation refreshes the manifest while the run is still in progress
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.rotate().await.unwrap();
+        let manifest = read_manifest().await;
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 1);
+        assert!(manifest["run_end"].is_null());
+
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.write(test_batch).await.unwrap();
+        writer.close().await.unwrap();
+
+        let manifest = read_manifest().await;
+        assert_eq!(manifest["node_id"], "node-a");
+        assert_eq!(manifest["collector_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest["total_rows"], 6);
+        assert!(manifest["run_start"].is_string());
+        assert!(manifest["run_end"].is_string());
+
+        let fields: Vec<_> = manifest["schema"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(fields, ["id", "name", "value", "active"]);
+        assert_eq!(manifest["schema"][1]["data_type"], "Utf8");
+        assert_eq!(manifest["schema"][1]["nullable"], true);
+
+        // Every listed file exists in the store with the recorded row count
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        let rows: Vec<_> = files.iter().map(|f| f["rows"].as_i64().unwrap()).collect();
+        assert_eq!(rows, [2, 4]);
+        for file in files {
+            let path = Path::from(file["path"].as_str().unwrap());
+            assert!(path.as_ref().ends_with(".parquet"));
+            memory_storage.head(&path).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_manifest_lists_most_recent_files() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let memory_storage = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            write_manifest: true,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new(memory_storage.clone(), schema.clone(), config).unwrap();
+        writer.manifest_max_files = 2;
+        for i in 0..3 {
+            if i > 0 {
+                writer.rotate().await.unwrap();
+            }
+            writer.write(test_batch.clone()).await.unwrap();
+        }
+        let manifest_path = writer.manifest_path();
+        writer.close().await.unwrap();
+
+        let bytes = memory_storage
+            .get(&manifest_path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 2);
+        assert_eq!(manifest["files_omitted"], 1);
+        assert_eq!(manifest["total_rows"], 6);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_failure_does_not_fail_rotation() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        // A directory where the manifest object should go makes its writes fail
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("node-a").join(MANIFEST_NAME)).unwrap();
+        let store =
+            Arc::new(object_store::local::LocalFileSystem::new_with_prefix(dir.path()).unwrap());
+        let config = ParquetWriterConfig {
+            storage_prefix: "node-a/".to_string(),
+            write_manifest: true,
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new(store, schema, config).unwrap();
+
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.rotate().await.unwrap();
+        assert_eq!(writer.files_closed(), 1);
+        writer.write(test_batch).await.unwrap();
+        writer.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_manifest_checksums_match_stored_files() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let memory_storage = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            storage_prefix: "node-a/".to_string(),
+            write_manifest: true,
+            // Flush every batch as its own row group, so files span several writes
+            max_row_group_size: 1,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new(memory_storage.clone(), schema.clone(), config).unwrap();
+        let manifest_path = writer.manifest_path();
+
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.rotate().await.unwrap();
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.write(test_batch).await.unwrap();
+        writer.close().await.unwrap();
+
+        let bytes = memory_storage
+            .get(&manifest_path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        for file in files {
+            let path = Path::from(file["path"].as_str().unwrap());
+            let stored = memory_storage
+                .get(&path)
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            let expected = format!("{:x}", Sha256::digest(&stored));
+            assert_eq!(file["sha256"], expected.as_str(), "{}", path);
+        }
+        assert_ne!(files[0]["sha256"], files[1]["sha256"]);
+    }
+
+    #[tokio::test]
+    async fn test_status_counts_closed_files_and_dropped_rows() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let memory_storage = Arc::new(InMemory::new());
+        let status = Arc::new(CollectorStatus::new("run"));
+        let config = ParquetWriterConfig {
+            // Reached by the first file
+            storage_quota: Some(1),
+            status: Some(status.clone()),
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new(memory_storage, schema, config).unwrap();
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.rotate().await.unwrap();
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.close().await.unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&status.to_json()).unwrap();
+        assert_eq!(json["files_written"], 1);
+        assert!(json["bytes_written"].as_u64().unwrap() > 0);
+        assert_eq!(json["rows_dropped"], test_batch.num_rows() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_threshold_uploads_complete_file() {
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let memory_storage = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            // Far below the file size, so the file is uploaded in many parts
+            multipart_threshold: Some(256),
+            max_row_group_size: 1,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new(memory_storage.clone(), schema.clone(), config).unwrap();
+        for _ in 0..3 {
+            writer.write(test_batch.clone()).await.unwrap();
+        }
+        writer.close().await.unwrap();
+
+        let files: Vec<_> = memory_storage
+            .list(None)
+            .map(|meta| meta.unwrap().location)
+            .collect()
+            .await;
+        assert_eq!(files.len(), 1);
+        let bytes = memory_storage
+            .get(&files[0])
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert!(bytes.len() > 256);
+        let rows: usize = ParquetRecordBatchReaderBuilder::try_new(bytes)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(rows, 3 * test_batch.num_rows());
+    }
+
+    #[tokio::test]
+    async fn test_rotated_files_copied_to_tee_store() {
+        use crate::tee::{TeeFailurePolicy, TeeStore};
+
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        let primary = Arc::new(InMemory::new());
+        let secondary: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            storage_prefix: "tee-".to_string(),
+            tee: Some(Arc::new(TeeConfig {
+                stores: vec![TeeStore {
+                    name: "memory".to_string(),
+                    store: secondary.clone(),
+                }],
+                policy: TeeFailurePolicy::RequireAll,
+                max_attempts: 1,
+                retry_backoff: Duration::ZERO,
+            })),
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new(primary.clone(), schema, config).unwrap();
+
+        writer.write(test_batch.clone()).await.unwrap();
+        writer.rotate().await.unwrap();
+        writer.write(test_batch).await.unwrap();
+        writer.close().await.unwrap();
+
+        // Both stores hold the same two files with identical contents
+        let list = |store: Arc<dyn ObjectStore>| async move {
+            let mut paths: Vec<Path> = store
+                .list(None)
+                .map(|m| m.unwrap().location)
+                .collect()
+                .await;
+            paths.sort();
+            paths
+        };
+        let files = list(primary.clone()).await;
+        assert_eq!(files.len(), 2);
+        assert_eq!(list(secondary.clone()).await, files);
+        for path in &files {
+            let a = primary.get(path).await.unwrap().bytes().await.unwrap();
+            let b = secondary.get(path).await.unwrap().bytes().await.unwrap();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_tee_copy_keeps_file_in_manifest() {
+        use crate::tee::{TeeFailurePolicy, TeeStore};
+
+        let schema = create_test_schema();
+        let test_batch = create_test_batch(schema.clone()).unwrap();
+
+        // A tee store rooted at a regular file rejects every copy
+        let blocker = tempfile::NamedTempFile::new().unwrap();
+        let broken: Arc<dyn ObjectStore> = Arc::new(
+            object_store::local::LocalFileSystem::new_with_prefix(blocker.path()).unwrap(),
+        );
+        let primary = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            write_manifest: true,
+            tee: Some(Arc::new(TeeConfig {
+                stores: vec![TeeStore {
+                    name: "broken".to_string(),
+                    store: broken,
+                }],
+                policy: TeeFailurePolicy::RequireAll,
+                max_attempts: 1,
+                retry_backoff: Duration::ZERO,
+            })),
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new(primary.clone(), schema, config).unwrap();
+
+        writer.write(test_batch).await.unwrap();
+        assert!(writer.rotate().await.is_err());
+        assert_eq!(writer.files_closed(), 1);
+
+        let bytes = primary
+            .get(&writer.manifest_path())
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        primary
+            .head(&Path::from(files[0]["path"].as_str().unwrap()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_partition_by_time_uses_first_timestamp() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Int64, false),
+            Field::new("value", DataType::Float64, false),
+        ]));
+        let batch = |timestamps: Vec<i64>| {
+            let values = vec![1.0; timestamps.len()];
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(timestamps)),
+                    Arc::new(arrow_array::Float64Array::from(values)),
+                ],
+            )
+            .unwrap()
+        };
+        // 2024-01-02T15:30:00Z and 2024-01-02T16:05:00Z
+        let first = 1_704_209_400_000_000_000i64;
+        let second = 1_704_211_500_000_000_000i64;
+
+        // Timestamps are relative to an arbitrary clock, offset to the epoch
+        let offset = first - 1_000;
+        let store = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            storage_prefix: "metrics/node1-".to_string(),
+            partition_by_time: true,
+            time_offset_ns: offset,
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new(store.clone(), schema.clone(), config).unwrap();
+
+        // The first timestamp of each file picks its partition
+        writer
+            .write(batch(vec![1_000, second - offset]))
+            .await
+            .unwrap();
+        writer.rotate().await.unwrap();
+        writer.write(batch(vec![second - offset])).await.unwrap();
+        // No empty file is written for the partition opened by the rotation
+        writer.rotate().await.unwrap();
+        writer.close().await.unwrap();
+
+        let mut paths: Vec<String> = store
+            .list(None)
+            .map(|m| m.unwrap().location.to_string())
+            .collect()
+            .await;
+        paths.sort();
+        assert_eq!(paths.len(), 2, "{:?}", paths);
+        assert!(
+            paths[0].starts_with("metrics/dt=2024-01-02/hr=15/node1-"),
+            "{}",
+            paths[0]
+        );
+        assert!(
+            paths[1].starts_with("metrics/dt=2024-01-02/hr=16/node1-"),
+            "{}",
+            paths[1]
+        );
+        assert!(paths.iter().all(|p| p.ends_with(".parquet")));
+    }
 }
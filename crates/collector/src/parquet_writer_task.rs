@@ -1,20 +1,121 @@
-use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
 use arrow_array::RecordBatch;
+use async_trait::async_trait;
+use object_store::ObjectStore;
 use tokio::sync::mpsc;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
 
+use crate::error::Result;
 use crate::parquet_writer::ParquetWriter;
 
-/// Worker task for processing record batches and writing them to parquet
-pub struct ParquetWriterTask {
+/// Default upper bound on how long the task keeps running after shutdown
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time writes may keep failing before the failure policy applies, so a
+/// transient store error (e.g. during a requested rotation) does not stop the task
+pub const DEFAULT_FAILURE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What the writer task does once writes have kept failing for the failure timeout
+pub enum WriteFailurePolicy {
+    /// Return an error from the task, shutting the collector down
+    Terminate,
+    /// Write subsequent files to a fallback store (e.g. local disk) instead
+    Spill(Arc<dyn ObjectStore>),
+}
+
+/// Output format writer driven by `ParquetWriterTask`
+#[async_trait]
+pub trait BatchWriter: Send + Sized {
+    /// Write a record batch
+    async fn write(&mut self, batch: RecordBatch) -> Result<()>;
+
+    /// Finish the current file and start a new one
+    async fn rotate(&mut self) -> Result<()>;
+
+    /// Finish all output
+    async fn close(self) -> Result<()>;
+
+    /// Interval after which the current file should be rotated if it holds data
+    fn flush_interval(&self) -> Option<Duration>;
+
+    /// Whether the current file has rows that are not yet in a closed file
+    fn has_buffered_rows(&self) -> bool;
+
+    /// Number of files closed successfully
+    fn files_closed(&self) -> u64;
+
+    /// Record rows that were dropped before reaching the writer during shutdown
+    fn record_shutdown_drops(&mut self, rows: u64);
+
+    /// Drop the current file after a failed write and start a new one
+    fn discard_current_file(&mut self) -> Result<()>;
+
+    /// Write subsequent files to a different object store
+    fn set_store(&mut self, store: Arc<dyn ObjectStore>) -> Result<()>;
+}
+
+#[async_trait]
+impl BatchWriter for ParquetWriter {
+    async fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        ParquetWriter::write(self, batch).await
+    }
+
+    async fn rotate(&mut self) -> Result<()> {
+        ParquetWriter::rotate(self).await
+    }
+
+    async fn close(self) -> Result<()> {
+        ParquetWriter::close(self).await
+    }
+
+    fn flush_interval(&self) -> Option<Duration> {
+        ParquetWriter::flush_interval(self)
+    }
+
+    fn has_buffered_rows(&self) -> bool {
+        ParquetWriter::has_buffered_rows(self)
+    }
+
+    fn files_closed(&self) -> u64 {
+        ParquetWriter::files_closed(self)
+    }
+
+    fn record_shutdown_drops(&mut self, rows: u64) {
+        ParquetWriter::record_shutdown_drops(self, rows)
+    }
+
+    fn discard_current_file(&mut self) -> Result<()> {
+        ParquetWriter::discard_current_file(self)
+    }
+
+    fn set_store(&mut self, store: Arc<dyn ObjectStore>) -> Result<()> {
+        ParquetWriter::set_store(self, store)
+    }
+}
+
+/// Worker task for processing record batches and writing them to parquet, or
+/// another output format through `BatchWriter`
+pub struct ParquetWriterTask<W = ParquetWriter> {
     batch_receiver: mpsc::Receiver<RecordBatch>,
-    writer: ParquetWriter,
+    writer: W,
     rotate_receiver: mpsc::Receiver<()>,
+    shutdown_token: CancellationToken,
+    drain_timeout: Duration,
+    failure_policy: WriteFailurePolicy,
+    failure_timeout: Duration,
+    // Start of the current run of failures (reset when a file closes successfully)
+    failing_since: Option<Instant>,
+    files_closed_seen: u64,
+    spilling: bool,
 }
 
-impl ParquetWriterTask {
+impl<W: BatchWriter> ParquetWriterTask<W> {
     /// Create a new ParquetWriterTask with pre-configured channels
     pub fn new(
-        writer: ParquetWriter,
+        writer: W,
         batch_receiver: mpsc::Receiver<RecordBatch>,
         rotate_receiver: mpsc::Receiver<()>,
     ) -> Self {
@@ -22,18 +123,116 @@ impl ParquetWriterTask {
             batch_receiver,
             writer,
             rotate_receiver,
+            shutdown_token: CancellationToken::new(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            failure_policy: WriteFailurePolicy::Terminate,
+            failure_timeout: DEFAULT_FAILURE_TIMEOUT,
+            failing_since: None,
+            files_closed_seen: 0,
+            spilling: false,
+        }
+    }
+
+    /// Watch `shutdown_token`; once it is cancelled, finish within `drain_timeout`.
+    /// Batches already queued (or still arriving) are written during the first half
+    /// of it, and the rest is left to close the current file. A file that cannot be
+    /// closed in time is abandoned, so it never appears in the store incomplete.
+    pub fn with_drain(
+        mut self,
+        shutdown_token: CancellationToken,
+        drain_timeout: Duration,
+    ) -> Self {
+        self.shutdown_token = shutdown_token;
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// Tolerate write failures for up to `failure_timeout`, discarding the affected
+    /// file and retrying with a new one, then apply `policy`. A failure run ends
+    /// when a file is closed successfully. Without this, failures are tolerated
+    /// for `DEFAULT_FAILURE_TIMEOUT` and then terminate the task.
+    pub fn with_failure_policy(
+        mut self,
+        policy: WriteFailurePolicy,
+        failure_timeout: Duration,
+    ) -> Self {
+        self.failure_policy = policy;
+        self.failure_timeout = failure_timeout;
+        self
+    }
+
+    /// Track the outcome of a writer operation and decide whether to keep going.
+    /// Returns an error only when the task should stop.
+    fn handle_write_result(&mut self, result: Result<()>) -> Result<()> {
+        let err = match result {
+            Ok(()) => {
+                if self.writer.files_closed() != self.files_closed_seen {
+                    self.files_closed_seen = self.writer.files_closed();
+                    if self.failing_since.take().is_some() {
+                        log::info!("Parquet writes recovered");
+                    }
+                }
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+
+        let failing_since = *self.failing_since.get_or_insert_with(Instant::now);
+        if failing_since.elapsed() < self.failure_timeout {
+            log::warn!(
+                "Parquet write failed (failing for {:?}), discarding current file: {:#}",
+                failing_since.elapsed(),
+                err
+            );
+            self.writer.discard_current_file()?;
+            return Ok(());
+        }
+
+        match &self.failure_policy {
+            WriteFailurePolicy::Spill(store) if !self.spilling => {
+                log::error!(
+                    "Parquet writes failing for {:?}, spilling to fallback store: {:#}",
+                    failing_since.elapsed(),
+                    err
+                );
+                self.writer.set_store(store.clone())?;
+                self.spilling = true;
+                self.failing_since = None;
+                Ok(())
+            }
+            _ => Err(err.context(format!(
+                "no parquet file written successfully for {:?}",
+                failing_since.elapsed()
+            ))),
+        }
+    }
+
+    /// Wait for the next flush tick, or forever when no flush interval is configured
+    async fn flush_tick(timer: &mut Option<Interval>) {
+        match timer {
+            Some(timer) => {
+                timer.tick().await;
+            }
+            None => std::future::pending().await,
         }
     }
 
     /// Run the task, processing record batches until the channel is closed
     pub async fn run(mut self) -> Result<()> {
+        let mut flush_timer = self.writer.flush_interval().map(|period| {
+            let mut timer = tokio::time::interval_at(Instant::now() + period, period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            timer
+        });
+        let mut drain = false;
         loop {
             tokio::select! {
                 batch_result = self.batch_receiver.recv() => {
                     match batch_result {
                         Some(batch) => {
                             // Write the batch
-                            self.writer.write(batch).await?;
+                            let res = self.writer.write(batch).await;
+                            self.handle_write_result(res)?;
                         }
                         None => {
                             // Channel closed - pipeline shutting down
@@ -44,17 +243,483 @@ impl ParquetWriterTask {
                 }
                 Some(_) = self.rotate_receiver.recv() => {
                     // Rotation signal received
-                    if let Err(e) = self.writer.rotate().await {
-                        log::warn!("Failed to rotate parquet file: {}", e);
-                    } else {
-                        log::info!("Parquet file rotated successfully");
+                    let res = self.writer.rotate().await;
+                    match &res {
+                        Ok(()) => log::info!("Parquet file rotated successfully"),
+                        Err(e) => log::warn!("Requested rotation failed: {:#}", e),
+                    }
+                    self.handle_write_result(res)?;
+                    // Restart the flush interval from this rotation
+                    if let Some(timer) = flush_timer.as_mut() {
+                        timer.reset();
+                    }
+                }
+                _ = Self::flush_tick(&mut flush_timer) => {
+                    if self.writer.has_buffered_rows() {
+                        log::debug!("Flush interval elapsed, rotating parquet file");
+                        let res = self.writer.rotate().await;
+                        self.handle_write_result(res)?;
                     }
                 }
+                _ = self.shutdown_token.cancelled() => {
+                    drain = true;
+                    break;
+                }
             }
         }
 
+        if drain {
+            let deadline = Instant::now() + self.drain_timeout;
+            self.drain(deadline - self.drain_timeout / 2).await?;
+            return Self::finalize(self.writer, deadline).await;
+        }
+
         // Close writer on shutdown
         log::debug!("Closing parquet writer");
         self.writer.close().await
     }
+
+    /// Write remaining batches until the channel closes or `deadline` passes
+    async fn drain(&mut self, deadline: Instant) -> Result<()> {
+        log::debug!(
+            "Shutdown requested, draining batches for up to {:?}",
+            deadline.saturating_duration_since(Instant::now())
+        );
+        let mut drained = 0usize;
+        loop {
+            match tokio::time::timeout_at(deadline, self.batch_receiver.recv()).await {
+                Ok(Some(batch)) => {
+                    let rows = batch.num_rows() as u64;
+                    match tokio::time::timeout_at(deadline, self.writer.write(batch)).await {
+                        Ok(res) => {
+                            self.handle_write_result(res)?;
+                            drained += 1;
+                        }
+                        Err(_) => {
+                            // The interrupted write leaves the current file unusable
+                            log::warn!(
+                                "Drain timeout of {:?} reached while writing a batch; discarding the current file",
+                                self.drain_timeout
+                            );
+                            self.writer.record_shutdown_drops(rows);
+                            self.writer.discard_current_file()?;
+                            self.drop_queued();
+                            break;
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    self.drop_queued();
+                    break;
+                }
+            }
+        }
+        log::info!("Wrote {} batches while draining on shutdown", drained);
+        Ok(())
+    }
+
+    /// Discard the batches still queued, recording their rows as shutdown drops
+    fn drop_queued(&mut self) {
+        let mut dropped_batches = 0usize;
+        let mut dropped_rows = 0u64;
+        while let Ok(batch) = self.batch_receiver.try_recv() {
+            dropped_batches += 1;
+            dropped_rows += batch.num_rows() as u64;
+        }
+        log::warn!(
+            "Drain timeout of {:?} reached; dropping {} queued batches ({} rows)",
+            self.drain_timeout,
+            dropped_batches,
+            dropped_rows
+        );
+        self.writer.record_shutdown_drops(dropped_rows);
+    }
+
+    /// Close the writer, giving up once `deadline` passes. The store only publishes
+    /// a file once its upload completes, so an abandoned file is lost, not corrupt.
+    async fn finalize(writer: W, deadline: Instant) -> Result<()> {
+        log::debug!("Closing parquet writer");
+        match tokio::time::timeout_at(deadline, writer.close()).await {
+            Ok(res) => res,
+            Err(_) => {
+                log::warn!("Drain timeout reached while closing the parquet writer; the current file was abandoned");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::builder::Int32Builder;
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use futures::StreamExt;
+    use object_store::local::LocalFileSystem;
+    use object_store::memory::InMemory;
+    use object_store::throttle::{ThrottleConfig, ThrottledStore};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::parquet_writer::ParquetWriterConfig;
+
+    fn create_test_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]))
+    }
+
+    fn create_test_batch(schema: SchemaRef, id: i32) -> RecordBatch {
+        let mut id_builder = Int32Builder::with_capacity(1);
+        id_builder.append_value(id);
+        RecordBatch::try_new(schema, vec![Arc::new(id_builder.finish())]).unwrap()
+    }
+
+    /// Count rows across all parquet files in the store
+    async fn count_rows(store: Arc<InMemory>) -> usize {
+        let files: Vec<_> = store.list(None).collect().await;
+        let mut rows = 0;
+        for file in files {
+            let bytes = store
+                .get(&file.unwrap().location)
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)
+                .unwrap()
+                .build()
+                .unwrap();
+            rows += reader.map(|b| b.unwrap().num_rows()).sum::<usize>();
+        }
+        rows
+    }
+
+    #[tokio::test]
+    async fn test_drain_writes_queued_batches_after_shutdown() {
+        let schema = create_test_schema();
+        let store = Arc::new(InMemory::new());
+        let writer = ParquetWriter::new(
+            store.clone(),
+            schema.clone(),
+            ParquetWriterConfig::default(),
+        )
+        .unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (_rotate_tx, rotate_rx) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+
+        // Queue batches, then cancel and drop the sender before the task runs
+        for i in 0..5 {
+            batch_tx
+                .send(create_test_batch(schema.clone(), i))
+                .await
+                .unwrap();
+        }
+        shutdown.cancel();
+        drop(batch_tx);
+
+        ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+            .with_drain(shutdown, Duration::from_secs(5))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(count_rows(store).await, 5);
+    }
+
+    #[tokio::test]
+    async fn test_drain_is_bounded_when_sender_stays_open() {
+        let schema = create_test_schema();
+        let store = Arc::new(InMemory::new());
+        let writer = ParquetWriter::new(
+            store.clone(),
+            schema.clone(),
+            ParquetWriterConfig::default(),
+        )
+        .unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (_rotate_tx, rotate_rx) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+
+        for i in 0..3 {
+            batch_tx
+                .send(create_test_batch(schema.clone(), i))
+                .await
+                .unwrap();
+        }
+        shutdown.cancel();
+
+        // Sender is kept alive, so only the deadline ends the drain
+        ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+            .with_drain(shutdown, Duration::from_millis(50))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(count_rows(store).await, 3);
+        drop(batch_tx);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_is_bounded_with_slow_store() {
+        let schema = create_test_schema();
+        let inner: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let slow = ThrottledStore::new(
+            inner.clone(),
+            ThrottleConfig {
+                wait_put_per_call: Duration::from_secs(60),
+                ..Default::default()
+            },
+        );
+        let writer = ParquetWriter::new(
+            Arc::new(slow),
+            schema.clone(),
+            ParquetWriterConfig::default(),
+        )
+        .unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (_rotate_tx, rotate_rx) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+
+        for i in 0..3 {
+            batch_tx
+                .send(create_test_batch(schema.clone(), i))
+                .await
+                .unwrap();
+        }
+        shutdown.cancel();
+
+        // Closing the file would take a minute; the drain timeout cuts it short
+        let start = Instant::now();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+                .with_drain(shutdown, Duration::from_millis(200))
+                .run(),
+        )
+        .await
+        .expect("shutdown bounded by the drain timeout")
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // The abandoned file never reached the store
+        assert!(inner.list(None).collect::<Vec<_>>().await.is_empty());
+        drop(batch_tx);
+    }
+
+    #[tokio::test]
+    async fn test_flush_interval_rotates_buffered_data() {
+        let schema = create_test_schema();
+        let store = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            flush_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let writer = ParquetWriter::new(store.clone(), schema.clone(), config).unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (_rotate_tx, rotate_rx) = mpsc::channel(1);
+        let handle = tokio::spawn(ParquetWriterTask::new(writer, batch_rx, rotate_rx).run());
+
+        batch_tx
+            .send(create_test_batch(schema.clone(), 1))
+            .await
+            .unwrap();
+
+        // Well below the size limit, the row becomes visible once the interval elapses
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(count_rows(store.clone()).await, 1);
+        // Idle intervals do not produce additional files
+        assert_eq!(store.list(None).collect::<Vec<_>>().await.len(), 1);
+
+        drop(batch_tx);
+        handle.await.unwrap().unwrap();
+    }
+
+    /// A store whose writes always fail: its root is a regular file, not a directory
+    fn failing_store() -> (tempfile::TempDir, Arc<dyn ObjectStore>) {
+        let dir = tempfile::tempdir().unwrap();
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, b"").unwrap();
+        let store = LocalFileSystem::new_with_prefix(&blocker).unwrap();
+        (dir, Arc::new(store))
+    }
+
+    /// Write a batch and force it out to the store twice, `gap` apart
+    async fn write_and_rotate_twice(
+        schema: SchemaRef,
+        batch_tx: &mpsc::Sender<RecordBatch>,
+        rotate_tx: &mpsc::Sender<()>,
+        gap: Duration,
+    ) {
+        for i in 0..2 {
+            batch_tx
+                .send(create_test_batch(schema.clone(), i))
+                .await
+                .unwrap();
+            rotate_tx.send(()).await.unwrap();
+            tokio::time::sleep(gap).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sustained_write_failure_terminates() {
+        let schema = create_test_schema();
+        let (_dir, store) = failing_store();
+        let writer =
+            ParquetWriter::new(store, schema.clone(), ParquetWriterConfig::default()).unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (rotate_tx, rotate_rx) = mpsc::channel(1);
+        let handle = tokio::spawn(
+            ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+                .with_failure_policy(WriteFailurePolicy::Terminate, Duration::from_millis(50))
+                .run(),
+        );
+
+        // The first failure is tolerated; the one past the timeout stops the task
+        write_and_rotate_twice(schema, &batch_tx, &rotate_tx, Duration::from_millis(100)).await;
+        let err = handle.await.unwrap().unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("no parquet file written successfully"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_rotation_is_tolerated_by_default() {
+        let schema = create_test_schema();
+        let (_dir, store) = failing_store();
+        let writer =
+            ParquetWriter::new(store, schema.clone(), ParquetWriterConfig::default()).unwrap();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (rotate_tx, rotate_rx) = mpsc::channel(1);
+        let handle = tokio::spawn(ParquetWriterTask::new(writer, batch_rx, rotate_rx).run());
+
+        // A requested rotation failing once discards the file and keeps writing
+        write_and_rotate_twice(schema, &batch_tx, &rotate_tx, Duration::from_millis(20)).await;
+        assert!(!handle.is_finished());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_sustained_write_failure_spills() {
+        let schema = create_test_schema();
+        let (_dir, store) = failing_store();
+        let writer =
+            ParquetWriter::new(store, schema.clone(), ParquetWriterConfig::default()).unwrap();
+        let spill = Arc::new(InMemory::new());
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (rotate_tx, rotate_rx) = mpsc::channel(1);
+        let handle = tokio::spawn(
+            ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+                .with_failure_policy(
+                    WriteFailurePolicy::Spill(spill.clone()),
+                    Duration::from_millis(50),
+                )
+                .run(),
+        );
+
+        write_and_rotate_twice(
+            schema.clone(),
+            &batch_tx,
+            &rotate_tx,
+            Duration::from_millis(100),
+        )
+        .await;
+        // Written after the switch, so it lands in the spill store
+        batch_tx
+            .send(create_test_batch(schema.clone(), 2))
+            .await
+            .unwrap();
+        drop(batch_tx);
+
+        handle.await.unwrap().unwrap();
+        assert!(count_rows(spill).await >= 1);
+        drop(rotate_tx);
+    }
+
+    #[tokio::test]
+    async fn test_run_summary_written_on_clean_shutdown() {
+        let schema = create_test_schema();
+        let store = Arc::new(InMemory::new());
+        let config = ParquetWriterConfig {
+            storage_prefix: "node-a/".to_string(),
+            node_id: Some("node-a".to_string()),
+            write_run_summary: true,
+            ..Default::default()
+        };
+        let writer = ParquetWriter::new(store.clone(), schema.clone(), config).unwrap();
+        let summary_path = writer.run_summary_path();
+        let run_id = writer.run_id().to_string();
+
+        let (batch_tx, batch_rx) = mpsc::channel(16);
+        let (rotate_tx, rotate_rx) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(
+            ParquetWriterTask::new(writer, batch_rx, rotate_rx)
+                .with_drain(shutdown.clone(), Duration::from_secs(5))
+                .run(),
+        );
+
+        // Rows before and after a rotation, the last ones written while draining
+        for i in 0..2 {
+            batch_tx
+                .send(create_test_batch(schema.clone(), i))
+                .await
+                .unwrap();
+        }
+        rotate_tx.send(()).await.unwrap();
+        batch_tx
+            .send(create_test_batch(schema.clone(), 2))
+            .await
+            .unwrap();
+        shutdown.cancel();
+        drop(batch_tx);
+        handle.await.unwrap().unwrap();
+
+        let bytes = store
+            .get(&summary_path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let summary: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(summary["run_id"], run_id.as_str());
+        assert_eq!(summary["node_id"], "node-a");
+        assert_eq!(summary["total_rows"], 3);
+        assert_eq!(summary["dropped_rows"]["shutdown"], 0);
+        assert_eq!(summary["dropped_rows"]["write_failure"], 0);
+        assert!(summary["run_start"].as_str().unwrap() <= summary["run_end"].as_str().unwrap());
+
+        // The summary lists exactly the parquet files in the store
+        let mut listed: Vec<String> = summary["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["path"].as_str().unwrap().to_string())
+            .collect();
+        let mut stored: Vec<String> = store
+            .list(None)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|m| m.unwrap().location.to_string())
+            .filter(|p| p.ends_with(".parquet"))
+            .collect();
+        listed.sort();
+        stored.sort();
+        assert!(!listed.is_empty());
+        assert_eq!(listed, stored);
+        drop(rotate_tx);
+    }
 }
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use arrow_array::RecordBatch;
 use tokio::sync::mpsc;
@@ -11,7 +12,10 @@ use crate::bpf_perf_to_timeslot::BpfPerfToTimeslot;
 use crate::bpf_perf_to_trace::BpfPerfToTrace;
 use crate::bpf_task_tracker::BpfTaskTracker;
 use crate::bpf_timeslot_tracker::BpfTimeslotTracker;
-use crate::timeslot_data::TimeslotData;
+use crate::comm_filter::CommFilter;
+use crate::control_socket::CollectorStatus;
+use crate::metrics::PerfEvents;
+use crate::timeslot_data::{Granularity, TimeslotData};
 
 /// Enum for selecting processor mode and channel type
 pub enum ProcessorMode {
@@ -33,11 +37,23 @@ pub struct PerfEventProcessor {
 }
 
 impl PerfEventProcessor {
-    // Create a new PerfEventProcessor with mode-specific configuration
+    // Create a new PerfEventProcessor with mode-specific configuration.
+    // `min_task_lifetime_ns`, `downsample_factor` and `granularity` only apply to
+    // timeslot mode, and
+    // `perf_events` to trace mode (timeslot rows are masked when converted to record
+    // batches). `max_tasks` bounds the task metadata table (0 = unbounded), and
+    // measurements of tasks whose comm does not match `comm_filter` are dropped.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         bpf_loader: &mut BpfLoader,
         num_cpus: usize,
         mode: ProcessorMode,
+        min_task_lifetime_ns: Option<u64>,
+        downsample_factor: u32,
+        granularity: Granularity,
+        perf_events: PerfEvents,
+        max_tasks: usize,
+        comm_filter: Option<CommFilter>,
     ) -> Rc<RefCell<Self>> {
         // Create BpfTimeslotTracker (always present)
         let timeslot_tracker = BpfTimeslotTracker::new(bpf_loader, num_cpus);
@@ -46,7 +62,8 @@ impl PerfEventProcessor {
         let error_handler = BpfErrorHandler::new(bpf_loader);
 
         // Create BpfTaskTracker with timeslot tracker reference
-        let task_tracker = BpfTaskTracker::new(bpf_loader, timeslot_tracker.clone());
+        let task_tracker =
+            BpfTaskTracker::new(bpf_loader, timeslot_tracker.clone(), max_tasks, comm_filter);
 
         // Create mode-specific processor
         let (perf_to_timeslot, perf_to_trace) = match mode {
@@ -57,6 +74,9 @@ impl PerfEventProcessor {
                     timeslot_tracker.clone(),
                     task_tracker.clone(),
                     timeslot_tx,
+                    min_task_lifetime_ns,
+                    downsample_factor,
+                    granularity,
                 );
                 (Some(perf_to_timeslot), None)
             }
@@ -68,6 +88,7 @@ impl PerfEventProcessor {
                     task_tracker.clone(),
                     batch_tx,
                     32 * 1024, // Default batch capacity
+                    perf_events,
                 );
                 (None, Some(perf_to_trace))
             }
@@ -82,11 +103,24 @@ impl PerfEventProcessor {
         }))
     }
 
+    /// Count timeslots dropped before conversion in `status` (timeslot mode only)
+    pub fn set_status(&mut self, status: Arc<CollectorStatus>) {
+        if let Some(ref timeslot_proc) = self._perf_to_timeslot {
+            timeslot_proc.borrow_mut().set_status(status);
+        }
+    }
+
     /// Take the receiver from the error handler for running the error reporting task
     pub fn take_error_receiver(&mut self) -> Option<mpsc::Receiver<ErrorEvent>> {
         self.error_handler.borrow_mut().take_receiver()
     }
 
+    /// Samples the perf rings reported lost so far. Timeslot mode also reports
+    /// the timeslots they were missing from.
+    pub fn lost_samples(&self) -> u64 {
+        self.error_handler.borrow().lost_samples()
+    }
+
     /// Run the error reporting task
     pub async fn run_error_reporting(receiver: mpsc::Receiver<ErrorEvent>) {
         BpfErrorHandler::run_error_reporting(receiver).await;
@@ -0,0 +1,376 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use log::warn;
+use object_store::path::Path;
+use object_store::{
+    Error, GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, ObjectStore,
+    PutMultipartOpts, PutOptions, PutPayload, PutResult, Result, UploadPart,
+};
+
+/// Retries of object store writes that failed with a transient error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteRetry {
+    /// Attempts per request, including the first (at least one is made)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub initial_backoff: Duration,
+}
+
+impl Default for WriteRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Whether a failed request may succeed when repeated.
+///
+/// Stores report requests they reject (authentication, missing objects, failed
+/// preconditions) as dedicated variants, leaving server errors, timeouts and
+/// I/O failures as generic errors. Of those, I/O errors are only transient when
+/// the connection failed, so a local directory that cannot be written fails fast.
+pub fn is_retryable(err: &Error) -> bool {
+    let Error::Generic { source, .. } = err else {
+        return false;
+    };
+    let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(source.as_ref());
+    while let Some(err) = cause {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::ConnectionRefused
+                    | ErrorKind::NotConnected
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::Interrupted
+                    | ErrorKind::UnexpectedEof
+            );
+        }
+        cause = err.source();
+    }
+    true
+}
+
+/// Attempts made so far at one request
+struct Attempts {
+    retry: WriteRetry,
+    attempt: u32,
+    backoff: Duration,
+}
+
+impl Attempts {
+    fn new(retry: WriteRetry) -> Self {
+        Self {
+            retry,
+            attempt: 1,
+            backoff: retry.initial_backoff,
+        }
+    }
+
+    /// After `err`, wait out the backoff and return true if the request should
+    /// be repeated, or return false if the error is final
+    async fn retry_after(&mut self, err: &Error, request: &str, location: &Path) -> bool {
+        if self.attempt >= self.retry.max_attempts || !is_retryable(err) {
+            return false;
+        }
+        warn!(
+            "{} of '{}' failed (attempt {}/{}), retrying in {:?}: {}",
+            request, location, self.attempt, self.retry.max_attempts, self.backoff, err
+        );
+        tokio::time::sleep(self.backoff).await;
+        self.attempt += 1;
+        self.backoff = self.backoff.saturating_mul(2);
+        true
+    }
+}
+
+/// Object store that retries failed writes with exponential backoff.
+///
+/// Covers single-request uploads, starting and completing multipart uploads.
+/// Parts of a multipart upload are numbered when they are sent, so they are
+/// left to the store client's own retries. Reads and listings pass through.
+#[derive(Debug)]
+pub struct RetryStore {
+    inner: Arc<dyn ObjectStore>,
+    retry: WriteRetry,
+}
+
+impl RetryStore {
+    /// Wrap `inner`, or return it unchanged if `retry` allows a single attempt
+    pub fn wrap(inner: Arc<dyn ObjectStore>, retry: WriteRetry) -> Arc<dyn ObjectStore> {
+        if retry.max_attempts <= 1 {
+            return inner;
+        }
+        Arc::new(Self { inner, retry })
+    }
+}
+
+impl Display for RetryStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RetryStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for RetryStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> Result<PutResult> {
+        let mut attempts = Attempts::new(self.retry);
+        loop {
+            match self
+                .inner
+                .put_opts(location, payload.clone(), opts.clone())
+                .await
+            {
+                Err(e) if attempts.retry_after(&e, "Upload", location).await => {}
+                result => return result,
+            }
+        }
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOpts,
+    ) -> Result<Box<dyn MultipartUpload>> {
+        let mut attempts = Attempts::new(self.retry);
+        loop {
+            match self.inner.put_multipart_opts(location, opts.clone()).await {
+                Ok(upload) => {
+                    return Ok(Box::new(RetryUpload {
+                        inner: upload,
+                        location: location.clone(),
+                        retry: self.retry,
+                    }))
+                }
+                Err(e)
+                    if attempts
+                        .retry_after(&e, "Multipart upload start", location)
+                        .await => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'static, Result<ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+/// Multipart upload whose completion is retried
+#[derive(Debug)]
+struct RetryUpload {
+    inner: Box<dyn MultipartUpload>,
+    location: Path,
+    retry: WriteRetry,
+}
+
+#[async_trait]
+impl MultipartUpload for RetryUpload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        self.inner.put_part(data)
+    }
+
+    async fn complete(&mut self) -> Result<PutResult> {
+        let mut attempts = Attempts::new(self.retry);
+        loop {
+            match self.inner.complete().await {
+                Err(e)
+                    if attempts
+                        .retry_after(&e, "Multipart upload completion", &self.location)
+                        .await => {}
+                result => return result,
+            }
+        }
+    }
+
+    async fn abort(&mut self) -> Result<()> {
+        self.inner.abort().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    /// In-memory store whose first `failures` uploads fail with `make_error`
+    #[derive(Debug)]
+    struct FlakyStore {
+        inner: InMemory,
+        failures: u32,
+        make_error: fn() -> Error,
+        puts: AtomicU32,
+    }
+
+    impl FlakyStore {
+        fn new(failures: u32, make_error: fn() -> Error) -> Self {
+            Self {
+                inner: InMemory::new(),
+                failures,
+                make_error,
+                puts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    impl Display for FlakyStore {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "FlakyStore")
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for FlakyStore {
+        async fn put_opts(
+            &self,
+            location: &Path,
+            payload: PutPayload,
+            opts: PutOptions,
+        ) -> Result<PutResult> {
+            if self.puts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.make_error)());
+            }
+            self.inner.put_opts(location, payload, opts).await
+        }
+
+        async fn put_multipart_opts(
+            &self,
+            location: &Path,
+            opts: PutMultipartOpts,
+        ) -> Result<Box<dyn MultipartUpload>> {
+            self.inner.put_multipart_opts(location, opts).await
+        }
+
+        async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+            self.inner.get_opts(location, options).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.inner.delete(location).await
+        }
+
+        fn list(&self, prefix: Option<&Path>) -> BoxStream<'static, Result<ObjectMeta>> {
+            self.inner.list(prefix)
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    fn unavailable() -> Error {
+        Error::Generic {
+            store: "FlakyStore",
+            source: "503 Service Unavailable".into(),
+        }
+    }
+
+    fn unauthenticated() -> Error {
+        Error::Unauthenticated {
+            path: "metrics-test.parquet".to_string(),
+            source: "401 Unauthorized".into(),
+        }
+    }
+
+    fn retry(max_attempts: u32) -> WriteRetry {
+        WriteRetry {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let flaky = Arc::new(FlakyStore::new(2, unavailable));
+        let store = RetryStore::wrap(flaky.clone(), retry(3));
+        let path = Path::from("metrics-test.parquet");
+
+        store.put(&path, "data".into()).await.unwrap();
+        assert_eq!(flaky.puts.load(Ordering::SeqCst), 3);
+        let stored = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(stored.as_ref(), b"data");
+    }
+
+    #[tokio::test]
+    async fn test_error_returned_after_exhausting_attempts() {
+        let flaky = Arc::new(FlakyStore::new(3, unavailable));
+        let store = RetryStore::wrap(flaky.clone(), retry(3));
+
+        let err = store
+            .put(&Path::from("metrics-test.parquet"), "data".into())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Generic { .. }), "{}", err);
+        assert_eq!(flaky.puts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_auth_failure_is_not_retried() {
+        let flaky = Arc::new(FlakyStore::new(1, unauthenticated));
+        let store = RetryStore::wrap(flaky.clone(), retry(3));
+
+        let err = store
+            .put(&Path::from("metrics-test.parquet"), "data".into())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Unauthenticated { .. }), "{}", err);
+        assert_eq!(flaky.puts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_io_errors_retried_only_for_connection_failures() {
+        let io_error = |kind: ErrorKind| Error::Generic {
+            store: "LocalFileSystem",
+            source: Box::new(std::io::Error::new(kind, "io")),
+        };
+        assert!(is_retryable(&io_error(ErrorKind::TimedOut)));
+        assert!(is_retryable(&io_error(ErrorKind::ConnectionReset)));
+        assert!(!is_retryable(&io_error(ErrorKind::PermissionDenied)));
+        assert!(!is_retryable(&io_error(ErrorKind::Other)));
+    }
+}
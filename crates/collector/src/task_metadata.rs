@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+use log::warn;
 
 /// Represents metadata for a single task
 #[derive(Clone)]
@@ -6,6 +8,12 @@ pub struct TaskMetadata {
     pub pid: u32,
     pub comm: [u8; 16],
     pub cgroup_id: u64,
+    /// Nice value (-20..19) when the task was first reported
+    pub nice: i32,
+    /// Timestamp (ns) the task was first reported, 0 if unknown
+    pub start_ns: u64,
+    /// Timestamp (ns) the task was freed, once known
+    pub end_ns: Option<u64>,
 }
 
 impl TaskMetadata {
@@ -14,14 +22,52 @@ impl TaskMetadata {
             pid,
             comm,
             cgroup_id,
+            nice: 0,
+            start_ns: 0,
+            end_ns: None,
+        }
+    }
+
+    /// Set the task's nice value
+    pub fn with_nice(mut self, nice: i32) -> Self {
+        self.nice = nice;
+        self
+    }
+
+    /// Set the timestamp the task was first reported
+    pub fn with_start_ns(mut self, start_ns: u64) -> Self {
+        self.start_ns = start_ns;
+        self
+    }
+
+    /// Observed lifetime, available once the task has been freed and its start is known
+    pub fn lifetime_ns(&self) -> Option<u64> {
+        match self.end_ns {
+            Some(end_ns) if self.start_ns != 0 => Some(end_ns.saturating_sub(self.start_ns)),
+            _ => None,
         }
     }
 }
 
 /// Collection to manage multiple tasks with queued removal support
+///
+/// With a maximum size, adding a task to a full collection evicts the task
+/// added longest ago, so the collection stays bounded even if task free events
+/// are lost. Eviction is by insertion order: re-adding a task makes it the
+/// newest, while lookups do not.
 pub struct TaskCollection {
     tasks: HashMap<u32, TaskMetadata>,
     removal_queue: Vec<u32>,
+    // Tasks removed by the most recent flush, kept for lifetime lookups
+    recently_removed: HashMap<u32, TaskMetadata>,
+    // Maximum number of tasks (0 = unbounded)
+    max_entries: usize,
+    // Insertion order as (pid, sequence); entries whose sequence no longer
+    // matches `sequences` are stale and skipped
+    insertion_order: VecDeque<(u32, u64)>,
+    sequences: HashMap<u32, u64>,
+    next_sequence: u64,
+    evicted: u64,
 }
 
 impl TaskCollection {
@@ -29,12 +75,61 @@ impl TaskCollection {
         Self {
             tasks: HashMap::new(),
             removal_queue: Vec::new(),
+            recently_removed: HashMap::new(),
+            max_entries: 0,
+            insertion_order: VecDeque::new(),
+            sequences: HashMap::new(),
+            next_sequence: 0,
+            evicted: 0,
         }
     }
 
-    /// Add a task to the collection
+    /// Create a collection holding at most `max_entries` tasks (0 = unbounded)
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            ..Self::new()
+        }
+    }
+
+    /// Add a task to the collection, evicting the tasks added longest ago if it
+    /// is full
     pub fn add(&mut self, metadata: TaskMetadata) {
-        self.tasks.insert(metadata.pid, metadata);
+        let pid = metadata.pid;
+        self.tasks.insert(pid, metadata);
+        if self.max_entries == 0 {
+            return;
+        }
+
+        self.sequences.insert(pid, self.next_sequence);
+        self.insertion_order.push_back((pid, self.next_sequence));
+        self.next_sequence += 1;
+
+        while self.tasks.len() > self.max_entries {
+            let Some((old_pid, sequence)) = self.insertion_order.pop_front() else {
+                break;
+            };
+            if self.sequences.get(&old_pid) != Some(&sequence) {
+                continue;
+            }
+            self.sequences.remove(&old_pid);
+            self.tasks.remove(&old_pid);
+            self.evicted += 1;
+            // Warn on the first eviction and then at powers of two to avoid log floods
+            if self.evicted.is_power_of_two() {
+                warn!(
+                    "Task metadata table full ({} entries), evicted oldest task {} ({} evictions so far; task free events may have been lost)",
+                    self.max_entries, old_pid, self.evicted
+                );
+            }
+        }
+
+        // Drop stale order entries left by regular removals
+        if self.insertion_order.len() > 2 * self.tasks.len() + 64 {
+            let sequences = &self.sequences;
+            self.insertion_order
+                .retain(|(pid, sequence)| sequences.get(pid) == Some(sequence));
+        }
     }
 
     /// Look up a task by its PID
@@ -42,19 +137,39 @@ impl TaskCollection {
         self.tasks.get(&pid)
     }
 
-    /// Queue a task for removal without immediately removing it
-    pub fn queue_removal(&mut self, pid: u32) {
-        if self.tasks.contains_key(&pid) {
+    /// Look up a task by its PID, including tasks removed by the last flush
+    pub fn lookup_recent(&self, pid: u32) -> Option<&TaskMetadata> {
+        self.tasks
+            .get(&pid)
+            .or_else(|| self.recently_removed.get(&pid))
+    }
+
+    /// Queue a task for removal without immediately removing it, recording
+    /// `timestamp` as the end of its lifetime
+    pub fn queue_removal(&mut self, pid: u32, timestamp: u64) {
+        if let Some(task) = self.tasks.get_mut(&pid) {
+            task.end_ns = Some(timestamp);
             self.removal_queue.push(pid);
         }
     }
 
     /// Execute all queued removals
+    ///
+    /// Removed tasks stay available to [`TaskCollection::lifetime_ns`] until the next flush.
     pub fn flush_removals(&mut self) {
+        self.recently_removed.clear();
         for pid in self.removal_queue.drain(..) {
-            self.tasks.remove(&pid);
+            if let Some(task) = self.tasks.remove(&pid) {
+                self.sequences.remove(&pid);
+                self.recently_removed.insert(pid, task);
+            }
         }
     }
+
+    /// Lifetime of a task that has been freed (still queued or removed by the last flush)
+    pub fn lifetime_ns(&self, pid: u32) -> Option<u64> {
+        self.lookup_recent(pid).and_then(TaskMetadata::lifetime_ns)
+    }
 }
 
 #[cfg(test)]
@@ -77,7 +192,7 @@ mod tests {
         assert!(collection.lookup(3).is_none());
 
         // Queue removal
-        collection.queue_removal(1);
+        collection.queue_removal(1, 100);
 
         // Task should still be available before flush
         assert!(collection.lookup(1).is_some());
@@ -89,4 +204,56 @@ mod tests {
         assert!(collection.lookup(1).is_none());
         assert!(collection.lookup(2).is_some());
     }
+
+    #[test]
+    fn test_lifetime_tracking() {
+        let mut collection = TaskCollection::new();
+        collection.add(TaskMetadata::new(1, [0; 16], 0).with_start_ns(1_000));
+        collection.add(TaskMetadata::new(2, [0; 16], 0));
+
+        // Alive tasks have no lifetime yet
+        assert_eq!(collection.lifetime_ns(1), None);
+
+        collection.queue_removal(1, 1_500);
+        collection.queue_removal(2, 1_500);
+        assert_eq!(collection.lifetime_ns(1), Some(500));
+        // Unknown start time: lifetime cannot be computed
+        assert_eq!(collection.lifetime_ns(2), None);
+
+        // Still known right after the flush that removed the task, gone after the next
+        collection.flush_removals();
+        assert!(collection.lookup(1).is_none());
+        assert_eq!(collection.lifetime_ns(1), Some(500));
+        collection.flush_removals();
+        assert_eq!(collection.lifetime_ns(1), None);
+    }
+
+    #[test]
+    fn test_max_entries_evicts_oldest() {
+        let mut collection = TaskCollection::with_max_entries(3);
+        for pid in 1..=3 {
+            collection.add(TaskMetadata::new(pid, [0; 16], 0));
+        }
+        assert_eq!(collection.evicted, 0);
+
+        // Re-adding a PID makes it the newest
+        collection.add(TaskMetadata::new(1, [0; 16], 7));
+        collection.add(TaskMetadata::new(4, [0; 16], 0));
+        collection.add(TaskMetadata::new(5, [0; 16], 0));
+        assert_eq!(collection.evicted, 2);
+        assert!(collection.lookup(2).is_none());
+        assert!(collection.lookup(3).is_none());
+        assert_eq!(collection.lookup(1).map(|t| t.cgroup_id), Some(7));
+        assert!(collection.lookup(4).is_some());
+        assert!(collection.lookup(5).is_some());
+
+        // Tasks removed normally free their slot without evicting others
+        collection.queue_removal(4, 100);
+        collection.flush_removals();
+        collection.add(TaskMetadata::new(6, [0; 16], 0));
+        assert_eq!(collection.evicted, 2);
+        assert!(collection.lookup(1).is_some());
+        assert!(collection.lookup(5).is_some());
+        assert!(collection.lookup(6).is_some());
+    }
 }
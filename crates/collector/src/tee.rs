@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use futures::StreamExt;
+use log::{debug, warn};
+use object_store::{path::Path, ObjectStore, WriteMultipart};
+
+use crate::error::{CollectorError, Result};
+
+/// What a failed copy to a tee store means for the write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeFailurePolicy {
+    /// Every tee store must receive the file; otherwise the write fails and
+    /// the storage failure policy applies
+    RequireAll,
+    /// The file is kept as long as one store has it. Since files are copied
+    /// only after they were written to the primary store, failed copies are
+    /// logged and skipped
+    RequireAny,
+}
+
+impl TeeFailurePolicy {
+    /// Parse the `--tee-failure-policy` value ("all" or "any")
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(TeeFailurePolicy::RequireAll),
+            "any" => Ok(TeeFailurePolicy::RequireAny),
+            other => Err(CollectorError::Config(anyhow!(
+                "unknown tee failure policy '{}' (expected 'all' or 'any')",
+                other
+            ))),
+        }
+    }
+}
+
+/// An additional destination for closed output files
+pub struct TeeStore {
+    /// Name used in log and error messages (e.g. the `--tee` spec)
+    pub name: String,
+    pub store: Arc<dyn ObjectStore>,
+}
+
+/// Copies every closed output file from the primary store to additional stores
+pub struct TeeConfig {
+    pub stores: Vec<TeeStore>,
+    pub policy: TeeFailurePolicy,
+    /// Copy attempts per store and file (at least one is made)
+    pub max_attempts: u32,
+    /// Delay between attempts
+    pub retry_backoff: Duration,
+}
+
+impl TeeConfig {
+    /// Copy `path` from `source` into every tee store, retrying each store
+    /// independently. Failures are reported according to `policy`.
+    pub async fn replicate(&self, source: &Arc<dyn ObjectStore>, path: &Path) -> Result<()> {
+        let mut failed = Vec::new();
+        for tee in &self.stores {
+            match self.copy_with_retry(source, tee, path).await {
+                Ok(()) => debug!("Copied '{}' to tee store {}", path, tee.name),
+                Err(e) => {
+                    warn!(
+                        "Failed to copy '{}' to tee store {}: {:#}",
+                        path, tee.name, e
+                    );
+                    failed.push(tee.name.as_str());
+                }
+            }
+        }
+
+        match (failed.is_empty(), self.policy) {
+            (false, TeeFailurePolicy::RequireAll) => Err(CollectorError::Storage(anyhow!(
+                "failed to copy '{}' to tee store(s): {}",
+                path,
+                failed.join(", ")
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    async fn copy_with_retry(
+        &self,
+        source: &Arc<dyn ObjectStore>,
+        tee: &TeeStore,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match copy_object(source, &tee.store, path).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= attempts => {
+                    return Err(e).context(format!("giving up after {} attempt(s)", attempts))
+                }
+                Err(e) => {
+                    debug!(
+                        "Copy of '{}' to tee store {} failed (attempt {}/{}): {:#}",
+                        path, tee.name, attempt, attempts, e
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_backoff).await;
+                }
+            }
+        }
+    }
+}
+
+/// Stream an object from `source` to the same path in `dest`
+async fn copy_object(
+    source: &Arc<dyn ObjectStore>,
+    dest: &Arc<dyn ObjectStore>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let mut chunks = source.get(path).await?.into_stream();
+    let mut upload = WriteMultipart::new(dest.put_multipart(path).await?);
+    while let Some(chunk) = chunks.next().await {
+        match chunk {
+            Ok(chunk) => upload.write(&chunk),
+            Err(e) => {
+                let _ = upload.abort().await;
+                return Err(e.into());
+            }
+        }
+    }
+    upload.finish().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::local::LocalFileSystem;
+    use object_store::memory::InMemory;
+
+    fn tee_config(stores: Vec<TeeStore>, policy: TeeFailurePolicy) -> TeeConfig {
+        TeeConfig {
+            stores,
+            policy,
+            max_attempts: 2,
+            retry_backoff: Duration::from_millis(1),
+        }
+    }
+
+    fn memory_store(name: &str) -> (Arc<dyn ObjectStore>, TeeStore) {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let tee = TeeStore {
+            name: name.to_string(),
+            store: store.clone(),
+        };
+        (store, tee)
+    }
+
+    #[tokio::test]
+    async fn test_single_store_failure_follows_policy() {
+        let source: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let path = Path::from("metrics-test.parquet");
+        source.put(&path, "data".into()).await.unwrap();
+
+        // A store rooted below a regular file cannot be written to
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let broken = || TeeStore {
+            name: "broken".to_string(),
+            store: Arc::new(LocalFileSystem::new_with_prefix(file.path()).unwrap()),
+        };
+
+        let (ok_store, ok_tee) = memory_store("memory");
+        let any = tee_config(vec![broken(), ok_tee], TeeFailurePolicy::RequireAny);
+        any.replicate(&source, &path).await.unwrap();
+        let copied = ok_store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(copied.as_ref(), b"data");
+
+        let (_, ok_tee) = memory_store("memory");
+        let all = tee_config(vec![ok_tee, broken()], TeeFailurePolicy::RequireAll);
+        let err = all.replicate(&source, &path).await.unwrap_err();
+        assert!(err.to_string().contains("broken"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(
+            TeeFailurePolicy::parse("ALL").unwrap(),
+            TeeFailurePolicy::RequireAll
+        );
+        assert_eq!(
+            TeeFailurePolicy::parse("any").unwrap(),
+            TeeFailurePolicy::RequireAny
+        );
+        assert!(TeeFailurePolicy::parse("some").is_err());
+    }
+}
@@ -1,17 +1,54 @@
+use crate::error::{CollectorError, Result};
 use crate::metrics::Metric;
 use crate::task_metadata::TaskMetadata;
+use anyhow::anyhow;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+/// PID reported for rows aggregating short-lived tasks
+pub const EPHEMERAL_PID: i32 = -1;
+
+/// Process name reported for rows aggregating short-lived tasks
+pub const EPHEMERAL_PROCESS_NAME: &str = "<ephemeral>";
+
+/// What a timeslot row aggregates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    /// One row per process: the metrics of all its threads are summed
+    #[default]
+    Process,
+    /// One row per thread, identified by the `tid` column
+    Thread,
+}
+
+impl Granularity {
+    /// Parse `process` or `thread`
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "process" => Ok(Self::Process),
+            "thread" => Ok(Self::Thread),
+            other => Err(CollectorError::Config(anyhow!(
+                "unknown granularity '{}' (expected 'process' or 'thread')",
+                other
+            ))),
+        }
+    }
+}
+
 /// Represents data collected for a specific timeslot
 pub struct TimeslotData {
     /// Timestamp at the end of this timeslot
     pub start_timestamp: u64,
-    /// Map from PID to task data (metadata + metrics)
+    /// Map from PID (or TID, when recorded per thread) to task data (metadata + metrics)
     pub tasks: HashMap<u32, TaskData>,
+    /// Aggregated metrics of short-lived tasks, keyed by cgroup_id
+    pub ephemeral: HashMap<u64, Metric>,
 }
 
 /// Combines task metadata with metrics
 pub struct TaskData {
+    /// Process the task belongs to; metadata and lifetimes are tracked per process
+    pub pid: u32,
     /// Task metadata (may be None for kernel threads)
     pub metadata: Option<TaskMetadata>,
     /// Performance metrics for this task
@@ -24,20 +61,81 @@ impl TimeslotData {
         Self {
             start_timestamp,
             tasks: HashMap::new(),
+            ephemeral: HashMap::new(),
         }
     }
 
     /// Updates or inserts task data for a given PID
     pub fn update(&mut self, pid: u32, metadata: Option<TaskMetadata>, metrics: Metric) {
-        if let Some(task_data) = self.tasks.get_mut(&pid) {
-            // Update existing entry
+        self.update_task(pid, pid, metadata, metrics);
+    }
+
+    /// Updates or inserts task data for thread `tid` of process `pid`
+    pub fn update_thread(
+        &mut self,
+        pid: u32,
+        tid: u32,
+        metadata: Option<TaskMetadata>,
+        metrics: Metric,
+    ) {
+        self.update_task(tid, pid, metadata, metrics);
+    }
+
+    fn update_task(&mut self, key: u32, pid: u32, metadata: Option<TaskMetadata>, metrics: Metric) {
+        if let Some(task_data) = self.tasks.get_mut(&key) {
+            // Update existing entry, taking metadata that arrived after earlier metrics
             task_data.metrics.add(&metrics);
+            if task_data.metadata.is_none() {
+                task_data.metadata = metadata;
+            }
         } else {
             // Create new entry
-            self.tasks.insert(pid, TaskData::new(metadata, metrics));
+            self.tasks
+                .insert(key, TaskData::new(pid, metadata, metrics));
         }
     }
 
+    /// Add the metrics of `other` into this timeslot, per PID (or TID) and per
+    /// ephemeral bucket, keeping the earlier of the two start timestamps
+    pub fn merge(&mut self, other: TimeslotData) {
+        self.start_timestamp = self.start_timestamp.min(other.start_timestamp);
+        for (key, task_data) in other.tasks {
+            match self.tasks.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    let existing = entry.get_mut();
+                    existing.metrics.add(&task_data.metrics);
+                    if existing.metadata.is_none() {
+                        existing.metadata = task_data.metadata;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(task_data);
+                }
+            }
+        }
+        for (cgroup_id, metrics) in other.ephemeral {
+            self.ephemeral.entry(cgroup_id).or_default().add(&metrics);
+        }
+    }
+
+    /// Fill in metadata for tasks recorded before their metadata was known, using
+    /// `lookup` by PID. Returns the number of tasks still without metadata.
+    pub fn resolve_metadata<F>(&mut self, lookup: F) -> usize
+    where
+        F: Fn(u32) -> Option<TaskMetadata>,
+    {
+        let mut unresolved = 0;
+        for task_data in self.tasks.values_mut() {
+            if task_data.metadata.is_none() {
+                task_data.metadata = lookup(task_data.pid);
+                if task_data.metadata.is_none() {
+                    unresolved += 1;
+                }
+            }
+        }
+        unresolved
+    }
+
     /// Returns an iterator over all task data
     pub fn iter_tasks(&self) -> impl Iterator<Item = (&u32, &TaskData)> {
         self.tasks.iter()
@@ -47,11 +145,178 @@ impl TimeslotData {
     pub fn task_count(&self) -> usize {
         self.tasks.len()
     }
+
+    /// Move tasks whose lifetime is below `min_lifetime_ns` into the per-cgroup
+    /// ephemeral buckets. `lifetime_of` returns a process's lifetime once it has
+    /// exited; tasks with unknown lifetime are kept as individual entries.
+    pub fn bucket_short_lived<F>(&mut self, min_lifetime_ns: u64, lifetime_of: F)
+    where
+        F: Fn(u32) -> Option<u64>,
+    {
+        let short_lived: Vec<u32> = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| lifetime_of(task.pid).is_some_and(|l| l < min_lifetime_ns))
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in short_lived {
+            if let Some(task_data) = self.tasks.remove(&key) {
+                let cgroup_id = task_data.metadata.map(|m| m.cgroup_id).unwrap_or(0);
+                self.ephemeral
+                    .entry(cgroup_id)
+                    .or_default()
+                    .add(&task_data.metrics);
+            }
+        }
+    }
 }
 
 impl TaskData {
     /// Creates a new task data entry
-    pub fn new(metadata: Option<TaskMetadata>, metrics: Metric) -> Self {
-        Self { metadata, metrics }
+    pub fn new(pid: u32, metadata: Option<TaskMetadata>, metrics: Metric) -> Self {
+        Self {
+            pid,
+            metadata,
+            metrics,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_short_lived() {
+        let mut timeslot = TimeslotData::new(1000);
+        timeslot.update(
+            1,
+            Some(TaskMetadata::new(1, [0; 16], 10)),
+            Metric::from_deltas(100, 200, 1, 2, 50),
+        );
+        timeslot.update(
+            2,
+            Some(TaskMetadata::new(2, [0; 16], 10)),
+            Metric::from_deltas(300, 400, 3, 4, 70),
+        );
+        timeslot.update(
+            3,
+            Some(TaskMetadata::new(3, [0; 16], 10)),
+            Metric::from_deltas(1000, 1000, 10, 10, 900),
+        );
+        timeslot.update(4, None, Metric::from_deltas(5, 5, 0, 0, 5));
+
+        // Tasks 1, 2 and 4 exited quickly, task 3 lived long, others are still alive
+        let lifetimes = HashMap::from([(1u32, 200u64), (2, 400), (3, 5_000_000), (4, 10)]);
+        timeslot.bucket_short_lived(1_000, |pid| lifetimes.get(&pid).copied());
+
+        // Only the long-lived task remains as an individual entry
+        assert_eq!(timeslot.task_count(), 1);
+        assert!(timeslot.tasks.contains_key(&3));
+
+        // Short-lived tasks are aggregated per cgroup (tasks without metadata go to cgroup 0)
+        assert_eq!(timeslot.ephemeral.len(), 2);
+        let bucket = timeslot.ephemeral[&10];
+        assert_eq!(bucket.cycles, 400);
+        assert_eq!(bucket.instructions, 600);
+        assert_eq!(bucket.time_ns, 120);
+        assert_eq!(timeslot.ephemeral[&0].cycles, 5);
+    }
+
+    #[test]
+    fn test_merge_adds_metrics() {
+        let mut first = TimeslotData::new(1_000);
+        first.update(1, None, Metric::from_deltas(100, 200, 1, 2, 50));
+        first.update(2, None, Metric::from_deltas(10, 20, 0, 0, 5));
+        first
+            .ephemeral
+            .insert(10, Metric::from_deltas(7, 7, 0, 0, 7));
+
+        let mut second = TimeslotData::new(2_000);
+        second.update(
+            1,
+            Some(TaskMetadata::new(1, [0; 16], 10)),
+            Metric::from_deltas(300, 400, 3, 4, 70),
+        );
+        second.update(3, None, Metric::from_deltas(1, 1, 1, 1, 1));
+        second
+            .ephemeral
+            .insert(10, Metric::from_deltas(3, 3, 0, 0, 3));
+
+        first.merge(second);
+
+        // The merged timeslot starts at the earlier timestamp
+        assert_eq!(first.start_timestamp, 1_000);
+        assert_eq!(first.task_count(), 3);
+
+        // Metrics of a PID present in both are summed, and known metadata is kept
+        let task = &first.tasks[&1];
+        assert_eq!(task.metrics.cycles, 400);
+        assert_eq!(task.metrics.instructions, 600);
+        assert_eq!(task.metrics.llc_misses, 4);
+        assert_eq!(task.metrics.cache_references, 6);
+        assert_eq!(task.metrics.time_ns, 120);
+        assert_eq!(task.metadata.as_ref().map(|m| m.cgroup_id), Some(10));
+
+        assert_eq!(first.tasks[&2].metrics.cycles, 10);
+        assert_eq!(first.tasks[&3].metrics.cycles, 1);
+        assert_eq!(first.ephemeral[&10].cycles, 10);
+    }
+
+    #[test]
+    fn test_metric_before_metadata_is_resolved() {
+        let mut timeslot = TimeslotData::new(1000);
+        // Metrics arrive for PIDs whose metadata has not been seen yet
+        timeslot.update(1, None, Metric::from_deltas(100, 200, 1, 2, 50));
+        timeslot.update(2, None, Metric::from_deltas(10, 20, 0, 0, 5));
+        timeslot.update(3, None, Metric::from_deltas(1, 1, 0, 0, 1));
+
+        // A later metric for PID 1 carries its metadata
+        timeslot.update(
+            1,
+            Some(TaskMetadata::new(1, [0; 16], 10)),
+            Metric::from_deltas(100, 200, 1, 2, 50),
+        );
+        assert_eq!(timeslot.tasks[&1].metadata.as_ref().unwrap().cgroup_id, 10);
+        assert_eq!(timeslot.tasks[&1].metrics.cycles, 200);
+
+        // PID 2's metadata arrives afterwards; PID 3 stays unknown (e.g. a kernel thread)
+        let known = HashMap::from([(2u32, TaskMetadata::new(2, [0; 16], 20))]);
+        let unresolved = timeslot.resolve_metadata(|pid| known.get(&pid).cloned());
+        assert_eq!(unresolved, 1);
+        assert_eq!(timeslot.tasks[&2].metadata.as_ref().unwrap().cgroup_id, 20);
+        assert!(timeslot.tasks[&3].metadata.is_none());
+        assert_eq!(timeslot.tasks[&2].metrics.cycles, 10);
+    }
+
+    #[test]
+    fn test_threads_are_recorded_separately() {
+        let mut timeslot = TimeslotData::new(1000);
+        let metric = || Metric::from_deltas(100, 200, 1, 2, 50);
+        timeslot.update_thread(10, 10, None, metric());
+        timeslot.update_thread(10, 11, None, metric());
+        timeslot.update_thread(10, 11, None, metric());
+        assert_eq!(timeslot.task_count(), 2);
+        assert_eq!(timeslot.tasks[&11].pid, 10);
+        assert_eq!(timeslot.tasks[&11].metrics.cycles, 200);
+
+        // Metadata and lifetimes are looked up by the process
+        let known = HashMap::from([(10u32, TaskMetadata::new(10, [0; 16], 20))]);
+        assert_eq!(timeslot.resolve_metadata(|pid| known.get(&pid).cloned()), 0);
+        assert_eq!(timeslot.tasks[&11].metadata.as_ref().unwrap().cgroup_id, 20);
+        timeslot.bucket_short_lived(1_000, |pid| (pid == 10).then_some(500));
+        assert_eq!(timeslot.task_count(), 0);
+        assert_eq!(timeslot.ephemeral[&20].cycles, 300);
+    }
+
+    #[test]
+    fn test_parse_granularity() {
+        assert_eq!(Granularity::parse("process").unwrap(), Granularity::Process);
+        assert_eq!(Granularity::parse("Thread").unwrap(), Granularity::Thread);
+        assert!(matches!(
+            Granularity::parse("core"),
+            Err(CollectorError::Config(_))
+        ));
     }
 }
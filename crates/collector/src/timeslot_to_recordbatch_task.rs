@@ -1,32 +1,43 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
 use arrow_array::builder::{Int32Builder, Int64Builder, StringBuilder};
 use arrow_array::{ArrayRef, RecordBatch};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use tokio::sync::mpsc;
 
-use crate::timeslot_data::TimeslotData;
+use crate::error::Result;
+use crate::metrics::{Metric, PerfEvents};
+use crate::timeslot_data::{Granularity, TimeslotData, EPHEMERAL_PID, EPHEMERAL_PROCESS_NAME};
 
-/// Create the schema for timeslot record batches
-pub fn create_timeslot_schema() -> SchemaRef {
-    Arc::new(Schema::new(vec![
+/// Create the schema for timeslot record batches. Rows recorded per thread get a
+/// `tid` column; `pid` is the thread's process in either case.
+pub fn create_timeslot_schema(events: &PerfEvents, granularity: Granularity) -> SchemaRef {
+    let mut fields = vec![
         Field::new("start_time", DataType::Int64, false),
         Field::new("pid", DataType::Int32, false),
         Field::new("process_name", DataType::Utf8, true),
         Field::new("cgroup_id", DataType::Int64, false),
-        Field::new("cycles", DataType::Int64, false),
-        Field::new("instructions", DataType::Int64, false),
-        Field::new("llc_misses", DataType::Int64, false),
-        Field::new("cache_references", DataType::Int64, false),
-        Field::new("duration", DataType::Int64, false),
-    ]))
+    ];
+    fields.extend(events.fields());
+    fields.push(Field::new("duration", DataType::Int64, false));
+    // Appended so existing column positions are unchanged
+    fields.push(Field::new("nice", DataType::Int32, true));
+    if granularity == Granularity::Thread {
+        // Null for ephemeral bucket rows, which mix threads
+        fields.push(Field::new("tid", DataType::Int32, true));
+    }
+    Arc::new(Schema::new(fields))
 }
 
-/// Convert a TimeslotData to an Arrow RecordBatch
-pub fn timeslot_to_batch(timeslot: TimeslotData, schema: SchemaRef) -> Result<RecordBatch> {
-    // Get the task count to preallocate builders
-    let task_count = timeslot.task_count();
+/// Convert a TimeslotData to an Arrow RecordBatch. Counters not in `events` are null.
+pub fn timeslot_to_batch(
+    timeslot: TimeslotData,
+    schema: SchemaRef,
+    events: &PerfEvents,
+    granularity: Granularity,
+) -> Result<RecordBatch> {
+    // Get the row count (tasks + ephemeral buckets) to preallocate builders
+    let task_count = timeslot.task_count() + timeslot.ephemeral.len();
 
     // Create array builders for each column
     let mut start_time_builder = Int64Builder::with_capacity(task_count);
@@ -35,19 +46,20 @@ pub fn timeslot_to_batch(timeslot: TimeslotData, schema: SchemaRef) -> Result<Re
     // Estimate 16 bytes per string for process names
     let mut process_name_builder = StringBuilder::with_capacity(task_count, task_count * 16);
     let mut cgroup_id_builder = Int64Builder::with_capacity(task_count);
-    let mut cycles_builder = Int64Builder::with_capacity(task_count);
-    let mut instructions_builder = Int64Builder::with_capacity(task_count);
-    let mut llc_misses_builder = Int64Builder::with_capacity(task_count);
-    let mut cache_references_builder = Int64Builder::with_capacity(task_count);
+    let mut counter_builders: [Int64Builder; 4] =
+        std::array::from_fn(|_| Int64Builder::with_capacity(task_count));
     let mut duration_builder = Int64Builder::with_capacity(task_count);
+    let mut nice_builder = Int32Builder::with_capacity(task_count);
+    let mut tid_builder = Int32Builder::with_capacity(task_count);
 
     // Convert timeslot data to arrays
-    for (pid, task_data) in timeslot.iter_tasks() {
+    for (key, task_data) in timeslot.iter_tasks() {
         // Add start timestamp (common for all tasks in this timeslot)
         start_time_builder.append_value(timeslot.start_timestamp as i64);
 
-        // Add PID
-        pid_builder.append_value(*pid as i32);
+        // Add PID, and the TID that keys rows recorded per thread
+        pid_builder.append_value(task_data.pid as i32);
+        tid_builder.append_value(*key as i32);
 
         // Add process name and cgroup_id (from metadata if available)
         if let Some(ref metadata) = task_data.metadata {
@@ -58,34 +70,56 @@ pub fn timeslot_to_batch(timeslot: TimeslotData, schema: SchemaRef) -> Result<Re
                 .to_string();
             process_name_builder.append_value(comm);
             cgroup_id_builder.append_value(metadata.cgroup_id as i64);
+            nice_builder.append_value(metadata.nice);
         } else {
             process_name_builder.append_null();
             cgroup_id_builder.append_value(0); // Default value when no metadata available
+            nice_builder.append_null();
         }
 
         // Add metrics
-        cycles_builder.append_value(task_data.metrics.cycles as i64);
-        instructions_builder.append_value(task_data.metrics.instructions as i64);
-        llc_misses_builder.append_value(task_data.metrics.llc_misses as i64);
-        cache_references_builder.append_value(task_data.metrics.cache_references as i64);
+        append_counters(&mut counter_builders, &task_data.metrics, events);
         duration_builder.append_value(task_data.metrics.time_ns as i64);
     }
 
+    // One row per cgroup aggregating short-lived tasks
+    for (cgroup_id, metrics) in timeslot.ephemeral.iter() {
+        start_time_builder.append_value(timeslot.start_timestamp as i64);
+        pid_builder.append_value(EPHEMERAL_PID);
+        process_name_builder.append_value(EPHEMERAL_PROCESS_NAME);
+        cgroup_id_builder.append_value(*cgroup_id as i64);
+        append_counters(&mut counter_builders, metrics, events);
+        duration_builder.append_value(metrics.time_ns as i64);
+        // Tasks in the bucket may differ in priority
+        nice_builder.append_null();
+        tid_builder.append_null();
+    }
+
     // Finish building arrays
-    let arrays: Vec<ArrayRef> = vec![
+    let mut arrays: Vec<ArrayRef> = vec![
         Arc::new(start_time_builder.finish()),
         Arc::new(pid_builder.finish()),
         Arc::new(process_name_builder.finish()),
         Arc::new(cgroup_id_builder.finish()),
-        Arc::new(cycles_builder.finish()),
-        Arc::new(instructions_builder.finish()),
-        Arc::new(llc_misses_builder.finish()),
-        Arc::new(cache_references_builder.finish()),
-        Arc::new(duration_builder.finish()),
     ];
+    for builder in counter_builders.iter_mut() {
+        arrays.push(Arc::new(builder.finish()));
+    }
+    arrays.push(Arc::new(duration_builder.finish()));
+    arrays.push(Arc::new(nice_builder.finish()));
+    if granularity == Granularity::Thread {
+        arrays.push(Arc::new(tid_builder.finish()));
+    }
 
     // Create and return the RecordBatch
-    RecordBatch::try_new(schema, arrays).map_err(|e| anyhow!("Failed to create RecordBatch: {}", e))
+    Ok(RecordBatch::try_new(schema, arrays)?)
+}
+
+/// Append one row of counter values, with nulls for counters not collected
+fn append_counters(builders: &mut [Int64Builder; 4], metrics: &Metric, events: &PerfEvents) {
+    for (builder, value) in builders.iter_mut().zip(metrics.counters(events)) {
+        builder.append_option(value);
+    }
 }
 
 /// Worker task for converting timeslots to record batches
@@ -93,6 +127,8 @@ pub struct TimeslotToRecordBatchTask {
     timeslot_receiver: mpsc::Receiver<TimeslotData>,
     batch_sender: mpsc::Sender<RecordBatch>,
     schema: SchemaRef,
+    events: PerfEvents,
+    granularity: Granularity,
 }
 
 impl TimeslotToRecordBatchTask {
@@ -101,14 +137,31 @@ impl TimeslotToRecordBatchTask {
         timeslot_receiver: mpsc::Receiver<TimeslotData>,
         batch_sender: mpsc::Sender<RecordBatch>,
     ) -> Self {
-        let schema = create_timeslot_schema();
+        let events = PerfEvents::default();
         Self {
             timeslot_receiver,
             batch_sender,
-            schema,
+            schema: create_timeslot_schema(&events, Granularity::default()),
+            events,
+            granularity: Granularity::default(),
         }
     }
 
+    /// Only record the given counters; the others are written as nulls
+    pub fn with_perf_events(mut self, events: PerfEvents) -> Self {
+        self.schema = create_timeslot_schema(&events, self.granularity);
+        self.events = events;
+        self
+    }
+
+    /// Convert timeslots recorded with `granularity`, adding a `tid` column for
+    /// per-thread rows
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.schema = create_timeslot_schema(&self.events, granularity);
+        self.granularity = granularity;
+        self
+    }
+
     /// Get the schema for the record batches this task produces
     pub fn schema(&self) -> SchemaRef {
         self.schema.clone()
@@ -120,7 +173,12 @@ impl TimeslotToRecordBatchTask {
             match self.timeslot_receiver.recv().await {
                 Some(timeslot) => {
                     // Convert timeslot to a batch
-                    let batch = timeslot_to_batch(timeslot, self.schema.clone())?;
+                    let batch = timeslot_to_batch(
+                        timeslot,
+                        self.schema.clone(),
+                        &self.events,
+                        self.granularity,
+                    )?;
 
                     // Send the batch to the output channel
                     if self.batch_sender.send(batch).await.is_err() {
@@ -170,12 +228,18 @@ mod tests {
         timeslot.update(202, metadata2, metrics2);
 
         // Convert to batch
-        let schema = create_timeslot_schema();
-        let batch = timeslot_to_batch(timeslot, schema).unwrap();
+        let schema = create_timeslot_schema(&PerfEvents::default(), Granularity::Process);
+        let batch = timeslot_to_batch(
+            timeslot,
+            schema,
+            &PerfEvents::default(),
+            Granularity::Process,
+        )
+        .unwrap();
 
         // Verify batch structure
         assert_eq!(batch.num_rows(), 2);
-        assert_eq!(batch.num_columns(), 9);
+        assert_eq!(batch.num_columns(), 10);
 
         // Verify content - extract arrays and check values (accounting for unordered timeslot iteration)
         use arrow_array::{Int32Array, Int64Array, StringArray};
@@ -263,6 +327,137 @@ mod tests {
         assert_eq!(duration_array.value(proc_two_idx), 200000);
     }
 
+    #[test]
+    fn test_ephemeral_bucket_rows() {
+        use arrow_array::{Array, Int32Array, Int64Array, StringArray};
+
+        let mut timeslot = TimeslotData::new(1000);
+        let mut comm = [0u8; 16];
+        comm[..4].copy_from_slice(b"long");
+        timeslot.update(
+            7,
+            Some(TaskMetadata::new(7, comm, 55).with_nice(5)),
+            Metric::from_deltas(10, 20, 1, 2, 30),
+        );
+        timeslot
+            .ephemeral
+            .insert(55, Metric::from_deltas(100, 200, 3, 4, 500));
+
+        let events = PerfEvents::default();
+        let batch = timeslot_to_batch(
+            timeslot,
+            create_timeslot_schema(&events, Granularity::Process),
+            &events,
+            Granularity::Process,
+        )
+        .unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let pids = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        let names = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let cgroups = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let cycles = batch
+            .column(4)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+
+        let nice = batch
+            .column_by_name("nice")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+
+        let row = (0..2).find(|&i| pids.value(i) == EPHEMERAL_PID).unwrap();
+        assert_eq!(names.value(row), EPHEMERAL_PROCESS_NAME);
+        assert_eq!(cgroups.value(row), 55);
+        assert_eq!(cycles.value(row), 100);
+        assert_eq!(pids.value(1 - row), 7);
+        // The bucket mixes tasks, so it has no single nice value
+        assert!(nice.is_null(row));
+        assert_eq!(nice.value(1 - row), 5);
+    }
+
+    #[test]
+    fn test_disabled_counters_are_null() {
+        use arrow_array::{Array, Int64Array};
+
+        let mut timeslot = TimeslotData::new(1000);
+        timeslot.update(7, None, Metric::from_deltas(10, 20, 1, 2, 30));
+
+        let events = PerfEvents::parse("cycles,instructions").unwrap();
+        let schema = create_timeslot_schema(&events, Granularity::Process);
+        let batch = timeslot_to_batch(timeslot, schema, &events, Granularity::Process).unwrap();
+
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(column("cycles").value(0), 10);
+        assert_eq!(column("instructions").value(0), 20);
+        assert!(column("llc_misses").is_null(0));
+        assert!(column("cache_references").is_null(0));
+        assert_eq!(column("duration").value(0), 30);
+    }
+
+    #[test]
+    fn test_thread_rows_have_tid_column() {
+        use arrow_array::{Array, Int32Array};
+
+        let mut timeslot = TimeslotData::new(1000);
+        let metadata = Some(TaskMetadata::new(7, [0; 16], 55));
+        timeslot.update_thread(
+            7,
+            7,
+            metadata.clone(),
+            Metric::from_deltas(10, 20, 1, 2, 30),
+        );
+        timeslot.update_thread(7, 8, metadata, Metric::from_deltas(5, 5, 0, 0, 10));
+        timeslot
+            .ephemeral
+            .insert(55, Metric::from_deltas(100, 200, 3, 4, 500));
+
+        let events = PerfEvents::default();
+        let schema = create_timeslot_schema(&events, Granularity::Thread);
+        // Appended, so the process schema's column positions are unchanged
+        assert_eq!(schema.fields().last().unwrap().name(), "tid");
+        let batch = timeslot_to_batch(timeslot, schema, &events, Granularity::Thread).unwrap();
+
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap()
+                .clone()
+        };
+        let (pids, tids) = (column("pid"), column("tid"));
+        let mut rows: Vec<_> = (0..batch.num_rows())
+            .map(|i| (pids.value(i), tids.is_valid(i).then(|| tids.value(i))))
+            .collect();
+        rows.sort();
+        assert_eq!(rows, [(EPHEMERAL_PID, None), (7, Some(7)), (7, Some(8))]);
+    }
+
     #[tokio::test]
     async fn test_conversion_task() {
         // Create channels
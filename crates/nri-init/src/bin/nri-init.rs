@@ -16,7 +16,13 @@ fn init_tracing(level: LogLevel) {
 }
 
 fn main() {
-    let opts = nri_init::opts::from_env_and_args();
+    let opts = match nri_init::opts::from_env_and_args() {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("nri-init: {e}");
+            std::process::exit(1);
+        }
+    };
     init_tracing(opts.log_level);
     match nri_init::run(opts) {
         Ok(out) => {
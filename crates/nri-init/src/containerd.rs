@@ -1,16 +1,36 @@
+use semver::Version;
 use std::fs;
 use std::path::Path;
 use toml_edit::DocumentMut;
 use tracing::info;
 
 use crate::error::{NriError, Result};
-use crate::toml_util::{ensure_nri_section, ensure_version2};
+use crate::toml_util::{ensure_nri_section, ensure_version2, nri_enabled_by_default};
 
 pub const DEFAULT_CONFIG_PATH: &str = "/etc/containerd/config.toml";
 pub const DEFAULT_SOCKET_PATH: &str = "/var/run/nri/nri.sock";
 
-pub fn configure_containerd(path: &str, socket_path: &str, dry_run: bool) -> Result<bool> {
+pub fn configure_containerd(
+    path: &str,
+    socket_path: &str,
+    version: Option<&Version>,
+    dry_run: bool,
+) -> Result<bool> {
     let p = Path::new(path);
+
+    // containerd 2.x enables NRI by default; leave its config alone unless it opts out
+    if let Some(v) = version.filter(|v| v.major >= 2) {
+        let doc = if p.exists() {
+            parse(&fs::read_to_string(p)?)?
+        } else {
+            DocumentMut::new()
+        };
+        if nri_enabled_by_default(&doc, socket_path) {
+            info!("containerd {v} has NRI already enabled, no changes");
+            return Ok(false);
+        }
+    }
+
     if !p.exists() {
         info!("Containerd config not found at {path}, creating minimal file");
         if dry_run {
@@ -23,9 +43,7 @@ pub fn configure_containerd(path: &str, socket_path: &str, dry_run: bool) -> Res
     }
 
     let content = fs::read_to_string(p)?;
-    let mut doc: DocumentMut = content
-        .parse()
-        .map_err(|e| NriError::TomlMutation(format!("parse error: {e}")))?;
+    let mut doc = parse(&content)?;
     let mut changed = ensure_version2(&mut doc);
     changed |= ensure_nri_section(&mut doc, socket_path);
 
@@ -39,3 +57,9 @@ pub fn configure_containerd(path: &str, socket_path: &str, dry_run: bool) -> Res
     }
     Ok(changed)
 }
+
+fn parse(content: &str) -> Result<DocumentMut> {
+    content
+        .parse()
+        .map_err(|e| NriError::TomlMutation(format!("parse error: {e}")))
+}
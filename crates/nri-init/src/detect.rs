@@ -18,19 +18,16 @@ pub struct Detection {
 }
 
 fn parse_first_semver(s: &str) -> Option<Version> {
-    // Find first x.y.z pattern and try parse
+    // Find first x.y.z pattern (optionally v-prefixed, as containerd 2.x prints) and try parse
     let re = regex_for_semver();
-    if let Some(m) = re.find(s) {
-        Version::parse(m.as_str()).ok()
-    } else {
-        None
-    }
+    let c = re.captures(s)?;
+    Version::parse(&format!("{}.{}.{}", &c[1], &c[2], &c[3])).ok()
 }
 
 fn regex_for_semver() -> regex::Regex {
     // compile-once using once_cell
     static ONCE: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
-    ONCE.get_or_init(|| regex::Regex::new(r"\b(\d+)\.(\d+)\.(\d+)\b").unwrap())
+    ONCE.get_or_init(|| regex::Regex::new(r"\bv?(\d+)\.(\d+)\.(\d+)\b").unwrap())
         .clone()
 }
 
@@ -76,6 +73,9 @@ fn detect_k3s_version(opts: &Options) -> Result<Option<Version>> {
 }
 
 pub fn detect_containerd_version(opts: &Options) -> Result<Option<Version>> {
+    if let Some(ref v) = opts.containerd_version {
+        return Ok(Some(v.clone()));
+    }
     let runner = default_runner(&opts.nsenter);
     // containerd --version
     if let Ok(out) = runner.run_ok("containerd", &["--version"]) {
@@ -85,6 +85,12 @@ pub fn detect_containerd_version(opts: &Options) -> Result<Option<Version>> {
     if let Ok(out) = runner.run_ok("containerd", &["-v"]) {
         return Ok(parse_first_semver(&out));
     }
+    // ctr --version reports the client version, which ships with containerd
+    if let Ok(out) = runner.run_ok("ctr", &["--version"]) {
+        if let Some(v) = parse_first_semver(&out) {
+            return Ok(Some(v));
+        }
+    }
     // ctr version -> parse Server: containerd then Version: line (best-effort)
     if let Ok(out) = runner.run_ok("ctr", &["version"]) {
         // naive scan for first line with "Version:"
@@ -102,3 +108,23 @@ pub fn detect_containerd_version(opts: &Options) -> Result<Option<Version>> {
     warn!("Unable to determine containerd version");
     Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_containerd_version_strings() {
+        let v1 = parse_first_semver(
+            "containerd containerd.io 1.7.25 bcc810d6b9066471b0b6fa75f557a15a1cbf31bb",
+        );
+        assert_eq!(v1, Some(Version::new(1, 7, 25)));
+        let v2 = parse_first_semver(
+            "containerd github.com/containerd/containerd/v2 v2.0.2 c507a0257ea6462fbd6f5ba4f5c74facb04021f4",
+        );
+        assert_eq!(v2, Some(Version::new(2, 0, 2)));
+        let ctr = parse_first_semver("ctr github.com/containerd/containerd/v2 v2.1.0");
+        assert_eq!(ctr, Some(Version::new(2, 1, 0)));
+        assert_eq!(parse_first_semver("containerd unknown"), None);
+    }
+}
@@ -20,6 +20,8 @@ pub enum NriError {
     Timeout(String),
     #[error("Verification failed: {0}")]
     VerificationFailed(String),
+    #[error("NRI socket unavailable: {0}")]
+    SocketUnavailable(String),
     #[error("Detection failed: {0}")]
     DetectionFailed(String),
     #[error("TOML mutation failed: {0}")]
@@ -1,26 +1,23 @@
 use std::fs;
 use std::path::PathBuf;
+use toml_edit::DocumentMut;
 use tracing::info;
 
-pub const DEFAULT_TEMPLATE_DIR: &str = "/var/lib/rancher/k3s/agent/etc/containerd";
+use crate::toml_util::ensure_nri_section;
 
-const NRI_SECTION: &str = r#"
+pub const DEFAULT_TEMPLATE_DIR: &str = "/var/lib/rancher/k3s/agent/etc/containerd";
 
-[plugins."io.containerd.nri.v1.nri"]
-  disable = false
-  disable_connections = false
-  plugin_config_path = "/etc/nri/conf.d"
-  plugin_path = "/opt/nri/plugins"
-  plugin_registration_timeout = "5s"
-  plugin_request_timeout = "2s"
-  socket_path = "/var/run/nri/nri.sock"
-"#;
+const NRI_HEADER: &str = r#"[plugins."io.containerd.nri.v1.nri"]"#;
 
-pub fn configure_k3s_templates(dry_run: bool) -> std::io::Result<bool> {
-    configure_k3s_templates_in(DEFAULT_TEMPLATE_DIR, dry_run)
+pub fn configure_k3s_templates(socket_path: &str, dry_run: bool) -> std::io::Result<bool> {
+    configure_k3s_templates_in(DEFAULT_TEMPLATE_DIR, socket_path, dry_run)
 }
 
-pub fn configure_k3s_templates_in(base_dir: &str, dry_run: bool) -> std::io::Result<bool> {
+pub fn configure_k3s_templates_in(
+    base_dir: &str,
+    socket_path: &str,
+    dry_run: bool,
+) -> std::io::Result<bool> {
     let mut changed = false;
 
     // Ensure template files exist; if not, create both with base header
@@ -47,26 +44,62 @@ pub fn configure_k3s_templates_in(base_dir: &str, dry_run: bool) -> std::io::Res
     // Ensure NRI section present in whichever templates exist
     for p in [&template_v2, &template_v3] {
         if p.exists() {
-            let mut content = fs::read_to_string(p)?;
-            if !content.contains("plugins.\"io.containerd.nri.v1.nri\"") {
-                info!("Adding NRI section to {}", p.display());
-                content.push_str(NRI_SECTION);
+            let content = fs::read_to_string(p)?;
+            if let Some(newc) = merge_nri_section(&content, socket_path)? {
+                info!("Updating NRI section in {}", p.display());
                 if !dry_run {
-                    fs::write(p, content)?;
+                    fs::write(p, newc)?;
                 }
                 changed = true;
-            } else if content.contains("disable = true") {
-                info!("Flipping disable=true to disable=false in {}", p.display());
-                let newc = content.replace("disable = true", "disable = false");
-                if newc != content {
-                    if !dry_run {
-                        fs::write(p, newc)?;
-                    }
-                    changed = true;
-                }
             }
         }
     }
 
     Ok(changed)
 }
+
+/// Add the NRI table to a template, or fill in the keys an existing one lacks, leaving
+/// the rest of the template (and any custom NRI values) as written. Returns the new
+/// content, or `None` if nothing changed.
+///
+/// Templates contain Go template directives and are not TOML as a whole, so only the
+/// NRI table (from its header up to the next table header or directive) is parsed.
+fn merge_nri_section(content: &str, socket_path: &str) -> std::io::Result<Option<String>> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let start = lines.iter().position(|l| l.trim() == NRI_HEADER);
+    let (before, block, after) = match start {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| {
+                    let t = l.trim_start();
+                    t.starts_with('[') || t.starts_with("{{")
+                })
+                .map_or(lines.len(), |i| start + 1 + i);
+            (
+                lines[..start].concat(),
+                lines[start..end].concat(),
+                lines[end..].concat(),
+            )
+        }
+        None => {
+            let mut before = content.to_string();
+            if !before.is_empty() && !before.ends_with('\n') {
+                before.push('\n');
+            }
+            before.push('\n');
+            (before, format!("{NRI_HEADER}\n"), String::new())
+        }
+    };
+
+    let mut doc: DocumentMut = block.parse().map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("cannot parse NRI section: {e}"),
+        )
+    })?;
+    if !ensure_nri_section(&mut doc, socket_path) && start.is_some() {
+        return Ok(None);
+    }
+    Ok(Some(format!("{before}{doc}{after}")))
+}
@@ -10,7 +10,7 @@ mod verify;
 pub use detect::EnvKind;
 pub use error::{NriError, Result};
 pub use opts::{LogLevel, Mode, Nsenter, Options};
-pub use verify::RestartResult;
+pub use verify::{RestartResult, SocketStatus};
 
 use semver::Version;
 use tracing::{error, info, warn};
@@ -73,9 +73,10 @@ pub fn run(opts: Options) -> Result<NriOutcome> {
         match det.env {
             EnvKind::K3s { .. } => {
                 configured = if let Some(ref dir) = opts.k3s_template_dir {
-                    k3s::configure_k3s_templates_in(dir, opts.dry_run).map_err(NriError::Io)?
+                    k3s::configure_k3s_templates_in(dir, socket_path, opts.dry_run)
+                        .map_err(NriError::Io)?
                 } else {
-                    k3s::configure_k3s_templates(opts.dry_run).map_err(NriError::Io)?
+                    k3s::configure_k3s_templates(socket_path, opts.dry_run).map_err(NriError::Io)?
                 };
             }
             EnvKind::Containerd => {
@@ -83,7 +84,12 @@ pub fn run(opts: Options) -> Result<NriOutcome> {
                     .containerd_config_path
                     .as_deref()
                     .unwrap_or(containerd::DEFAULT_CONFIG_PATH);
-                configured = containerd::configure_containerd(cfg_path, socket_path, opts.dry_run)?;
+                configured = containerd::configure_containerd(
+                    cfg_path,
+                    socket_path,
+                    det.containerd_version.as_ref(),
+                    opts.dry_run,
+                )?;
             }
         }
     } else {
@@ -128,13 +134,23 @@ pub fn run(opts: Options) -> Result<NriOutcome> {
         }
     }
 
+    // Post-configure verification: the socket must exist and be connectable.
+    // This only reads the socket file, so it runs in dry-run mode as well.
+    let socket_status = verify::check_socket(socket_path)?;
+    match socket_status {
+        SocketStatus::Ready { .. } | SocketStatus::Missing => {}
+        SocketStatus::NotASocket { .. } | SocketStatus::Inaccessible { .. } => {
+            warn!("NRI socket at {socket_path} is unusable: {socket_status}");
+        }
+    }
+
     // Final availability policy
-    let socket_available_final = std::path::Path::new(socket_path).exists();
+    let socket_available_final = socket_status.is_ready();
     if opts.fail_if_unavailable && !socket_available_final {
         error!("NRI unavailable and fail_if_unavailable=true");
-        return Err(NriError::VerificationFailed(
-            "NRI socket unavailable".into(),
-        ));
+        return Err(NriError::SocketUnavailable(format!(
+            "{socket_path}: {socket_status}"
+        )));
     }
 
     Ok(NriOutcome {
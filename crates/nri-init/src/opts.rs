@@ -1,3 +1,7 @@
+use semver::Version;
+
+use crate::error::{NriError, Result};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Auto,
@@ -32,6 +36,8 @@ pub struct Options {
     pub containerd_config_path: Option<String>,
     pub socket_path: Option<String>,
     pub k3s_template_dir: Option<String>,
+    /// Use this containerd version instead of detecting it
+    pub containerd_version: Option<Version>,
 }
 
 impl Default for Options {
@@ -47,6 +53,7 @@ impl Default for Options {
             containerd_config_path: None,
             socket_path: None,
             k3s_template_dir: None,
+            containerd_version: None,
         }
     }
 }
@@ -55,7 +62,7 @@ fn parse_bool(s: &str) -> bool {
     matches!(s.trim(), "1" | "true" | "TRUE" | "True" | "yes" | "on")
 }
 
-pub fn from_env_and_args() -> Options {
+pub fn from_env_and_args() -> Result<Options> {
     // Minimal, argument-light parser to avoid extra deps; flags override envs.
     let mut opts = Options::default();
 
@@ -111,6 +118,14 @@ pub fn from_env_and_args() -> Options {
                     opts.k3s_template_dir = Some(p);
                 }
             }
+            "--containerd-version" => {
+                if let Some(v) = args.next() {
+                    let version = Version::parse(v.trim_start_matches('v')).map_err(|e| {
+                        NriError::Parse(format!("invalid --containerd-version {v:?}: {e}"))
+                    })?;
+                    opts.containerd_version = Some(version);
+                }
+            }
             "--log-level" => {
                 if let Some(v) = args.next() {
                     opts.log_level = match v.as_str() {
@@ -134,5 +149,5 @@ pub fn from_env_and_args() -> Options {
         });
     }
 
-    opts
+    Ok(opts)
 }
@@ -1,5 +1,7 @@
 use toml_edit::{value, DocumentMut, Item, Table};
 
+use crate::containerd::DEFAULT_SOCKET_PATH;
+
 pub fn ensure_version2(doc: &mut DocumentMut) -> bool {
     // Ensure top-level version = 2 if absent
     if !doc.contains_key("version") {
@@ -39,7 +41,8 @@ pub fn ensure_nri_section(doc: &mut DocumentMut, socket_path: &str) -> bool {
         .map(|v| v.as_bool().unwrap_or(false))
         != Some(false)
     {
-        t.insert("disable", value(false));
+        // Assign in place so an existing key keeps its position and indentation
+        t["disable"] = value(false);
         changed = true;
     }
     if !t.contains_key("disable_connections") {
@@ -71,13 +74,30 @@ pub fn ensure_nri_section(doc: &mut DocumentMut, socket_path: &str) -> bool {
         .map(|s| s != socket_path)
         .unwrap_or(true);
     if need_socket_update {
-        t.insert("socket_path", value(socket_path));
+        t["socket_path"] = value(socket_path);
         changed = true;
     }
 
     changed
 }
 
+/// Whether a containerd 2.x config leaves NRI enabled on `socket_path`. NRI is on by
+/// default there, so only an explicit `disable = true` or another socket path turns it off.
+pub fn nri_enabled_by_default(doc: &DocumentMut, socket_path: &str) -> bool {
+    let Some(t) = doc
+        .get("plugins")
+        .and_then(|p| p.get("io.containerd.nri.v1.nri"))
+    else {
+        return socket_path == DEFAULT_SOCKET_PATH;
+    };
+    let disabled = t.get("disable").and_then(|v| v.as_bool()).unwrap_or(false);
+    let configured_socket = t
+        .get("socket_path")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_SOCKET_PATH);
+    !disabled && configured_socket == socket_path
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +126,49 @@ mod tests {
         let second = d.to_string();
         assert_eq!(first, second);
     }
+
+    #[test]
+    fn nri_enabled_by_default_on_v2() {
+        let sock = "/var/run/nri/nri.sock";
+        let empty: DocumentMut = "version = 3\n".parse().unwrap();
+        assert!(nri_enabled_by_default(&empty, sock));
+        assert!(!nri_enabled_by_default(&empty, "/run/other.sock"));
+
+        let disabled: DocumentMut = "[plugins.\"io.containerd.nri.v1.nri\"]\ndisable = true\n"
+            .parse()
+            .unwrap();
+        assert!(!nri_enabled_by_default(&disabled, sock));
+
+        let custom: DocumentMut =
+            "[plugins.\"io.containerd.nri.v1.nri\"]\nsocket_path = \"/run/other.sock\"\n"
+                .parse()
+                .unwrap();
+        assert!(nri_enabled_by_default(&custom, "/run/other.sock"));
+        assert!(!nri_enabled_by_default(&custom, sock));
+    }
+
+    #[test]
+    fn partial_table_is_merged() {
+        let sock = "/var/run/nri/nri.sock";
+        let original = "[plugins.\"io.containerd.nri.v1.nri\"]\n  disable = true\n  disable_connections = false\n  plugin_config_path = \"/etc/nri/conf.d\"\n  plugin_path = \"/custom/plugins\"\n  plugin_registration_timeout = \"5s\"\n  plugin_request_timeout = \"2s\"\n  socket_path = \"/var/run/nri/nri.sock\"\n";
+        let mut d: DocumentMut = original.parse().unwrap();
+        assert!(ensure_nri_section(&mut d, sock));
+        // Only disable flips; the custom plugin_path and layout are kept
+        assert_eq!(
+            d.to_string(),
+            original.replace("disable = true", "disable = false")
+        );
+
+        // A table with only socket_path gets the missing keys
+        let mut d: DocumentMut =
+            "[plugins.\"io.containerd.nri.v1.nri\"]\nsocket_path = \"/var/run/nri/nri.sock\"\n"
+                .parse()
+                .unwrap();
+        assert!(ensure_nri_section(&mut d, sock));
+        let s = d.to_string();
+        assert!(s.contains("disable = false"));
+        assert!(s.contains("plugin_path = \"/opt/nri/plugins\""));
+        assert_eq!(s.matches("socket_path").count(), 1);
+        assert!(!ensure_nri_section(&mut d, sock));
+    }
 }
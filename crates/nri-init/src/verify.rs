@@ -1,9 +1,11 @@
+use std::fmt;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 use std::path::Path;
 use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
 use crate::cmd::default_runner;
-use crate::error::Result;
+use crate::error::{NriError, Result};
 use crate::opts::Options;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -115,3 +117,55 @@ pub fn wait_for_socket(path: &str, timeout: Duration) -> bool {
     }
     false
 }
+
+/// State of the NRI socket file at the configured path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketStatus {
+    /// Nothing exists at the path
+    Missing,
+    /// The path exists but is not a Unix socket
+    NotASocket { mode: u32 },
+    /// A socket whose owner cannot both read and write it
+    Inaccessible { mode: u32 },
+    /// A socket the owner can connect to
+    Ready { mode: u32 },
+}
+
+impl SocketStatus {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, SocketStatus::Ready { .. })
+    }
+}
+
+impl fmt::Display for SocketStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SocketStatus::Missing => write!(f, "socket file does not exist"),
+            SocketStatus::NotASocket { mode } => write!(f, "not a Unix socket (mode {mode:o})"),
+            SocketStatus::Inaccessible { mode } => {
+                write!(
+                    f,
+                    "socket is not readable and writable by its owner (mode {mode:o})"
+                )
+            }
+            SocketStatus::Ready { mode } => write!(f, "socket ready (mode {mode:o})"),
+        }
+    }
+}
+
+/// Check that `path` is a Unix socket with owner read/write permission
+pub fn check_socket(path: &str) -> Result<SocketStatus> {
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SocketStatus::Missing),
+        Err(e) => return Err(NriError::Io(e)),
+    };
+    let mode = meta.permissions().mode() & 0o7777;
+    Ok(if !meta.file_type().is_socket() {
+        SocketStatus::NotASocket { mode }
+    } else if mode & 0o600 != 0o600 {
+        SocketStatus::Inaccessible { mode }
+    } else {
+        SocketStatus::Ready { mode }
+    })
+}
@@ -2,7 +2,7 @@ use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
 
-use nri_init::{LogLevel, Mode, Options};
+use nri_init::{Mode, Options};
 
 fn temp_path(dir: &TempDir, rel: &str) -> String {
     let mut p = PathBuf::from(dir.path());
@@ -22,15 +22,10 @@ fn containerd_config_minimal_is_created_and_patched() {
 
     let opts = Options {
         configure: true,
-        restart: false,
-        fail_if_unavailable: false,
         mode: Mode::Containerd,
-        nsenter: None,
-        log_level: LogLevel::Info,
-        dry_run: false,
         containerd_config_path: Some(cfg.clone()),
         socket_path: Some(socket.clone()),
-        k3s_template_dir: None,
+        ..Default::default()
     };
     let out = nri_init::run(opts).expect("run ok");
     assert!(out.configured);
@@ -48,15 +43,9 @@ fn k3s_templates_created_and_patched() {
 
     let opts = Options {
         configure: true,
-        restart: false,
-        fail_if_unavailable: false,
         mode: Mode::K3s,
-        nsenter: None,
-        log_level: LogLevel::Info,
-        dry_run: false,
-        containerd_config_path: None,
-        socket_path: None,
         k3s_template_dir: Some(base.clone()),
+        ..Default::default()
     };
     let out = nri_init::run(opts).expect("run ok");
     assert!(out.configured);
@@ -78,15 +67,9 @@ fn containerd_config_idempotent() {
     // First run creates and patches
     let opts1 = Options {
         configure: true,
-        restart: false,
-        fail_if_unavailable: false,
         mode: Mode::Containerd,
-        nsenter: None,
-        log_level: LogLevel::Info,
-        dry_run: false,
         containerd_config_path: Some(cfg.clone()),
-        socket_path: None,
-        k3s_template_dir: None,
+        ..Default::default()
     };
     // Clone to avoid moving opts1 so we can reuse it below
     let out1 = nri_init::run(opts1.clone()).expect("first run ok");
@@ -101,3 +84,149 @@ fn containerd_config_idempotent() {
         "second run should be idempotent (no changes)"
     );
 }
+
+#[test]
+fn socket_verified_after_configure() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    for dry_run in [false, true] {
+        let tmp = TempDir::new().unwrap();
+        let cfg = temp_path(&tmp, "etc/containerd/config.toml");
+        fs::create_dir_all(PathBuf::from(&cfg).parent().unwrap()).unwrap();
+        let socket = temp_path(&tmp, "var/run/nri/nri.sock");
+        fs::create_dir_all(PathBuf::from(&socket).parent().unwrap()).unwrap();
+
+        let opts = Options {
+            configure: true,
+            fail_if_unavailable: true,
+            mode: Mode::Containerd,
+            dry_run,
+            containerd_config_path: Some(cfg.clone()),
+            socket_path: Some(socket.clone()),
+            ..Default::default()
+        };
+
+        // Missing socket
+        let err = nri_init::run(opts.clone()).expect_err("missing socket must fail");
+        assert!(matches!(err, nri_init::NriError::SocketUnavailable(_)));
+        assert!(err.to_string().contains(&socket), "{err}");
+        assert_eq!(PathBuf::from(&cfg).exists(), !dry_run);
+
+        // A regular file is not a socket
+        fs::write(&socket, "").unwrap();
+        let err = nri_init::run(opts.clone()).expect_err("regular file must fail");
+        assert!(err.to_string().contains("not a Unix socket"), "{err}");
+        fs::remove_file(&socket).unwrap();
+
+        // A socket without owner read/write permission is unusable
+        let _listener = UnixListener::bind(&socket).unwrap();
+        fs::set_permissions(&socket, fs::Permissions::from_mode(0o200)).unwrap();
+        let err = nri_init::run(opts.clone()).expect_err("inaccessible socket must fail");
+        assert!(err.to_string().contains("mode 200"), "{err}");
+        let out = nri_init::run(Options {
+            fail_if_unavailable: false,
+            ..opts.clone()
+        })
+        .expect("inaccessible socket is only reported without fail_if_unavailable");
+        assert!(!out.socket_available);
+
+        // A readable and writable socket passes
+        fs::set_permissions(&socket, fs::Permissions::from_mode(0o660)).unwrap();
+        let out = nri_init::run(opts).expect("socket ready");
+        assert!(out.socket_available);
+    }
+}
+
+#[test]
+fn containerd_v2_with_nri_enabled_skips_config() {
+    let tmp = TempDir::new().unwrap();
+    let cfg = temp_path(&tmp, "etc/containerd/config.toml");
+    fs::create_dir_all(PathBuf::from(&cfg).parent().unwrap()).unwrap();
+
+    let opts = Options {
+        configure: true,
+        restart: true,
+        mode: Mode::Containerd,
+        containerd_config_path: Some(cfg.clone()),
+        containerd_version: Some(semver::Version::parse("2.0.2").unwrap()),
+        ..Default::default()
+    };
+
+    // No config file: NRI is on by default, so nothing is written or restarted
+    let out = nri_init::run(opts.clone()).expect("run ok");
+    assert!(!out.configured);
+    assert!(!out.restarted);
+    assert!(!PathBuf::from(&cfg).exists());
+
+    // An existing config without an NRI section is left untouched
+    let original = "version = 3\n\n[plugins.\"io.containerd.grpc.v1.cri\"]\n";
+    fs::write(&cfg, original).unwrap();
+    let out = nri_init::run(opts.clone()).expect("run ok");
+    assert!(!out.configured);
+    assert_eq!(fs::read_to_string(&cfg).unwrap(), original);
+
+    // NRI explicitly disabled still gets enabled
+    fs::write(
+        &cfg,
+        "version = 3\n\n[plugins.\"io.containerd.nri.v1.nri\"]\ndisable = true\n",
+    )
+    .unwrap();
+    let out = nri_init::run(Options {
+        restart: false,
+        ..opts
+    })
+    .expect("run ok");
+    assert!(out.configured);
+    assert!(fs::read_to_string(&cfg)
+        .unwrap()
+        .contains("disable = false"));
+}
+
+#[test]
+fn k3s_template_customizations_are_preserved() {
+    let tmp = TempDir::new().unwrap();
+    let base = temp_path(&tmp, "var/lib/rancher/k3s/agent/etc/containerd");
+    fs::create_dir_all(&base).unwrap();
+    let tmpl = PathBuf::from(&base).join("config.toml.tmpl");
+    fs::write(
+        &tmpl,
+        r#"{{ template "base" . }}
+
+[plugins."io.containerd.nri.v1.nri"]
+  disable = true
+  plugin_path = "/custom/nri/plugins"
+
+[plugins."io.containerd.other"]
+  disable = true
+"#,
+    )
+    .unwrap();
+
+    let opts = Options {
+        configure: true,
+        mode: Mode::K3s,
+        k3s_template_dir: Some(base.clone()),
+        ..Default::default()
+    };
+    let out = nri_init::run(opts.clone()).expect("run ok");
+    assert!(out.configured);
+
+    let content = fs::read_to_string(&tmpl).unwrap();
+    assert!(content.starts_with("{{ template \"base\" . }}\n"));
+    assert_eq!(
+        content
+            .matches("plugins.\"io.containerd.nri.v1.nri\"")
+            .count(),
+        1
+    );
+    assert!(content.contains("  disable = false\n  plugin_path = \"/custom/nri/plugins\"\n"));
+    assert!(content.contains("socket_path = \"/var/run/nri/nri.sock\""));
+    // Other plugins' settings are untouched
+    assert!(content.ends_with("[plugins.\"io.containerd.other\"]\n  disable = true\n"));
+
+    // Second run finds nothing to merge
+    let out = nri_init::run(opts).expect("second run ok");
+    assert!(!out.configured);
+    assert_eq!(fs::read_to_string(&tmpl).unwrap(), content);
+}
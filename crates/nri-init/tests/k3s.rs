@@ -1,19 +1,13 @@
-use nri_init::{LogLevel, Mode, Options};
+use nri_init::{Mode, Options};
 
 #[ignore]
 #[test]
 fn detect_k3s_and_configure_without_restart() {
     let opts = Options {
         configure: true,
-        restart: false,
-        fail_if_unavailable: false,
         mode: Mode::K3s,
-        nsenter: None,
-        log_level: LogLevel::Info,
         dry_run: true,
-        containerd_config_path: None,
-        socket_path: None,
-        k3s_template_dir: None,
+        ..Default::default()
     };
     let out = nri_init::run(opts).expect("run ok");
     match out.env {
@@ -29,14 +23,9 @@ fn detect_k3s_and_configure_with_restart() {
     let opts = Options {
         configure: true,
         restart: true,
-        fail_if_unavailable: false,
         mode: Mode::K3s,
-        nsenter: None,
-        log_level: LogLevel::Info,
         dry_run: true,
-        containerd_config_path: None,
-        socket_path: None,
-        k3s_template_dir: None,
+        ..Default::default()
     };
     let out = nri_init::run(opts).expect("run ok");
     match out.env {
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+use nri::send_policy::SendPolicy;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::PodResctrlEvent;
+
+/// Max events queued behind a full channel before new ones are dropped
+const MAX_BACKLOG: usize = 4096;
+
+/// Sends plugin events to the collector without blocking the caller.
+///
+/// Events are emitted from synchronous code, often under the plugin's state
+/// lock to keep them ordered, so `send` never waits. An event that finds the
+/// channel full is dropped with `SendPolicy::Drop`; with
+/// `SendPolicy::BlockWithTimeout` it is queued, and a task sends the queue in
+/// order, waiting for capacity per the policy. Events emitted while the queue
+/// drains are queued behind it, so ordering is kept.
+#[derive(Clone)]
+pub(crate) struct EventSender {
+    tx: mpsc::Sender<PodResctrlEvent>,
+    policy: SendPolicy,
+    dropped: Arc<AtomicUsize>,
+    backlog: Arc<Mutex<Backlog>>,
+}
+
+#[derive(Default)]
+struct Backlog {
+    queue: VecDeque<PodResctrlEvent>,
+    // A task is sending the queue
+    draining: bool,
+}
+
+impl EventSender {
+    pub(crate) fn new(tx: mpsc::Sender<PodResctrlEvent>, policy: SendPolicy) -> Self {
+        Self {
+            tx,
+            policy,
+            dropped: Arc::new(AtomicUsize::new(0)),
+            backlog: Arc::new(Mutex::new(Backlog::default())),
+        }
+    }
+
+    /// Number of events dropped due to a full channel.
+    pub(crate) fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Send `ev`, or queue it behind earlier events that are still waiting
+    pub(crate) fn send(&self, ev: PodResctrlEvent) {
+        let mut backlog = self.backlog.lock().unwrap();
+        if backlog.draining {
+            if backlog.queue.len() >= MAX_BACKLOG {
+                self.drop_event("event backlog full");
+            } else {
+                backlog.queue.push_back(ev);
+            }
+            return;
+        }
+
+        let ev = match (self.policy, self.tx.try_send(ev)) {
+            (_, Ok(())) => return,
+            (SendPolicy::BlockWithTimeout(_), Err(TrySendError::Full(ev))) => ev,
+            (_, Err(e)) => return self.drop_event(e),
+        };
+        let Ok(handle) = Handle::try_current() else {
+            return self.drop_event("channel full and no runtime to wait on");
+        };
+        backlog.queue.push_back(ev);
+        backlog.draining = true;
+        handle.spawn(self.clone().drain());
+    }
+
+    /// Send queued events in order until the queue is empty
+    async fn drain(self) {
+        loop {
+            let next = {
+                let mut backlog = self.backlog.lock().unwrap();
+                let next = backlog.queue.pop_front();
+                backlog.draining = next.is_some();
+                next
+            };
+            let Some(ev) = next else {
+                return;
+            };
+            if let Err(e) = self.policy.send(&self.tx, ev).await {
+                self.drop_event(e);
+            }
+        }
+    }
+
+    fn drop_event(&self, reason: impl std::fmt::Display) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        warn!("resctrl-plugin: failed to send event: {}", reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn removed(uid: &str) -> PodResctrlEvent {
+        PodResctrlEvent::Removed(crate::PodResctrlRemoved {
+            pod_uid: uid.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_full_channel_queues_in_order_without_blocking() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let sender = EventSender::new(tx, SendPolicy::BlockWithTimeout(Duration::from_secs(5)));
+
+        // Returns immediately although the channel fills up after the first event
+        for uid in ["a", "b", "c"] {
+            sender.send(removed(uid));
+        }
+        let mut uids = Vec::new();
+        for _ in 0..3 {
+            match rx.recv().await {
+                Some(PodResctrlEvent::Removed(r)) => uids.push(r.pod_uid),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(uids, vec!["a", "b", "c"]);
+        assert_eq!(sender.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_event_dropped_after_timeout() {
+        let (tx, _rx) = mpsc::channel(1);
+        let sender = EventSender::new(tx, SendPolicy::BlockWithTimeout(Duration::from_millis(10)));
+        sender.send(removed("a"));
+        sender.send(removed("b"));
+        assert_eq!(sender.dropped(), 0);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(sender.dropped(), 1);
+    }
+}
@@ -1,41 +1,64 @@
+mod event_sender;
 mod pid_source;
+mod warn_limiter;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut as _;
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc, Mutex,
 };
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 use log::{debug, error, info, warn};
 use tokio::sync::mpsc;
 use ttrpc::r#async::TtrpcContext;
 
 use nri::api::{
-    ConfigureRequest, ConfigureResponse, CreateContainerRequest, CreateContainerResponse, Empty,
-    Event, StateChangeEvent, StopContainerRequest, StopContainerResponse, SynchronizeRequest,
-    SynchronizeResponse, UpdateContainerRequest, UpdateContainerResponse, UpdatePodSandboxRequest,
-    UpdatePodSandboxResponse,
+    ConfigureRequest, ConfigureResponse, ContainerAdjustment, CreateContainerRequest,
+    CreateContainerResponse, Empty, Event, LinuxContainerAdjustment, LinuxResources,
+    OptionalString, StateChangeEvent, StopContainerRequest, StopContainerResponse,
+    SynchronizeRequest, SynchronizeResponse, UpdateContainerRequest, UpdateContainerResponse,
+    UpdatePodSandboxRequest, UpdatePodSandboxResponse,
 };
 use nri::api_ttrpc::Plugin;
 use nri::events_mask::EventMask;
+use nri::send_policy::SendPolicy;
 
 use resctrl::{Config as ResctrlConfig, FsProvider, RealFs, Resctrl};
+use serde::Serialize;
 
+use crate::event_sender::EventSender;
 use crate::pid_source::{CgroupPidSource, RealCgroupPidSource};
+use crate::warn_limiter::WarnLimiter;
+
+/// Container annotation set by `create_container` (with `adjust_on_create`) to
+/// the resctrl group path the container's tasks belong to.
+pub const GROUP_ANNOTATION: &str = "resctrl.unvariance.io/group";
+
+/// Window over which repeated resctrl failure warnings are coalesced
+const WARN_COALESCE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Pods (and containers) handled between progress logs of `synchronize`
+const SYNC_PROGRESS_INTERVAL: usize = 50;
 
 /// Resctrl group state for a pod.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ResctrlGroupState {
     /// Group exists at the given path
     Exists(String),
     /// Group could not be created (e.g., RMID exhaustion)
+    #[default]
     Failed,
+    /// No group was created since the plugin only observes (`observe_only`)
+    Observed,
 }
 
 /// Event payload for an added/updated pod.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct PodResctrlAddOrUpdate {
     pub pod_uid: String,
     pub group_state: ResctrlGroupState,
@@ -43,6 +66,10 @@ pub struct PodResctrlAddOrUpdate {
     pub total_containers: usize,
     /// Number of containers reconciled successfully
     pub reconciled_containers: usize,
+    /// PIDs of the pod's containers still outside the group after their last
+    /// reconcile (e.g. kernel threads that cannot be moved). 0 when every PID
+    /// was assigned or nothing could be read.
+    pub unreconciled_pids: usize,
 }
 
 /// Event payload for a removed/disassociated pod.
@@ -58,19 +85,141 @@ pub enum PodResctrlEvent {
     Removed(PodResctrlRemoved),
 }
 
+/// Snapshot of the plugin's view of pods and containers together with the
+/// groups present in the resctrl filesystem, for troubleshooting.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DebugSnapshot {
+    /// Tracked pods, sorted by UID
+    pub pods: Vec<PodSnapshot>,
+    /// Tracked containers, sorted by ID
+    pub containers: Vec<ContainerSnapshot>,
+    /// UIDs of pods with monitoring disabled by annotation, sorted
+    pub unmonitored_pods: Vec<String>,
+    /// Paths of the groups with the configured prefix found on disk
+    pub groups: Vec<String>,
+    /// Error listing the groups on disk, if any (`groups` is then empty)
+    pub groups_error: Option<String>,
+}
+
+/// Pod entry of a [`DebugSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PodSnapshot {
+    pub pod_uid: String,
+    pub group_state: ResctrlGroupState,
+    pub total_containers: usize,
+    pub reconciled_containers: usize,
+    pub unreconciled_pids: usize,
+    /// Allocation requested via the schemata annotation
+    pub schemata: Option<String>,
+    /// Group class from `class_rules`, when the pod shares its class's group
+    pub class: Option<String>,
+}
+
+/// Container entry of a [`DebugSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ContainerSnapshot {
+    pub container_id: String,
+    pub pod_uid: String,
+    pub cgroup_path: String,
+    pub state: ContainerSyncState,
+    pub missing_pids: usize,
+}
+
+/// Places pods whose annotation `annotation` is `value` in the shared group of
+/// class `class`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassRule {
+    /// Pod annotation key
+    pub annotation: String,
+    /// Annotation value selecting the class (compared after trimming)
+    pub value: String,
+    /// Class name; the class's group is `<group_prefix>class_<class>`
+    pub class: String,
+}
+
 /// Configuration for the resctrl NRI plugin.
 #[derive(Clone, Debug)]
 pub struct ResctrlPluginConfig {
     /// Prefix used for resctrl group naming (e.g., "pod_")
     pub group_prefix: String,
-    /// Cleanup stale groups with the given prefix on start
+    /// Cleanup stale groups with the given prefix on start. Refused (with an
+    /// error logged) for prefixes that could match unrelated groups, see
+    /// `resctrl::validate_group_prefix`
     pub cleanup_on_start: bool,
+    /// With `cleanup_on_start`, only log which stale groups would be removed
+    /// instead of removing them (to validate `group_prefix` first)
+    pub cleanup_dry_run: bool,
+    /// Keep groups left by a previous run for the pods seen in the first
+    /// `synchronize`, removing only groups no pod adopts. Replaces the startup
+    /// cleanup, so pods keep their groups (and RMIDs) across restarts.
+    /// `cleanup_dry_run` then only logs the orphaned groups.
+    pub reconcile_existing_on_start: bool,
     /// Max reconciliation passes when assigning tasks per pod
     pub max_reconcile_passes: usize,
-    /// Max concurrent pod operations
+    /// Wall-clock deadline for reconciling one container (or one batch of a
+    /// pod's containers), measured from the first pass. Reconciliation stops
+    /// after `max_reconcile_passes` or this deadline, whichever comes first; a
+    /// container still gaining PIDs is left `Partial` for the background retry.
+    /// `None` runs all passes.
+    pub max_reconcile_time: Option<Duration>,
+    /// Max pods reconciled concurrently during `synchronize` (containers of
+    /// the same pod are always handled in order)
     pub concurrency_limit: usize,
     /// Whether `resctrl` should auto-mount when not present
     pub auto_mount: bool,
+    /// Levels of child cgroups below each container cgroup whose PIDs are also
+    /// assigned (0 = the container cgroup only)
+    pub pid_recursion_depth: usize,
+    /// During `synchronize`, reconcile all new containers of a pod into its group
+    /// together, reading their cgroups once, instead of one container at a time
+    pub batch_pod_pid_reads: bool,
+    /// Pod annotation holding a resctrl allocation in `schemata` format (e.g.
+    /// `L3:0=f;1=f`). Annotated pods get a control group with that allocation
+    /// instead of a monitoring group. `None` disables allocation.
+    pub schemata_annotation: Option<String>,
+    /// Pods matching a rule (the first that matches) share one group per class
+    /// instead of getting a group each. The class group is created by the
+    /// first pod of the class, with that pod's schemata, and deleted when the
+    /// last pod of the class leaves. Empty gives every pod its own group.
+    pub class_rules: Vec<ClassRule>,
+    /// Subscribe to `CreateContainer` and return a container adjustment: the
+    /// container is annotated with its pod's group (`GROUP_ANNOTATION`), and for
+    /// pods with a control group the runtime is asked to start the container
+    /// in it (`rdt_class`), so its tasks are placed before they first run.
+    pub adjust_on_create: bool,
+    /// Fail `synchronize` with `FAILED_PRECONDITION` when resctrl is not (and
+    /// cannot be) mounted, instead of tracking every pod with a `Failed` group
+    pub abort_sync_if_unmounted: bool,
+    /// Behavior when the event channel is full: drop the event, or queue it
+    /// and have a background task wait for capacity up to a timeout first
+    pub send_policy: SendPolicy,
+    /// Pod annotation that turns monitoring off (`false`, `off`, `disabled` or
+    /// `0`) or back on (any other value, or removing it). Re-evaluated on
+    /// `UpdatePodSandbox`: disabling deletes the pod's group and stops tracking
+    /// the pod, enabling creates the group and reconciles its running
+    /// containers. `None` monitors every pod.
+    pub monitoring_annotation: Option<String>,
+    /// Runtime names (as reported in `Configure`) the plugin operates under.
+    /// Empty allows every runtime not in `denied_runtimes`.
+    pub allowed_runtimes: Vec<String>,
+    /// Runtime names the plugin never operates under; takes precedence over
+    /// `allowed_runtimes`. Under a denied runtime the plugin subscribes to no
+    /// events and ignores `synchronize`.
+    pub denied_runtimes: Vec<String>,
+    /// Track pods and containers and emit their events without touching the
+    /// resctrl filesystem: no mount, cleanup, group creation or deletion, and
+    /// no task assignment. Pods report `ResctrlGroupState::Observed` and their
+    /// containers stay unreconciled. Overrides `abort_sync_if_unmounted`.
+    pub observe_only: bool,
+}
+
+impl ResctrlPluginConfig {
+    /// Whether the plugin should operate under the runtime named `runtime_name`
+    pub fn runtime_allowed(&self, runtime_name: &str) -> bool {
+        !self.denied_runtimes.iter().any(|r| r == runtime_name)
+            && (self.allowed_runtimes.is_empty()
+                || self.allowed_runtimes.iter().any(|r| r == runtime_name))
+    }
 }
 
 impl Default for ResctrlPluginConfig {
@@ -78,9 +227,23 @@ impl Default for ResctrlPluginConfig {
         Self {
             group_prefix: "pod_".to_string(),
             cleanup_on_start: true,
+            cleanup_dry_run: false,
+            reconcile_existing_on_start: false,
             max_reconcile_passes: 1,
+            max_reconcile_time: None,
             concurrency_limit: 1,
             auto_mount: true,
+            pid_recursion_depth: 0,
+            batch_pod_pid_reads: false,
+            schemata_annotation: None,
+            class_rules: Vec::new(),
+            adjust_on_create: false,
+            abort_sync_if_unmounted: false,
+            send_policy: SendPolicy::Drop,
+            monitoring_annotation: None,
+            allowed_runtimes: Vec::new(),
+            denied_runtimes: Vec::new(),
+            observe_only: false,
         }
     }
 }
@@ -90,14 +253,45 @@ struct PodState {
     group_state: ResctrlGroupState,
     total_containers: usize,
     reconciled_containers: usize,
+    // Sum of `missing_pids` over the pod's containers
+    unreconciled_pids: usize,
+    // Allocation requested via the schemata annotation, reapplied on retries
+    schemata: Option<String>,
+    // Class whose shared group the pod uses, from `class_rules`
+    class: Option<String>,
+}
+
+/// Outcome of a `synchronize` for the pods and containers of its request,
+/// logged once it completes.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SyncSummary {
+    /// Pods with a group, created or adopted
+    groups_ready: usize,
+    /// Pods whose group could not be created
+    groups_failed: usize,
+    /// Pods not tracked since monitoring is disabled for them
+    unmonitored_pods: usize,
+    containers_reconciled: usize,
+    containers_partial: usize,
+    /// Containers without a known pod, or whose PIDs cannot be read
+    containers_other: usize,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
-pub(crate) enum ContainerSyncState {
+/// Reconcile state of a container's tasks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerSyncState {
+    /// The container's pod is not known yet
     #[default]
     NoPod,
+    /// Some tasks are not in the pod's group, or the group does not exist
     Partial,
+    /// All tasks are in the pod's group
     Reconciled,
+    /// Reading the container's `cgroup.procs` failed with EACCES/EPERM.
+    /// Terminal: retries are skipped since they cannot succeed without a
+    /// permission change.
+    PermissionDenied,
 }
 
 #[derive(Default)]
@@ -106,24 +300,38 @@ struct ContainerState {
     // Last known full cgroup path for this container
     cgroup_path: String,
     state: ContainerSyncState,
+    // PIDs the last reconcile could not assign to the group
+    missing_pids: usize,
 }
 
 #[derive(Default)]
 struct InnerState {
     pods: HashMap<String, PodState>,             // keyed by pod UID
     containers: HashMap<String, ContainerState>, // keyed by container ID
+    // Pods with monitoring disabled by annotation, keyed by pod UID, with the
+    // (container ID, cgroup path) of their containers for when it is re-enabled
+    unmonitored: HashMap<String, Vec<(String, String)>>,
 }
 
 /// Resctrl NRI plugin. Generic over `FsProvider` for testability.
+// Fields are shared (see `share`) so blocking work can run on
+// `spawn_blocking` tasks
 pub struct ResctrlPlugin<P: FsProvider = RealFs> {
+    cfg: Arc<ResctrlPluginConfig>,
     #[allow(dead_code)]
-    cfg: ResctrlPluginConfig,
-    #[allow(dead_code)]
-    resctrl: Resctrl<P>,
-    state: Mutex<InnerState>,
-    tx: mpsc::Sender<PodResctrlEvent>,
-    dropped_events: Arc<AtomicUsize>,
+    resctrl: Arc<Resctrl<P>>,
+    state: Arc<Mutex<InnerState>>,
+    // Serializes group creation retries with pod removal, so a group created
+    // by a retry cannot outlive its pod. Taken before `state`, never while
+    // holding it
+    group_lock: Arc<Mutex<()>>,
+    events: EventSender,
     pid_source: Arc<dyn CgroupPidSource>,
+    // Set by `configure` when the runtime is not allowed by the config, or
+    // resctrl is unavailable; the plugin then does no work
+    disabled: Arc<AtomicBool>,
+    // Coalesces warnings repeated while resctrl is unavailable
+    warnings: Arc<WarnLimiter>,
 }
 
 impl ResctrlPlugin<RealFs> {
@@ -134,13 +342,16 @@ impl ResctrlPlugin<RealFs> {
             group_prefix: cfg.group_prefix.clone(),
             ..Default::default()
         };
+        let events = EventSender::new(tx, cfg.send_policy);
         Self {
-            cfg,
-            resctrl: Resctrl::new(rc_cfg),
-            state: Mutex::new(InnerState::default()),
-            tx,
-            dropped_events: Arc::new(AtomicUsize::new(0)),
+            cfg: Arc::new(cfg),
+            resctrl: Arc::new(Resctrl::new(rc_cfg)),
+            state: Arc::new(Mutex::new(InnerState::default())),
+            group_lock: Arc::new(Mutex::new(())),
+            events,
             pid_source: Arc::new(RealCgroupPidSource::new()),
+            disabled: Arc::new(AtomicBool::new(false)),
+            warnings: Arc::new(WarnLimiter::new(WARN_COALESCE_WINDOW)),
         }
     }
 }
@@ -157,6 +368,15 @@ pub enum PluginError {
     Resctrl(#[from] resctrl::Error),
 }
 
+// Why probing resctrl failed, as seen by `configure` and `synchronize` alike
+enum ProbeFailure {
+    // No kernel support, or not mounted while we may not mount it: every group
+    // creation would fail until the plugin is restarted
+    Unusable(resctrl::Error),
+    // Anything else, e.g. a busy mount point; retried on the next synchronize
+    Transient(resctrl::Error),
+}
+
 impl<P: FsProvider> ResctrlPlugin<P> {
     /// Create a new plugin with a custom resctrl handle (DI for tests).
     /// The caller provides the event sender channel.
@@ -165,13 +385,16 @@ impl<P: FsProvider> ResctrlPlugin<P> {
         resctrl: Resctrl<P>,
         tx: mpsc::Sender<PodResctrlEvent>,
     ) -> Self {
+        let events = EventSender::new(tx, cfg.send_policy);
         Self {
-            cfg,
-            resctrl,
-            state: Mutex::new(InnerState::default()),
-            tx,
-            dropped_events: Arc::new(AtomicUsize::new(0)),
+            cfg: Arc::new(cfg),
+            resctrl: Arc::new(resctrl),
+            state: Arc::new(Mutex::new(InnerState::default())),
+            group_lock: Arc::new(Mutex::new(())),
+            events,
             pid_source: Arc::new(RealCgroupPidSource::new()),
+            disabled: Arc::new(AtomicBool::new(false)),
+            warnings: Arc::new(WarnLimiter::new(WARN_COALESCE_WINDOW)),
         }
     }
 
@@ -181,55 +404,232 @@ impl<P: FsProvider> ResctrlPlugin<P> {
         tx: mpsc::Sender<PodResctrlEvent>,
         pid_source: Arc<dyn CgroupPidSource>,
     ) -> Self {
+        let events = EventSender::new(tx, cfg.send_policy);
         Self {
-            cfg,
-            resctrl,
-            state: Mutex::new(InnerState::default()),
-            tx,
-            dropped_events: Arc::new(AtomicUsize::new(0)),
+            cfg: Arc::new(cfg),
+            resctrl: Arc::new(resctrl),
+            state: Arc::new(Mutex::new(InnerState::default())),
+            group_lock: Arc::new(Mutex::new(())),
+            events,
             pid_source,
+            disabled: Arc::new(AtomicBool::new(false)),
+            warnings: Arc::new(WarnLimiter::new(WARN_COALESCE_WINDOW)),
         }
     }
 
     /// Number of events dropped due to a full channel.
     pub fn dropped_events(&self) -> usize {
-        self.dropped_events.load(Ordering::Relaxed)
+        self.events.dropped()
     }
 
-    /// Emit an event to the collector, drop if channel is full.
-    fn emit_event(&self, ev: PodResctrlEvent) {
-        if let Err(e) = self.tx.try_send(ev) {
-            self.dropped_events.fetch_add(1, Ordering::Relaxed);
-            warn!("resctrl-plugin: failed to send event: {}", e);
+    /// Resctrl group path currently holding the given container's tasks.
+    ///
+    /// Returns `None` for unknown containers, containers that are not fully
+    /// reconciled, and pods whose group could not be created.
+    pub fn group_for_container(&self, container_id: &str) -> Option<String> {
+        let st = self.state.lock().unwrap();
+        let cs = st.containers.get(container_id)?;
+        if cs.state != ContainerSyncState::Reconciled {
+            return None;
+        }
+        match &st.pods.get(&cs.pod_uid)?.group_state {
+            ResctrlGroupState::Exists(path) => Some(path.clone()),
+            ResctrlGroupState::Failed | ResctrlGroupState::Observed => None,
         }
     }
 
-    /// Emit pod state update event
-    fn emit_pod_add_or_update(&self, pod_uid: &str, ps: &PodState) {
-        let ev = PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+    /// Snapshot the tracked pods and containers and the groups on disk.
+    ///
+    /// The state lock is held while listing the groups, so the listing is
+    /// consistent with the pods in the snapshot.
+    pub fn debug_snapshot(&self) -> DebugSnapshot {
+        let st = self.state.lock().unwrap();
+
+        let mut pods: Vec<PodSnapshot> = st
+            .pods
+            .iter()
+            .map(|(uid, ps)| PodSnapshot {
+                pod_uid: uid.clone(),
+                group_state: ps.group_state.clone(),
+                total_containers: ps.total_containers,
+                reconciled_containers: ps.reconciled_containers,
+                unreconciled_pids: ps.unreconciled_pids,
+                schemata: ps.schemata.clone(),
+                class: ps.class.clone(),
+            })
+            .collect();
+        pods.sort_by(|a, b| a.pod_uid.cmp(&b.pod_uid));
+
+        let mut containers: Vec<ContainerSnapshot> = st
+            .containers
+            .iter()
+            .map(|(id, cs)| ContainerSnapshot {
+                container_id: id.clone(),
+                pod_uid: cs.pod_uid.clone(),
+                cgroup_path: cs.cgroup_path.clone(),
+                state: cs.state,
+                missing_pids: cs.missing_pids,
+            })
+            .collect();
+        containers.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+
+        let mut unmonitored_pods: Vec<String> = st.unmonitored.keys().cloned().collect();
+        unmonitored_pods.sort();
+
+        let (groups, groups_error) = match self.resctrl.list_groups() {
+            Ok(groups) => (groups, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        DebugSnapshot {
+            pods,
+            containers,
+            unmonitored_pods,
+            groups,
+            groups_error,
+        }
+    }
+
+    /// `AddOrUpdate` events for every tracked pod, sorted by UID, for a consumer
+    /// that missed earlier events (e.g. one attached after `synchronize`) to
+    /// prime its view.
+    ///
+    /// Built under the state lock, so the events describe one consistent state.
+    /// Events emitted after the snapshot still arrive on the channel.
+    pub fn snapshot_events(&self) -> Vec<PodResctrlEvent> {
+        let st = self.state.lock().unwrap();
+        let mut uids: Vec<&String> = st.pods.keys().collect();
+        uids.sort();
+        uids.into_iter()
+            .map(|uid| Self::add_or_update_event(uid, &st.pods[uid]))
+            .collect()
+    }
+
+    /// Emit an event to the collector; a full channel is handled per `send_policy`.
+    fn emit_event(&self, ev: PodResctrlEvent) {
+        self.events.send(ev);
+    }
+
+    /// Ensure resctrl is mounted according to config, classifying a failure
+    fn probe_resctrl(&self) -> Result<(), ProbeFailure> {
+        self.resctrl
+            .ensure_mounted(self.cfg.auto_mount)
+            .map_err(|e| match e {
+                e @ (resctrl::Error::Unsupported { .. } | resctrl::Error::NotMounted { .. }) => {
+                    ProbeFailure::Unusable(e)
+                }
+                e => ProbeFailure::Transient(e),
+            })
+    }
+
+    /// Stop doing any work, after a probe found resctrl unusable
+    fn disable_unusable(&self, e: &resctrl::Error) {
+        error!(
+            "resctrl-plugin: resctrl is unavailable ({}), disabling the plugin",
+            e
+        );
+        self.disabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Pod state update event
+    fn add_or_update_event(pod_uid: &str, ps: &PodState) -> PodResctrlEvent {
+        PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
             pod_uid: pod_uid.to_string(),
             group_state: ps.group_state.clone(),
             total_containers: ps.total_containers,
             reconciled_containers: ps.reconciled_containers,
-        });
-        self.emit_event(ev);
+            unreconciled_pids: ps.unreconciled_pids,
+        })
+    }
+
+    /// Emit pod state update event
+    fn emit_pod_add_or_update(&self, pod_uid: &str, ps: &PodState) {
+        self.emit_event(Self::add_or_update_event(pod_uid, ps));
+    }
+
+    /// Whether the pod's monitoring annotation (if configured) leaves it monitored
+    fn monitoring_enabled(&self, pod: &nri::api::PodSandbox) -> bool {
+        let Some(value) = self
+            .cfg
+            .monitoring_annotation
+            .as_ref()
+            .and_then(|key| pod.annotations.get(key))
+        else {
+            return true;
+        };
+        !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "false" | "off" | "disabled" | "0"
+        )
+    }
+
+    /// Class of the first `class_rules` entry matching the pod's annotations
+    fn pod_class(&self, pod: &nri::api::PodSandbox) -> Option<String> {
+        self.cfg
+            .class_rules
+            .iter()
+            .find(|rule| {
+                !rule.class.is_empty()
+                    && pod
+                        .annotations
+                        .get(&rule.annotation)
+                        .is_some_and(|v| v.trim() == rule.value)
+            })
+            .map(|rule| rule.class.clone())
     }
 
-    // Create or fetch pod state and ensure group exists
+    // Create or fetch pod state and ensure group exists. Idempotent per UID: the
+    // state lock is held from the lookup to the insert, so `synchronize` and a
+    // RUN_POD_SANDBOX for the same pod create its group once, in either order
     fn handle_new_pod(&self, pod: &nri::api::PodSandbox) {
         let pod_uid = &pod.uid;
         let mut st = self.state.lock().unwrap();
 
+        if !st.pods.contains_key(pod_uid) && !self.monitoring_enabled(pod) {
+            debug!("resctrl-plugin: monitoring disabled for pod {}", pod_uid);
+            st.unmonitored.entry(pod_uid.clone()).or_default();
+            return;
+        }
+
         // If pod doesn't exist yet, create it with appropriate group state
         if !st.pods.contains_key(pod_uid) {
-            let group_state = match self.resctrl.create_group(pod_uid) {
-                Ok(p) => ResctrlGroupState::Exists(p),
-                Err(e) => {
-                    warn!(
-                        "resctrl-plugin: failed to create group for pod {}: {}",
-                        pod_uid, e
-                    );
-                    ResctrlGroupState::Failed
+            let schemata = self
+                .cfg
+                .schemata_annotation
+                .as_ref()
+                .and_then(|key| pod.annotations.get(key))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+            let class = self.pod_class(pod);
+            // Join the group of another pod of the class if it has one
+            let class_group = class.as_ref().and_then(|class| {
+                st.pods.values().find_map(|ps| match &ps.group_state {
+                    ResctrlGroupState::Exists(path) if ps.class.as_ref() == Some(class) => {
+                        Some(path.clone())
+                    }
+                    _ => None,
+                })
+            });
+            let group_state = if self.cfg.observe_only {
+                ResctrlGroupState::Observed
+            } else {
+                let created = match class_group {
+                    Some(path) => Ok(path),
+                    None => self.create_pod_group(pod_uid, class.as_deref(), schemata.as_deref()),
+                };
+                match created {
+                    Ok(p) => ResctrlGroupState::Exists(p),
+                    Err(e) => {
+                        self.warnings.warn(
+                            "create_group",
+                            &e,
+                            format_args!(
+                                "resctrl-plugin: failed to create group for pod {}: {}",
+                                pod_uid, e
+                            ),
+                        );
+                        ResctrlGroupState::Failed
+                    }
                 }
             };
 
@@ -239,6 +639,9 @@ impl<P: FsProvider> ResctrlPlugin<P> {
                     group_state,
                     total_containers: 0,
                     reconciled_containers: 0,
+                    unreconciled_pids: 0,
+                    schemata,
+                    class,
                 },
             );
         }
@@ -248,6 +651,107 @@ impl<P: FsProvider> ResctrlPlugin<P> {
         drop(st);
     }
 
+    /// Build the `CreateContainer` adjustment for a container of `pod_uid`, or
+    /// `None` when the pod has no resctrl group.
+    ///
+    /// Runtimes can only place a container in a control group (`rdt_class`
+    /// names a directory under the resctrl root); monitoring groups under
+    /// `mon_groups` get the annotation only.
+    fn container_adjustment(&self, pod_uid: &str) -> Option<ContainerAdjustment> {
+        let group_path = {
+            let st = self.state.lock().unwrap();
+            match &st.pods.get(pod_uid)?.group_state {
+                ResctrlGroupState::Exists(path) => path.clone(),
+                ResctrlGroupState::Failed | ResctrlGroupState::Observed => return None,
+            }
+        };
+
+        let mut adjust = ContainerAdjustment::default();
+        adjust
+            .annotations
+            .insert(GROUP_ANNOTATION.to_string(), group_path.clone());
+
+        let path = std::path::Path::new(&group_path);
+        let in_mon_groups = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .is_some_and(|n| n == "mon_groups");
+        if let (false, Some(class)) = (in_mon_groups, path.file_name()) {
+            let resources = LinuxResources {
+                rdt_class: protobuf::MessageField::some(OptionalString {
+                    value: class.to_string_lossy().into_owned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            adjust.linux = protobuf::MessageField::some(LinuxContainerAdjustment {
+                resources: protobuf::MessageField::some(resources),
+                ..Default::default()
+            });
+        }
+        Some(adjust)
+    }
+
+    /// Create the resctrl group for a pod, or the shared group of its `class`: a
+    /// monitoring group, or a control group carrying `schemata` when the pod
+    /// requested an allocation. Creating an existing group returns its path.
+    ///
+    /// Pods whose allocation cannot be had are still monitored: when the control
+    /// group cannot be created (`Error::Unsupported` on hosts without cache
+    /// allocation, `Error::Capacity` once CLOS IDs run out), or the allocation
+    /// cannot be applied to it, this is logged and a monitoring group is created
+    /// instead. A control group left without its allocation is deleted so it
+    /// does not hold a CLOS ID.
+    fn create_pod_group(
+        &self,
+        pod_uid: &str,
+        class: Option<&str>,
+        schemata: Option<&str>,
+    ) -> resctrl::Result<String> {
+        let group_id = match class {
+            Some(class) => format!("class_{}", class),
+            None => pod_uid.to_string(),
+        };
+        let Some(schemata) = schemata else {
+            return self.resctrl.create_group(&group_id);
+        };
+        let path = match self.resctrl.create_control_group(&group_id) {
+            Ok(path) => path,
+            Err(e @ (resctrl::Error::Unsupported { .. } | resctrl::Error::Capacity { .. })) => {
+                warn!(
+                    "resctrl-plugin: allocation unavailable for pod {}; monitoring only: {}",
+                    pod_uid, e
+                );
+                return self.resctrl.create_group(&group_id);
+            }
+            Err(e) => return Err(e),
+        };
+        match self.resctrl.set_schemata(&path, schemata) {
+            Ok(()) => {
+                info!(
+                    "resctrl-plugin: applied schemata '{}' to pod {}",
+                    schemata, pod_uid
+                );
+                return Ok(path);
+            }
+            Err(e @ resctrl::Error::Unsupported { .. }) => warn!(
+                "resctrl-plugin: allocation unavailable for pod {}; monitoring only: {}",
+                pod_uid, e
+            ),
+            Err(e) => warn!(
+                "resctrl-plugin: failed to apply schemata for pod {}; monitoring only: {}",
+                pod_uid, e
+            ),
+        }
+        if let Err(e) = self.resctrl.delete_group(&path) {
+            warn!(
+                "resctrl-plugin: failed to delete control group {} without allocation: {}",
+                path, e
+            );
+        }
+        self.resctrl.create_group(&group_id)
+    }
+
     fn handle_new_container(&self, pod: &nri::api::PodSandbox, container: &nri::api::Container) {
         let pod_uid = pod.uid.clone();
         let container_id = container.id.clone();
@@ -265,6 +769,15 @@ impl<P: FsProvider> ResctrlPlugin<P> {
             return;
         }
 
+        if let Some(containers) = st.unmonitored.get_mut(&pod_uid) {
+            // Monitoring disabled: remember the container for re-enabling
+            if !containers.iter().any(|(id, _)| *id == container_id) {
+                let full = nri::compute_full_cgroup_path(container, Some(pod));
+                containers.push((container_id, full));
+            }
+            return;
+        }
+
         if !st.pods.contains_key(&pod_uid) {
             // No pod yet: mark container as NoPod and return
             error!(
@@ -278,6 +791,7 @@ impl<P: FsProvider> ResctrlPlugin<P> {
                     pod_uid: pod_uid.clone(),
                     cgroup_path: full,
                     state: ContainerSyncState::NoPod,
+                    ..Default::default()
                 },
             );
             return;
@@ -298,6 +812,7 @@ impl<P: FsProvider> ResctrlPlugin<P> {
                     pod_uid: pod_uid.clone(),
                     cgroup_path: full,
                     state: ContainerSyncState::Partial,
+                    ..Default::default()
                 },
             );
             let ps = st
@@ -315,65 +830,553 @@ impl<P: FsProvider> ResctrlPlugin<P> {
         // The path is non-empty
         let group_path = gp.unwrap();
 
-        // Create a closure that reads PIDs fresh each time
-        let pid_source = self.pid_source.clone();
-        let full_path = nri::compute_full_cgroup_path(container, Some(pod));
-        let full_for_closure = full_path.clone();
-        let pid_resolver = move || -> Result<Vec<i32>, resctrl::Error> {
-            pid_source.pids_for_path(&full_for_closure)
-        };
-
         // Reconcile this container's PIDs into the pod group
-        let passes = self.cfg.max_reconcile_passes;
-        let res = self
-            .resctrl
-            .reconcile_group(&group_path, pid_resolver, passes);
+        let full_path = nri::compute_full_cgroup_path(container, Some(pod));
+        self.add_container(&pod_uid, container_id, &group_path, full_path);
+    }
 
-        let new_state = match res {
-            Ok(ar) if ar.missing == 0 => ContainerSyncState::Reconciled,
-            _ => ContainerSyncState::Partial,
+    /// Handle START_CONTAINER, when the container's first process exists.
+    ///
+    /// A container first seen before it started (e.g. listed by `synchronize`
+    /// while only created) had no PIDs to assign and was left `Partial`; it is
+    /// reconciled again here rather than waiting for the next retry pass.
+    fn handle_container_start(&self, pod: &nri::api::PodSandbox, container: &nri::api::Container) {
+        let known = {
+            let st = self.state.lock().unwrap();
+            st.containers.get(&container.id).map(|c| c.state)
         };
+        match known {
+            None => self.handle_new_container(pod, container),
+            Some(ContainerSyncState::Partial) => {
+                match self.retry_container_reconcile(&container.id) {
+                    Ok(state) => debug!(
+                        "resctrl-plugin: container {} reconciled on start: {:?}",
+                        container.id, state
+                    ),
+                    Err(e) => warn!(
+                        "resctrl-plugin: failed to reconcile started container {}: {}",
+                        container.id, e
+                    ),
+                }
+            }
+            Some(_) => {
+                debug!(
+                    "resctrl-plugin: container {} already tracked; ignoring start",
+                    container.id
+                );
+            }
+        }
+    }
+
+    /// Reconcile a new container into its pod's existing group, then record it
+    /// and update the pod's counts. Must be called without holding the state lock.
+    fn add_container(
+        &self,
+        pod_uid: &str,
+        container_id: String,
+        group_path: &str,
+        full_path: String,
+    ) {
+        let (new_state, missing_pids) = self
+            .reconcile_container(&container_id, group_path, &full_path)
+            .unwrap_or((ContainerSyncState::Partial, 0));
 
         // Update container state and pod counts, then emit update
         let mut st = self.state.lock().unwrap();
         st.containers.insert(
             container_id,
             ContainerState {
-                pod_uid: pod_uid.clone(),
+                pod_uid: pod_uid.to_string(),
                 cgroup_path: full_path,
                 state: new_state,
+                missing_pids,
             },
         );
-        if let Some(ps) = st.pods.get_mut(&pod_uid) {
+        if let Some(ps) = st.pods.get_mut(pod_uid) {
             // Incremental count updates per state transition
             ps.total_containers += 1;
             if new_state == ContainerSyncState::Reconciled {
                 ps.reconciled_containers += 1
             }
-            self.emit_pod_add_or_update(&pod_uid, ps);
+            ps.unreconciled_pids += missing_pids;
+            self.emit_pod_add_or_update(pod_uid, ps);
+        }
+    }
+
+    /// Follow a container whose cgroup path changed (e.g. it was moved to
+    /// another slice by a resource update): store the new path and reconcile
+    /// its PIDs from there. Emits AddOrUpdate only if the reconciled count changes.
+    fn handle_container_update(&self, pod: &nri::api::PodSandbox, container: &nri::api::Container) {
+        let full_path = nri::compute_full_cgroup_path(container, Some(pod));
+        let group_path = {
+            let mut st = self.state.lock().unwrap();
+            if let Some(containers) = st.unmonitored.get_mut(&pod.uid) {
+                for (id, path) in containers.iter_mut() {
+                    if *id == container.id {
+                        *path = full_path.clone();
+                    }
+                }
+                return;
+            }
+            let st_mut = st.deref_mut();
+            let Some(cs) = st_mut.containers.get_mut(&container.id) else {
+                return;
+            };
+            if cs.cgroup_path == full_path {
+                return;
+            }
+            debug!(
+                "resctrl-plugin: container {} moved from {} to {}",
+                container.id, cs.cgroup_path, full_path
+            );
+            cs.cgroup_path = full_path.clone();
+            if cs.state == ContainerSyncState::NoPod {
+                return;
+            }
+            match st_mut.pods.get(&cs.pod_uid).map(|p| &p.group_state) {
+                Some(ResctrlGroupState::Exists(path)) => path.clone(),
+                // Reconciled once the group exists
+                _ => return,
+            }
+        };
+
+        // Reconcile outside the lock
+        let (new_state, missing_pids) = self
+            .reconcile_container(&container.id, &group_path, &full_path)
+            .unwrap_or((ContainerSyncState::Partial, 0));
+
+        let mut st = self.state.lock().unwrap();
+        let st_mut = st.deref_mut();
+        let Some(cs) = st_mut.containers.get_mut(&container.id) else {
+            return;
+        };
+        if cs.cgroup_path != full_path {
+            // Superseded by a newer update
+            return;
+        }
+        let old_state = std::mem::replace(&mut cs.state, new_state);
+        let old_missing = std::mem::replace(&mut cs.missing_pids, missing_pids);
+        let was_reconciled = old_state == ContainerSyncState::Reconciled;
+        let is_reconciled = new_state == ContainerSyncState::Reconciled;
+        if was_reconciled == is_reconciled && old_missing == missing_pids {
+            return;
+        }
+        if let Some(ps) = st_mut.pods.get_mut(&cs.pod_uid) {
+            if is_reconciled && !was_reconciled {
+                ps.reconciled_containers += 1;
+            } else if was_reconciled && !is_reconciled {
+                ps.reconciled_containers = ps.reconciled_containers.saturating_sub(1);
+            }
+            ps.unreconciled_pids = ps.unreconciled_pids.saturating_sub(old_missing) + missing_pids;
+            // Emit under lock to preserve ordering
+            self.emit_pod_add_or_update(&pod.uid, ps);
+        }
+    }
+
+    /// Stop tracking a pod: forget it and its containers, emit `Removed` and
+    /// delete its group, unless it is a class group other pods still use. With
+    /// `keep_unmonitored` the pod's containers are kept aside so monitoring can
+    /// be re-enabled later.
+    fn remove_pod(&self, pod_uid: &str, keep_unmonitored: bool) {
+        // Wait for any group creation retry for this pod to settle, and keep new
+        // ones out until the group is deleted
+        let _group_guard = self.group_lock.lock().unwrap();
+        let mut st = self.state.lock().unwrap();
+
+        // Get group path and class before removing pod state
+        let (group_path, class) = match st.pods.get(pod_uid) {
+            Some(pod_state) => (
+                match &pod_state.group_state {
+                    ResctrlGroupState::Exists(path) => Some(path.clone()),
+                    ResctrlGroupState::Failed | ResctrlGroupState::Observed => None,
+                },
+                pod_state.class.clone(),
+            ),
+            None => (None, None),
+        };
+
+        // Remove all containers for this pod
+        let mut containers = Vec::new();
+        st.containers.retain(|id, c| {
+            if c.pod_uid != pod_uid {
+                return true;
+            }
+            containers.push((id.clone(), std::mem::take(&mut c.cgroup_path)));
+            false
+        });
+        // Remove pod state
+        st.pods.remove(pod_uid);
+        if keep_unmonitored {
+            st.unmonitored.insert(pod_uid.to_string(), containers);
+        } else {
+            st.unmonitored.remove(pod_uid);
+        }
+        // Emit removal event under lock to preserve ordering
+        self.emit_event(PodResctrlEvent::Removed(PodResctrlRemoved {
+            pod_uid: pod_uid.to_string(),
+        }));
+
+        if let Some(class) = class {
+            // A class group stays while any pod of the class remains. Delete it
+            // under the lock, so a new pod of the class cannot join it meanwhile
+            let in_use = st.pods.values().any(|ps| ps.class.as_ref() == Some(&class));
+            if let (false, Some(group_path)) = (in_use, group_path) {
+                if let Err(e) = self.resctrl.delete_group(&group_path) {
+                    warn!(
+                        "resctrl-plugin: failed to delete class group {}: {}",
+                        group_path, e
+                    );
+                }
+            }
+            return;
+        }
+        drop(st);
+
+        // Delete resctrl group if it exists
+        if let Some(group_path) = group_path {
+            if let Err(e) = self.resctrl.delete_group(&group_path) {
+                warn!(
+                    "resctrl-plugin: failed to delete group {}: {}",
+                    group_path, e
+                );
+            }
+        }
+    }
+
+    /// Apply a change of the pod's monitoring annotation: start tracking a pod
+    /// (with its known containers) when monitoring was turned on, stop
+    /// tracking it when it was turned off.
+    fn handle_pod_update(&self, pod: &nri::api::PodSandbox) {
+        let enabled = self.monitoring_enabled(pod);
+        let (tracked, unmonitored) = {
+            let st = self.state.lock().unwrap();
+            (
+                st.pods.contains_key(&pod.uid),
+                st.unmonitored.contains_key(&pod.uid),
+            )
+        };
+
+        if tracked && !enabled {
+            info!("resctrl-plugin: monitoring disabled for pod {}", pod.uid);
+            self.remove_pod(&pod.uid, true);
+        } else if unmonitored && enabled {
+            info!("resctrl-plugin: monitoring enabled for pod {}", pod.uid);
+            let containers = {
+                let mut st = self.state.lock().unwrap();
+                st.unmonitored.remove(&pod.uid).unwrap_or_default()
+            };
+            self.handle_new_pod(pod);
+
+            let group_path = {
+                let st = self.state.lock().unwrap();
+                match st.pods.get(&pod.uid).map(|p| &p.group_state) {
+                    Some(ResctrlGroupState::Exists(path)) => Some(path.clone()),
+                    Some(ResctrlGroupState::Failed | ResctrlGroupState::Observed) => None,
+                    None => return,
+                }
+            };
+            match group_path {
+                Some(group_path) => {
+                    for (id, cgroup_path) in containers {
+                        self.add_container(&pod.uid, id, &group_path, cgroup_path);
+                    }
+                }
+                None => {
+                    // No group yet: containers stay Partial until it is retried
+                    let mut st = self.state.lock().unwrap();
+                    let added = containers.len();
+                    for (id, cgroup_path) in containers {
+                        st.containers.insert(
+                            id,
+                            ContainerState {
+                                pod_uid: pod.uid.clone(),
+                                cgroup_path,
+                                state: ContainerSyncState::Partial,
+                                ..Default::default()
+                            },
+                        );
+                    }
+                    if let Some(ps) = st.pods.get_mut(&pod.uid) {
+                        ps.total_containers += added;
+                        self.emit_pod_add_or_update(&pod.uid, ps);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recreate the groups of pods added before the startup cleanup removed them.
+    ///
+    /// Pods are normally first seen by `synchronize`, but a RUN_POD_SANDBOX for
+    /// a new pod can be delivered before it; its group was then created ahead of
+    /// the cleanup. Creation adopts groups that exist, so this is a no-op for
+    /// groups the cleanup left alone.
+    fn restore_groups_after_cleanup(&self, st: &mut InnerState) {
+        let uids: Vec<String> = st
+            .pods
+            .iter()
+            .filter(|(_, ps)| matches!(ps.group_state, ResctrlGroupState::Exists(_)))
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        for uid in uids {
+            let ps = st.pods.get_mut(&uid).unwrap();
+            match self.create_pod_group(&uid, ps.class.as_deref(), ps.schemata.as_deref()) {
+                Ok(path) => {
+                    debug!(
+                        "resctrl-plugin: restored group {} of pod {} after startup cleanup",
+                        path, uid
+                    );
+                    ps.group_state = ResctrlGroupState::Exists(path);
+                }
+                Err(e) => {
+                    self.warnings.warn(
+                        "restore_group",
+                        &e,
+                        format_args!(
+                            "resctrl-plugin: failed to restore group of pod {} after startup cleanup: {}",
+                            uid, e
+                        ),
+                    );
+                    ps.group_state = ResctrlGroupState::Failed;
+                    self.emit_pod_add_or_update(&uid, ps);
+                }
+            }
+        }
+    }
+
+    /// Delete the groups of `existing` that no tracked pod uses (with
+    /// `cleanup_dry_run`, only log them).
+    ///
+    /// Deletes under the state lock, so a pod created meanwhile cannot adopt a
+    /// group that is being removed.
+    fn remove_orphan_groups(&self, existing: Vec<String>) {
+        let st = self.state.lock().unwrap();
+        let in_use: HashSet<&str> = st
+            .pods
+            .values()
+            .filter_map(|ps| match &ps.group_state {
+                ResctrlGroupState::Exists(path) => Some(path.as_str()),
+                ResctrlGroupState::Failed | ResctrlGroupState::Observed => None,
+            })
+            .collect();
+        let (adopted, orphans): (Vec<String>, Vec<String>) = existing
+            .into_iter()
+            .partition(|group| in_use.contains(group.as_str()));
+
+        if self.cfg.cleanup_dry_run {
+            info!(
+                "resctrl-plugin: adopted {} existing groups; would remove {} orphaned: {:?}",
+                adopted.len(),
+                orphans.len(),
+                orphans
+            );
+            return;
+        }
+        let mut failures = 0;
+        for group in &orphans {
+            if let Err(e) = self.resctrl.delete_group(group) {
+                warn!(
+                    "resctrl-plugin: failed to delete orphaned group {}: {}",
+                    group, e
+                );
+                failures += 1;
+            }
+        }
+        info!(
+            "resctrl-plugin: adopted {} existing groups, removed {} orphaned (failures={})",
+            adopted.len(),
+            orphans.len() - failures,
+            failures
+        );
+    }
+
+    /// A handle sharing this plugin's state, for work moved to blocking tasks
+    fn share(&self) -> Self {
+        Self {
+            cfg: self.cfg.clone(),
+            resctrl: self.resctrl.clone(),
+            state: self.state.clone(),
+            group_lock: self.group_lock.clone(),
+            events: self.events.clone(),
+            pid_source: self.pid_source.clone(),
+            disabled: self.disabled.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Reconcile one pod's containers of a synchronize request, in request
+    /// order, reporting progress through `done` out of `total` containers
+    fn synchronize_pod(
+        &self,
+        pod: &nri::api::PodSandbox,
+        containers: &[nri::api::Container],
+        done: &AtomicUsize,
+        total: usize,
+    ) {
+        let refs: Vec<&nri::api::Container> = containers.iter().collect();
+        let batched = self.cfg.batch_pod_pid_reads
+            && refs.len() > 1
+            && self.handle_new_containers_batched(pod, &refs);
+        if !batched {
+            for c in containers {
+                self.handle_new_container(pod, c);
+            }
+        }
+        let before = done.fetch_add(containers.len(), Ordering::Relaxed);
+        let after = before + containers.len();
+        if after / SYNC_PROGRESS_INTERVAL > before / SYNC_PROGRESS_INTERVAL {
+            info!(
+                "resctrl-plugin: synchronize: handled {}/{} containers",
+                after, total
+            );
+        }
+    }
+
+    /// Count the outcome of synchronizing `pods` and `containers` from the
+    /// tracked state.
+    fn sync_summary(
+        &self,
+        pods: &[nri::api::PodSandbox],
+        containers: &[nri::api::Container],
+    ) -> SyncSummary {
+        let st = self.state.lock().unwrap();
+        let mut summary = SyncSummary::default();
+        for pod in pods {
+            match st.pods.get(&pod.uid).map(|ps| &ps.group_state) {
+                Some(ResctrlGroupState::Exists(_)) => summary.groups_ready += 1,
+                Some(ResctrlGroupState::Failed) => summary.groups_failed += 1,
+                Some(ResctrlGroupState::Observed) => {}
+                None if st.unmonitored.contains_key(&pod.uid) => summary.unmonitored_pods += 1,
+                // Removed by a concurrent REMOVE_POD_SANDBOX
+                None => {}
+            }
+        }
+        for container in containers {
+            match st.containers.get(&container.id).map(|cs| cs.state) {
+                Some(ContainerSyncState::Reconciled) => summary.containers_reconciled += 1,
+                Some(ContainerSyncState::Partial) => summary.containers_partial += 1,
+                Some(ContainerSyncState::NoPod | ContainerSyncState::PermissionDenied) => {
+                    summary.containers_other += 1
+                }
+                // Of an unmonitored pod, of a pod missing from the request, or removed
+                None => {}
+            }
+        }
+        summary
+    }
+
+    /// Reconcile several new containers of a pod together, with a single read of
+    /// their cgroups per pass.
+    ///
+    /// Only the PIDs under the containers' own cgroups are assigned, as when
+    /// reconciling per container; the pod's sandbox is left out. Applies only when
+    /// the pod's group exists and none of the containers is known yet; returns
+    /// false without changing anything otherwise, so the caller can reconcile per
+    /// container.
+    /// All containers take the state of the combined reconcile; PIDs it could not
+    /// assign are counted against the container they belong to.
+    fn handle_new_containers_batched(
+        &self,
+        pod: &nri::api::PodSandbox,
+        containers: &[&nri::api::Container],
+    ) -> bool {
+        let full_paths: Vec<String> = containers
+            .iter()
+            .map(|c| nri::compute_full_cgroup_path(c, Some(pod)))
+            .collect();
+
+        let group_path = {
+            let st = self.state.lock().unwrap();
+            if containers.iter().any(|c| st.containers.contains_key(&c.id)) {
+                return false;
+            }
+            match st.pods.get(&pod.uid).map(|p| &p.group_state) {
+                Some(ResctrlGroupState::Exists(path)) => path.clone(),
+                _ => return false,
+            }
+        };
+
+        let cgroup_paths: Vec<&str> = full_paths.iter().map(String::as_str).collect();
+        let (new_state, missing_pids) = self
+            .reconcile_cgroups(&format!("pod {}", pod.uid), &group_path, &cgroup_paths)
+            .unwrap_or((ContainerSyncState::Partial, 0));
+
+        let missing = self.missing_pids_by_container(&group_path, &full_paths, missing_pids);
+
+        let mut st = self.state.lock().unwrap();
+        for ((c, full_path), &missing_pids) in containers.iter().zip(full_paths).zip(&missing) {
+            st.containers.insert(
+                c.id.clone(),
+                ContainerState {
+                    pod_uid: pod.uid.clone(),
+                    cgroup_path: full_path,
+                    state: new_state,
+                    missing_pids,
+                },
+            );
+        }
+        if let Some(ps) = st.pods.get_mut(&pod.uid) {
+            ps.total_containers += containers.len();
+            if new_state == ContainerSyncState::Reconciled {
+                ps.reconciled_containers += containers.len();
+            }
+            ps.unreconciled_pids += missing.iter().sum::<usize>();
+            self.emit_pod_add_or_update(&pod.uid, ps);
+        }
+        true
+    }
+
+    /// Split the PIDs a batched reconcile left outside `group_path` by container:
+    /// for each of `full_paths`, count its PIDs not in the group. Reads nothing
+    /// when the reconcile left no PIDs out (`missing` is 0).
+    fn missing_pids_by_container(
+        &self,
+        group_path: &str,
+        full_paths: &[String],
+        missing: usize,
+    ) -> Vec<usize> {
+        if missing == 0 {
+            return vec![0; full_paths.len()];
         }
+        let Ok(tasks) = self.resctrl.list_group_tasks(group_path) else {
+            return vec![0; full_paths.len()];
+        };
+        let tasks: HashSet<i32> = tasks.into_iter().collect();
+        full_paths
+            .iter()
+            .map(|path| {
+                self.pid_source
+                    .pids_for_path_recursive(path, self.cfg.pid_recursion_depth)
+                    .map(|pids| pids.iter().filter(|pid| !tasks.contains(pid)).count())
+                    .unwrap_or(0)
+            })
+            .collect()
     }
 
     /// Try to create a resctrl group for a pod if currently Failed.
-    /// Emits AddOrUpdate only on state transition.
+    /// Emits AddOrUpdate only on state transition. Runs to completion before a
+    /// concurrent removal of the pod, which then deletes the new group.
     pub fn retry_group_creation(&self, pod_uid: &str) -> Result<ResctrlGroupState, PluginError> {
+        let _group_guard = self.group_lock.lock().unwrap();
         // Snapshot decision under lock. If pod missing → PodNotFound.
         // If state is not Failed, return current state immediately to avoid unlock/relock races.
-        {
+        let (schemata, class) = {
             let st = self.state.lock().unwrap();
             match st.pods.get(pod_uid) {
                 Some(pod_state) => match &pod_state.group_state {
-                    ResctrlGroupState::Failed => { /* continue and try create */ }
-                    ResctrlGroupState::Exists(path) => {
-                        return Ok(ResctrlGroupState::Exists(path.clone()))
+                    ResctrlGroupState::Failed => {
+                        (pod_state.schemata.clone(), pod_state.class.clone())
+                    }
+                    state @ (ResctrlGroupState::Exists(_) | ResctrlGroupState::Observed) => {
+                        return Ok(state.clone())
                     }
                 },
                 None => return Err(PluginError::PodNotFound),
             }
-        }
+        };
 
         // Drop lock while performing filesystem operation
-        let res = self.resctrl.create_group(pod_uid);
+        let res = self.create_pod_group(pod_uid, class.as_deref(), schemata.as_deref());
         match res {
             Ok(path) => {
                 let mut st = self.state.lock().unwrap();
@@ -386,10 +1389,13 @@ impl<P: FsProvider> ResctrlPlugin<P> {
                             self.emit_pod_add_or_update(pod_uid, pod_state);
                             Ok(ResctrlGroupState::Exists(path))
                         }
-                        ResctrlGroupState::Exists(p) => Ok(ResctrlGroupState::Exists(p.clone())),
+                        state @ (ResctrlGroupState::Exists(_) | ResctrlGroupState::Observed) => {
+                            Ok(state.clone())
+                        }
                     },
                     None => {
-                        // Pod disappeared concurrently; best-effort cleanup not under lock
+                        // Removal waits for `group_lock`, so the pod cannot vanish here;
+                        // delete the group rather than leak it should that change
                         drop(st);
                         if let Err(e) = self.resctrl.delete_group(&path) {
                             warn!(
@@ -406,20 +1412,24 @@ impl<P: FsProvider> ResctrlPlugin<P> {
     }
 
     /// Retry reconciling a single container if its pod group exists.
-    /// Emits AddOrUpdate only if reconciled count is incremented.
+    /// Emits AddOrUpdate only if the reconciled count is incremented or the
+    /// number of unreconciled PIDs changes.
     pub(crate) fn retry_container_reconcile(
         &self,
         container_id: &str,
     ) -> Result<ContainerSyncState, PluginError> {
         // Snapshot under lock: group path, cgroup path, passes, current state
-        let (group_path, cgroup_path, pod_uid, _current_state, passes) = {
+        let (group_path, cgroup_path, pod_uid) = {
             let st = self.state.lock().unwrap();
             let container_state = st
                 .containers
                 .get(container_id)
                 .ok_or(PluginError::ContainerNotFound)?;
-            if container_state.state == ContainerSyncState::NoPod {
-                return Ok(ContainerSyncState::NoPod);
+            if matches!(
+                container_state.state,
+                ContainerSyncState::NoPod | ContainerSyncState::PermissionDenied
+            ) {
+                return Ok(container_state.state);
             }
             let pod_state = st
                 .pods
@@ -433,25 +1443,12 @@ impl<P: FsProvider> ResctrlPlugin<P> {
                 group_path,
                 container_state.cgroup_path.clone(),
                 container_state.pod_uid.clone(),
-                container_state.state,
-                self.cfg.max_reconcile_passes,
             )
         };
 
         // Perform reconcile outside the lock
-        let pid_source = self.pid_source.clone();
-        let pid_resolver =
-            move || -> resctrl::Result<Vec<i32>> { pid_source.pids_for_path(&cgroup_path) };
-        let new_state = match self
-            .resctrl
-            .reconcile_group(&group_path, pid_resolver, passes)
-        {
-            Ok(res) if res.missing == 0 => ContainerSyncState::Reconciled,
-            Ok(_) => ContainerSyncState::Partial,
-            // Treat empty PID set as a non-fatal partial reconcile
-            Err(resctrl::Error::EmptyPidSet) => ContainerSyncState::Partial,
-            Err(e) => return Err(PluginError::from(e)),
-        };
+        let (new_state, missing_pids) =
+            self.reconcile_container(container_id, &group_path, &cgroup_path)?;
 
         // Re-acquire lock and update counters/state conditionally.
         // Ensure both container and pod are present before applying any change.
@@ -466,6 +1463,11 @@ impl<P: FsProvider> ResctrlPlugin<P> {
             .get_mut(&pod_uid)
             .ok_or(PluginError::PodNotFound)?;
 
+        // Track PIDs still outside the group, whatever the state transition
+        let old_missing = std::mem::replace(&mut container_entry.missing_pids, missing_pids);
+        pod_entry.unreconciled_pids =
+            pod_entry.unreconciled_pids.saturating_sub(old_missing) + missing_pids;
+
         if matches!(&container_entry.state, ContainerSyncState::Partial)
             && new_state == ContainerSyncState::Reconciled
         {
@@ -475,12 +1477,97 @@ impl<P: FsProvider> ResctrlPlugin<P> {
             self.emit_pod_add_or_update(&pod_uid, pod_entry);
             return Ok(ContainerSyncState::Reconciled);
         }
+        if old_missing != missing_pids {
+            self.emit_pod_add_or_update(&pod_uid, pod_entry);
+        }
+        if matches!(&container_entry.state, ContainerSyncState::Partial)
+            && new_state == ContainerSyncState::PermissionDenied
+        {
+            // Terminal; counts are unchanged so no event is needed
+            container_entry.state = ContainerSyncState::PermissionDenied;
+        }
         Ok(container_entry.state)
     }
 
+    /// Reconcile a container's PIDs into its pod group. Must be called without
+    /// holding the state lock.
+    ///
+    /// Returns `PermissionDenied` when the PID source cannot read the container's
+    /// cgroup due to EACCES/EPERM, and treats an empty PID set as `Partial`.
+    fn reconcile_container(
+        &self,
+        container_id: &str,
+        group_path: &str,
+        cgroup_path: &str,
+    ) -> Result<(ContainerSyncState, usize), resctrl::Error> {
+        self.reconcile_cgroups(
+            &format!("container {}", container_id),
+            group_path,
+            &[cgroup_path],
+        )
+    }
+
+    /// Reconcile the PIDs of `cgroup_paths` and their descendants up to
+    /// `pid_recursion_depth` levels into `group_path`. `what` names the reconciled
+    /// object in logs.
+    ///
+    /// Returns the resulting state and the number of PIDs left outside the group.
+    fn reconcile_cgroups(
+        &self,
+        what: &str,
+        group_path: &str,
+        cgroup_paths: &[&str],
+    ) -> Result<(ContainerSyncState, usize), resctrl::Error> {
+        // Track whether a failure came from reading PIDs (vs. writing resctrl tasks)
+        let mut pid_access_denied = false;
+        let pid_resolver = || -> resctrl::Result<Vec<i32>> {
+            let mut pids = Vec::new();
+            for path in cgroup_paths {
+                let res = self
+                    .pid_source
+                    .pids_for_path_recursive(path, self.cfg.pid_recursion_depth);
+                if matches!(res, Err(resctrl::Error::NoPermission { .. })) {
+                    pid_access_denied = true;
+                }
+                pids.extend(res?);
+            }
+            Ok(pids)
+        };
+
+        let deadline = self.cfg.max_reconcile_time.map(|d| Instant::now() + d);
+        let res = self.resctrl.reconcile_group_until(
+            group_path,
+            pid_resolver,
+            self.cfg.max_reconcile_passes,
+            deadline,
+        );
+        match res {
+            Ok(res) if res.missing == 0 => Ok((ContainerSyncState::Reconciled, 0)),
+            Ok(res) => Ok((ContainerSyncState::Partial, res.missing)),
+            // Treat empty PID set as a non-fatal partial reconcile
+            Err(resctrl::Error::EmptyPidSet) => Ok((ContainerSyncState::Partial, 0)),
+            Err(e) if pid_access_denied => {
+                warn!(
+                    "resctrl-plugin: permission denied reading PIDs for {}; not retrying: {}",
+                    what, e
+                );
+                Ok((ContainerSyncState::PermissionDenied, 0))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Retry once across all pods/containers.
     /// Stops group-creation retries on first Capacity error in this pass.
     pub fn retry_all_once(&self) -> Result<(), PluginError> {
+        if self.disabled.load(Ordering::Relaxed) || self.cfg.observe_only {
+            return Ok(());
+        }
+
+        // Runs on the retry timer: report warnings suppressed for a whole window
+        // even if the failures stopped
+        self.warnings.flush(true);
+
         // Snapshot lists under lock
         let (failed_pods, partial_containers): (Vec<String>, Vec<String>) = {
             let st = self.state.lock().unwrap();
@@ -531,6 +1618,57 @@ impl<P: FsProvider> ResctrlPlugin<P> {
     }
 }
 
+impl<P: FsProvider + Send + Sync + 'static> ResctrlPlugin<P> {
+    /// Reconcile the containers of a synchronize request.
+    ///
+    /// Containers are grouped by pod and each pod's containers are handled in
+    /// request order by a single task, so per-pod events keep their ordering.
+    /// Reconciliation blocks on filesystem syscalls, so each pod runs on a
+    /// `spawn_blocking` task, up to `concurrency_limit` pods at a time.
+    async fn synchronize_containers(
+        &self,
+        pods: &[nri::api::PodSandbox],
+        containers: &[nri::api::Container],
+    ) {
+        let pods_map: HashMap<&str, &nri::api::PodSandbox> =
+            pods.iter().map(|p| (p.id.as_str(), p)).collect();
+
+        // Group containers by pod, preserving first-seen pod order
+        let mut work: Vec<(nri::api::PodSandbox, Vec<nri::api::Container>)> = Vec::new();
+        let mut work_idx: HashMap<&str, usize> = HashMap::new();
+        for c in containers {
+            let Some(pod) = pods_map.get(c.pod_sandbox_id.as_str()) else {
+                continue;
+            };
+            let idx = *work_idx.entry(pod.id.as_str()).or_insert_with(|| {
+                work.push(((*pod).clone(), Vec::new()));
+                work.len() - 1
+            });
+            work[idx].1.push(c.clone());
+        }
+
+        let total: usize = work.iter().map(|(_, containers)| containers.len()).sum();
+        let done = Arc::new(AtomicUsize::new(0));
+        let mut tasks = stream::iter(work)
+            .map(|(pod, containers)| {
+                let plugin = self.share();
+                let done = done.clone();
+                tokio::task::spawn_blocking(move || {
+                    plugin.synchronize_pod(&pod, &containers, &done, total)
+                })
+            })
+            .buffer_unordered(self.cfg.concurrency_limit.max(1));
+        while let Some(res) = tasks.next().await {
+            if let Err(e) = res {
+                error!(
+                    "resctrl-plugin: synchronize: pod reconcile task failed: {}",
+                    e
+                );
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
     async fn configure(
@@ -543,14 +1681,43 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
             req.runtime_name, req.runtime_version
         );
 
-        // Subscribe to container and pod lifecycle events we handle.
-        let mut events = EventMask::new();
-        events.set(&[
-            Event::START_CONTAINER,
-            Event::REMOVE_CONTAINER,
-            Event::RUN_POD_SANDBOX,
-            Event::REMOVE_POD_SANDBOX,
-        ]);
+        if !self.cfg.runtime_allowed(&req.runtime_name) {
+            warn!(
+                "resctrl-plugin: runtime '{}' is not allowed by configuration, subscribing to no events",
+                req.runtime_name
+            );
+            self.disabled.store(true, Ordering::Relaxed);
+            return Ok(ConfigureResponse {
+                events: EventMask::new().raw_value(),
+                special_fields: protobuf::SpecialFields::default(),
+            });
+        }
+
+        // Probe resctrl once, so an unusable resctrl subscribes to no events.
+        // Transient failures are left to `synchronize` to retry, and
+        // `abort_sync_if_unmounted` asks for `synchronize` to fail instead.
+        // Observing needs no resctrl at all.
+        if !self.cfg.abort_sync_if_unmounted && !self.cfg.observe_only {
+            if let Err(ProbeFailure::Unusable(e)) = self.probe_resctrl() {
+                self.disable_unusable(&e);
+                return Ok(ConfigureResponse {
+                    events: EventMask::new().raw_value(),
+                    special_fields: protobuf::SpecialFields::default(),
+                });
+            }
+        }
+        self.disabled.store(false, Ordering::Relaxed);
+
+        // Subscribe to container and pod lifecycle events we handle. PIDs are
+        // assigned at START_CONTAINER, once the container's process exists.
+        let mut events = EventMask::for_container_lifecycle().union(EventMask::for_pod_lifecycle());
+        events.set(&[Event::UPDATE_CONTAINER]);
+        if self.cfg.adjust_on_create {
+            events.set(&[Event::CREATE_CONTAINER]);
+        }
+        if self.cfg.monitoring_annotation.is_some() {
+            events.set(&[Event::UPDATE_POD_SANDBOX]);
+        }
 
         Ok(ConfigureResponse {
             events: events.raw_value(),
@@ -563,18 +1730,109 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
         _ctx: &TtrpcContext,
         req: SynchronizeRequest,
     ) -> ttrpc::Result<SynchronizeResponse> {
-        // Ensure resctrl is mounted according to config on every startup synchronize.
-        // If mounting fails, log and continue; subsequent operations may be no-ops.
-        let mounted_ok = match self.resctrl.ensure_mounted(self.cfg.auto_mount) {
-            Ok(()) => true,
-            Err(e) => {
-                warn!("resctrl-plugin: ensure_mounted failed: {}", e);
-                false
+        // Disabled by `configure` or an earlier synchronize: leave resctrl and
+        // the pods alone
+        if self.disabled.load(Ordering::Relaxed) {
+            return Ok(SynchronizeResponse {
+                update: vec![],
+                more: req.more,
+                special_fields: protobuf::SpecialFields::default(),
+            });
+        }
+
+        let started = Instant::now();
+
+        // Ensure resctrl is mounted according to config on every startup synchronize,
+        // classified like the probe in `configure`: the plugin gives up on an
+        // unusable resctrl, and logs and continues on transient failures, which
+        // are retried on the next synchronize.
+        // When only observing, resctrl is left alone, including its stale groups.
+        let mounted_ok = if self.cfg.observe_only {
+            false
+        } else {
+            match self.probe_resctrl() {
+                Ok(()) => true,
+                Err(ProbeFailure::Unusable(e) | ProbeFailure::Transient(e))
+                    if self.cfg.abort_sync_if_unmounted =>
+                {
+                    error!(
+                        "resctrl-plugin: resctrl unavailable, aborting synchronize of {} pods: {}",
+                        req.pods.len(),
+                        e
+                    );
+                    return Err(ttrpc::Error::RpcStatus(ttrpc::get_status(
+                        ttrpc::Code::FAILED_PRECONDITION,
+                        format!("resctrl is not mounted: {}", e),
+                    )));
+                }
+                Err(ProbeFailure::Unusable(e)) => {
+                    self.disable_unusable(&e);
+                    return Ok(SynchronizeResponse {
+                        update: vec![],
+                        more: req.more,
+                        special_fields: protobuf::SpecialFields::default(),
+                    });
+                }
+                Err(ProbeFailure::Transient(e)) => {
+                    self.warnings.warn(
+                        "ensure_mounted",
+                        &e,
+                        format_args!(
+                            "resctrl-plugin: ensure_mounted failed, retrying on next synchronize: {}",
+                            e
+                        ),
+                    );
+                    false
+                }
+            }
+        };
+
+        // Groups of a previous run to adopt, instead of the startup cleanup
+        let existing_groups = if self.cfg.reconcile_existing_on_start && mounted_ok {
+            match resctrl::validate_group_prefix(&self.cfg.group_prefix)
+                .and_then(|()| self.resctrl.list_groups())
+            {
+                Ok(groups) => Some(groups),
+                Err(e) => {
+                    self.warnings.warn(
+                        "list_groups",
+                        &e,
+                        format_args!("resctrl-plugin: not adopting existing groups: {}", e),
+                    );
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        // Startup cleanup: if enabled and mounted, remove stale groups.
-        if self.cfg.cleanup_on_start && mounted_ok {
+        // Startup cleanup: if enabled and mounted, remove stale groups. The state
+        // lock is held throughout so a RUN_POD_SANDBOX handled concurrently either
+        // completes before the cleanup (and has its group restored below) or waits
+        // for it, never creating a group the cleanup then removes.
+        let mut st = self.state.lock().unwrap();
+        if self.cfg.reconcile_existing_on_start {
+            // Orphans are removed once the pods are known
+        } else if self.cfg.cleanup_on_start && mounted_ok && self.cfg.cleanup_dry_run {
+            match self.resctrl.cleanup_all_dry_run() {
+                Ok(rep) => {
+                    info!(
+                        "resctrl-plugin: startup cleanup dry-run: would remove={}, non_prefix={}",
+                        rep.removed, rep.non_prefix_groups
+                    );
+                }
+                Err(e @ resctrl::Error::InvalidPrefix { .. }) => {
+                    error!("resctrl-plugin: refusing startup cleanup dry-run: {}", e);
+                }
+                Err(e) => {
+                    self.warnings.warn(
+                        "cleanup",
+                        &e,
+                        format_args!("resctrl-plugin: cleanup dry-run failed: {}", e),
+                    );
+                }
+            }
+        } else if self.cfg.cleanup_on_start && mounted_ok {
             match self.resctrl.cleanup_all() {
                 Ok(rep) => {
                     info!(
@@ -582,31 +1840,65 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
                         rep.removed, rep.removal_failures, rep.removal_race, rep.non_prefix_groups
                     );
                 }
+                Err(e @ resctrl::Error::InvalidPrefix { .. }) => {
+                    // A prefix that could match unrelated groups removes nothing
+                    error!("resctrl-plugin: refusing startup cleanup: {}", e);
+                }
                 Err(e) => {
                     // Log and continue; do not emit events for cleanup-only actions
-                    warn!("resctrl-plugin: cleanup_all failed: {}", e);
+                    self.warnings.warn(
+                        "cleanup",
+                        &e,
+                        format_args!("resctrl-plugin: cleanup_all failed: {}", e),
+                    );
                 }
             }
+            self.restore_groups_after_cleanup(&mut st);
         }
+        drop(st);
         info!(
             "Synchronizing resctrl plugin with {} pods and {} containers",
             req.pods.len(),
             req.containers.len()
         );
 
-        // Ensure groups for all pods first
-        for pod in &req.pods {
+        // Ensure groups for all pods first; creating a group that exists adopts it.
+        // Pods already added by a concurrent RUN_POD_SANDBOX keep their group
+        for (i, pod) in req.pods.iter().enumerate() {
             self.handle_new_pod(pod);
-        }
-
-        // Then reconcile each container individually
-        let pods_map: std::collections::HashMap<String, nri::api::PodSandbox> =
-            req.pods.iter().map(|p| (p.id.clone(), p.clone())).collect();
-        for c in &req.containers {
-            if let Some(pod) = pods_map.get(&c.pod_sandbox_id) {
-                self.handle_new_container(pod, c);
+            if (i + 1) % SYNC_PROGRESS_INTERVAL == 0 {
+                info!(
+                    "resctrl-plugin: synchronize: handled {}/{} pods",
+                    i + 1,
+                    req.pods.len()
+                );
             }
         }
+        if let Some(existing_groups) = existing_groups {
+            self.remove_orphan_groups(existing_groups);
+        }
+
+        // Then reconcile containers, up to `concurrency_limit` pods at a time
+        self.synchronize_containers(&req.pods, &req.containers)
+            .await;
+
+        // Report the failures coalesced during this synchronize now rather than
+        // after the next window
+        self.warnings.flush(false);
+
+        let summary = self.sync_summary(&req.pods, &req.containers);
+        info!(
+            "resctrl-plugin: synchronized {} pods and {} containers in {:?}: groups ready={}, failed={}, unmonitored pods={}; containers reconciled={}, partial={}, other={}",
+            req.pods.len(),
+            req.containers.len(),
+            started.elapsed(),
+            summary.groups_ready,
+            summary.groups_failed,
+            summary.unmonitored_pods,
+            summary.containers_reconciled,
+            summary.containers_partial,
+            summary.containers_other
+        );
 
         Ok(SynchronizeResponse {
             update: vec![],
@@ -621,8 +1913,17 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
         req: CreateContainerRequest,
     ) -> ttrpc::Result<CreateContainerResponse> {
         debug!("resctrl-plugin: create_container: {}", req.container.id);
-        // No-op: container handling is performed on START_CONTAINER via state_change.
-        Ok(CreateContainerResponse::default())
+        // Task assignment is performed on START_CONTAINER via state_change; the
+        // adjustment only lets the runtime place tasks early where it can.
+        let adjust = if self.cfg.adjust_on_create {
+            self.container_adjustment(&req.pod.uid)
+        } else {
+            None
+        };
+        Ok(CreateContainerResponse {
+            adjust: adjust.into(),
+            ..Default::default()
+        })
     }
 
     async fn update_container(
@@ -631,6 +1932,12 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
         req: UpdateContainerRequest,
     ) -> ttrpc::Result<UpdateContainerResponse> {
         debug!("resctrl-plugin: update_container: {}", req.container.id);
+        if self.disabled.load(Ordering::Relaxed) {
+            return Ok(UpdateContainerResponse::default());
+        }
+        if let (Some(pod), Some(container)) = (req.pod.as_ref(), req.container.as_ref()) {
+            self.handle_container_update(pod, container);
+        }
         Ok(UpdateContainerResponse::default())
     }
 
@@ -649,6 +1956,11 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
         req: UpdatePodSandboxRequest,
     ) -> ttrpc::Result<UpdatePodSandboxResponse> {
         debug!("resctrl-plugin: update_pod_sandbox: {}", req.pod.uid);
+        if self.cfg.monitoring_annotation.is_some() && !self.disabled.load(Ordering::Relaxed) {
+            if let Some(pod) = req.pod.as_ref() {
+                self.handle_pod_update(pod);
+            }
+        }
         Ok(UpdatePodSandboxResponse::default())
     }
 
@@ -658,6 +1970,10 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
         req: StateChangeEvent,
     ) -> ttrpc::Result<Empty> {
         debug!("resctrl-plugin: state_change: event={:?}", req.event);
+        // Events subscribed to before synchronize disabled the plugin
+        if self.disabled.load(Ordering::Relaxed) {
+            return Ok(Empty::default());
+        }
         match req.event.enum_value() {
             Ok(Event::RUN_POD_SANDBOX) => {
                 if let Some(pod) = req.pod.as_ref() {
@@ -666,42 +1982,12 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
             }
             Ok(Event::START_CONTAINER) => {
                 if let (Some(pod), Some(container)) = (req.pod.as_ref(), req.container.as_ref()) {
-                    self.handle_new_container(pod, container);
+                    self.handle_container_start(pod, container);
                 }
             }
             Ok(Event::REMOVE_POD_SANDBOX) => {
                 if let Some(pod) = req.pod.as_ref() {
-                    let pod_uid = pod.uid.clone();
-                    let mut st = self.state.lock().unwrap();
-
-                    // Get group path before removing pod state
-                    let group_path =
-                        st.pods
-                            .get(&pod_uid)
-                            .and_then(|pod_state| match &pod_state.group_state {
-                                ResctrlGroupState::Exists(path) => Some(path.clone()),
-                                _ => None,
-                            });
-
-                    // Remove all containers for this pod
-                    st.containers.retain(|_, c| c.pod_uid != pod_uid);
-                    // Remove pod state
-                    st.pods.remove(&pod_uid);
-                    // Emit removal event under lock to preserve ordering
-                    self.emit_event(PodResctrlEvent::Removed(PodResctrlRemoved {
-                        pod_uid: pod_uid.clone(),
-                    }));
-                    drop(st);
-
-                    // Delete resctrl group if it exists
-                    if let Some(group_path) = group_path {
-                        if let Err(e) = self.resctrl.delete_group(&group_path) {
-                            warn!(
-                                "resctrl-plugin: failed to delete group {}: {}",
-                                group_path, e
-                            );
-                        }
-                    }
+                    self.remove_pod(&pod.uid, false);
                 }
             }
             Ok(Event::REMOVE_CONTAINER) => {
@@ -709,9 +1995,18 @@ impl<P: FsProvider + Send + Sync + 'static> Plugin for ResctrlPlugin<P> {
                     let pod_uid = pod.uid.clone();
                     let mut st = self.state.lock().unwrap();
 
+                    if let Some(containers) = st.unmonitored.get_mut(&pod_uid) {
+                        containers.retain(|(id, _)| *id != container.id);
+                    }
+
                     // Adjust counts based on the removed container's previous state
-                    let old_state = st.containers.remove(&container.id).map(|c| c.state);
+                    let old = st.containers.remove(&container.id);
+                    let old_state = old.as_ref().map(|c| c.state);
                     if let Some(pod_state) = st.pods.get_mut(&pod_uid) {
+                        if let Some(c) = &old {
+                            pod_state.unreconciled_pids =
+                                pod_state.unreconciled_pids.saturating_sub(c.missing_pids);
+                        }
                         if matches!(old_state, Some(s) if s != ContainerSyncState::NoPod) {
                             pod_state.total_containers =
                                 pod_state.total_containers.saturating_sub(1);
@@ -789,366 +2084,2503 @@ mod tests {
         assert!(fs.exists(&root.join("mon_groups").join("foo")));
     }
 
-    #[test]
-    fn test_default_config() {
-        let cfg = ResctrlPluginConfig::default();
-        assert_eq!(cfg.group_prefix, "pod_");
-        assert!(cfg.cleanup_on_start);
-        assert_eq!(cfg.max_reconcile_passes, 1);
-        assert_eq!(cfg.concurrency_limit, 1);
-        assert!(cfg.auto_mount);
-    }
-
     #[tokio::test]
-    async fn test_configure_event_mask() {
+    async fn test_cleanup_dry_run_keeps_groups() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = std::path::PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("mon_groups"));
+        fs.add_dir(&root.join("pod_x1"));
+        fs.add_dir(&root.join("mon_groups").join("pod_mx"));
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
         let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
-        let plugin = ResctrlPlugin::new(ResctrlPluginConfig::default(), tx);
+        let cfg = ResctrlPluginConfig {
+            cleanup_dry_run: true,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
 
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
-            metadata: std::collections::HashMap::<String, Vec<String>>::default(),
+            metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-        let req = ConfigureRequest {
-            config: String::new(),
-            runtime_name: "test-runtime".into(),
-            runtime_version: "1.0".into(),
-            registration_timeout: 1000,
-            request_timeout: 1000,
-            special_fields: SpecialFields::default(),
-        };
-
-        let resp = plugin.configure(&ctx, req).await.unwrap();
-        let events = EventMask::from_raw(resp.events);
+        plugin
+            .synchronize(&ctx, SynchronizeRequest::default())
+            .await
+            .unwrap();
 
-        // Must include minimal container/pod events we need
-        assert!(events.is_set(Event::START_CONTAINER));
-        assert!(events.is_set(Event::RUN_POD_SANDBOX));
-        assert!(events.is_set(Event::REMOVE_POD_SANDBOX));
-        assert!(events.is_set(Event::REMOVE_CONTAINER));
+        assert!(fs.exists(&root.join("pod_x1")));
+        assert!(fs.exists(&root.join("mon_groups").join("pod_mx")));
     }
 
     #[tokio::test]
-    #[cfg(target_os = "linux")]
-    async fn test_synchronize_emits_counts() {
-        // This test requires Linux-specific functionality
-        let fs = MockFs::new();
-        // Ensure resctrl root exists
-        fs.add_dir(std::path::Path::new("/sys"));
-        fs.add_dir(std::path::Path::new("/sys/fs"));
-        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
-
-        // Create fake cgroups with PIDs for two containers belonging to the same pod
-        let cg = std::path::PathBuf::from("/cg/podX/containerA");
-        fs.add_dir(cg.parent().unwrap());
-        fs.add_dir(&cg);
-        fs.add_file(&cg.join("cgroup.procs"), "1\n2\n");
-        let cg2 = std::path::PathBuf::from("/cg/podX/containerB");
-        fs.add_dir(&cg2);
-        fs.add_file(&cg2.join("cgroup.procs"), "3\n4\n");
+    async fn test_reconcile_existing_on_start_adopts_known_pod_groups() {
+        let fs = MockFs::with_premounted_resctrl();
+        let mon = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups");
+        fs.add_dir(&mon);
+        // Groups left by a previous run: one for a live pod, one orphaned
+        fs.add_dir(&mon.join("pod_u-keep"));
+        fs.add_file(&mon.join("pod_u-keep").join("tasks"), "4242\n");
+        fs.add_dir(&mon.join("pod_u-gone"));
+        fs.add_dir(&mon.join("other"));
 
         let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
-
-        // Use mock PID source from the module
-        use crate::pid_source::test_support::MockCgroupPidSource;
-        let mut mock_pid_src = MockCgroupPidSource::new();
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
-
-        // Build synchronize request with one pod and one container
-        let pod = nri::api::PodSandbox {
-            id: "pod-sb-1".into(),
-            uid: "u123".into(),
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            reconcile_existing_on_start: true,
             ..Default::default()
         };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
 
-        // Prepare containers: one for initial synchronize and another to add later
-        let linux = nri::api::LinuxContainer {
-            cgroups_path: cg.to_string_lossy().into_owned(),
-            ..Default::default()
-        };
-        let container = nri::api::Container {
-            id: "ctr1".into(),
-            pod_sandbox_id: pod.id.clone(),
-            linux: protobuf::MessageField::some(linux),
-            ..Default::default()
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
         };
-        let second_container = nri::api::Container {
-            id: "ctr2".into(),
-            pod_sandbox_id: pod.id.clone(),
-            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
-                cgroups_path: cg2.to_string_lossy().into_owned(),
-                ..Default::default()
-            }),
+        let pod = nri::api::PodSandbox {
+            id: "sb-keep".into(),
+            uid: "u-keep".into(),
             ..Default::default()
         };
+        plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods: vec![pod],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
 
-        // Register the full cgroup path with mock pid source before plugin creation
-        let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod));
-        mock_pid_src.set_pids(full_cg, vec![1, 2]);
-        let full_cg_second = nri::compute_full_cgroup_path(&second_container, Some(&pod));
-        mock_pid_src.set_pids(full_cg_second, vec![3, 4]);
-
-        // Create plugin with the configured mock pid source
-        let plugin = ResctrlPlugin::with_pid_source(
-            ResctrlPluginConfig::default(),
-            rc,
-            tx,
-            Arc::new(mock_pid_src),
+        // The live pod's group is adopted with its tasks intact
+        assert_eq!(
+            fs.file_contents(&mon.join("pod_u-keep").join("tasks"))
+                .as_deref(),
+            Some("4242\n")
         );
+        let keep_path = mon.join("pod_u-keep").to_string_lossy().to_string();
+        {
+            let st = plugin.state.lock().unwrap();
+            let ps = st.pods.get("u-keep").expect("pod state present");
+            assert!(matches!(
+                &ps.group_state,
+                ResctrlGroupState::Exists(path) if *path == keep_path
+            ));
+        }
+        // The orphan is removed; groups outside the prefix are left alone
+        assert!(!fs.dir_exists(&mon.join("pod_u-gone")));
+        assert!(fs.dir_exists(&mon.join("other")));
+    }
 
-        // First synchronize including the container
-        let req = SynchronizeRequest {
-            pods: vec![pod.clone()],
-            containers: vec![container.clone()],
-            more: false,
-            special_fields: SpecialFields::default(),
-        };
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_run_pod_sandbox_before_synchronize_keeps_group() {
+        let fs = MockFs::with_premounted_resctrl();
+        let mon = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups");
+        fs.add_dir(&mon);
+        fs.add_dir(&mon.join("pod_stale"));
+
+        // Hold the startup cleanup until u2's RUN_POD_SANDBOX has been issued
+        let (cleanup_started_tx, cleanup_started_rx) = std::sync::mpsc::channel();
+        let (resume_tx, resume_rx) = std::sync::mpsc::channel::<()>();
+        let resume_rx = Mutex::new(resume_rx);
+        fs.set_remove_dir_hook(&mon.join("pod_stale"), move || {
+            let _ = cleanup_started_tx.send(());
+            let _ = resume_rx.lock().unwrap().recv();
+            // Give the RUN_POD_SANDBOX time to reach the state lock
+            std::thread::sleep(Duration::from_millis(50));
+        });
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
 
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-        let _ = plugin.synchronize(&ctx, req).await.unwrap();
+        let pod = |uid: &str| nri::api::PodSandbox {
+            id: format!("sb-{}", uid),
+            uid: uid.into(),
+            ..Default::default()
+        };
+        let run_pod_sandbox = |uid: &str| StateChangeEvent {
+            event: Event::RUN_POD_SANDBOX.into(),
+            pod: protobuf::MessageField::some(pod(uid)),
+            container: protobuf::MessageField::none(),
+            special_fields: SpecialFields::default(),
+        };
+        let sync_req = SynchronizeRequest {
+            pods: vec![pod("u1"), pod("u2")],
+            ..Default::default()
+        };
 
-        // Expect two events from synchronize:
-        // 1) pod creation (0/0)
-        // 2) container reconcile (1/1)
-        use tokio::time::{timeout, Duration};
-        let ev = timeout(Duration::from_millis(200), rx.recv())
+        // u1 is delivered ahead of synchronize, whose startup cleanup would
+        // remove its group; u2 arrives while the cleanup is in progress
+        plugin
+            .state_change(&ctx, run_pod_sandbox("u1"))
             .await
-            .expect("event")
-            .expect("ev");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.pod_uid, "u123");
-                assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
-                assert_eq!(a.total_containers, 0);
-                assert_eq!(a.reconciled_containers, 0);
-            }
-            _ => panic!("unexpected event type"),
-        }
-
-        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .unwrap();
+        let syncing = plugin.share();
+        let synced = tokio::spawn(async move {
+            let ctx = TtrpcContext {
+                mh: ttrpc::MessageHeader::default(),
+                metadata: std::collections::HashMap::new(),
+                timeout_nano: 5_000,
+            };
+            syncing.synchronize(&ctx, sync_req).await
+        });
+        tokio::task::spawn_blocking(move || cleanup_started_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        resume_tx.send(()).unwrap();
+        plugin
+            .state_change(&ctx, run_pod_sandbox("u2"))
+            .await
+            .unwrap();
+        synced.await.unwrap().unwrap();
+
+        assert!(!fs.dir_exists(&mon.join("pod_stale")));
+        for uid in ["u1", "u2"] {
+            let group = mon.join(format!("pod_{}", uid));
+            assert!(fs.dir_exists(&group), "group of {} missing", uid);
+            let expected = group.to_string_lossy().to_string();
+            let st = plugin.state.lock().unwrap();
+            assert!(matches!(
+                &st.pods[uid].group_state,
+                ResctrlGroupState::Exists(path) if *path == expected
+            ));
+        }
+
+        // Every event reports the single group of each pod
+        let mut events = 0;
+        while let Ok(ev) = rx.try_recv() {
+            match ev {
+                PodResctrlEvent::AddOrUpdate(a) => {
+                    let expected = mon
+                        .join(format!("pod_{}", a.pod_uid))
+                        .to_string_lossy()
+                        .to_string();
+                    assert_eq!(a.group_state, ResctrlGroupState::Exists(expected));
+                    events += 1;
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        // One event per RUN_POD_SANDBOX and one per synchronized pod
+        assert_eq!(events, 4);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_refused_for_dangerous_prefix() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = std::path::PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("mon_groups"));
+        fs.add_dir(&root.join("pod_x1"));
+        fs.add_dir(&root.join("other"));
+        fs.add_dir(&root.join("mon_groups").join("pod_mx"));
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        for prefix in ["", "p", "pod/"] {
+            let rc = Resctrl::with_provider(
+                fs.clone(),
+                resctrl::Config {
+                    group_prefix: prefix.to_string(),
+                    ..Default::default()
+                },
+            );
+            let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
+            let cfg = ResctrlPluginConfig {
+                group_prefix: prefix.to_string(),
+                ..Default::default()
+            };
+            let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+
+            // Synchronization still succeeds, but nothing is removed
+            plugin
+                .synchronize(&ctx, SynchronizeRequest::default())
+                .await
+                .unwrap();
+            for dir in ["pod_x1", "other", "mon_groups/pod_mx"] {
+                assert!(
+                    fs.exists(&root.join(dir)),
+                    "{} removed with prefix {:?}",
+                    dir,
+                    prefix
+                );
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_blocking_send_policy_delivers_on_full_channel() {
+        use std::time::Duration;
+
+        let pods: Vec<_> = (0..3)
+            .map(|i| nri::api::PodSandbox {
+                id: format!("sb{}", i),
+                uid: format!("u{}", i),
+                ..Default::default()
+            })
+            .collect();
+
+        for (policy, expect_dropped) in [
+            (SendPolicy::Drop, 2),
+            (SendPolicy::BlockWithTimeout(Duration::from_secs(5)), 0),
+        ] {
+            let fs = MockFs::with_premounted_resctrl();
+            fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+            let rc = Resctrl::with_provider(fs, resctrl::Config::default());
+            let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(1);
+            let cfg = ResctrlPluginConfig {
+                send_policy: policy,
+                ..Default::default()
+            };
+            let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+
+            // The consumer starts reading only after the channel has filled up
+            let consumer = tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let mut uids = Vec::new();
+                while let Ok(Some(PodResctrlEvent::AddOrUpdate(a))) =
+                    tokio::time::timeout(Duration::from_millis(200), rx.recv()).await
+                {
+                    uids.push(a.pod_uid);
+                }
+                uids
+            });
+            for pod in &pods {
+                plugin.handle_new_pod(pod);
+            }
+
+            assert_eq!(plugin.dropped_events(), expect_dropped);
+            let uids = consumer.await.unwrap();
+            assert_eq!(uids.len(), pods.len() - expect_dropped);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abort_sync_if_unmounted() {
+        // No resctrl mount, and mounting is not allowed
+        let fs = MockFs::new();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            auto_mount: false,
+            abort_sync_if_unmounted: true,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let pods = (0..3)
+            .map(|i| nri::api::PodSandbox {
+                id: format!("sb{}", i),
+                uid: format!("u{}", i),
+                ..Default::default()
+            })
+            .collect();
+        let res = plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods,
+                    containers: vec![],
+                    more: false,
+                    special_fields: protobuf::SpecialFields::default(),
+                },
+            )
+            .await;
+
+        match res {
+            Err(ttrpc::Error::RpcStatus(status)) => {
+                assert_eq!(status.code, ttrpc::Code::FAILED_PRECONDITION.into())
+            }
+            other => panic!("expected FAILED_PRECONDITION, got {:?}", other.map(|_| ())),
+        }
+        // No per-pod Failed events, and no pods tracked
+        assert!(rx.try_recv().is_err());
+        assert!(plugin.state.lock().unwrap().pods.is_empty());
+        assert_eq!(
+            fs.mkdir_count(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u0")),
+            0
+        );
+    }
+
+    #[test]
+    fn test_default_config() {
+        let cfg = ResctrlPluginConfig::default();
+        assert_eq!(cfg.group_prefix, "pod_");
+        assert!(cfg.cleanup_on_start);
+        assert_eq!(cfg.max_reconcile_passes, 1);
+        assert!(cfg.max_reconcile_time.is_none());
+        assert_eq!(cfg.concurrency_limit, 1);
+        assert!(cfg.auto_mount);
+        assert_eq!(cfg.pid_recursion_depth, 0);
+        assert!(!cfg.batch_pod_pid_reads);
+        assert!(cfg.schemata_annotation.is_none());
+        assert!(!cfg.adjust_on_create);
+        assert!(!cfg.abort_sync_if_unmounted);
+        assert_eq!(cfg.send_policy, SendPolicy::Drop);
+        assert!(cfg.monitoring_annotation.is_none());
+        assert!(cfg.allowed_runtimes.is_empty());
+        assert!(cfg.denied_runtimes.is_empty());
+    }
+
+    #[test]
+    fn test_runtime_allowed() {
+        let cfg = ResctrlPluginConfig::default();
+        assert!(cfg.runtime_allowed("containerd"));
+
+        let cfg = ResctrlPluginConfig {
+            allowed_runtimes: vec!["containerd".into(), "cri-o".into()],
+            denied_runtimes: vec!["cri-o".into()],
+            ..Default::default()
+        };
+        assert!(cfg.runtime_allowed("containerd"));
+        assert!(!cfg.runtime_allowed("cri-o"));
+        assert!(!cfg.runtime_allowed("other"));
+    }
+
+    #[tokio::test]
+    async fn test_denied_runtime_subscribes_to_no_events() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            denied_runtimes: vec!["cri-o".into()],
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let resp = plugin
+            .configure(
+                &ctx,
+                ConfigureRequest {
+                    runtime_name: "cri-o".into(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.events, 0);
+
+        // Synchronize is a no-op: no groups, no events
+        let pod = nri::api::PodSandbox {
+            id: "sb0".into(),
+            uid: "u0".into(),
+            ..Default::default()
+        };
+        plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods: vec![pod],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        assert!(plugin.state.lock().unwrap().pods.is_empty());
+        assert_eq!(
+            fs.mkdir_count(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u0")),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_resctrl_disables_plugin() {
+        // No resctrl mount, and mounting is not allowed
+        let fs = MockFs::new();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            auto_mount: false,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let resp = plugin
+            .configure(&ctx, ConfigureRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(resp.events, 0);
+
+        // Neither synchronize nor the retry timer attempts to create groups
+        let pods = (0..3)
+            .map(|i| nri::api::PodSandbox {
+                id: format!("sb{}", i),
+                uid: format!("u{}", i),
+                ..Default::default()
+            })
+            .collect();
+        plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        plugin.retry_all_once().unwrap();
+
+        assert!(rx.try_recv().is_err());
+        assert!(plugin.state.lock().unwrap().pods.is_empty());
+        for i in 0..3 {
+            let group = format!("/sys/fs/resctrl/mon_groups/pod_u{}", i);
+            assert_eq!(fs.mkdir_count(std::path::Path::new(&group)), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_resctrl_on_synchronize_disables_plugin() {
+        // Mounting fails because the kernel lacks resctrl
+        let fs = MockFs::new();
+        fs.set_mount_err(libc::ENODEV);
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let pod = nri::api::PodSandbox {
+            id: "sb0".into(),
+            uid: "u0".into(),
+            ..Default::default()
+        };
+        plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods: vec![pod.clone()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(plugin.disabled.load(Ordering::Relaxed));
+
+        // Later events and retries leave resctrl alone
+        plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        plugin.retry_all_once().unwrap();
+
+        assert!(rx.try_recv().is_err());
+        assert!(plugin.state.lock().unwrap().pods.is_empty());
+        assert_eq!(
+            fs.mkdir_count(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u0")),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_mounted_on_synchronize_disables_plugin() {
+        // Classified like the probe in configure: resctrl is not mounted and we
+        // may not mount it
+        let fs = MockFs::new();
+        let rc = Resctrl::with_provider(fs, resctrl::Config::default());
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            auto_mount: false,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        plugin
+            .synchronize(&ctx, SynchronizeRequest::default())
+            .await
+            .unwrap();
+        assert!(plugin.disabled.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_transient_mount_failure_retries_on_next_synchronize() {
+        // The first mount attempt fails with an error that does not mean resctrl
+        // is unsupported
+        let fs = MockFs::new();
+        fs.set_mount_err(libc::EBUSY);
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        plugin
+            .synchronize(&ctx, SynchronizeRequest::default())
+            .await
+            .unwrap();
+        assert!(!plugin.disabled.load(Ordering::Relaxed));
+
+        // The next synchronize mounts resctrl and creates the pod's group
+        let pod = nri::api::PodSandbox {
+            id: "sb0".into(),
+            uid: "u0".into(),
+            ..Default::default()
+        };
+        plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods: vec![pod],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!plugin.disabled.load(Ordering::Relaxed));
+        assert!(fs.dir_exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u0")));
+        match rx.try_recv().unwrap() {
+            PodResctrlEvent::AddOrUpdate(add) => {
+                assert_eq!(add.pod_uid, "u0");
+                assert!(matches!(add.group_state, ResctrlGroupState::Exists(_)));
+            }
+            _ => panic!("expected AddOrUpdate"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configure_event_mask() {
+        let fs = MockFs::with_premounted_resctrl();
+        let rc = Resctrl::with_provider(fs, resctrl::Config::default());
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::<String, Vec<String>>::default(),
+            timeout_nano: 5_000,
+        };
+        let req = ConfigureRequest {
+            config: String::new(),
+            runtime_name: "test-runtime".into(),
+            runtime_version: "1.0".into(),
+            registration_timeout: 1000,
+            request_timeout: 1000,
+            special_fields: SpecialFields::default(),
+        };
+
+        let resp = plugin.configure(&ctx, req).await.unwrap();
+        let events = EventMask::from_raw(resp.events);
+
+        // Must include minimal container/pod events we need
+        assert!(events.is_set(Event::START_CONTAINER));
+        assert!(events.is_set(Event::RUN_POD_SANDBOX));
+        assert!(events.is_set(Event::REMOVE_POD_SANDBOX));
+        assert!(events.is_set(Event::REMOVE_CONTAINER));
+        assert!(events.is_set(Event::UPDATE_CONTAINER));
+        assert!(!events.is_set(Event::CREATE_CONTAINER));
+        assert!(!events.is_set(Event::UPDATE_POD_SANDBOX));
+    }
+
+    #[tokio::test]
+    async fn test_observe_only_leaves_resctrl_untouched() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        use tokio::time::{timeout, Duration};
+
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        // A stale group the startup cleanup would otherwise remove
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_stale"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let pod = nri::api::PodSandbox {
+            id: "pod-obs".into(),
+            uid: "uid-obs".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "ctr-obs".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: "/cg/obs".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut pids = MockCgroupPidSource::new();
+        pids.set_pids(
+            nri::compute_full_cgroup_path(&container, Some(&pod)),
+            vec![77],
+        );
+
+        let cfg = ResctrlPluginConfig {
+            observe_only: true,
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(pids));
+        let before = fs.entries();
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let req = ConfigureRequest {
+            config: String::new(),
+            runtime_name: "test-runtime".into(),
+            runtime_version: "1.0".into(),
+            registration_timeout: 1000,
+            request_timeout: 1000,
+            special_fields: SpecialFields::default(),
+        };
+        let resp = plugin.configure(&ctx, req).await.unwrap();
+        assert!(EventMask::from_raw(resp.events).is_set(Event::START_CONTAINER));
+
+        let req = SynchronizeRequest {
+            pods: vec![pod.clone()],
+            containers: vec![container.clone()],
+            more: false,
+            special_fields: SpecialFields::default(),
+        };
+        plugin.synchronize(&ctx, req).await.unwrap();
+        plugin.retry_all_once().unwrap();
+        Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::REMOVE_POD_SANDBOX.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::none(),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        // Events still describe the pod's lifecycle
+        let mut events = Vec::new();
+        while let Ok(Some(ev)) = timeout(Duration::from_millis(100), rx.recv()).await {
+            events.push(ev);
+        }
+        let observed = |total_containers| {
+            PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+                pod_uid: "uid-obs".into(),
+                group_state: ResctrlGroupState::Observed,
+                total_containers,
+                reconciled_containers: 0,
+                unreconciled_pids: 0,
+            })
+        };
+        assert_eq!(
+            events,
+            vec![
+                observed(0),
+                observed(1),
+                PodResctrlEvent::Removed(PodResctrlRemoved {
+                    pod_uid: "uid-obs".into(),
+                }),
+            ]
+        );
+
+        // No group was created, cleaned up or assigned tasks
+        assert_eq!(fs.entries(), before);
+    }
+
+    #[tokio::test]
+    async fn test_create_container_adjustment() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        fs.add_file(
+            std::path::Path::new("/sys/fs/resctrl/schemata"),
+            "L3:0=fff;1=fff\n",
+        );
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            schemata_annotation: Some("schemata".into()),
+            adjust_on_create: true,
+            ..Default::default()
+        };
+        let plugin =
+            ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(MockCgroupPidSource::new()));
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let resp = plugin
+            .configure(
+                &ctx,
+                ConfigureRequest {
+                    runtime_name: "test-runtime".into(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(EventMask::from_raw(resp.events).is_set(Event::CREATE_CONTAINER));
+
+        let mut alloc = nri::api::PodSandbox {
+            id: "sb-alloc".into(),
+            uid: "alloc".into(),
+            ..Default::default()
+        };
+        alloc
+            .annotations
+            .insert("schemata".into(), "L3:0=f;1=f".into());
+        let plain = nri::api::PodSandbox {
+            id: "sb-plain".into(),
+            uid: "plain".into(),
+            ..Default::default()
+        };
+        plugin.handle_new_pod(&alloc);
+        plugin.handle_new_pod(&plain);
+
+        let create = |pod: &nri::api::PodSandbox| CreateContainerRequest {
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::some(nri::api::Container {
+                id: format!("{}-c1", pod.uid),
+                pod_sandbox_id: pod.id.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Control group: annotated, and the runtime is asked to use the class
+        let resp = plugin.create_container(&ctx, create(&alloc)).await.unwrap();
+        assert_eq!(
+            resp.adjust
+                .annotations
+                .get(GROUP_ANNOTATION)
+                .map(String::as_str),
+            Some("/sys/fs/resctrl/pod_alloc")
+        );
+        assert_eq!(resp.adjust.linux.resources.rdt_class.value, "pod_alloc");
+        assert!(resp.update.is_empty() && resp.evict.is_empty());
+
+        // Monitoring group: annotation only
+        let resp = plugin.create_container(&ctx, create(&plain)).await.unwrap();
+        assert_eq!(
+            resp.adjust
+                .annotations
+                .get(GROUP_ANNOTATION)
+                .map(String::as_str),
+            Some("/sys/fs/resctrl/mon_groups/pod_plain")
+        );
+        assert!(resp.adjust.linux.is_none());
+
+        // Unknown pod: no adjustment
+        let unknown = nri::api::PodSandbox {
+            uid: "unknown".into(),
+            ..Default::default()
+        };
+        let resp = plugin
+            .create_container(&ctx, create(&unknown))
+            .await
+            .unwrap();
+        assert!(resp.adjust.is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_synchronize_emits_counts() {
+        // This test requires Linux-specific functionality
+        let fs = MockFs::new();
+        // Ensure resctrl root exists
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+
+        // Create fake cgroups with PIDs for two containers belonging to the same pod
+        let cg = std::path::PathBuf::from("/cg/podX/containerA");
+        fs.add_dir(cg.parent().unwrap());
+        fs.add_dir(&cg);
+        fs.add_file(&cg.join("cgroup.procs"), "1\n2\n");
+        let cg2 = std::path::PathBuf::from("/cg/podX/containerB");
+        fs.add_dir(&cg2);
+        fs.add_file(&cg2.join("cgroup.procs"), "3\n4\n");
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        // Use mock PID source from the module
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let mut mock_pid_src = MockCgroupPidSource::new();
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+
+        // Build synchronize request with one pod and one container
+        let pod = nri::api::PodSandbox {
+            id: "pod-sb-1".into(),
+            uid: "u123".into(),
+            ..Default::default()
+        };
+
+        // Prepare containers: one for initial synchronize and another to add later
+        let linux = nri::api::LinuxContainer {
+            cgroups_path: cg.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "ctr1".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(linux),
+            ..Default::default()
+        };
+        let second_container = nri::api::Container {
+            id: "ctr2".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: cg2.to_string_lossy().into_owned(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Register the full cgroup path with mock pid source before plugin creation
+        let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod));
+        mock_pid_src.set_pids(full_cg, vec![1, 2]);
+        let full_cg_second = nri::compute_full_cgroup_path(&second_container, Some(&pod));
+        mock_pid_src.set_pids(full_cg_second, vec![3, 4]);
+
+        // Create plugin with the configured mock pid source
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            Arc::new(mock_pid_src),
+        );
+
+        // First synchronize including the container
+        let req = SynchronizeRequest {
+            pods: vec![pod.clone()],
+            containers: vec![container.clone()],
+            more: false,
+            special_fields: SpecialFields::default(),
+        };
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let _ = plugin.synchronize(&ctx, req).await.unwrap();
+
+        // Expect two events from synchronize:
+        // 1) pod creation (0/0)
+        // 2) container reconcile (1/1)
+        use tokio::time::{timeout, Duration};
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.pod_uid, "u123");
+                assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
+                assert_eq!(a.total_containers, 0);
+                assert_eq!(a.reconciled_containers, 0);
+            }
+            _ => panic!("unexpected event type"),
+        }
+
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.pod_uid, "u123");
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 1);
+            }
+            _ => panic!("unexpected event type"),
+        }
+
+        // Now add another container for the existing pod and expect updated counts
+        let _ = Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::START_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::some(second_container.clone()),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.pod_uid, "u123");
+                assert_eq!(a.total_containers, 2);
+                assert_eq!(a.reconciled_containers, 2);
+            }
+            _ => panic!("unexpected event type"),
+        }
+
+        // Verify tasks file now includes the PIDs from both containers
+        let group_path = "/sys/fs/resctrl/mon_groups/pod_u123";
+        let pids = plugin
+            .resctrl
+            .list_group_tasks(group_path)
+            .expect("list tasks");
+        assert!(pids.contains(&1));
+        assert!(pids.contains(&2));
+        assert!(pids.contains(&3));
+        assert!(pids.contains(&4));
+    }
+
+    #[tokio::test]
+    async fn test_synchronize_summary_counts() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+
+        let fs = MockFs::new();
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+        // RMIDs run out for the second pod
+        fs.set_nospace_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u2"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let pod = |n: u32, monitored: bool| nri::api::PodSandbox {
+            id: format!("sb{}", n),
+            uid: format!("u{}", n),
+            annotations: [("example.com/monitoring".to_string(), monitored.to_string())].into(),
+            ..Default::default()
+        };
+        let container = |id: &str, pod: &nri::api::PodSandbox| nri::api::Container {
+            id: id.into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: format!("/cg/{}", id),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let pods = vec![
+            pod(1, true),
+            pod(2, true),
+            pod(3, true),
+            pod(4, false),
+            pod(5, true),
+        ];
+        let containers = vec![
+            // Reconciled
+            container("c1", &pods[0]),
+            // Unreadable PIDs
+            container("c2", &pods[4]),
+            // No PIDs yet
+            container("c3", &pods[2]),
+            // Group creation failed
+            container("c4", &pods[1]),
+            // Unmonitored pod
+            container("c5", &pods[3]),
+        ];
+
+        let mut pids = MockCgroupPidSource::new();
+        pids.set_pids(
+            nri::compute_full_cgroup_path(&containers[0], Some(&pods[0])),
+            vec![10],
+        );
+        pids.set_permission_denied(nri::compute_full_cgroup_path(
+            &containers[1],
+            Some(&pods[4]),
+        ));
+        pids.set_pids(
+            nri::compute_full_cgroup_path(&containers[3], Some(&pods[1])),
+            vec![40],
+        );
+        let cfg = ResctrlPluginConfig {
+            monitoring_annotation: Some("example.com/monitoring".to_string()),
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(64);
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(pids));
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let req = SynchronizeRequest {
+            pods: pods.clone(),
+            containers: containers.clone(),
+            more: false,
+            special_fields: SpecialFields::default(),
+        };
+        plugin.synchronize(&ctx, req).await.unwrap();
+
+        assert_eq!(
+            plugin.sync_summary(&pods, &containers),
+            SyncSummary {
+                groups_ready: 3,
+                groups_failed: 1,
+                unmonitored_pods: 1,
+                containers_reconciled: 1,
+                containers_partial: 2,
+                containers_other: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_container_events_do_not_change_counts() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        use tokio::time::{timeout, Duration};
+
+        let fs = MockFs::default();
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let mut mock_pid_src = MockCgroupPidSource::new();
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+
+        let pod = nri::api::PodSandbox {
+            id: "pod-dup".into(),
+            uid: "uid-dup".into(),
+            ..Default::default()
+        };
+        let linux = nri::api::LinuxContainer {
+            cgroups_path: "/cg/dup".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "ctr-dup".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(linux),
+            ..Default::default()
+        };
+
+        let full_path = nri::compute_full_cgroup_path(&container, Some(&pod));
+        mock_pid_src.set_pids(full_path, vec![4242]);
+
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            Arc::new(mock_pid_src),
+        );
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        // Register pod once → expect initial AddOrUpdate with counts 0/0
+        let _ = plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod.clone()),
+                    container: protobuf::MessageField::none(),
+                    special_fields: protobuf::SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("initial event")
+            .expect("event value");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(add) => {
+                assert_eq!(add.total_containers, 0);
+                assert_eq!(add.reconciled_containers, 0);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let start_req = StateChangeEvent {
+            event: Event::START_CONTAINER.into(),
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::some(container.clone()),
+            special_fields: protobuf::SpecialFields::default(),
+        };
+        let _ = Plugin::state_change(&plugin, &ctx, start_req.clone())
+            .await
+            .unwrap();
+
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event after first container")
+            .expect("event value");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(add) => {
+                assert_eq!(add.total_containers, 1);
+                assert_eq!(add.reconciled_containers, 1);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Duplicate START_CONTAINER → should not emit another event
+        let _ = Plugin::state_change(&plugin, &ctx, start_req.clone())
+            .await
+            .unwrap();
+        match timeout(Duration::from_millis(100), rx.recv()).await {
+            Ok(Some(ev)) => panic!("unexpected event for duplicate container: {:?}", ev),
+            Ok(None) => panic!("event channel closed unexpectedly"),
+            Err(_) => {}
+        }
+
+        // UpdateContainer for same container should not emit anything either
+        let update_req = UpdateContainerRequest {
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::some(container.clone()),
+            linux_resources: protobuf::MessageField::none(),
+            special_fields: protobuf::SpecialFields::default(),
+        };
+        let _ = Plugin::update_container(&plugin, &ctx, update_req)
+            .await
+            .unwrap();
+        match timeout(Duration::from_millis(100), rx.recv()).await {
+            Ok(Some(ev)) => panic!("unexpected event for UpdateContainer: {:?}", ev),
+            Ok(None) => panic!("event channel closed unexpectedly"),
+            Err(_) => {}
+        }
+
+        // Internal counters remain unchanged at 1/1
+        let st = plugin.state.lock().unwrap();
+        let pod_state = st.pods.get(&pod.uid).expect("pod state present");
+        assert_eq!(pod_state.total_containers, 1);
+        assert_eq!(pod_state.reconciled_containers, 1);
+    }
+
+    #[tokio::test]
+    async fn test_start_container_reconciles_container_created_without_pids() {
+        use tokio::time::{timeout, Duration};
+
+        /// PID source whose PIDs appear once the container process starts
+        struct LatePids(Mutex<Vec<i32>>);
+        impl CgroupPidSource for LatePids {
+            fn pids_for_path(&self, _cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+                Ok(self.0.lock().unwrap().clone())
+            }
+        }
+
+        let fs = MockFs::new();
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let pids = Arc::new(LatePids(Mutex::new(Vec::new())));
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin =
+            ResctrlPlugin::with_pid_source(ResctrlPluginConfig::default(), rc, tx, pids.clone());
+
+        let pod = nri::api::PodSandbox {
+            id: "pod-late".into(),
+            uid: "uid-late".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "ctr-late".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: "/cg/late".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        // The container is created but not started: no PIDs to assign yet
+        let req = SynchronizeRequest {
+            pods: vec![pod.clone()],
+            containers: vec![container.clone()],
+            more: false,
+            special_fields: SpecialFields::default(),
+        };
+        plugin.synchronize(&ctx, req).await.unwrap();
+        let mut counts = Vec::new();
+        for _ in 0..2 {
+            match timeout(Duration::from_millis(200), rx.recv()).await {
+                Ok(Some(PodResctrlEvent::AddOrUpdate(a))) => {
+                    counts.push((a.total_containers, a.reconciled_containers))
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(counts, vec![(0, 0), (1, 0)]);
+        assert_eq!(
+            plugin.state.lock().unwrap().containers["ctr-late"].state,
+            ContainerSyncState::Partial
+        );
+
+        // The process exists once the container starts
+        *pids.0.lock().unwrap() = vec![5150];
+        Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::START_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::some(container.clone()),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        match timeout(Duration::from_millis(200), rx.recv()).await {
+            Ok(Some(PodResctrlEvent::AddOrUpdate(a))) => {
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 1);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(
+            plugin.state.lock().unwrap().containers["ctr-late"].state,
+            ContainerSyncState::Reconciled
+        );
+        let tasks = plugin
+            .resctrl
+            .list_group_tasks("/sys/fs/resctrl/mon_groups/pod_uid-late")
+            .expect("list tasks");
+        assert_eq!(tasks, vec![5150]);
+    }
+
+    #[tokio::test]
+    async fn test_run_pod_sandbox_creates_group_and_emits_event() {
+        let fs = MockFs::new();
+        // Ensure resctrl root exists
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+
+        // Define a pod sandbox and a container up-front so we can seed PIDs
+        // into the mock pid source for the full cgroup path
+        let pod = nri::api::PodSandbox {
+            id: "pod-sb-run-test".into(),
+            uid: "u789".into(),
+            ..Default::default()
+        };
+        let linux = nri::api::LinuxContainer {
+            cgroups_path: "/cg/x:cri-containerd:c1".into(),
+            ..Default::default()
+        };
+        let ctr = nri::api::Container {
+            id: "c1".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(linux),
+            ..Default::default()
+        };
+        let full_cg = nri::compute_full_cgroup_path(&ctr, Some(&pod));
+
+        // Seed mock PIDs for this container
+        let mut pid_src = Arc::new(MockCgroupPidSource::new());
+        Arc::get_mut(&mut pid_src)
+            .unwrap()
+            .set_pids(full_cg, vec![7777]);
+
+        let plugin =
+            ResctrlPlugin::with_pid_source(ResctrlPluginConfig::default(), rc, tx, pid_src);
+        // Send RUN_POD_SANDBOX via state_change
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let state_req = StateChangeEvent {
+            event: Event::RUN_POD_SANDBOX.into(),
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::none(),
+            special_fields: SpecialFields::default(),
+        };
+        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
+
+        // Should receive an AddOrUpdate event with existing group state
+        use tokio::time::{timeout, Duration};
+        timeout(Duration::from_millis(100), async {
+            if let Some(ev) = rx.recv().await {
+                match ev {
+                    PodResctrlEvent::AddOrUpdate(a) => {
+                        assert_eq!(a.pod_uid, "u789");
+                        assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
+                    }
+                    _ => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+                }
+            } else {
+                panic!("Expected AddOrUpdate event, got nothing");
+            }
+        })
+        .await
+        .expect("Should receive event within timeout");
+
+        // Verify the directory for the resctrl group was created
+        assert!(fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u789")));
+
+        // After pod exists, add a container for it and expect reconcile to complete
+        let _ = Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::START_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::some(ctr.clone()),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        // Expect counts 1/1
+        timeout(Duration::from_millis(200), async {
+            if let Some(PodResctrlEvent::AddOrUpdate(a)) = rx.recv().await {
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 1);
+            }
+        })
+        .await
+        .ok();
+
+        // Verify the tasks file includes the seeded PID
+        let pids = plugin
+            .resctrl
+            .list_group_tasks("/sys/fs/resctrl/mon_groups/pod_u789")
+            .expect("list tasks");
+        assert!(pids.contains(&7777));
+
+        // Remove the container → expect counts 0/0
+        let _ = Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::REMOVE_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::some(ctr.clone()),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        timeout(Duration::from_millis(200), async {
+            if let Some(PodResctrlEvent::AddOrUpdate(a)) = rx.recv().await {
+                assert_eq!(a.total_containers, 0);
+                assert_eq!(a.reconciled_containers, 0);
+            }
+        })
+        .await
+        .ok();
+
+        // Now remove the pod and verify removal event + directory deletion
+        let state_req = StateChangeEvent {
+            event: Event::REMOVE_POD_SANDBOX.into(),
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::none(),
+            special_fields: SpecialFields::default(),
+        };
+        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
+
+        timeout(Duration::from_millis(100), async {
+            if let Some(ev) = rx.recv().await {
+                match ev {
+                    PodResctrlEvent::Removed(r) => {
+                        assert_eq!(r.pod_uid, "u789");
+                    }
+                    _ => panic!("Expected Removed event, got: {:?}", ev),
+                }
+            } else {
+                panic!("Expected Removed event, got nothing");
+            }
+        })
+        .await
+        .expect("Should receive removal event within timeout");
+
+        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u789")));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_events_reproduce_pod_states() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        // Group creation for one pod fails with ENOSPC
+        fs.set_nospace_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u1"));
+        let rc = Resctrl::with_provider(fs, resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
+
+        for i in [2, 0, 1] {
+            plugin.handle_new_pod(&nri::api::PodSandbox {
+                id: format!("sb{}", i),
+                uid: format!("u{}", i),
+                ..Default::default()
+            });
+        }
+        plugin.remove_pod("u2", false);
+
+        // The latest event of each pod still tracked, as a consumer attached from
+        // the start would have seen them
+        let mut latest = std::collections::BTreeMap::new();
+        while let Ok(ev) = rx.try_recv() {
+            match &ev {
+                PodResctrlEvent::AddOrUpdate(a) => {
+                    latest.insert(a.pod_uid.clone(), ev.clone());
+                }
+                PodResctrlEvent::Removed(r) => {
+                    latest.remove(&r.pod_uid);
+                }
+            }
+        }
+        assert_eq!(latest.len(), 2);
+
+        let snapshot = plugin.snapshot_events();
+        assert_eq!(snapshot, latest.into_values().collect::<Vec<_>>());
+        assert!(matches!(
+            &snapshot[1],
+            PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+                group_state: ResctrlGroupState::Failed,
+                ..
+            })
+        ));
+        // Snapshots are not sent on the channel
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debug_snapshot_reflects_created_and_failed_pods() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+
+        let fs = MockFs::new();
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        // Group creation for the second pod fails with ENOSPC
+        fs.set_nospace_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u2"));
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let pod1 = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
+            ..Default::default()
+        };
+        let pod2 = nri::api::PodSandbox {
+            id: "sb2".into(),
+            uid: "u2".into(),
+            ..Default::default()
+        };
+        let linux = nri::api::LinuxContainer {
+            cgroups_path: "/cg/x:cri-containerd:c1".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "c1".into(),
+            pod_sandbox_id: pod1.id.clone(),
+            linux: protobuf::MessageField::some(linux),
+            ..Default::default()
+        };
+        let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod1));
+
+        let mut pid_src = Arc::new(MockCgroupPidSource::new());
+        Arc::get_mut(&mut pid_src)
+            .unwrap()
+            .set_pids(full_cg.clone(), vec![4242]);
+
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin =
+            ResctrlPlugin::with_pid_source(ResctrlPluginConfig::default(), rc, tx, pid_src);
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        for pod in [&pod1, &pod2] {
+            let _ = Plugin::state_change(
+                &plugin,
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod.clone()),
+                    container: protobuf::MessageField::none(),
+                    special_fields: SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let _ = Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::START_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod1.clone()),
+                container: protobuf::MessageField::some(container.clone()),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let snap = plugin.debug_snapshot();
+        let group = "/sys/fs/resctrl/mon_groups/pod_u1".to_string();
+        assert_eq!(
+            snap.pods,
+            vec![
+                PodSnapshot {
+                    pod_uid: "u1".into(),
+                    group_state: ResctrlGroupState::Exists(group.clone()),
+                    total_containers: 1,
+                    reconciled_containers: 1,
+                    unreconciled_pids: 0,
+                    schemata: None,
+                    class: None,
+                },
+                PodSnapshot {
+                    pod_uid: "u2".into(),
+                    group_state: ResctrlGroupState::Failed,
+                    total_containers: 0,
+                    reconciled_containers: 0,
+                    unreconciled_pids: 0,
+                    schemata: None,
+                    class: None,
+                },
+            ]
+        );
+        assert_eq!(
+            snap.containers,
+            vec![ContainerSnapshot {
+                container_id: "c1".into(),
+                pod_uid: "u1".into(),
+                cgroup_path: full_cg,
+                state: ContainerSyncState::Reconciled,
+                missing_pids: 0,
+            }]
+        );
+        assert!(snap.unmonitored_pods.is_empty());
+        // Only the created group is on disk
+        assert_eq!(snap.groups, vec![group]);
+        assert_eq!(snap.groups_error, None);
+
+        let json = serde_json::to_value(&snap).expect("serialize");
+        assert_eq!(json["pods"][1]["group_state"], "failed");
+        assert_eq!(json["containers"][0]["state"], "reconciled");
+    }
+
+    #[tokio::test]
+    async fn test_schemata_annotation_applies_allocation() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        fs.add_file(
+            std::path::Path::new("/sys/fs/resctrl/schemata"),
+            "L3:0=fff;1=fff\n",
+        );
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            schemata_annotation: Some("resctrl.unvariance.io/schemata".into()),
+            ..Default::default()
+        };
+        let plugin =
+            ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(MockCgroupPidSource::new()));
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let mut annotated = nri::api::PodSandbox {
+            id: "sb-alloc".into(),
+            uid: "alloc".into(),
+            ..Default::default()
+        };
+        annotated
+            .annotations
+            .insert("resctrl.unvariance.io/schemata".into(), "L3:0=f;1=f".into());
+        let plain = nri::api::PodSandbox {
+            id: "sb-plain".into(),
+            uid: "plain".into(),
+            ..Default::default()
+        };
+        for pod in [annotated, plain] {
+            let _ = plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: Event::RUN_POD_SANDBOX.into(),
+                        pod: protobuf::MessageField::some(pod),
+                        container: protobuf::MessageField::none(),
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+            match rx.recv().await.unwrap() {
+                PodResctrlEvent::AddOrUpdate(a) => {
+                    assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)))
+                }
+                ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+            }
+        }
+
+        // Annotated pod gets a control group carrying the requested allocation
+        let schemata = fs
+            .file_contents(std::path::Path::new("/sys/fs/resctrl/pod_alloc/schemata"))
+            .unwrap();
+        assert!(schemata.ends_with("L3:0=f;1=f\n"), "{}", schemata);
+        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_alloc")));
+
+        // Pods without the annotation keep a plain monitoring group
+        assert!(fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_plain")));
+        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/pod_plain")));
+    }
+
+    #[tokio::test]
+    async fn test_schemata_falls_back_to_monitoring_group() {
+        // Root without allocation support (no schemata file): the kernel refuses
+        // control groups
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        fs.set_no_perm_dir(std::path::Path::new("/sys/fs/resctrl/pod_nocat"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            schemata_annotation: Some("resctrl.unvariance.io/schemata".into()),
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let pod = |uid: &str, schemata: &str| {
+            let mut pod = nri::api::PodSandbox {
+                id: format!("sb-{}", uid),
+                uid: uid.into(),
+                ..Default::default()
+            };
+            pod.annotations
+                .insert("resctrl.unvariance.io/schemata".into(), schemata.into());
+            pod
+        };
+        let expect_group = |ev: PodResctrlEvent, expected: &str| match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.group_state, ResctrlGroupState::Exists(expected.into()))
+            }
+            ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+        };
+
+        plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod("nocat", "L3:0=f")),
+                    container: protobuf::MessageField::none(),
+                    special_fields: SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
+        expect_group(
+            rx.recv().await.unwrap(),
+            "/sys/fs/resctrl/mon_groups/pod_nocat",
+        );
+
+        // With allocation support, a control group whose allocation is rejected
+        // is deleted rather than kept without it
+        fs.add_file(
+            std::path::Path::new("/sys/fs/resctrl/schemata"),
+            "L3:0=fff;1=fff\n",
+        );
+        plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod("bad", "L3 without domains")),
+                    container: protobuf::MessageField::none(),
+                    special_fields: SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
+        expect_group(
+            rx.recv().await.unwrap(),
+            "/sys/fs/resctrl/mon_groups/pod_bad",
+        );
+        assert_eq!(
+            fs.mkdir_count(std::path::Path::new("/sys/fs/resctrl/pod_bad")),
+            1
+        );
+        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/pod_bad")));
+    }
+
+    #[tokio::test]
+    async fn test_class_rules_share_group_until_last_pod_leaves() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let cfg = ResctrlPluginConfig {
+            class_rules: vec![ClassRule {
+                annotation: "rdt.class".into(),
+                value: "gold".into(),
+                class: "gold".into(),
+            }],
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_resctrl(cfg, rc, tx);
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        let pod = |uid: &str, class: Option<&str>| {
+            let mut pod = nri::api::PodSandbox {
+                id: format!("sb-{}", uid),
+                uid: uid.into(),
+                ..Default::default()
+            };
+            if let Some(class) = class {
+                pod.annotations.insert("rdt.class".into(), class.into());
+            }
+            pod
+        };
+        let pods = [
+            pod("gold1", Some("gold")),
+            pod("gold2", Some(" gold ")),
+            pod("solo", None),
+        ];
+
+        let mut groups = Vec::new();
+        for pod in &pods {
+            plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: Event::RUN_POD_SANDBOX.into(),
+                        pod: protobuf::MessageField::some(pod.clone()),
+                        container: protobuf::MessageField::none(),
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+            match rx.recv().await.unwrap() {
+                PodResctrlEvent::AddOrUpdate(a) => match a.group_state {
+                    ResctrlGroupState::Exists(path) => groups.push(path),
+                    state => panic!("expected a group for {}, got {:?}", a.pod_uid, state),
+                },
+                ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+            }
+        }
+
+        let gold = "/sys/fs/resctrl/mon_groups/pod_class_gold";
+        let solo = "/sys/fs/resctrl/mon_groups/pod_solo";
+        assert_eq!(groups, [gold, gold, solo]);
+        let snapshot = plugin.debug_snapshot();
+        assert_eq!(snapshot.pods[0].class.as_deref(), Some("gold"));
+        assert_eq!(snapshot.pods[2].class, None);
+
+        let remove = |pod: &nri::api::PodSandbox| StateChangeEvent {
+            event: Event::REMOVE_POD_SANDBOX.into(),
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::none(),
+            special_fields: SpecialFields::default(),
+        };
+
+        // The class group outlives its first pod
+        plugin.state_change(&ctx, remove(&pods[0])).await.unwrap();
+        assert!(fs.exists(std::path::Path::new(gold)));
+
+        // And is deleted with the last one, leaving the standalone group alone
+        plugin.state_change(&ctx, remove(&pods[1])).await.unwrap();
+        assert!(!fs.exists(std::path::Path::new(gold)));
+        assert!(fs.exists(std::path::Path::new(solo)));
+
+        plugin.state_change(&ctx, remove(&pods[2])).await.unwrap();
+        assert!(!fs.exists(std::path::Path::new(solo)));
+    }
+
+    #[tokio::test]
+    async fn test_update_container_follows_cgroup_move() {
+        let fs = MockFs::with_premounted_resctrl();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let pod = nri::api::PodSandbox {
+            id: "sb-move".into(),
+            uid: "move".into(),
+            ..Default::default()
+        };
+        let container_at = |cgroups_path: &str| nri::api::Container {
+            id: "c-move".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: cgroups_path.into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let before = container_at("/cg/burstable/c-move");
+        let after = container_at("/cg/guaranteed/c-move");
+        let emptied = container_at("/cg/besteffort/c-move");
+        let mut pid_src = MockCgroupPidSource::new();
+        pid_src.set_pids(
+            nri::compute_full_cgroup_path(&before, Some(&pod)),
+            vec![1111],
+        );
+        pid_src.set_pids(
+            nri::compute_full_cgroup_path(&after, Some(&pod)),
+            vec![2222],
+        );
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            Arc::new(pid_src),
+        );
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        for (event, container) in [
+            (Event::RUN_POD_SANDBOX, None),
+            (Event::START_CONTAINER, Some(before.clone())),
+        ] {
+            plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: event.into(),
+                        pod: protobuf::MessageField::some(pod.clone()),
+                        container: container.into(),
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        let _ = rx.recv().await.unwrap();
+        match rx.recv().await.unwrap() {
+            PodResctrlEvent::AddOrUpdate(a) => assert_eq!(a.reconciled_containers, 1),
+            ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+        }
+
+        let update = |container: &nri::api::Container| UpdateContainerRequest {
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::some(container.clone()),
+            ..Default::default()
+        };
+        let group = "/sys/fs/resctrl/mon_groups/pod_move";
+
+        // Moved to a new cgroup: its tasks are reassigned; counts are unchanged
+        plugin.update_container(&ctx, update(&after)).await.unwrap();
+        assert!(plugin
+            .resctrl
+            .list_group_tasks(group)
+            .unwrap()
+            .contains(&2222));
+        assert!(rx.try_recv().is_err());
+        {
+            let st = plugin.state.lock().unwrap();
+            let cs = st.containers.get("c-move").unwrap();
+            assert_eq!(
+                cs.cgroup_path,
+                nri::compute_full_cgroup_path(&after, Some(&pod))
+            );
+            assert_eq!(cs.state, ContainerSyncState::Reconciled);
+        }
+
+        // Updates that keep the path are ignored
+        plugin.update_container(&ctx, update(&after)).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // Moved to a cgroup without PIDs: no longer reconciled
+        plugin
+            .update_container(&ctx, update(&emptied))
+            .await
+            .unwrap();
+        match rx.recv().await.unwrap() {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 0);
+            }
+            ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monitoring_annotation_toggled_by_update_pod_sandbox() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl/mon_groups"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        let key = "resctrl.unvariance.io/monitoring";
+        let mut pod = nri::api::PodSandbox {
+            id: "sb-toggle".into(),
+            uid: "toggle".into(),
+            ..Default::default()
+        };
+        pod.annotations.insert(key.into(), "false".into());
+        let ctr = nri::api::Container {
+            id: "c-toggle".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: "/cg/x:cri-containerd:c-toggle".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut pid_src = MockCgroupPidSource::new();
+        pid_src.set_pids(nri::compute_full_cgroup_path(&ctr, Some(&pod)), vec![4242]);
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let cfg = ResctrlPluginConfig {
+            monitoring_annotation: Some(key.into()),
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(pid_src));
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let group = std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_toggle");
+
+        // The annotation subscribes to pod updates
+        let resp = plugin
+            .configure(&ctx, ConfigureRequest::default())
+            .await
+            .unwrap();
+        assert!(EventMask::from_raw(resp.events).is_set(Event::UPDATE_POD_SANDBOX));
+
+        // Disabled at start: neither the pod nor its container is tracked
+        for (event, container) in [
+            (Event::RUN_POD_SANDBOX, None),
+            (Event::START_CONTAINER, Some(ctr.clone())),
+        ] {
+            plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: event.into(),
+                        pod: protobuf::MessageField::some(pod.clone()),
+                        container: container.into(),
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+        assert!(!fs.exists(group));
+
+        // Enabling creates the group and reconciles the running container
+        pod.annotations.insert(key.into(), "true".into());
+        plugin
+            .update_pod_sandbox(
+                &ctx,
+                UpdatePodSandboxRequest {
+                    pod: protobuf::MessageField::some(pod.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(fs.exists(group));
+        match rx.recv().await.unwrap() {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.pod_uid, "toggle");
+                assert_eq!(a.total_containers, 0);
+            }
+            ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+        }
+        match rx.recv().await.unwrap() {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 1);
+            }
+            ev => panic!("Expected AddOrUpdate event, got: {:?}", ev),
+        }
+        let tasks = plugin
+            .resctrl
+            .list_group_tasks(group.to_str().unwrap())
+            .unwrap();
+        assert!(tasks.contains(&4242));
+
+        // Disabling deletes the group and stops tracking the pod
+        pod.annotations.insert(key.into(), "disabled".into());
+        plugin
+            .update_pod_sandbox(
+                &ctx,
+                UpdatePodSandboxRequest {
+                    pod: protobuf::MessageField::some(pod.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            PodResctrlEvent::Removed(r) if r.pod_uid == "toggle"
+        ));
+        assert!(!fs.exists(group));
+        assert!(plugin.group_for_container("c-toggle").is_none());
+        let st = plugin.state.lock().unwrap();
+        assert!(st.pods.is_empty());
+        assert!(st.containers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_preexisting_pod_removal_cleans_up() {
+        // Setup resctrl root and plugin
+        let fs = MockFs::default();
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
+
+        // Define a preexisting pod and synchronize with it present
+        let pod = nri::api::PodSandbox {
+            id: "sb-preexist".into(),
+            uid: "u-pre".into(),
+            ..Default::default()
+        };
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let _ = Plugin::synchronize(
+            &plugin,
+            &ctx,
+            SynchronizeRequest {
+                pods: vec![pod.clone()],
+                containers: vec![],
+                more: false,
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        // Drain AddOrUpdate from synchronize
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(200), rx.recv())
+            .await
+            .ok();
+
+        // Verify group exists
+        assert!(fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u-pre")));
+
+        // Now remove the pod and expect Removed + cleanup
+        let _ = Plugin::state_change(
+            &plugin,
+            &ctx,
+            StateChangeEvent {
+                event: Event::REMOVE_POD_SANDBOX.into(),
+                pod: protobuf::MessageField::some(pod.clone()),
+                container: protobuf::MessageField::none(),
+                special_fields: SpecialFields::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+        // Expect a Removed event
+        use tokio::time::{timeout, Duration};
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::Removed(r) => assert_eq!(r.pod_uid, "u-pre"),
+            _ => panic!("expected Removed event"),
+        }
+        // Group cleaned up
+        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u-pre")));
+    }
+
+    #[tokio::test]
+    async fn test_capacity_error_emits_failed_and_retry_group_creation_transitions() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        use tokio::time::{timeout, Duration};
+
+        let fs = MockFs::new();
+        // Ensure resctrl root exists
+        fs.add_dir(std::path::Path::new("/sys"));
+        fs.add_dir(std::path::Path::new("/sys/fs"));
+        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
+
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+
+        let mock_pid_src = Arc::new(MockCgroupPidSource::new());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            mock_pid_src.clone(),
+        );
+
+        // Configure ENOSPC for the pod's group dir
+        let group_path = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_u1");
+        fs.set_nospace_dir(&group_path);
+
+        // Define pod and container
+        let pod = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
+            ..Default::default()
+        };
+        let linux = nri::api::LinuxContainer {
+            cgroups_path: "/cg/runtime:cri-containerd:c1".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "c1".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(linux),
+            ..Default::default()
+        };
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+
+        // Send RUN_POD_SANDBOX; expect Failed event
+        let state_req = StateChangeEvent {
+            event: Event::RUN_POD_SANDBOX.into(),
+            pod: protobuf::MessageField::some(pod.clone()),
+            container: protobuf::MessageField::none(),
+            special_fields: SpecialFields::default(),
+        };
+        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
+
+        // Receive Failed AddOrUpdate (0/0)
+        let ev = timeout(Duration::from_millis(100), rx.recv())
             .await
             .expect("event")
             .expect("ev");
         match ev {
             PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.pod_uid, "u123");
-                assert_eq!(a.total_containers, 1);
-                assert_eq!(a.reconciled_containers, 1);
+                assert_eq!(a.pod_uid, "u1");
+                assert!(matches!(a.group_state, ResctrlGroupState::Failed));
+                assert_eq!(a.total_containers, 0);
+                assert_eq!(a.reconciled_containers, 0);
             }
-            _ => panic!("unexpected event type"),
+            _ => panic!("unexpected event"),
         }
 
-        // Now add another container for the existing pod and expect updated counts
+        // Add a container while pod Failed → expect counts 1/0
         let _ = Plugin::state_change(
             &plugin,
             &ctx,
             StateChangeEvent {
                 event: Event::START_CONTAINER.into(),
                 pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::some(second_container.clone()),
+                container: protobuf::MessageField::some(container.clone()),
                 special_fields: SpecialFields::default(),
             },
         )
         .await
         .unwrap();
+        // Expect update with counts 1/0
+        let ev = timeout(Duration::from_millis(100), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 0);
+                assert!(matches!(a.group_state, ResctrlGroupState::Failed));
+            }
+            _ => panic!("unexpected event"),
+        }
 
-        let ev = timeout(Duration::from_millis(200), rx.recv())
+        // First retry: still ENOSPC → expect Error::Capacity and no event
+        let err = plugin.retry_group_creation("u1").unwrap_err();
+        match err {
+            PluginError::Resctrl(resctrl::Error::Capacity { .. }) => {}
+            other => panic!("expected capacity error, got: {:?}", other),
+        }
+        assert!(
+            timeout(Duration::from_millis(50), rx.recv())
+                .await
+                .ok()
+                .is_none(),
+            "no duplicate events expected"
+        );
+
+        // Clear ENOSPC and retry again → should transition to Exists and emit event with counts 1/0
+        fs.clear_nospace_dir(&group_path);
+        let st = plugin.retry_group_creation("u1").expect("retry ok");
+        match st {
+            ResctrlGroupState::Exists(p) => {
+                assert!(p.ends_with("/sys/fs/resctrl/mon_groups/pod_u1"))
+            }
+            _ => panic!("expected Exists"),
+        }
+        let ev = timeout(Duration::from_millis(100), rx.recv())
             .await
             .expect("event")
             .expect("ev");
         match ev {
             PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.pod_uid, "u123");
-                assert_eq!(a.total_containers, 2);
-                assert_eq!(a.reconciled_containers, 2);
+                assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 0);
             }
-            _ => panic!("unexpected event type"),
+            _ => panic!("unexpected event"),
         }
+    }
 
-        // Verify tasks file now includes the PIDs from both containers
-        let group_path = "/sys/fs/resctrl/mon_groups/pod_u123";
-        let pids = plugin
-            .resctrl
-            .list_group_tasks(group_path)
-            .expect("list tasks");
-        assert!(pids.contains(&1));
-        assert!(pids.contains(&2));
-        assert!(pids.contains(&3));
-        assert!(pids.contains(&4));
+    /// MockFs whose `create_dir` of `gate`, once armed, blocks until released
+    #[derive(Clone)]
+    struct GatedFs {
+        inner: MockFs,
+        gate: std::path::PathBuf,
+        armed: Arc<AtomicBool>,
+        entered: Arc<std::sync::Barrier>,
+        release: Arc<std::sync::Barrier>,
     }
 
-    #[tokio::test]
-    async fn test_duplicate_container_events_do_not_change_counts() {
-        use crate::pid_source::test_support::MockCgroupPidSource;
-        use tokio::time::{timeout, Duration};
+    impl FsProvider for GatedFs {
+        fn exists(&self, p: &std::path::Path) -> bool {
+            self.inner.exists(p)
+        }
+        fn create_dir(&self, p: &std::path::Path) -> std::io::Result<()> {
+            if p == self.gate && self.armed.swap(false, Ordering::SeqCst) {
+                self.entered.wait();
+                self.release.wait();
+            }
+            self.inner.create_dir(p)
+        }
+        fn remove_dir(&self, p: &std::path::Path) -> std::io::Result<()> {
+            self.inner.remove_dir(p)
+        }
+        fn write_str(&self, p: &std::path::Path, data: &str) -> std::io::Result<()> {
+            self.inner.write_str(p, data)
+        }
+        fn read_to_string(&self, p: &std::path::Path) -> std::io::Result<String> {
+            self.inner.read_to_string(p)
+        }
+        fn check_can_open_for_write(&self, p: &std::path::Path) -> std::io::Result<()> {
+            self.inner.check_can_open_for_write(p)
+        }
+        fn read_child_dirs(&self, p: &std::path::Path) -> std::io::Result<Vec<String>> {
+            self.inner.read_child_dirs(p)
+        }
+        fn mount_resctrl(&self, target: &std::path::Path) -> std::io::Result<()> {
+            self.inner.mount_resctrl(target)
+        }
+    }
 
-        let fs = MockFs::default();
+    #[tokio::test]
+    async fn test_removal_during_group_retry_leaves_no_group() {
+        let fs = MockFs::new();
         fs.add_dir(std::path::Path::new("/sys"));
         fs.add_dir(std::path::Path::new("/sys/fs"));
         fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
-
-        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
-        let mut mock_pid_src = MockCgroupPidSource::new();
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
-
-        let pod = nri::api::PodSandbox {
-            id: "pod-dup".into(),
-            uid: "uid-dup".into(),
-            ..Default::default()
-        };
-        let linux = nri::api::LinuxContainer {
-            cgroups_path: "/cg/dup".into(),
-            ..Default::default()
-        };
-        let container = nri::api::Container {
-            id: "ctr-dup".into(),
-            pod_sandbox_id: pod.id.clone(),
-            linux: protobuf::MessageField::some(linux),
-            ..Default::default()
+        let group_path = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_u1");
+        let gated = GatedFs {
+            inner: fs.clone(),
+            gate: group_path.clone(),
+            armed: Arc::new(AtomicBool::new(false)),
+            entered: Arc::new(std::sync::Barrier::new(2)),
+            release: Arc::new(std::sync::Barrier::new(2)),
         };
 
-        let full_path = nri::compute_full_cgroup_path(&container, Some(&pod));
-        mock_pid_src.set_pids(full_path, vec![4242]);
-
-        let plugin = ResctrlPlugin::with_pid_source(
-            ResctrlPluginConfig::default(),
-            rc,
-            tx,
-            Arc::new(mock_pid_src),
-        );
+        let rc = Resctrl::with_provider(gated.clone(), resctrl::Config::default());
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
 
+        // The pod starts out Failed
+        fs.set_nospace_dir(&group_path);
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-
-        // Register pod once → expect initial AddOrUpdate with counts 0/0
+        let pod = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
+            ..Default::default()
+        };
         let _ = plugin
             .state_change(
                 &ctx,
                 StateChangeEvent {
                     event: Event::RUN_POD_SANDBOX.into(),
-                    pod: protobuf::MessageField::some(pod.clone()),
+                    pod: protobuf::MessageField::some(pod),
                     container: protobuf::MessageField::none(),
-                    special_fields: protobuf::SpecialFields::default(),
+                    special_fields: SpecialFields::default(),
                 },
             )
             .await
             .unwrap();
-
-        let ev = timeout(Duration::from_millis(200), rx.recv())
-            .await
-            .expect("initial event")
-            .expect("event value");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(add) => {
-                assert_eq!(add.total_containers, 0);
-                assert_eq!(add.reconciled_containers, 0);
+        match rx.try_recv().expect("event") {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert!(matches!(a.group_state, ResctrlGroupState::Failed))
             }
-            other => panic!("unexpected event: {:?}", other),
+            _ => panic!("unexpected event"),
         }
 
-        let start_req = StateChangeEvent {
-            event: Event::START_CONTAINER.into(),
-            pod: protobuf::MessageField::some(pod.clone()),
-            container: protobuf::MessageField::some(container.clone()),
-            special_fields: protobuf::SpecialFields::default(),
-        };
-        let _ = Plugin::state_change(&plugin, &ctx, start_req.clone())
-            .await
-            .unwrap();
+        // Remove the pod while a retry is between its state read and creating
+        // the group
+        fs.clear_nospace_dir(&group_path);
+        gated.armed.store(true, Ordering::SeqCst);
+        let retry = std::thread::scope(|s| {
+            let retry = s.spawn(|| plugin.retry_group_creation("u1"));
+            gated.entered.wait();
+            let removal = s.spawn(|| plugin.remove_pod("u1", false));
+            // Give the removal time to reach the lock before the retry continues
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!removal.is_finished(), "removal must wait for the retry");
+            gated.release.wait();
+            removal.join().unwrap();
+            retry.join().unwrap()
+        });
 
-        let ev = timeout(Duration::from_millis(200), rx.recv())
-            .await
-            .expect("event after first container")
-            .expect("event value");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(add) => {
-                assert_eq!(add.total_containers, 1);
-                assert_eq!(add.reconciled_containers, 1);
+        // The retry completed first, then the removal deleted its group
+        assert!(matches!(retry, Ok(ResctrlGroupState::Exists(_))));
+        assert!(!fs.dir_exists(&group_path), "group leaked");
+        assert!(plugin.state.lock().unwrap().pods.is_empty());
+        match rx.try_recv().expect("event") {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)))
             }
-            other => panic!("unexpected event: {:?}", other),
-        }
-
-        // Duplicate START_CONTAINER → should not emit another event
-        let _ = Plugin::state_change(&plugin, &ctx, start_req.clone())
-            .await
-            .unwrap();
-        match timeout(Duration::from_millis(100), rx.recv()).await {
-            Ok(Some(ev)) => panic!("unexpected event for duplicate container: {:?}", ev),
-            Ok(None) => panic!("event channel closed unexpectedly"),
-            Err(_) => {}
-        }
-
-        // UpdateContainer for same container should not emit anything either
-        let update_req = UpdateContainerRequest {
-            pod: protobuf::MessageField::some(pod.clone()),
-            container: protobuf::MessageField::some(container.clone()),
-            linux_resources: protobuf::MessageField::none(),
-            special_fields: protobuf::SpecialFields::default(),
-        };
-        let _ = Plugin::update_container(&plugin, &ctx, update_req)
-            .await
-            .unwrap();
-        match timeout(Duration::from_millis(100), rx.recv()).await {
-            Ok(Some(ev)) => panic!("unexpected event for UpdateContainer: {:?}", ev),
-            Ok(None) => panic!("event channel closed unexpectedly"),
-            Err(_) => {}
+            _ => panic!("unexpected event"),
         }
-
-        // Internal counters remain unchanged at 1/1
-        let st = plugin.state.lock().unwrap();
-        let pod_state = st.pods.get(&pod.uid).expect("pod state present");
-        assert_eq!(pod_state.total_containers, 1);
-        assert_eq!(pod_state.reconciled_containers, 1);
+        assert!(matches!(
+            rx.try_recv().expect("event"),
+            PodResctrlEvent::Removed(_)
+        ));
+
+        // A retry after the removal finds no pod and creates nothing
+        assert!(matches!(
+            plugin.retry_group_creation("u1"),
+            Err(PluginError::PodNotFound)
+        ));
+        assert!(!fs.dir_exists(&group_path));
     }
 
     #[tokio::test]
-    async fn test_run_pod_sandbox_creates_group_and_emits_event() {
+    async fn test_retry_container_reconcile_improves_counts() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        use tokio::time::{timeout, Duration};
+
         let fs = MockFs::new();
-        // Ensure resctrl root exists
         fs.add_dir(std::path::Path::new("/sys"));
         fs.add_dir(std::path::Path::new("/sys/fs"));
         fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
 
         let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
 
-        use crate::pid_source::test_support::MockCgroupPidSource;
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
+        let gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_u1");
+        fs.add_dir(&gp);
+        fs.add_file(&gp.join("tasks"), "");
 
-        // Define a pod sandbox and a container up-front so we can seed PIDs
-        // into the mock pid source for the full cgroup path
         let pod = nri::api::PodSandbox {
-            id: "pod-sb-run-test".into(),
-            uid: "u789".into(),
+            id: "sb1".into(),
+            uid: "u1".into(),
             ..Default::default()
         };
         let linux = nri::api::LinuxContainer {
             cgroups_path: "/cg/x:cri-containerd:c1".into(),
             ..Default::default()
         };
-        let ctr = nri::api::Container {
+        let container = nri::api::Container {
             id: "c1".into(),
             pod_sandbox_id: pod.id.clone(),
             linux: protobuf::MessageField::some(linux),
             ..Default::default()
         };
-        let full_cg = nri::compute_full_cgroup_path(&ctr, Some(&pod));
+        let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod));
 
-        // Seed mock PIDs for this container
-        let mut pid_src = Arc::new(MockCgroupPidSource::new());
-        Arc::get_mut(&mut pid_src)
+        let mut mock_pid_src = Arc::new(MockCgroupPidSource::new());
+        Arc::get_mut(&mut mock_pid_src)
             .unwrap()
-            .set_pids(full_cg, vec![7777]);
+            .set_pids(full_cg.clone(), vec![101, 102]);
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            mock_pid_src.clone(),
+        );
+        // Initially PIDs unassignable (ESRCH)
+        fs.set_missing_pid(101);
+        fs.set_missing_pid(102);
 
-        let plugin =
-            ResctrlPlugin::with_pid_source(ResctrlPluginConfig::default(), rc, tx, pid_src);
-        // Send RUN_POD_SANDBOX via state_change
+        // Run pod + add container → expect counts 1/0
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
@@ -1161,214 +4593,243 @@ mod tests {
             special_fields: SpecialFields::default(),
         };
         let _ = plugin.state_change(&ctx, state_req).await.unwrap();
-
-        // Should receive an AddOrUpdate event with existing group state
-        use tokio::time::{timeout, Duration};
-        timeout(Duration::from_millis(100), async {
-            if let Some(ev) = rx.recv().await {
-                match ev {
-                    PodResctrlEvent::AddOrUpdate(a) => {
-                        assert_eq!(a.pod_uid, "u789");
-                        assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
-                    }
-                    _ => panic!("Expected AddOrUpdate event, got: {:?}", ev),
-                }
-            } else {
-                panic!("Expected AddOrUpdate event, got nothing");
-            }
-        })
-        .await
-        .expect("Should receive event within timeout");
-
-        // Verify the directory for the resctrl group was created
-        assert!(fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u789")));
-
-        // After pod exists, add a container for it and expect reconcile to complete
         let _ = Plugin::state_change(
             &plugin,
             &ctx,
             StateChangeEvent {
                 event: Event::START_CONTAINER.into(),
                 pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::some(ctr.clone()),
+                container: protobuf::MessageField::some(container.clone()),
                 special_fields: SpecialFields::default(),
             },
         )
         .await
         .unwrap();
 
-        // Expect counts 1/1
-        timeout(Duration::from_millis(200), async {
-            if let Some(PodResctrlEvent::AddOrUpdate(a)) = rx.recv().await {
+        // Drain two events (pod created Exists and container accounted)
+        let _ = timeout(Duration::from_millis(100), rx.recv()).await; // pod exists
+        let ev = timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev"); // container accounted
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.reconciled_containers, 0);
+                assert_eq!(a.unreconciled_pids, 2);
+            }
+            _ => panic!("unexpected event"),
+        }
+
+        // Make current PIDs assignable by clearing missing flags
+        fs.clear_missing_pid(101);
+        fs.clear_missing_pid(102);
+
+        // Retry just this container → expect transition to Reconciled and one event with counts 1/1
+        let st = plugin.retry_container_reconcile("c1").expect("retry ok");
+        assert_eq!(st, ContainerSyncState::Reconciled);
+        // Drain the event emitted for the transition to Reconciled (counts 1/1)
+        let ev = timeout(Duration::from_millis(100), rx.recv())
+            .await
+            .expect("event")
+            .expect("ev");
+        match ev {
+            PodResctrlEvent::AddOrUpdate(a) => {
                 assert_eq!(a.total_containers, 1);
                 assert_eq!(a.reconciled_containers, 1);
+                assert_eq!(a.unreconciled_pids, 0);
             }
-        })
-        .await
-        .ok();
+            _ => panic!("unexpected event"),
+        }
 
-        // Verify the tasks file includes the seeded PID
+        // Verify resctrl tasks now include the desired PIDs (101, 102)
         let pids = plugin
             .resctrl
-            .list_group_tasks("/sys/fs/resctrl/mon_groups/pod_u789")
+            .list_group_tasks(gp.to_str().unwrap())
             .expect("list tasks");
-        assert!(pids.contains(&7777));
-
-        // Remove the container → expect counts 0/0
-        let _ = Plugin::state_change(
-            &plugin,
-            &ctx,
-            StateChangeEvent {
-                event: Event::REMOVE_CONTAINER.into(),
-                pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::some(ctr.clone()),
-                special_fields: SpecialFields::default(),
-            },
-        )
-        .await
-        .unwrap();
-
-        timeout(Duration::from_millis(200), async {
-            if let Some(PodResctrlEvent::AddOrUpdate(a)) = rx.recv().await {
-                assert_eq!(a.total_containers, 0);
-                assert_eq!(a.reconciled_containers, 0);
-            }
-        })
-        .await
-        .ok();
-
-        // Now remove the pod and verify removal event + directory deletion
-        let state_req = StateChangeEvent {
-            event: Event::REMOVE_POD_SANDBOX.into(),
-            pod: protobuf::MessageField::some(pod.clone()),
-            container: protobuf::MessageField::none(),
-            special_fields: SpecialFields::default(),
-        };
-        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
+        assert!(pids.contains(&101) && pids.contains(&102));
 
-        timeout(Duration::from_millis(100), async {
-            if let Some(ev) = rx.recv().await {
-                match ev {
-                    PodResctrlEvent::Removed(r) => {
-                        assert_eq!(r.pod_uid, "u789");
-                    }
-                    _ => panic!("Expected Removed event, got: {:?}", ev),
-                }
-            } else {
-                panic!("Expected Removed event, got nothing");
-            }
-        })
-        .await
-        .expect("Should receive removal event within timeout");
+        // Validate internal state updated and counts improved
+        {
+            let inner = plugin.state.lock().unwrap();
+            let ps = inner.pods.get("u1").expect("pod state");
+            assert_eq!(ps.total_containers, 1);
+            assert_eq!(ps.reconciled_containers, 1);
+            assert_eq!(ps.unreconciled_pids, 0);
+            let cs = inner.containers.get("c1").expect("container");
+            assert_eq!(cs.state, ContainerSyncState::Reconciled);
+            assert_eq!(cs.missing_pids, 0);
+        }
+        // Re-run should not change counts further
+        let _ = plugin.retry_container_reconcile("c1").expect("ok");
 
-        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u789")));
+        // Ensure no further events are emitted after the second reconcile
+        assert!(
+            timeout(Duration::from_millis(50), rx.recv())
+                .await
+                .ok()
+                .is_none(),
+            "no extra event expected after second reconcile"
+        );
     }
 
     #[tokio::test]
-    async fn test_preexisting_pod_removal_cleans_up() {
-        // Setup resctrl root and plugin
-        let fs = MockFs::default();
+    async fn test_retry_all_once_early_stop_on_capacity_and_reconcile_others() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
+        use tokio::time::{timeout, Duration};
+
+        let fs = MockFs::new();
         fs.add_dir(std::path::Path::new("/sys"));
         fs.add_dir(std::path::Path::new("/sys/fs"));
         fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
         let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(8);
-        let plugin = ResctrlPlugin::with_resctrl(ResctrlPluginConfig::default(), rc, tx);
-
-        // Define a preexisting pod and synchronize with it present
-        let pod = nri::api::PodSandbox {
-            id: "sb-preexist".into(),
-            uid: "u-pre".into(),
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(32);
+        let pod_a = nri::api::PodSandbox {
+            id: "sbA".into(),
+            uid: "uA".into(),
+            ..Default::default()
+        };
+        let pod_b = nri::api::PodSandbox {
+            id: "sbB".into(),
+            uid: "uB".into(),
+            ..Default::default()
+        };
+        let linux_b = nri::api::LinuxContainer {
+            cgroups_path: "/cg/b:cri-containerd:b1".into(),
+            ..Default::default()
+        };
+        let ctr_b = nri::api::Container {
+            id: "b1".into(),
+            pod_sandbox_id: pod_b.id.clone(),
+            linux: protobuf::MessageField::some(linux_b),
             ..Default::default()
         };
+
+        let mut mock_pid_src = Arc::new(MockCgroupPidSource::new());
+        let cg_b = nri::compute_full_cgroup_path(&ctr_b, Some(&pod_b));
+        Arc::get_mut(&mut mock_pid_src)
+            .unwrap()
+            .set_pids(cg_b.clone(), vec![222, 223]);
+
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            mock_pid_src.clone(),
+        );
+
+        // uA: Failed pod due to ENOSPC
+        let u_a_gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uA");
+        fs.set_nospace_dir(&u_a_gp);
+        // uB: Existing group and one Partial container
+        let u_b_gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uB");
+        fs.add_dir(&u_b_gp);
+        fs.add_file(&u_b_gp.join("tasks"), "");
+        fs.set_missing_pid(222);
+        fs.set_missing_pid(223);
+
+        // Feed state
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-        let _ = Plugin::synchronize(
-            &plugin,
-            &ctx,
-            SynchronizeRequest {
-                pods: vec![pod.clone()],
-                containers: vec![],
-                more: false,
-                special_fields: SpecialFields::default(),
-            },
-        )
-        .await
-        .unwrap();
-
-        // Drain AddOrUpdate from synchronize
-        let _ = tokio::time::timeout(std::time::Duration::from_millis(200), rx.recv())
+        let _ = plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod_a.clone()),
+                    container: protobuf::MessageField::none(),
+                    special_fields: SpecialFields::default(),
+                },
+            )
             .await
-            .ok();
-
-        // Verify group exists
-        assert!(fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u-pre")));
-
-        // Now remove the pod and expect Removed + cleanup
+            .unwrap();
+        let _ = plugin
+            .state_change(
+                &ctx,
+                StateChangeEvent {
+                    event: Event::RUN_POD_SANDBOX.into(),
+                    pod: protobuf::MessageField::some(pod_b.clone()),
+                    container: protobuf::MessageField::none(),
+                    special_fields: SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
         let _ = Plugin::state_change(
             &plugin,
             &ctx,
             StateChangeEvent {
-                event: Event::REMOVE_POD_SANDBOX.into(),
-                pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::none(),
+                event: Event::START_CONTAINER.into(),
+                pod: protobuf::MessageField::some(pod_b.clone()),
+                container: protobuf::MessageField::some(ctr_b.clone()),
                 special_fields: SpecialFields::default(),
             },
         )
         .await
         .unwrap();
 
-        // Expect a Removed event
-        use tokio::time::{timeout, Duration};
-        let ev = timeout(Duration::from_millis(200), rx.recv())
+        // Drain initial events
+        let _ = timeout(Duration::from_millis(100), rx.recv())
+            .await
+            .expect("no-timeout")
+            .expect("received event"); // uA failed
+        let _ = timeout(Duration::from_millis(100), rx.recv())
+            .await
+            .expect("no-timeout")
+            .expect("received event"); // uB exists
+        let ev = timeout(Duration::from_millis(100), rx.recv())
             .await
-            .expect("event")
-            .expect("ev");
+            .expect("no-timeout")
+            .expect("received event"); // uB counts 1/0
         match ev {
-            PodResctrlEvent::Removed(r) => assert_eq!(r.pod_uid, "u-pre"),
-            _ => panic!("expected Removed event"),
+            PodResctrlEvent::AddOrUpdate(a) => {
+                assert_eq!(a.pod_uid, "uB");
+                assert_eq!(a.total_containers, 1);
+                assert_eq!(a.reconciled_containers, 0);
+            }
+            _ => panic!("unexpected event"),
+        }
+
+        // Make current PIDs assignable now
+        fs.clear_missing_pid(222);
+        fs.clear_missing_pid(223);
+
+        // Run retry_all_once: should attempt uA once and stop on capacity, then reconcile uB
+        let before = fs.mkdir_count(&u_a_gp);
+        plugin.retry_all_once().expect("retry all ok");
+        // mkdir called exactly once for uA during this pass
+        let after = fs.mkdir_count(&u_a_gp);
+        assert_eq!(
+            after.saturating_sub(before),
+            1,
+            "expected single create_dir attempt in this pass"
+        );
+
+        // Validate internal state improved for uB
+        {
+            let inner = plugin.state.lock().unwrap();
+            let ps = inner.pods.get("uB").expect("pod uB");
+            assert_eq!(ps.reconciled_containers, 1);
         }
-        // Group cleaned up
-        assert!(!fs.exists(std::path::Path::new("/sys/fs/resctrl/mon_groups/pod_u-pre")));
     }
 
     #[tokio::test]
-    async fn test_capacity_error_emits_failed_and_retry_group_creation_transitions() {
+    async fn test_cgroup_permission_denied_is_not_retried() {
         use crate::pid_source::test_support::MockCgroupPidSource;
         use tokio::time::{timeout, Duration};
 
-        let fs = MockFs::new();
-        // Ensure resctrl root exists
-        fs.add_dir(std::path::Path::new("/sys"));
-        fs.add_dir(std::path::Path::new("/sys/fs"));
-        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
-
+        let fs = MockFs::with_premounted_resctrl();
         let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
 
-        let mock_pid_src = Arc::new(MockCgroupPidSource::new());
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
-        let plugin = ResctrlPlugin::with_pid_source(
-            ResctrlPluginConfig::default(),
-            rc,
-            tx,
-            mock_pid_src.clone(),
-        );
-
-        // Configure ENOSPC for the pod's group dir
-        let group_path = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_u1");
-        fs.set_nospace_dir(&group_path);
-
-        // Define pod and container
         let pod = nri::api::PodSandbox {
             id: "sb1".into(),
             uid: "u1".into(),
             ..Default::default()
         };
         let linux = nri::api::LinuxContainer {
-            cgroups_path: "/cg/runtime:cri-containerd:c1".into(),
+            cgroups_path: "/cg/x:cri-containerd:c1".into(),
             ..Default::default()
         };
         let container = nri::api::Container {
@@ -1377,51 +4838,52 @@ mod tests {
             linux: protobuf::MessageField::some(linux),
             ..Default::default()
         };
+        let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod));
+
+        // Reading cgroup.procs for the container fails with EACCES
+        let mut mock_pid_src = Arc::new(MockCgroupPidSource::new());
+        Arc::get_mut(&mut mock_pid_src)
+            .unwrap()
+            .set_permission_denied(full_cg);
+
+        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let plugin = ResctrlPlugin::with_pid_source(
+            ResctrlPluginConfig::default(),
+            rc,
+            tx,
+            mock_pid_src.clone(),
+        );
 
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-
-        // Send RUN_POD_SANDBOX; expect Failed event
-        let state_req = StateChangeEvent {
-            event: Event::RUN_POD_SANDBOX.into(),
-            pod: protobuf::MessageField::some(pod.clone()),
-            container: protobuf::MessageField::none(),
-            special_fields: SpecialFields::default(),
-        };
-        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
-
-        // Receive Failed AddOrUpdate (0/0)
-        let ev = timeout(Duration::from_millis(100), rx.recv())
-            .await
-            .expect("event")
-            .expect("ev");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.pod_uid, "u1");
-                assert!(matches!(a.group_state, ResctrlGroupState::Failed));
-                assert_eq!(a.total_containers, 0);
-                assert_eq!(a.reconciled_containers, 0);
-            }
-            _ => panic!("unexpected event"),
+        for (event, with_container) in [
+            (Event::RUN_POD_SANDBOX, false),
+            (Event::START_CONTAINER, true),
+        ] {
+            let container_field = if with_container {
+                protobuf::MessageField::some(container.clone())
+            } else {
+                protobuf::MessageField::none()
+            };
+            let _ = plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: event.into(),
+                        pod: protobuf::MessageField::some(pod.clone()),
+                        container: container_field,
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
         }
 
-        // Add a container while pod Failed → expect counts 1/0
-        let _ = Plugin::state_change(
-            &plugin,
-            &ctx,
-            StateChangeEvent {
-                event: Event::START_CONTAINER.into(),
-                pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::some(container.clone()),
-                special_fields: SpecialFields::default(),
-            },
-        )
-        .await
-        .unwrap();
-        // Expect update with counts 1/0
+        // Pod created, then container accounted but not reconciled (1/0)
+        let _ = timeout(Duration::from_millis(100), rx.recv()).await;
         let ev = timeout(Duration::from_millis(100), rx.recv())
             .await
             .expect("event")
@@ -1430,58 +4892,294 @@ mod tests {
             PodResctrlEvent::AddOrUpdate(a) => {
                 assert_eq!(a.total_containers, 1);
                 assert_eq!(a.reconciled_containers, 0);
-                assert!(matches!(a.group_state, ResctrlGroupState::Failed));
             }
             _ => panic!("unexpected event"),
         }
 
-        // First retry: still ENOSPC → expect Error::Capacity and no event
-        let err = plugin.retry_group_creation("u1").unwrap_err();
-        match err {
-            PluginError::Resctrl(resctrl::Error::Capacity { .. }) => {}
-            other => panic!("expected capacity error, got: {:?}", other),
+        // Container is in the terminal state rather than Partial
+        {
+            let inner = plugin.state.lock().unwrap();
+            let cs = inner.containers.get("c1").expect("container");
+            assert_eq!(cs.state, ContainerSyncState::PermissionDenied);
+        }
+
+        // Retry loops skip it: no state change and no events
+        for _ in 0..3 {
+            plugin.retry_all_once().expect("retry_all ok");
         }
+        let st = plugin.retry_container_reconcile("c1").expect("retry ok");
+        assert_eq!(st, ContainerSyncState::PermissionDenied);
         assert!(
             timeout(Duration::from_millis(50), rx.recv())
                 .await
                 .ok()
                 .is_none(),
-            "no duplicate events expected"
+            "no events expected for a permission-denied container"
         );
+    }
 
-        // Clear ENOSPC and retry again → should transition to Exists and emit event with counts 1/0
-        fs.clear_nospace_dir(&group_path);
-        let st = plugin.retry_group_creation("u1").expect("retry ok");
-        match st {
-            ResctrlGroupState::Exists(p) => {
-                assert!(p.ends_with("/sys/fs/resctrl/mon_groups/pod_u1"))
+    // Reports one more PID on every read of a "churn" cgroup, like a container
+    // that keeps forking; other cgroups have a single stable PID
+    struct ChurnPidSource {
+        next_pid: AtomicUsize,
+    }
+
+    impl crate::pid_source::CgroupPidSource for ChurnPidSource {
+        fn pids_for_path(&self, cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+            if !cgroup_path.contains("churn") {
+                return Ok(vec![100]);
             }
-            _ => panic!("expected Exists"),
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            let last = self.next_pid.fetch_add(1, Ordering::SeqCst) as i32;
+            Ok((1000..=1000 + last).collect())
         }
-        let ev = timeout(Duration::from_millis(100), rx.recv())
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_time_budget_leaves_churning_container_partial() {
+        let fs = MockFs::with_premounted_resctrl();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let pid_src = Arc::new(ChurnPidSource {
+            next_pid: AtomicUsize::new(0),
+        });
+
+        let mut pods = Vec::new();
+        let mut containers = Vec::new();
+        for name in ["churn", "calm"] {
+            let pod = nri::api::PodSandbox {
+                id: format!("sb-{}", name),
+                uid: name.into(),
+                ..Default::default()
+            };
+            containers.push(nri::api::Container {
+                id: format!("c-{}", name),
+                pod_sandbox_id: pod.id.clone(),
+                linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                    cgroups_path: format!("/cg/{}/c", name),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+            pods.push(pod);
+        }
+
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(64);
+        let cfg = ResctrlPluginConfig {
+            max_reconcile_passes: 100_000,
+            max_reconcile_time: Some(std::time::Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, pid_src.clone());
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let start = std::time::Instant::now();
+        let _ = plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods,
+                    containers,
+                    more: false,
+                    special_fields: SpecialFields::default(),
+                },
+            )
             .await
-            .expect("event")
-            .expect("ev");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(a) => {
-                assert!(matches!(a.group_state, ResctrlGroupState::Exists(_)));
-                assert_eq!(a.total_containers, 1);
-                assert_eq!(a.reconciled_containers, 0);
+            .unwrap();
+        // Without the budget the churning container would take 100k passes
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(pid_src.next_pid.load(Ordering::SeqCst) < 1000);
+
+        let inner = plugin.state.lock().unwrap();
+        assert_eq!(
+            inner.containers.get("c-churn").unwrap().state,
+            ContainerSyncState::Partial
+        );
+        assert_eq!(
+            inner.containers.get("c-calm").unwrap().state,
+            ContainerSyncState::Reconciled
+        );
+        assert_eq!(inner.pods.get("churn").unwrap().reconciled_containers, 0);
+        assert_eq!(inner.pods.get("calm").unwrap().reconciled_containers, 1);
+    }
+
+    /// PID source that records how many reads overlap, holding each read open
+    /// briefly so that concurrent callers can be observed.
+    struct InFlightPidSource {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl crate::pid_source::CgroupPidSource for InFlightPidSource {
+        fn pids_for_path(&self, _cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            let deadline = std::time::Instant::now() + std::time::Duration::from_millis(500);
+            while self.in_flight.load(Ordering::SeqCst) < 2 && std::time::Instant::now() < deadline
+            {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synchronize_reconciles_pods_concurrently() {
+        let fs = MockFs::with_premounted_resctrl();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let pid_src = Arc::new(InFlightPidSource {
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        });
+
+        let mut pods = Vec::new();
+        let mut containers = Vec::new();
+        for i in 0..4 {
+            let pod = nri::api::PodSandbox {
+                id: format!("sb{}", i),
+                uid: format!("u{}", i),
+                ..Default::default()
+            };
+            containers.push(nri::api::Container {
+                id: format!("c{}", i),
+                pod_sandbox_id: pod.id.clone(),
+                linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                    cgroups_path: format!("/cg/pod{}/c{}", i, i),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+            pods.push(pod);
+        }
+
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(64);
+        let cfg = ResctrlPluginConfig {
+            concurrency_limit: 4,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, pid_src.clone());
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        let _ = plugin
+            .synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods,
+                    containers,
+                    more: false,
+                    special_fields: SpecialFields::default(),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(
+            pid_src.max_in_flight.load(Ordering::SeqCst) >= 2,
+            "expected overlapping reconciles with concurrency_limit > 1"
+        );
+
+        // Every container was still accounted against its pod
+        let inner = plugin.state.lock().unwrap();
+        assert_eq!(inner.containers.len(), 4);
+        for i in 0..4 {
+            let ps = inner.pods.get(&format!("u{}", i)).expect("pod");
+            assert_eq!(ps.total_containers, 1);
+        }
+    }
+
+    // Blocks each PID read until the runtime has run another task
+    struct RuntimeGatedPidSource {
+        released: Arc<AtomicBool>,
+        saw_release: AtomicBool,
+    }
+
+    impl crate::pid_source::CgroupPidSource for RuntimeGatedPidSource {
+        fn pids_for_path(&self, _cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+            while !self.released.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            if self.released.load(Ordering::SeqCst) {
+                self.saw_release.store(true, Ordering::SeqCst);
+            }
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synchronize_does_not_block_runtime() {
+        let fs = MockFs::with_premounted_resctrl();
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let released = Arc::new(AtomicBool::new(false));
+        let pid_src = Arc::new(RuntimeGatedPidSource {
+            released: released.clone(),
+            saw_release: AtomicBool::new(false),
+        });
+
+        let pod = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
+            ..Default::default()
+        };
+        let container = nri::api::Container {
+            id: "c1".into(),
+            pod_sandbox_id: pod.id.clone(),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: "/cg/pod1/c1".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(64);
+        let plugin =
+            ResctrlPlugin::with_pid_source(ResctrlPluginConfig::default(), rc, tx, pid_src.clone());
+
+        let ctx = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: std::collections::HashMap::new(),
+            timeout_nano: 5_000,
+        };
+        // On the current-thread runtime, the release below only runs while
+        // synchronize is suspended, not while it blocks on the PID read
+        let (res, _) = tokio::join!(
+            plugin.synchronize(
+                &ctx,
+                SynchronizeRequest {
+                    pods: vec![pod],
+                    containers: vec![container],
+                    more: false,
+                    special_fields: SpecialFields::default(),
+                },
+            ),
+            async {
+                released.store(true, Ordering::SeqCst);
             }
-            _ => panic!("unexpected event"),
-        }
+        );
+        res.unwrap();
+
+        assert!(
+            pid_src.saw_release.load(Ordering::SeqCst),
+            "synchronize blocked the runtime thread during reconciliation"
+        );
     }
 
     #[tokio::test]
-    async fn test_retry_container_reconcile_improves_counts() {
+    async fn test_group_for_container() {
         use crate::pid_source::test_support::MockCgroupPidSource;
-        use tokio::time::{timeout, Duration};
 
         let fs = MockFs::new();
         fs.add_dir(std::path::Path::new("/sys"));
         fs.add_dir(std::path::Path::new("/sys/fs"));
         fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
-
         let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
 
         let gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_u1");
@@ -1493,253 +5191,241 @@ mod tests {
             uid: "u1".into(),
             ..Default::default()
         };
-        let linux = nri::api::LinuxContainer {
-            cgroups_path: "/cg/x:cri-containerd:c1".into(),
-            ..Default::default()
-        };
         let container = nri::api::Container {
             id: "c1".into(),
             pod_sandbox_id: pod.id.clone(),
-            linux: protobuf::MessageField::some(linux),
+            linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                cgroups_path: "/cg/x:cri-containerd:c1".into(),
+                ..Default::default()
+            }),
             ..Default::default()
         };
         let full_cg = nri::compute_full_cgroup_path(&container, Some(&pod));
 
-        let mut mock_pid_src = Arc::new(MockCgroupPidSource::new());
-        Arc::get_mut(&mut mock_pid_src)
-            .unwrap()
-            .set_pids(full_cg.clone(), vec![101, 102]);
-
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let mut mock_pid_src = MockCgroupPidSource::new();
+        mock_pid_src.set_pids(full_cg, vec![101]);
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(16);
         let plugin = ResctrlPlugin::with_pid_source(
             ResctrlPluginConfig::default(),
             rc,
             tx,
-            mock_pid_src.clone(),
+            Arc::new(mock_pid_src),
         );
-        // Initially PIDs unassignable (ESRCH)
+        // PID cannot be assigned yet, so the container stays Partial
         fs.set_missing_pid(101);
-        fs.set_missing_pid(102);
 
-        // Run pod + add container → expect counts 1/0
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
-        let state_req = StateChangeEvent {
-            event: Event::RUN_POD_SANDBOX.into(),
-            pod: protobuf::MessageField::some(pod.clone()),
-            container: protobuf::MessageField::none(),
-            special_fields: SpecialFields::default(),
-        };
-        let _ = plugin.state_change(&ctx, state_req).await.unwrap();
-        let _ = Plugin::state_change(
-            &plugin,
-            &ctx,
-            StateChangeEvent {
-                event: Event::START_CONTAINER.into(),
-                pod: protobuf::MessageField::some(pod.clone()),
-                container: protobuf::MessageField::some(container.clone()),
-                special_fields: SpecialFields::default(),
-            },
-        )
-        .await
-        .unwrap();
+        for (event, container_field) in [
+            (Event::RUN_POD_SANDBOX, protobuf::MessageField::none()),
+            (
+                Event::START_CONTAINER,
+                protobuf::MessageField::some(container.clone()),
+            ),
+        ] {
+            let _ = plugin
+                .state_change(
+                    &ctx,
+                    StateChangeEvent {
+                        event: event.into(),
+                        pod: protobuf::MessageField::some(pod.clone()),
+                        container: container_field,
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+        }
 
-        // Drain two events (pod created Exists and container accounted)
-        let _ = timeout(Duration::from_millis(100), rx.recv()).await; // pod exists
-        let _ = timeout(Duration::from_millis(200), rx.recv()).await; // container accounted
+        // Partial and unknown containers have no group
+        assert_eq!(plugin.group_for_container("c1"), None);
+        assert_eq!(plugin.group_for_container("unknown"), None);
 
-        // Make current PIDs assignable by clearing missing flags
+        // Once reconciled, the pod's group path is returned
         fs.clear_missing_pid(101);
-        fs.clear_missing_pid(102);
-
-        // Retry just this container → expect transition to Reconciled and one event with counts 1/1
         let st = plugin.retry_container_reconcile("c1").expect("retry ok");
         assert_eq!(st, ContainerSyncState::Reconciled);
-        // Drain the event emitted for the transition to Reconciled (counts 1/1)
-        let ev = timeout(Duration::from_millis(100), rx.recv())
-            .await
-            .expect("event")
-            .expect("ev");
-        match ev {
-            PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.total_containers, 1);
-                assert_eq!(a.reconciled_containers, 1);
-            }
-            _ => panic!("unexpected event"),
-        }
+        assert_eq!(plugin.group_for_container("c1").as_deref(), gp.to_str());
+    }
 
-        // Verify resctrl tasks now include the desired PIDs (101, 102)
-        let pids = plugin
-            .resctrl
-            .list_group_tasks(gp.to_str().unwrap())
-            .expect("list tasks");
-        assert!(pids.contains(&101) && pids.contains(&102));
+    /// PID source that counts `cgroup.procs` reads
+    struct CountingPidSource {
+        inner: crate::pid_source::test_support::MockCgroupPidSource,
+        reads: AtomicUsize,
+    }
 
-        // Validate internal state updated and counts improved
-        {
-            let inner = plugin.state.lock().unwrap();
-            let ps = inner.pods.get("u1").expect("pod state");
-            assert_eq!(ps.total_containers, 1);
-            assert_eq!(ps.reconciled_containers, 1);
-            let cs = inner.containers.get("c1").expect("container");
-            assert_eq!(cs.state, ContainerSyncState::Reconciled);
+    impl crate::pid_source::CgroupPidSource for CountingPidSource {
+        fn pids_for_path(&self, cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.pids_for_path(cgroup_path)
         }
-        // Re-run should not change counts further
-        let _ = plugin.retry_container_reconcile("c1").expect("ok");
 
-        // Ensure no further events are emitted after the second reconcile
-        assert!(
-            timeout(Duration::from_millis(50), rx.recv())
-                .await
-                .ok()
-                .is_none(),
-            "no extra event expected after second reconcile"
-        );
+        fn child_cgroups(&self, cgroup_path: &str) -> resctrl::Result<Vec<String>> {
+            self.inner.child_cgroups(cgroup_path)
+        }
     }
 
     #[tokio::test]
-    async fn test_retry_all_once_early_stop_on_capacity_and_reconcile_others() {
+    async fn test_batch_pod_pid_reads_reduce_reads() {
         use crate::pid_source::test_support::MockCgroupPidSource;
-        use tokio::time::{timeout, Duration};
 
-        let fs = MockFs::new();
-        fs.add_dir(std::path::Path::new("/sys"));
-        fs.add_dir(std::path::Path::new("/sys/fs"));
-        fs.add_dir(std::path::Path::new("/sys/fs/resctrl"));
-        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
-        let (tx, mut rx) = mpsc::channel::<PodResctrlEvent>(32);
-        let pod_a = nri::api::PodSandbox {
-            id: "sbA".into(),
-            uid: "uA".into(),
-            ..Default::default()
-        };
-        let pod_b = nri::api::PodSandbox {
-            id: "sbB".into(),
-            uid: "uB".into(),
-            ..Default::default()
-        };
-        let linux_b = nri::api::LinuxContainer {
-            cgroups_path: "/cg/b:cri-containerd:b1".into(),
+        let pod = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
             ..Default::default()
         };
-        let ctr_b = nri::api::Container {
-            id: "b1".into(),
-            pod_sandbox_id: pod_b.id.clone(),
-            linux: protobuf::MessageField::some(linux_b),
-            ..Default::default()
+        let containers: Vec<nri::api::Container> = (0..3)
+            .map(|i| nri::api::Container {
+                id: format!("c{}", i),
+                pod_sandbox_id: pod.id.clone(),
+                linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                    cgroups_path: format!("/cg/pod1/c{}", i),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect();
+
+        // Returns (cgroup.procs reads, group tasks reads, final pod counts)
+        let run = |batch: bool| async move {
+            let fs = MockFs::with_premounted_resctrl();
+            let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+            let mut mock = MockCgroupPidSource::new();
+            for (i, c) in containers.iter().enumerate() {
+                let full = nri::compute_full_cgroup_path(c, Some(&pod));
+                mock.add_child("/sys/fs/cgroup/cg/pod1", full.clone());
+                mock.set_pids(full, vec![10 * i as i32 + 1, 10 * i as i32 + 2]);
+            }
+            // The pod cgroup and its sandbox are not reconciled either way
+            mock.set_pids("/sys/fs/cgroup/cg/pod1".into(), vec![98]);
+            mock.add_child(
+                "/sys/fs/cgroup/cg/pod1",
+                "/sys/fs/cgroup/cg/pod1/sandbox".into(),
+            );
+            mock.set_pids("/sys/fs/cgroup/cg/pod1/sandbox".into(), vec![99]);
+            let pid_src = Arc::new(CountingPidSource {
+                inner: mock,
+                reads: AtomicUsize::new(0),
+            });
+
+            let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(16);
+            let cfg = ResctrlPluginConfig {
+                batch_pod_pid_reads: batch,
+                ..Default::default()
+            };
+            let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, pid_src.clone());
+            let ctx = TtrpcContext {
+                mh: ttrpc::MessageHeader::default(),
+                metadata: std::collections::HashMap::new(),
+                timeout_nano: 5_000,
+            };
+            let _ = plugin
+                .synchronize(
+                    &ctx,
+                    SynchronizeRequest {
+                        pods: vec![pod.clone()],
+                        containers: containers.clone(),
+                        more: false,
+                        special_fields: SpecialFields::default(),
+                    },
+                )
+                .await
+                .unwrap();
+
+            let group_path = "/sys/fs/resctrl/mon_groups/pod_u1";
+            let mut tasks = plugin.resctrl.list_group_tasks(group_path).unwrap();
+            tasks.sort();
+            assert_eq!(tasks, vec![1, 2, 11, 12, 21, 22]);
+
+            let tasks_reads = fs.read_count(&std::path::Path::new(group_path).join("tasks"));
+            let inner = plugin.state.lock().unwrap();
+            let ps = inner.pods.get("u1").expect("pod");
+            let counts = (ps.total_containers, ps.reconciled_containers);
+            (pid_src.reads.load(Ordering::SeqCst), tasks_reads, counts)
         };
 
-        let mut mock_pid_src = Arc::new(MockCgroupPidSource::new());
-        let cg_b = nri::compute_full_cgroup_path(&ctr_b, Some(&pod_b));
-        Arc::get_mut(&mut mock_pid_src)
-            .unwrap()
-            .set_pids(cg_b.clone(), vec![222, 223]);
+        let (per_container_reads, per_container_tasks, per_container_counts) = run(false).await;
+        let (batch_reads, batch_tasks, batch_counts) = run(true).await;
 
-        let plugin = ResctrlPlugin::with_pid_source(
-            ResctrlPluginConfig::default(),
-            rc,
-            tx,
-            mock_pid_src.clone(),
+        assert_eq!(per_container_counts, (3, 3));
+        assert_eq!(batch_counts, (3, 3));
+        assert!(
+            batch_reads + batch_tasks < per_container_reads + per_container_tasks,
+            "batch: {} procs + {} tasks reads, per container: {} + {}",
+            batch_reads,
+            batch_tasks,
+            per_container_reads,
+            per_container_tasks
         );
+    }
 
-        // uA: Failed pod due to ENOSPC
-        let u_a_gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uA");
-        fs.set_nospace_dir(&u_a_gp);
-        // uB: Existing group and one Partial container
-        let u_b_gp = std::path::PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uB");
-        fs.add_dir(&u_b_gp);
-        fs.add_file(&u_b_gp.join("tasks"), "");
-        fs.set_missing_pid(222);
-        fs.set_missing_pid(223);
+    #[tokio::test]
+    async fn test_batched_reconcile_attributes_missing_pids_per_container() {
+        use crate::pid_source::test_support::MockCgroupPidSource;
 
-        // Feed state
+        let pod = nri::api::PodSandbox {
+            id: "sb1".into(),
+            uid: "u1".into(),
+            ..Default::default()
+        };
+        let containers: Vec<nri::api::Container> = (0..3)
+            .map(|i| nri::api::Container {
+                id: format!("c{}", i),
+                pod_sandbox_id: pod.id.clone(),
+                linux: protobuf::MessageField::some(nri::api::LinuxContainer {
+                    cgroups_path: format!("/cg/pod1/c{}", i),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect();
+
+        let fs = MockFs::with_premounted_resctrl();
+        // PIDs of the last two containers cannot be moved
+        fs.set_missing_pid(12);
+        fs.set_missing_pid(21);
+        fs.set_missing_pid(22);
+        let rc = Resctrl::with_provider(fs.clone(), resctrl::Config::default());
+        let mut mock = MockCgroupPidSource::new();
+        for (i, c) in containers.iter().enumerate() {
+            let full = nri::compute_full_cgroup_path(c, Some(&pod));
+            mock.add_child("/sys/fs/cgroup/cg/pod1", full.clone());
+            mock.set_pids(full, vec![10 * i as i32 + 1, 10 * i as i32 + 2]);
+        }
+
+        let (tx, _rx) = mpsc::channel::<PodResctrlEvent>(16);
+        let cfg = ResctrlPluginConfig {
+            batch_pod_pid_reads: true,
+            ..Default::default()
+        };
+        let plugin = ResctrlPlugin::with_pid_source(cfg, rc, tx, Arc::new(mock));
         let ctx = TtrpcContext {
             mh: ttrpc::MessageHeader::default(),
             metadata: std::collections::HashMap::new(),
             timeout_nano: 5_000,
         };
         let _ = plugin
-            .state_change(
-                &ctx,
-                StateChangeEvent {
-                    event: Event::RUN_POD_SANDBOX.into(),
-                    pod: protobuf::MessageField::some(pod_a.clone()),
-                    container: protobuf::MessageField::none(),
-                    special_fields: SpecialFields::default(),
-                },
-            )
-            .await
-            .unwrap();
-        let _ = plugin
-            .state_change(
+            .synchronize(
                 &ctx,
-                StateChangeEvent {
-                    event: Event::RUN_POD_SANDBOX.into(),
-                    pod: protobuf::MessageField::some(pod_b.clone()),
-                    container: protobuf::MessageField::none(),
+                SynchronizeRequest {
+                    pods: vec![pod.clone()],
+                    containers: containers.clone(),
+                    more: false,
                     special_fields: SpecialFields::default(),
                 },
             )
             .await
             .unwrap();
-        let _ = Plugin::state_change(
-            &plugin,
-            &ctx,
-            StateChangeEvent {
-                event: Event::START_CONTAINER.into(),
-                pod: protobuf::MessageField::some(pod_b.clone()),
-                container: protobuf::MessageField::some(ctr_b.clone()),
-                special_fields: SpecialFields::default(),
-            },
-        )
-        .await
-        .unwrap();
-
-        // Drain initial events
-        let _ = timeout(Duration::from_millis(100), rx.recv())
-            .await
-            .expect("no-timeout")
-            .expect("received event"); // uA failed
-        let _ = timeout(Duration::from_millis(100), rx.recv())
-            .await
-            .expect("no-timeout")
-            .expect("received event"); // uB exists
-        let ev = timeout(Duration::from_millis(100), rx.recv())
-            .await
-            .expect("no-timeout")
-            .expect("received event"); // uB counts 1/0
-        match ev {
-            PodResctrlEvent::AddOrUpdate(a) => {
-                assert_eq!(a.pod_uid, "uB");
-                assert_eq!(a.total_containers, 1);
-                assert_eq!(a.reconciled_containers, 0);
-            }
-            _ => panic!("unexpected event"),
-        }
-
-        // Make current PIDs assignable now
-        fs.clear_missing_pid(222);
-        fs.clear_missing_pid(223);
-
-        // Run retry_all_once: should attempt uA once and stop on capacity, then reconcile uB
-        let before = fs.mkdir_count(&u_a_gp);
-        plugin.retry_all_once().expect("retry all ok");
-        // mkdir called exactly once for uA during this pass
-        let after = fs.mkdir_count(&u_a_gp);
-        assert_eq!(
-            after.saturating_sub(before),
-            1,
-            "expected single create_dir attempt in this pass"
-        );
 
-        // Validate internal state improved for uB
-        {
-            let inner = plugin.state.lock().unwrap();
-            let ps = inner.pods.get("uB").expect("pod uB");
-            assert_eq!(ps.reconciled_containers, 1);
-        }
+        let inner = plugin.state.lock().unwrap();
+        let missing: Vec<usize> = ["c0", "c1", "c2"]
+            .iter()
+            .map(|id| inner.containers.get(*id).expect("container").missing_pids)
+            .collect();
+        assert_eq!(missing, vec![0, 1, 2]);
+        assert_eq!(inner.pods.get("u1").expect("pod").unreconciled_pids, 3);
     }
 }
@@ -1,13 +1,151 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use resctrl::{FsProvider, RealFs};
+
+/// Root of the cgroup filesystem that computed container paths are rooted at
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// cgroup v1 controller hierarchies searched, in order, for a container's
+/// `cgroup.procs` when the host uses split hierarchies
+const V1_CONTROLLERS: &[&str] = &["pids", "cpu,cpuacct", "cpuacct", "cpu", "memory", "systemd"];
+
 /// Source of PIDs for a container based on cgroup path.
 pub trait CgroupPidSource: Send + Sync {
     fn pids_for_path(&self, cgroup_path: &str) -> resctrl::Result<Vec<i32>>;
+
+    /// Paths of the immediate child cgroups of `cgroup_path`. Sources that cannot
+    /// enumerate children report none.
+    fn child_cgroups(&self, _cgroup_path: &str) -> resctrl::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// PIDs of `cgroup_path` and of its descendants up to `max_depth` levels
+    /// below it (0 = the cgroup itself only).
+    ///
+    /// Errors reading the cgroup itself are returned; errors in descendants are
+    /// skipped since child cgroups may come and go while we walk them.
+    fn pids_for_path_recursive(
+        &self,
+        cgroup_path: &str,
+        max_depth: usize,
+    ) -> resctrl::Result<Vec<i32>> {
+        let mut pids = self.pids_for_path(cgroup_path)?;
+        if max_depth == 0 {
+            return Ok(pids);
+        }
+        let mut frontier = self.child_cgroups(cgroup_path).unwrap_or_default();
+        for depth in 1..=max_depth {
+            let mut next = Vec::new();
+            for child in &frontier {
+                match self.pids_for_path(child) {
+                    Ok(child_pids) => pids.extend(child_pids),
+                    Err(e) => {
+                        log::debug!("skipping child cgroup {}: {}", child, e);
+                        continue;
+                    }
+                }
+                if depth < max_depth {
+                    next.extend(self.child_cgroups(child).unwrap_or_default());
+                }
+            }
+            frontier = next;
+        }
+        Ok(pids)
+    }
+}
+
+/// Layout of the host's cgroup filesystem
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CgroupVersion {
+    /// Split hierarchies, one mount per controller under `/sys/fs/cgroup`
+    V1,
+    /// Unified hierarchy mounted at `/sys/fs/cgroup`
+    V2,
+}
+
+/// Detect the cgroup layout. A root `cgroup.controllers` file means v2; otherwise
+/// any v1 controller mount means v1. Defaults to v2 when neither is found.
+pub(crate) fn detect_cgroup_version<P: FsProvider>(fs: &P) -> CgroupVersion {
+    let root = Path::new(CGROUP_ROOT);
+    if fs.exists(&root.join("cgroup.controllers")) {
+        return CgroupVersion::V2;
+    }
+    if V1_CONTROLLERS.iter().any(|c| fs.exists(&root.join(c))) {
+        return CgroupVersion::V1;
+    }
+    CgroupVersion::V2
+}
+
+/// Map a unified cgroup path (as computed from NRI) to the directory holding its
+/// `cgroup.procs`. On v1 the path is moved under the first controller hierarchy
+/// where it exists; paths that are already controller-specific, outside the
+/// cgroup root, or not found in any controller are returned unchanged.
+pub(crate) fn resolve_procs_dir<P: FsProvider>(
+    fs: &P,
+    version: CgroupVersion,
+    cgroup_path: &str,
+) -> String {
+    if version == CgroupVersion::V2 {
+        return cgroup_path.to_string();
+    }
+    let Some(rel) = cgroup_path.strip_prefix(CGROUP_ROOT) else {
+        return cgroup_path.to_string();
+    };
+    let rel = rel.trim_start_matches('/');
+    let first = rel.split('/').next().unwrap_or("");
+    if V1_CONTROLLERS.contains(&first) {
+        return cgroup_path.to_string();
+    }
+    V1_CONTROLLERS
+        .iter()
+        .map(|c| format!("{}/{}/{}", CGROUP_ROOT, c, rel))
+        .find(|candidate| fs.exists(Path::new(candidate)))
+        .unwrap_or_else(|| cgroup_path.to_string())
 }
 
-pub struct RealCgroupPidSource;
+/// Read and parse `cgroup.procs` under `dir`. A missing cgroup maps to ENOENT and
+/// EACCES/EPERM to `NoPermission`.
+pub(crate) fn read_cgroup_procs<P: FsProvider>(fs: &P, dir: &str) -> resctrl::Result<Vec<i32>> {
+    if !fs.exists(Path::new(dir)) {
+        return Err(resctrl::Error::Io {
+            path: PathBuf::from(dir),
+            source: io::Error::from_raw_os_error(libc::ENOENT),
+        });
+    }
+    let procs_path = Path::new(dir).join("cgroup.procs");
+    let content = fs.read_to_string(&procs_path).map_err(|e| {
+        if matches!(e.raw_os_error(), Some(libc::EACCES) | Some(libc::EPERM)) {
+            resctrl::Error::NoPermission {
+                path: procs_path.clone(),
+                source: e,
+            }
+        } else {
+            resctrl::Error::Io {
+                path: procs_path.clone(),
+                source: e,
+            }
+        }
+    })?;
+    Ok(content
+        .lines()
+        .filter_map(|l| l.trim().parse::<i32>().ok())
+        .collect())
+}
+
+pub struct RealCgroupPidSource {
+    version: CgroupVersion,
+}
 
 impl RealCgroupPidSource {
     pub fn new() -> Self {
-        Self
+        let version = detect_cgroup_version(&RealFs);
+        if version == CgroupVersion::V1 {
+            log::info!(
+                "resctrl-plugin: cgroup v1 detected; reading PIDs from controller hierarchies"
+            );
+        }
+        Self { version }
     }
 }
 
@@ -21,30 +159,61 @@ impl Default for RealCgroupPidSource {
 impl CgroupPidSource for RealCgroupPidSource {
     fn pids_for_path(&self, cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
         use cgroups_rs::{cgroup::Cgroup, hierarchies};
-        use std::io;
-        use std::path::Path;
 
         if cgroup_path.is_empty() {
             return Err(resctrl::Error::Io {
-                path: std::path::PathBuf::from("<cgroup path>"),
+                path: PathBuf::from("<cgroup path>"),
                 source: io::Error::new(io::ErrorKind::InvalidInput, "empty cgroup path"),
             });
         }
 
+        // Split hierarchies: read cgroup.procs from the controller subtree directly
+        if self.version == CgroupVersion::V1 {
+            let dir = resolve_procs_dir(&RealFs, self.version, cgroup_path);
+            return read_cgroup_procs(&RealFs, &dir);
+        }
+
         // Explicitly error if the cgroup path does not exist
         if !Path::new(cgroup_path).exists() {
             return Err(resctrl::Error::Io {
-                path: std::path::PathBuf::from(cgroup_path),
+                path: PathBuf::from(cgroup_path),
                 source: io::Error::from_raw_os_error(libc::ENOENT),
             });
         }
 
+        // cgroups_rs swallows read errors, so probe cgroup.procs ourselves to surface
+        // permission problems that no amount of retrying will fix.
+        let procs_path = Path::new(cgroup_path).join("cgroup.procs");
+        if let Err(e) = std::fs::File::open(&procs_path) {
+            if matches!(e.raw_os_error(), Some(libc::EACCES) | Some(libc::EPERM)) {
+                return Err(resctrl::Error::NoPermission {
+                    path: procs_path,
+                    source: e,
+                });
+            }
+        }
+
         let hier = hierarchies::auto();
         let cg = Cgroup::load(hier, cgroup_path);
 
         let procs = cg.procs();
         Ok(procs.into_iter().map(|pid| pid.pid as i32).collect())
     }
+
+    fn child_cgroups(&self, cgroup_path: &str) -> resctrl::Result<Vec<String>> {
+        let dir = resolve_procs_dir(&RealFs, self.version, cgroup_path);
+        let names = RealFs
+            .read_child_dirs(Path::new(&dir))
+            .map_err(|e| resctrl::Error::Io {
+                path: PathBuf::from(&dir),
+                source: e,
+            })?;
+        let parent = cgroup_path.trim_end_matches('/');
+        Ok(names
+            .into_iter()
+            .map(|name| format!("{}/{}", parent, name))
+            .collect())
+    }
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -57,11 +226,14 @@ impl CgroupPidSource for RealCgroupPidSource {
 #[cfg(test)]
 pub mod test_support {
     use super::*;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
 
     #[derive(Clone, Default)]
     pub struct MockCgroupPidSource {
         pids_map: HashMap<String, Vec<i32>>,
+        denied_paths: HashSet<String>,
+        children: HashMap<String, Vec<String>>,
     }
 
     impl MockCgroupPidSource {
@@ -73,11 +245,138 @@ pub mod test_support {
         pub fn set_pids(&mut self, cgroup_path: String, pids: Vec<i32>) {
             self.pids_map.insert(cgroup_path, pids);
         }
+
+        /// Register `child` as a child cgroup of `parent`.
+        pub fn add_child(&mut self, parent: &str, child: String) {
+            self.children
+                .entry(parent.to_string())
+                .or_default()
+                .push(child);
+        }
+
+        /// Simulate EACCES when reading `cgroup.procs` for the given path.
+        pub fn set_permission_denied(&mut self, cgroup_path: String) {
+            self.denied_paths.insert(cgroup_path);
+        }
     }
 
     impl CgroupPidSource for MockCgroupPidSource {
         fn pids_for_path(&self, cgroup_path: &str) -> resctrl::Result<Vec<i32>> {
+            if self.denied_paths.contains(cgroup_path) {
+                return Err(resctrl::Error::NoPermission {
+                    path: Path::new(cgroup_path).join("cgroup.procs"),
+                    source: std::io::Error::from_raw_os_error(libc::EACCES),
+                });
+            }
             Ok(self.pids_map.get(cgroup_path).cloned().unwrap_or_default())
         }
+
+        fn child_cgroups(&self, cgroup_path: &str) -> resctrl::Result<Vec<String>> {
+            Ok(self.children.get(cgroup_path).cloned().unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resctrl::test_utils::mock_fs::MockFs;
+
+    fn add_dirs(fs: &MockFs, dirs: &[&str]) {
+        for d in dirs {
+            fs.add_dir(Path::new(d));
+        }
+    }
+
+    #[test]
+    fn test_v1_split_hierarchy_maps_into_controller() {
+        let fs = MockFs::new();
+        add_dirs(
+            &fs,
+            &[
+                "/sys/fs/cgroup",
+                "/sys/fs/cgroup/cpu,cpuacct",
+                "/sys/fs/cgroup/cpu,cpuacct/kubepods",
+                "/sys/fs/cgroup/cpu,cpuacct/kubepods/podu1",
+                "/sys/fs/cgroup/cpu,cpuacct/kubepods/podu1/c1",
+                "/sys/fs/cgroup/memory",
+            ],
+        );
+        fs.add_file(
+            Path::new("/sys/fs/cgroup/cpu,cpuacct/kubepods/podu1/c1/cgroup.procs"),
+            "10\n11\n",
+        );
+        assert_eq!(detect_cgroup_version(&fs), CgroupVersion::V1);
+
+        let dir = resolve_procs_dir(&fs, CgroupVersion::V1, "/sys/fs/cgroup/kubepods/podu1/c1");
+        assert_eq!(dir, "/sys/fs/cgroup/cpu,cpuacct/kubepods/podu1/c1");
+        assert_eq!(read_cgroup_procs(&fs, &dir).unwrap(), vec![10, 11]);
+
+        // Already controller-specific paths are kept as-is
+        assert_eq!(
+            resolve_procs_dir(&fs, CgroupVersion::V1, &dir),
+            "/sys/fs/cgroup/cpu,cpuacct/kubepods/podu1/c1"
+        );
+
+        // A cgroup missing from every controller keeps the ENOENT mapping
+        let missing = resolve_procs_dir(&fs, CgroupVersion::V1, "/sys/fs/cgroup/kubepods/gone");
+        assert_eq!(missing, "/sys/fs/cgroup/kubepods/gone");
+        match read_cgroup_procs(&fs, &missing) {
+            Err(resctrl::Error::Io { source, .. }) => {
+                assert_eq!(source.raw_os_error(), Some(libc::ENOENT))
+            }
+            other => panic!("expected ENOENT, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_v2_paths_are_unchanged() {
+        let fs = MockFs::new();
+        add_dirs(&fs, &["/sys/fs/cgroup", "/sys/fs/cgroup/kubepods"]);
+        fs.add_file(
+            Path::new("/sys/fs/cgroup/cgroup.controllers"),
+            "cpu memory pids\n",
+        );
+        assert_eq!(detect_cgroup_version(&fs), CgroupVersion::V2);
+        assert_eq!(
+            resolve_procs_dir(&fs, CgroupVersion::V2, "/sys/fs/cgroup/kubepods/c1"),
+            "/sys/fs/cgroup/kubepods/c1"
+        );
+    }
+
+    #[test]
+    fn test_recursive_pids_include_nested_children() {
+        use super::test_support::MockCgroupPidSource;
+
+        let mut src = MockCgroupPidSource::new();
+        src.set_pids("/cg/ctr".into(), vec![1]);
+        src.set_pids("/cg/ctr/init".into(), vec![2]);
+        src.set_pids("/cg/ctr/workload".into(), vec![3]);
+        src.set_pids("/cg/ctr/workload/worker".into(), vec![4]);
+        src.add_child("/cg/ctr", "/cg/ctr/init".into());
+        src.add_child("/cg/ctr", "/cg/ctr/workload".into());
+        src.add_child("/cg/ctr/workload", "/cg/ctr/workload/worker".into());
+
+        assert_eq!(src.pids_for_path_recursive("/cg/ctr", 0).unwrap(), vec![1]);
+        assert_eq!(
+            src.pids_for_path_recursive("/cg/ctr", 1).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            src.pids_for_path_recursive("/cg/ctr", 5).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_read_cgroup_procs_permission_denied() {
+        let fs = MockFs::new();
+        add_dirs(&fs, &["/cg"]);
+        fs.add_file(Path::new("/cg/cgroup.procs"), "1\n");
+        fs.set_no_perm_file(Path::new("/cg/cgroup.procs"));
+        assert!(matches!(
+            read_cgroup_procs(&fs, "/cg"),
+            Err(resctrl::Error::NoPermission { .. })
+        ));
     }
 }
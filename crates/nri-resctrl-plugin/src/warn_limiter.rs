@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::mem::Discriminant;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+/// Coalesces repeated warnings so an outage does not flood the logs.
+///
+/// Warnings are grouped by the failing operation and the kind of error, so a
+/// new kind of failure is still logged right away. The first warning of a
+/// group is logged; further ones within `window` are only counted. The count is
+/// reported with the next warning logged after the window, or by `flush`, with
+/// the last suppressed message.
+pub(crate) struct WarnLimiter {
+    window: Duration,
+    keys: Mutex<HashMap<WarnKey, KeyState>>,
+}
+
+type WarnKey = (&'static str, Discriminant<resctrl::Error>);
+
+struct KeyState {
+    last_logged: Instant,
+    suppressed: u64,
+    // Most recent suppressed message, logged with the count by `flush`
+    last_message: String,
+}
+
+impl WarnLimiter {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Log `message` as a warning, unless a warning for `op` failing with the
+    /// same kind of error as `err` was logged within the window
+    pub(crate) fn warn(
+        &self,
+        op: &'static str,
+        err: &resctrl::Error,
+        message: std::fmt::Arguments<'_>,
+    ) {
+        let key = (op, std::mem::discriminant(err));
+        if let Some(suppressed) = self.admit(key, Instant::now(), || message.to_string()) {
+            if suppressed > 0 {
+                warn!(
+                    "{} ({} similar warnings suppressed in the last {:?})",
+                    message, suppressed, self.window
+                );
+            } else {
+                warn!("{}", message);
+            }
+        }
+    }
+
+    /// Log the warnings still suppressed, so they are reported even if no
+    /// warning of their group follows. With `only_expired`, only groups whose
+    /// window has passed are flushed.
+    pub(crate) fn flush(&self, only_expired: bool) {
+        for (message, suppressed) in self.take_suppressed(Instant::now(), only_expired) {
+            warn!(
+                "{} ({} similar warnings suppressed in the last {:?})",
+                message, suppressed, self.window
+            );
+        }
+    }
+
+    /// Record a warning for `key` at `now`, returning the number of warnings
+    /// suppressed since the last logged one if this one should be logged.
+    /// `message` is only formatted when the warning is suppressed.
+    fn admit(&self, key: WarnKey, now: Instant, message: impl FnOnce() -> String) -> Option<u64> {
+        let mut keys = self.keys.lock().unwrap();
+        match keys.get_mut(&key) {
+            Some(state) if now.duration_since(state.last_logged) < self.window => {
+                state.suppressed += 1;
+                state.last_message = message();
+                None
+            }
+            Some(state) => {
+                let suppressed = std::mem::take(&mut state.suppressed);
+                state.last_logged = now;
+                Some(suppressed)
+            }
+            None => {
+                keys.insert(
+                    key,
+                    KeyState {
+                        last_logged: now,
+                        suppressed: 0,
+                        last_message: String::new(),
+                    },
+                );
+                Some(0)
+            }
+        }
+    }
+
+    /// Take the last message and count of every group with suppressed
+    /// warnings, starting a new window for it
+    fn take_suppressed(&self, now: Instant, only_expired: bool) -> Vec<(String, u64)> {
+        let mut keys = self.keys.lock().unwrap();
+        keys.values_mut()
+            .filter(|state| state.suppressed > 0)
+            .filter(|state| !only_expired || now.duration_since(state.last_logged) >= self.window)
+            .map(|state| {
+                state.last_logged = now;
+                (
+                    std::mem::take(&mut state.last_message),
+                    std::mem::take(&mut state.suppressed),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn not_mounted() -> resctrl::Error {
+        resctrl::Error::NotMounted {
+            root: PathBuf::from("/sys/fs/resctrl"),
+        }
+    }
+
+    fn capacity() -> resctrl::Error {
+        resctrl::Error::Capacity {
+            source: std::io::Error::from_raw_os_error(libc::ENOSPC),
+        }
+    }
+
+    fn key(op: &'static str, err: &resctrl::Error) -> WarnKey {
+        (op, std::mem::discriminant(err))
+    }
+
+    #[test]
+    fn test_repeated_warnings_are_coalesced() {
+        let limiter = WarnLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mounted = key("ensure_mounted", &not_mounted());
+        let create = key("create_group", &capacity());
+        let msg = || String::from("failed");
+
+        // The first warning is logged, repeats within the window are counted
+        assert_eq!(limiter.admit(mounted, at(0), msg), Some(0));
+        for secs in 1..=5 {
+            assert_eq!(limiter.admit(mounted, at(secs), msg), None);
+        }
+        // Other operations are limited separately
+        assert_eq!(limiter.admit(create, at(10), msg), Some(0));
+
+        // After the window, the next warning is logged with the suppressed count
+        assert_eq!(limiter.admit(mounted, at(60), msg), Some(5));
+        assert_eq!(limiter.admit(mounted, at(61), msg), None);
+        assert_eq!(limiter.admit(mounted, at(130), msg), Some(1));
+        assert_eq!(limiter.admit(create, at(200), msg), Some(0));
+    }
+
+    #[test]
+    fn test_new_error_kind_is_logged() {
+        let limiter = WarnLimiter::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let msg = || String::from("failed");
+
+        assert_eq!(
+            limiter.admit(key("create_group", &capacity()), now, msg),
+            Some(0)
+        );
+        // The same operation failing differently is not suppressed
+        assert_eq!(
+            limiter.admit(key("create_group", &not_mounted()), now, msg),
+            Some(0)
+        );
+        assert_eq!(
+            limiter.admit(key("create_group", &capacity()), now, msg),
+            None
+        );
+    }
+
+    #[test]
+    fn test_flush_reports_suppressed_warnings() {
+        let limiter = WarnLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let create = key("create_group", &capacity());
+
+        limiter.admit(create, at(0), || "pod a".to_string());
+        limiter.admit(create, at(1), || "pod b".to_string());
+        limiter.admit(create, at(2), || "pod c".to_string());
+
+        // Still within the window
+        assert!(limiter.take_suppressed(at(30), true).is_empty());
+        assert_eq!(
+            limiter.take_suppressed(at(60), true),
+            vec![("pod c".to_string(), 2)]
+        );
+        // Nothing left, and a flush starts a new window
+        assert!(limiter.take_suppressed(at(61), false).is_empty());
+        assert_eq!(limiter.admit(create, at(90), || "pod d".to_string()), None);
+        assert_eq!(
+            limiter.take_suppressed(at(91), false),
+            vec![("pod d".to_string(), 1)]
+        );
+    }
+}
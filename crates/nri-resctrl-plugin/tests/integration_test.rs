@@ -506,6 +506,7 @@ async fn test_plugin_full_flow_impl() -> anyhow::Result<()> {
     let group_path_a = match event_a.group_state {
         ResctrlGroupState::Exists(ref path) => path.clone(),
         ResctrlGroupState::Failed => bail!("preexisting pod group creation failed"),
+        ResctrlGroupState::Observed => bail!("pod group not created in observe-only mode"),
     };
 
     // Verify tasks reflect existing containers.
@@ -554,6 +555,7 @@ async fn test_plugin_full_flow_impl() -> anyhow::Result<()> {
     let group_path_b = match update_b.group_state {
         ResctrlGroupState::Exists(ref path) => path.clone(),
         ResctrlGroupState::Failed => bail!("new pod group creation failed"),
+        ResctrlGroupState::Observed => bail!("pod group not created in observe-only mode"),
     };
     let _ = wait_for_tasks_with_pids(&group_path_b, &pids_b, Duration::from_secs(30)).await?;
 
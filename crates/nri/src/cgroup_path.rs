@@ -0,0 +1,133 @@
+use thiserror::Error;
+
+/// Components of a systemd-style container `cgroups_path`, formatted by the
+/// runtime as `<slice>:<runtime prefix>:<container id>`, e.g.
+/// `kubelet-kubepods-besteffort-pod<uid>.slice:cri-containerd:<id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CgroupsPath {
+    /// Parent slice of the container scope
+    pub slice: String,
+    /// Runtime prefix used in the scope name (e.g. "cri-containerd", "crio")
+    pub runtime: String,
+    /// Container ID
+    pub container_id: String,
+}
+
+/// Why a `cgroups_path` could not be parsed as `<slice>:<runtime>:<id>`
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CgroupsPathError {
+    #[error("cgroups path is empty")]
+    Empty,
+    #[error("cgroups path '{0}' is not colon-delimited (cgroupfs driver path?)")]
+    NotColonDelimited(String),
+    #[error(
+        "cgroups path '{path}' has {parts} colon-separated parts, expected 3 (slice:runtime:id)"
+    )]
+    PartCount { path: String, parts: usize },
+    #[error("cgroups path '{path}' has an empty {field}")]
+    EmptyField { path: String, field: &'static str },
+}
+
+impl CgroupsPath {
+    /// Parse a systemd-style `cgroups_path`.
+    pub fn parse(path: &str) -> Result<Self, CgroupsPathError> {
+        if path.is_empty() {
+            return Err(CgroupsPathError::Empty);
+        }
+        if !path.contains(':') {
+            return Err(CgroupsPathError::NotColonDelimited(path.to_string()));
+        }
+
+        let parts: Vec<&str> = path.split(':').collect();
+        let [slice, runtime, container_id] = parts[..] else {
+            return Err(CgroupsPathError::PartCount {
+                path: path.to_string(),
+                parts: parts.len(),
+            });
+        };
+        for (field, value) in [
+            ("slice", slice),
+            ("runtime", runtime),
+            ("container id", container_id),
+        ] {
+            if value.is_empty() {
+                return Err(CgroupsPathError::EmptyField {
+                    path: path.to_string(),
+                    field,
+                });
+            }
+        }
+
+        Ok(Self {
+            slice: slice.to_string(),
+            runtime: runtime.to_string(),
+            container_id: container_id.to_string(),
+        })
+    }
+}
+
+/// Inode number of the cgroup directory at `cgroup_path`, which is the cgroup id
+/// BPF reports on cgroup v2.
+#[cfg(unix)]
+pub fn cgroup_inode(cgroup_path: &str) -> std::io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(cgroup_path).map(|metadata| metadata.ino())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_three_parts() {
+        let parsed = CgroupsPath::parse(
+            "kubelet-kubepods-besteffort-podef89bdb6_d5d3.slice:cri-containerd:cafbf51b",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.slice,
+            "kubelet-kubepods-besteffort-podef89bdb6_d5d3.slice"
+        );
+        assert_eq!(parsed.runtime, "cri-containerd");
+        assert_eq!(parsed.container_id, "cafbf51b");
+    }
+
+    #[test]
+    fn test_parse_two_parts_is_rejected() {
+        assert_eq!(
+            CgroupsPath::parse("cri-containerd:cafbf51b"),
+            Err(CgroupsPathError::PartCount {
+                path: "cri-containerd:cafbf51b".into(),
+                parts: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert_eq!(CgroupsPath::parse(""), Err(CgroupsPathError::Empty));
+        assert!(matches!(
+            CgroupsPath::parse("/kubepods/besteffort/pod1/c1"),
+            Err(CgroupsPathError::NotColonDelimited(_))
+        ));
+        assert!(matches!(
+            CgroupsPath::parse("a.slice::c1"),
+            Err(CgroupsPathError::EmptyField {
+                field: "runtime",
+                ..
+            })
+        ));
+        assert!(matches!(
+            CgroupsPath::parse("a.slice:crio:"),
+            Err(CgroupsPathError::EmptyField {
+                field: "container id",
+                ..
+            })
+        ));
+        assert!(matches!(
+            CgroupsPath::parse("a:b:c:d"),
+            Err(CgroupsPathError::PartCount { parts: 4, .. })
+        ));
+    }
+}
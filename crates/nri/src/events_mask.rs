@@ -24,6 +24,30 @@ impl EventMask {
         Self(value)
     }
 
+    /// Create an EventMask with the container lifecycle events a plugin needs
+    /// to track running containers: START_CONTAINER and REMOVE_CONTAINER.
+    ///
+    /// START_CONTAINER is used rather than CREATE_CONTAINER because the
+    /// container's cgroup is guaranteed to exist by then.
+    pub fn for_container_lifecycle() -> Self {
+        let mut mask = Self::new();
+        mask.set(&[Event::START_CONTAINER, Event::REMOVE_CONTAINER]);
+        mask
+    }
+
+    /// Create an EventMask with the pod lifecycle events: RUN_POD_SANDBOX and
+    /// REMOVE_POD_SANDBOX.
+    pub fn for_pod_lifecycle() -> Self {
+        let mut mask = Self::new();
+        mask.set(&[Event::RUN_POD_SANDBOX, Event::REMOVE_POD_SANDBOX]);
+        mask
+    }
+
+    /// Return a mask with the Events set in either this mask or `other`.
+    pub fn union(self, other: Self) -> Self {
+        self | other
+    }
+
     /// Get the raw value of the EventMask.
     pub fn raw_value(&self) -> i32 {
         self.0
@@ -200,6 +224,38 @@ mod tests {
         assert!(!mask4.is_set(Event::STOP_CONTAINER));
     }
 
+    #[test]
+    fn test_lifecycle_masks() {
+        let container = EventMask::for_container_lifecycle();
+        assert!(container.is_set(Event::START_CONTAINER));
+        assert!(container.is_set(Event::REMOVE_CONTAINER));
+        assert!(!container.is_set(Event::CREATE_CONTAINER));
+        assert!(!container.is_set(Event::RUN_POD_SANDBOX));
+        assert_eq!(
+            container.pretty_string(),
+            "START_CONTAINER,REMOVE_CONTAINER"
+        );
+
+        let pod = EventMask::for_pod_lifecycle();
+        assert!(pod.is_set(Event::RUN_POD_SANDBOX));
+        assert!(pod.is_set(Event::REMOVE_POD_SANDBOX));
+        assert!(!pod.is_set(Event::UPDATE_POD_SANDBOX));
+        assert!(!pod.is_set(Event::START_CONTAINER));
+        assert_eq!(pod.pretty_string(), "RUN_POD_SANDBOX,REMOVE_POD_SANDBOX");
+
+        let both = container.union(pod);
+        assert_eq!(both, container | pod);
+        for event in [
+            Event::START_CONTAINER,
+            Event::REMOVE_CONTAINER,
+            Event::RUN_POD_SANDBOX,
+            Event::REMOVE_POD_SANDBOX,
+        ] {
+            assert!(both.is_set(event), "{:?} should be set", event);
+        }
+        assert_eq!(container.union(EventMask::new()), container);
+    }
+
     #[test]
     fn test_pretty_string() {
         let mut mask = EventMask::new();
@@ -6,18 +6,40 @@ pub mod api_ttrpc {
     include!(concat!(env!("OUT_DIR"), "/api_ttrpc.rs"));
 }
 
+pub mod cgroup_path;
 pub mod events_mask;
 pub mod metadata;
 pub mod multiplex;
+pub mod reconnect;
+pub mod send_policy;
 
 use anyhow::{anyhow, Result};
-use log::info;
+use log::{info, warn};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use ttrpc::context::Context;
 
 use api::RegisterPluginRequest;
 use api_ttrpc::{Plugin, RuntimeClient};
+use reconnect::{ReconnectConfig, ReconnectingNRI};
+
+/// Plugin indices that are popular defaults of the NRI sample plugins, so
+/// another plugin on the node is likely to share them. Plugins with equal
+/// indices are invoked in an undefined order. Our own default ("10") is not
+/// listed, so default deployments do not warn.
+pub const COMMON_PLUGIN_INDICES: &[&str] = &["00", "01"];
+
+/// Check that `plugin_idx` is a valid NRI plugin index: two decimal digits
+/// ("00" to "99"), which the runtime requires.
+pub fn validate_plugin_idx(plugin_idx: &str) -> Result<()> {
+    if plugin_idx.len() != 2 || !plugin_idx.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow!(
+            "invalid NRI plugin index '{}': must be two digits (00-99)",
+            plugin_idx
+        ));
+    }
+    Ok(())
+}
 
 /// NRI struct provides a focused interface for NRI plugins
 pub struct NRI {
@@ -153,14 +175,115 @@ impl NRI {
         Ok((nri, join_handle))
     }
 
+    /// Connect a plugin and keep it registered across runtime restarts
+    ///
+    /// Dials the socket with `dial`, creates the plugin server and registers it. When
+    /// the connection later drops, the socket is re-dialed and the plugin registered
+    /// again with exponential backoff according to `reconnect`. The runtime follows
+    /// each registration with `configure` and `synchronize`, which re-establishes the
+    /// plugin's event subscriptions and state.
+    ///
+    /// # Arguments
+    ///
+    /// * `dial` - Opens a new connection to the runtime socket
+    /// * `plugin` - Plugin implementation wrapped in `Arc`
+    /// * `plugin_name` - Name of the plugin
+    /// * `plugin_idx` - Index of the plugin (for ordering)
+    /// * `reconnect` - Backoff and retry limits for reconnection
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(ReconnectingNRI, JoinHandle<Result<()>>)>` - Handle and supervising task,
+    ///   which ends with an error once reconnection gives up. Fails if the first
+    ///   connection or registration fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nri::{NRI, metadata::MetadataPlugin, reconnect::ReconnectConfig};
+    /// use tokio::sync::mpsc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let (tx, rx) = mpsc::channel(100);
+    ///     let plugin = std::sync::Arc::new(MetadataPlugin::new(tx));
+    ///
+    ///     let (nri, join_handle) = NRI::new_with_reconnect(
+    ///         || async { Ok(tokio::net::UnixStream::connect("/var/run/nri/nri.sock").await?) },
+    ///         plugin,
+    ///         "metadata-plugin",
+    ///         "10",
+    ///         ReconnectConfig::default(),
+    ///     ).await?;
+    ///
+    ///     // Runs until reconnection gives up or `nri.close()` is called
+    ///     join_handle.await??;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new_with_reconnect<P, S, F, Fut>(
+        mut dial: F,
+        plugin: std::sync::Arc<P>,
+        plugin_name: &str,
+        plugin_idx: &str,
+        reconnect: ReconnectConfig,
+    ) -> Result<(ReconnectingNRI, JoinHandle<Result<()>>)>
+    where
+        P: Plugin + Send + Sync + 'static,
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<S>> + Send + 'static,
+    {
+        let socket = dial().await?;
+        let (nri, join_handle) =
+            Self::new_registered(socket, plugin.clone(), plugin_name, plugin_idx).await?;
+
+        Ok(reconnect::spawn_supervisor(
+            nri,
+            join_handle,
+            dial,
+            plugin,
+            plugin_name.to_string(),
+            plugin_idx.to_string(),
+            reconnect,
+        ))
+    }
+
+    /// Create the plugin server on `socket` and register it, closing it again if
+    /// registration fails
+    pub(crate) async fn new_registered<P: Plugin + Send + Sync + 'static>(
+        socket: impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        plugin: std::sync::Arc<P>,
+        plugin_name: &str,
+        plugin_idx: &str,
+    ) -> Result<(Self, JoinHandle<Result<()>>)> {
+        let (nri, join_handle) = Self::new(socket, plugin, plugin_name, plugin_idx).await?;
+        if let Err(e) = nri.register().await {
+            let _ = nri.close().await;
+            return Err(e);
+        }
+        Ok((nri, join_handle))
+    }
+
     /// Register the plugin with the runtime
     ///
-    /// This makes the RegisterPlugin RPC call to the runtime.
+    /// This makes the RegisterPlugin RPC call to the runtime. Fails without
+    /// contacting the runtime if the plugin index is invalid (see
+    /// `validate_plugin_idx`), and warns if it is one of
+    /// `COMMON_PLUGIN_INDICES`.
     ///
     /// # Returns
     ///
     /// * `Result<()>` - Success or error
     pub async fn register(&self) -> Result<()> {
+        validate_plugin_idx(&self.plugin_idx)?;
+        if COMMON_PLUGIN_INDICES.contains(&self.plugin_idx.as_str()) {
+            warn!(
+                "Plugin '{}' uses the common index {}; plugins sharing an index run in an undefined order",
+                self.plugin_name, self.plugin_idx
+            );
+        }
         info!("Registering plugin '{}' with runtime", self.plugin_name);
 
         // Create the register request
@@ -246,24 +369,25 @@ pub fn compute_full_cgroup_path(
         return String::new();
     }
 
-    // Helper to ensure a path is rooted at /sys/fs/cgroup without duplicating slashes
-    fn ensure_cgroup_prefix(path: &str) -> String {
-        if path.starts_with("/sys/fs/cgroup") {
-            path.to_string()
-        } else if path.starts_with('/') {
-            format!("/sys/fs/cgroup{}", path)
-        } else {
-            format!("/sys/fs/cgroup/{}", path)
+    // Parse the container cgroups path (slice:runtime:id)
+    let parsed = match cgroup_path::CgroupsPath::parse(container_cgroups_path) {
+        Ok(parsed) => Some(parsed),
+        // Plain paths (cgroupfs driver) and missing paths use the fallback below
+        Err(cgroup_path::CgroupsPathError::Empty)
+        | Err(cgroup_path::CgroupsPathError::NotColonDelimited(_)) => None,
+        Err(e) => {
+            warn!(
+                "container {}: unexpected cgroups_path format, using it verbatim: {}",
+                container.id, e
+            );
+            None
         }
-    }
-
-    // Parse the container cgroups path (colon-delimited)
-    let parts: Vec<&str> = container_cgroups_path.split(':').collect();
+    };
 
     // Preferred construction when we have both pod parent and container runtime/id
-    if parts.len() >= 3 && !pod_cgroup_parent.is_empty() {
-        let runtime = parts[1]; // e.g., "cri-containerd"
-        let container_id = parts[2]; // e.g., "cafb..."
+    if let (Some(parsed), false) = (parsed, pod_cgroup_parent.is_empty()) {
+        let runtime = parsed.runtime; // e.g., "cri-containerd"
+        let container_id = parsed.container_id; // e.g., "cafb..."
         let full_parent = ensure_cgroup_prefix(pod_cgroup_parent);
 
         // Detect cgroup hierarchy style:
@@ -280,3 +404,99 @@ pub fn compute_full_cgroup_path(
     // Fallback: return container path (already absolute) with the cgroup prefix if missing
     ensure_cgroup_prefix(container_cgroups_path)
 }
+
+/// Ensure a path is rooted at /sys/fs/cgroup without duplicating slashes
+fn ensure_cgroup_prefix(path: &str) -> String {
+    if path.starts_with("/sys/fs/cgroup") {
+        path.to_string()
+    } else if path.starts_with('/') {
+        format!("/sys/fs/cgroup{}", path)
+    } else {
+        format!("/sys/fs/cgroup/{}", path)
+    }
+}
+
+/// A cgroup path computed by [`compute_full_cgroup_path`] that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CgroupPathMismatch {
+    pub container_id: String,
+    /// The computed path
+    pub computed: String,
+    /// An existing path for the container under another naming scheme, with
+    /// the cgroup driver that names cgroups that way
+    pub found: Option<(String, &'static str)>,
+}
+
+impl std::fmt::Display for CgroupPathMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "computed cgroup path {} of container {} does not exist",
+            self.computed, self.container_id
+        )?;
+        match &self.found {
+            Some((path, driver)) => write!(
+                f,
+                "; found {} instead, so the runtime likely uses the {} cgroup driver",
+                path, driver
+            ),
+            None => write!(
+                f,
+                "; no systemd or cgroupfs style path exists either (is the host cgroup \
+                 filesystem mounted at /sys/fs/cgroup?)"
+            ),
+        }
+    }
+}
+
+/// Check the cgroup path computed for a running container against the host.
+///
+/// `exists` tells whether a path exists. Returns `None` when the computed path
+/// exists or none could be computed. Otherwise the paths the systemd and
+/// cgroupfs drivers would give the container are tried, so the mismatch can
+/// name the driver the host actually uses.
+pub fn check_cgroup_path(
+    container: &api::Container,
+    pod: Option<&api::PodSandbox>,
+    exists: impl Fn(&str) -> bool,
+) -> Option<CgroupPathMismatch> {
+    let computed = compute_full_cgroup_path(container, pod);
+    if computed.is_empty() || exists(computed.as_str()) {
+        return None;
+    }
+
+    let container_cgroups_path = container
+        .linux
+        .as_ref()
+        .map(|linux| linux.cgroups_path.as_str())
+        .unwrap_or("");
+    let pod_cgroup_parent = pod
+        .and_then(|p| p.linux.as_ref())
+        .map(|linux| linux.cgroup_parent.as_str())
+        .unwrap_or("");
+    let mut candidates = Vec::new();
+    if let Ok(parsed) = cgroup_path::CgroupsPath::parse(container_cgroups_path) {
+        if !pod_cgroup_parent.is_empty() {
+            let parent = ensure_cgroup_prefix(pod_cgroup_parent);
+            candidates.push((
+                format!(
+                    "{}/{}-{}.scope",
+                    parent, parsed.runtime, parsed.container_id
+                ),
+                "systemd",
+            ));
+            candidates.push((format!("{}/{}", parent, parsed.container_id), "cgroupfs"));
+        }
+    } else if !container_cgroups_path.is_empty() {
+        candidates.push((ensure_cgroup_prefix(container_cgroups_path), "cgroupfs"));
+    }
+
+    let found = candidates
+        .into_iter()
+        .find(|(path, _)| *path != computed && exists(path.as_str()));
+    Some(CgroupPathMismatch {
+        container_id: container.id.clone(),
+        computed,
+        found,
+    })
+}
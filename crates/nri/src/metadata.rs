@@ -1,7 +1,8 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 
 use log::{debug, info, warn};
@@ -16,9 +17,10 @@ use crate::api::{
 };
 use crate::api_ttrpc::Plugin;
 use crate::events_mask::EventMask;
+use crate::send_policy::SendPolicy;
 
 /// Container metadata collected from NRI.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ContainerMetadata {
     /// Container ID
     pub container_id: String,
@@ -32,8 +34,15 @@ pub struct ContainerMetadata {
     pub container_name: String,
     /// Cgroup path
     pub cgroup_path: String,
+    /// Inode number of `cgroup_path`, which matches the `cgroup_id` reported by
+    /// BPF on cgroup v2. `None` when the path could not be stat-ed (or off Linux).
+    pub cgroup_id: Option<u64>,
     /// Container process PID
     pub pid: Option<u32>,
+    /// When the container started (nanoseconds since the Unix epoch)
+    pub started_at: Option<u64>,
+    /// When the container exited (nanoseconds since the Unix epoch)
+    pub finished_at: Option<u64>,
     /// Container labels
     pub labels: HashMap<String, String>,
     /// Container annotations
@@ -49,24 +58,104 @@ pub enum MetadataMessage {
     Remove(String),
 }
 
+/// Destination of the messages emitted by `MetadataPlugin`.
+///
+/// Implemented for `mpsc::Sender<MetadataMessage>`; other sinks can deliver
+/// messages without a channel and separate consumer, e.g. straight to a file.
+#[async_trait::async_trait]
+pub trait MetadataSink: Send + Sync + 'static {
+    /// Deliver `msg`. Sinks with bounded capacity handle being full per
+    /// `policy`; an error means the message was dropped.
+    async fn send(&self, msg: MetadataMessage, policy: SendPolicy) -> anyhow::Result<()>;
+}
+
+#[async_trait::async_trait]
+impl MetadataSink for mpsc::Sender<MetadataMessage> {
+    async fn send(&self, msg: MetadataMessage, policy: SendPolicy) -> anyhow::Result<()> {
+        policy
+            .send(self, msg)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+}
+
+/// Pod namespaces whose containers the metadata plugin reports.
+///
+/// An empty allow list admits every namespace; the deny list applies on top of it.
+#[derive(Debug, Clone, Default)]
+pub struct NamespaceFilter {
+    /// Namespaces to report (empty = all)
+    pub allow: Vec<String>,
+    /// Namespaces never to report
+    pub deny: Vec<String>,
+}
+
+impl NamespaceFilter {
+    /// Whether containers in pod namespace `namespace` are reported.
+    pub fn allows(&self, namespace: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|n| n == namespace))
+            && !self.deny.iter().any(|n| n == namespace)
+    }
+}
+
 /// Metadata plugin for NRI.
 ///
-/// This plugin collects container metadata from the NRI runtime and sends it through
-/// a channel. It handles container lifecycle events and synchronization events.
+/// This plugin collects container metadata from the NRI runtime and sends it to a
+/// `MetadataSink`, by default a channel. It handles container lifecycle events and
+/// synchronization events.
 #[derive(Clone)]
-pub struct MetadataPlugin {
-    /// Channel for sending metadata messages
-    tx: mpsc::Sender<MetadataMessage>,
+pub struct MetadataPlugin<S = mpsc::Sender<MetadataMessage>> {
+    /// Sink receiving metadata messages
+    sink: S,
     /// Counter for dropped messages
     dropped_messages: Arc<AtomicUsize>,
+    /// Behavior when the sink is full
+    send_policy: SendPolicy,
+    /// Pod namespaces to report
+    namespace_filter: Arc<Mutex<NamespaceFilter>>,
+    /// Pod namespace of each container reported with an `Add`
+    reported: Arc<Mutex<HashMap<String, String>>>,
 }
 
-impl MetadataPlugin {
-    /// Create a new metadata plugin with the given sender.
-    pub fn new(tx: mpsc::Sender<MetadataMessage>) -> Self {
+impl<S: MetadataSink> MetadataPlugin<S> {
+    /// Create a new metadata plugin with the given sink (e.g. a channel sender).
+    pub fn new(sink: S) -> Self {
+        Self::with_send_policy(sink, SendPolicy::Drop)
+    }
+
+    /// Create a new metadata plugin that handles a full sink per `send_policy`.
+    pub fn with_send_policy(sink: S, send_policy: SendPolicy) -> Self {
         Self {
-            tx,
+            sink,
             dropped_messages: Arc::new(AtomicUsize::new(0)),
+            send_policy,
+            namespace_filter: Arc::new(Mutex::new(NamespaceFilter::default())),
+            reported: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Only report containers whose pod namespace passes `filter`.
+    ///
+    /// Containers already reported that fall outside the new filter get a
+    /// `Remove` message.
+    pub async fn set_namespace_filter(&self, filter: NamespaceFilter) {
+        let removed: Vec<String> = {
+            let mut reported = self.reported.lock().unwrap();
+            let removed = reported
+                .iter()
+                .filter(|(_, namespace)| !filter.allows(namespace))
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>();
+            for id in &removed {
+                reported.remove(id);
+            }
+            removed
+        };
+        *self.namespace_filter.lock().unwrap() = filter;
+
+        for id in removed {
+            debug!("container {} filtered out by namespace", id);
+            self.send_message(MetadataMessage::Remove(id)).await;
         }
     }
 
@@ -82,6 +171,7 @@ impl MetadataPlugin {
         pod: Option<&api::PodSandbox>,
     ) -> ContainerMetadata {
         let cgroup_path = crate::compute_full_cgroup_path(container, pod);
+        let cgroup_id = cgroup_id(&cgroup_path);
 
         let (pod_name, pod_namespace, pod_uid) = if let Some(pod) = pod {
             (pod.name.clone(), pod.namespace.clone(), pod.uid.clone())
@@ -96,45 +186,92 @@ impl MetadataPlugin {
             pod_uid,
             container_name: container.name.clone(),
             cgroup_path,
+            cgroup_id,
             pid: if container.pid > 0 {
                 Some(container.pid)
             } else {
                 None
             },
+            started_at: nonzero_timestamp(container.started_at),
+            finished_at: nonzero_timestamp(container.finished_at),
             labels: container.labels.clone(),
             annotations: container.annotations.clone(),
         }
     }
 
-    /// Send a metadata message through the channel.
-    fn send_message(&self, message: MetadataMessage) {
-        // Use try_send to avoid blocking the runtime
-        if let Err(e) = self.tx.try_send(message) {
+    /// Send a metadata message to the sink.
+    async fn send_message(&self, message: MetadataMessage) {
+        // Only waits (briefly) if configured with SendPolicy::BlockWithTimeout
+        if let Err(e) = self.sink.send(message, self.send_policy).await {
             self.dropped_messages.fetch_add(1, Ordering::Relaxed);
             warn!("Failed to send metadata message: {}", e);
         }
     }
 
+    /// Send an `Add` for a container if its pod namespace is in scope, or a
+    /// `Remove` if it was reported before and has left the scope.
+    async fn report_container(&self, container: &api::Container, pod: Option<&api::PodSandbox>) {
+        let namespace = pod.map_or("", |pod| pod.namespace.as_str());
+        if !self.namespace_filter.lock().unwrap().allows(namespace) {
+            let was_reported = self
+                .reported
+                .lock()
+                .unwrap()
+                .remove(&container.id)
+                .is_some();
+            if was_reported {
+                debug!("container {} filtered out by namespace", container.id);
+                self.send_message(MetadataMessage::Remove(container.id.clone()))
+                    .await;
+            } else {
+                debug!(
+                    "skipping container {} in filtered namespace '{}'",
+                    container.id, namespace
+                );
+            }
+            return;
+        }
+
+        let metadata = self.extract_metadata(container, pod);
+        debug!("Adding container metadata: {:?}", metadata);
+        self.reported
+            .lock()
+            .unwrap()
+            .insert(container.id.clone(), namespace.to_string());
+        self.send_message(MetadataMessage::Add(
+            container.id.clone(),
+            Box::new(metadata),
+        ))
+        .await;
+    }
+
     /// Initial synchronization handler for containers: send metadata messages.
-    fn process_containers(&self, containers: &[api::Container], pods: &[api::PodSandbox]) {
+    async fn process_containers(&self, containers: &[api::Container], pods: &[api::PodSandbox]) {
         let pods_map: HashMap<String, &api::PodSandbox> =
             pods.iter().map(|pod| (pod.id.clone(), pod)).collect();
 
         for container in containers {
             let pod = pods_map.get(&container.pod_sandbox_id).copied();
-            let metadata = self.extract_metadata(container, pod);
+            self.report_container(container, pod).await;
+        }
 
-            debug!("Adding container metadata: {:?}", metadata);
-            self.send_message(MetadataMessage::Add(
-                container.id.clone(),
-                Box::new(metadata),
-            ));
+        // Check path computation once against a container whose cgroup must exist,
+        // since a mismatch leaves every container without its cgroup id
+        let sample = containers
+            .iter()
+            .find(|c| c.state.enum_value() == Ok(api::ContainerState::CONTAINER_RUNNING));
+        if let Some(container) = sample {
+            let pod = pods_map.get(&container.pod_sandbox_id).copied();
+            match crate::check_cgroup_path(container, pod, |path| Path::new(path).exists()) {
+                Some(mismatch) => warn!("{}", mismatch),
+                None => debug!("cgroup path of container {} verified", container.id),
+            }
         }
     }
 }
 
 #[async_trait::async_trait]
-impl Plugin for MetadataPlugin {
+impl<S: MetadataSink> Plugin for MetadataPlugin<S> {
     async fn configure(
         &self,
         _ctx: &TtrpcContext,
@@ -147,8 +284,7 @@ impl Plugin for MetadataPlugin {
 
         // Subscribe to container lifecycle events where cgroup is guaranteed to exist
         // Use START_CONTAINER (not CREATE) and REMOVE_CONTAINER for cleanup notifications
-        let mut events = EventMask::new();
-        events.set(&[Event::START_CONTAINER, Event::REMOVE_CONTAINER]);
+        let events = EventMask::for_container_lifecycle();
 
         Ok(ConfigureResponse {
             events: events.raw_value(),
@@ -168,7 +304,7 @@ impl Plugin for MetadataPlugin {
         );
 
         // Process existing containers
-        self.process_containers(&req.containers, &req.pods);
+        self.process_containers(&req.containers, &req.pods).await;
 
         // We don't request any container updates
         Ok(SynchronizeResponse {
@@ -223,18 +359,25 @@ impl Plugin for MetadataPlugin {
         match req.event.enum_value() {
             Ok(Event::START_CONTAINER) => {
                 if let (Some(pod), Some(container)) = (req.pod.as_ref(), req.container.as_ref()) {
-                    let metadata = self.extract_metadata(container, Some(pod));
                     debug!("container started: {}", container.id);
-                    self.send_message(MetadataMessage::Add(
-                        container.id.clone(),
-                        Box::new(metadata),
-                    ));
+                    self.report_container(container, Some(pod)).await;
                 }
             }
             Ok(Event::REMOVE_CONTAINER) => {
                 if let Some(container) = req.container.as_ref() {
                     debug!("container removed: {}", container.id);
-                    self.send_message(MetadataMessage::Remove(container.id.clone()));
+                    let was_reported = self
+                        .reported
+                        .lock()
+                        .unwrap()
+                        .remove(&container.id)
+                        .is_some();
+                    let namespace = req.pod.as_ref().map_or("", |pod| pod.namespace.as_str());
+                    // Containers never reported because of the namespace filter need no Remove
+                    if was_reported || self.namespace_filter.lock().unwrap().allows(namespace) {
+                        self.send_message(MetadataMessage::Remove(container.id.clone()))
+                            .await;
+                    }
                 }
             }
             _ => {}
@@ -243,6 +386,31 @@ impl Plugin for MetadataPlugin {
     }
 }
 
+/// NRI timestamps are zero until the event has happened.
+fn nonzero_timestamp(ns: i64) -> Option<u64> {
+    (ns > 0).then_some(ns as u64)
+}
+
+/// Resolve the cgroup id of `cgroup_path` by stat-ing it for its inode number.
+#[cfg(target_os = "linux")]
+fn cgroup_id(cgroup_path: &str) -> Option<u64> {
+    if cgroup_path.is_empty() {
+        return None;
+    }
+    match crate::cgroup_path::cgroup_inode(cgroup_path) {
+        Ok(inode) => Some(inode),
+        Err(e) => {
+            debug!("cannot resolve cgroup id of {}: {}", cgroup_path, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_id(_cgroup_path: &str) -> Option<u64> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -261,6 +429,8 @@ mod tests {
             pod_sandbox_id: "pod1".to_string(),
             name: "test-container".to_string(),
             pid: 1234,
+            created_at: 1_700_000_000_000_000_000,
+            started_at: 1_700_000_001_000_000_000,
             linux: MessageField::some(api::LinuxContainer {
                 cgroups_path:
                     "kubelet-kubepods-besteffort-pod123.slice:cri-containerd:abc123def456"
@@ -316,12 +486,17 @@ mod tests {
             assert_eq!(metadata.container_name, "test-container");
             assert_eq!(metadata.cgroup_path, "/sys/fs/cgroup/kubelet.slice/kubelet-kubepods.slice/kubelet-kubepods-besteffort.slice/kubelet-kubepods-besteffort-pod123.slice/cri-containerd-abc123def456.scope");
             assert_eq!(metadata.pid, Some(1234));
+            assert_eq!(metadata.started_at, Some(1_700_000_001_000_000_000));
+            // Still running: finished_at is zero in NRI
+            assert_eq!(metadata.finished_at, None);
 
             // Test sending a message per iteration
-            plugin.send_message(MetadataMessage::Add(
-                container.id.clone(),
-                Box::new(metadata),
-            ));
+            plugin
+                .send_message(MetadataMessage::Add(
+                    container.id.clone(),
+                    Box::new(metadata),
+                ))
+                .await;
 
             // Verify message was received
             let message = rx.recv().await.unwrap();
@@ -375,6 +550,151 @@ mod tests {
         assert_eq!(metadata.pid, Some(1234));
     }
 
+    #[test]
+    fn test_cgroup_path_mismatch_names_likely_driver() {
+        let container = api::Container {
+            id: "abc123".to_string(),
+            linux: MessageField::some(api::LinuxContainer {
+                cgroups_path: "kubepods-pod123.slice:cri-containerd:abc123".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let pod = api::PodSandbox {
+            linux: MessageField::some(api::LinuxPodSandbox {
+                cgroup_parent: "/kubepods/besteffort/pod123".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let computed = "/sys/fs/cgroup/kubepods/besteffort/pod123/abc123";
+        let scope = "/sys/fs/cgroup/kubepods/besteffort/pod123/cri-containerd-abc123.scope";
+
+        // The computed path exists
+        assert_eq!(
+            crate::check_cgroup_path(&container, Some(&pod), |p| p == computed),
+            None
+        );
+
+        // Only the systemd-style scope exists
+        let mismatch = crate::check_cgroup_path(&container, Some(&pod), |p| p == scope).unwrap();
+        assert_eq!(mismatch.computed, computed);
+        assert_eq!(mismatch.found, Some((scope.to_string(), "systemd")));
+        assert!(mismatch.to_string().contains("systemd cgroup driver"));
+
+        // Nothing exists
+        let mismatch = crate::check_cgroup_path(&container, Some(&pod), |_| false).unwrap();
+        assert_eq!(mismatch.found, None);
+        assert!(mismatch.to_string().contains("does not exist"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_cgroup_id_is_path_inode() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cgroup = dir.path().join("cri-containerd-abc.scope");
+        std::fs::create_dir(&cgroup).unwrap();
+        let inode = std::fs::metadata(&cgroup).unwrap().ino();
+
+        assert_eq!(cgroup_id(cgroup.to_str().unwrap()), Some(inode));
+        assert_eq!(cgroup_id(dir.path().join("gone").to_str().unwrap()), None);
+        assert_eq!(cgroup_id(""), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_policy_on_full_channel() {
+        use std::time::Duration;
+
+        // Drop: the second message is lost while the channel is full
+        let (tx, _rx) = mpsc::channel(1);
+        let plugin = MetadataPlugin::new(tx);
+        plugin
+            .send_message(MetadataMessage::Remove("c1".into()))
+            .await;
+        plugin
+            .send_message(MetadataMessage::Remove("c2".into()))
+            .await;
+        assert_eq!(plugin.dropped_messages(), 1);
+
+        // BlockWithTimeout: the second message waits for the consumer
+        let (tx, mut rx) = mpsc::channel(1);
+        let plugin = MetadataPlugin::with_send_policy(
+            tx,
+            SendPolicy::BlockWithTimeout(Duration::from_secs(5)),
+        );
+        plugin
+            .send_message(MetadataMessage::Remove("c1".into()))
+            .await;
+        let consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut ids = Vec::new();
+            while let Some(MetadataMessage::Remove(id)) = rx.recv().await {
+                ids.push(id);
+            }
+            ids
+        });
+        plugin
+            .send_message(MetadataMessage::Remove("c2".into()))
+            .await;
+        assert_eq!(plugin.dropped_messages(), 0);
+        drop(plugin);
+        assert_eq!(consumer.await.unwrap(), vec!["c1", "c2"]);
+    }
+
+    /// Sink collecting messages in memory
+    #[derive(Clone, Default)]
+    struct VecSink(Arc<Mutex<Vec<MetadataMessage>>>);
+
+    #[async_trait::async_trait]
+    impl MetadataSink for VecSink {
+        async fn send(&self, msg: MetadataMessage, _policy: SendPolicy) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(msg);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_sink_receives_messages() {
+        let sink = VecSink::default();
+        let plugin = MetadataPlugin::new(sink.clone());
+
+        let pod = api::PodSandbox {
+            id: "pod1".to_string(),
+            name: "web".to_string(),
+            namespace: "default".to_string(),
+            uid: "uid1".to_string(),
+            ..Default::default()
+        };
+        let container = api::Container {
+            id: "c1".to_string(),
+            pod_sandbox_id: "pod1".to_string(),
+            name: "app".to_string(),
+            ..Default::default()
+        };
+        plugin.process_containers(&[container], &[pod]).await;
+        plugin
+            .set_namespace_filter(NamespaceFilter {
+                allow: Vec::new(),
+                deny: vec!["default".to_string()],
+            })
+            .await;
+
+        let messages = sink.0.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        match &messages[0] {
+            MetadataMessage::Add(id, metadata) => {
+                assert_eq!(id, "c1");
+                assert_eq!(metadata.pod_name, "web");
+                assert_eq!(metadata.container_name, "app");
+            }
+            other => panic!("expected Add, got {:?}", other),
+        }
+        assert!(matches!(&messages[1], MetadataMessage::Remove(id) if id == "c1"));
+        assert_eq!(plugin.dropped_messages(), 0);
+    }
+
     #[tokio::test]
     async fn test_metadata_plugin_lifecycle() {
         // Create a channel for testing with sufficient capacity
@@ -577,4 +897,82 @@ mod tests {
             _ => panic!("Expected Remove message for container1"),
         }
     }
+
+    #[tokio::test]
+    async fn test_namespace_filter() {
+        let (tx, mut rx) = mpsc::channel(100);
+        let plugin = MetadataPlugin::new(tx);
+        plugin
+            .set_namespace_filter(NamespaceFilter {
+                allow: vec![],
+                deny: vec!["kube-system".to_string()],
+            })
+            .await;
+
+        let context = TtrpcContext {
+            mh: ttrpc::MessageHeader::default(),
+            metadata: HashMap::<String, Vec<String>>::default(),
+            timeout_nano: 5000,
+        };
+        let pod = |id: &str, namespace: &str| api::PodSandbox {
+            id: id.to_string(),
+            namespace: namespace.to_string(),
+            ..Default::default()
+        };
+        let container = |id: &str, pod_id: &str| api::Container {
+            id: id.to_string(),
+            pod_sandbox_id: pod_id.to_string(),
+            ..Default::default()
+        };
+
+        // Synchronize: only the container outside kube-system is reported
+        let sync_req = SynchronizeRequest {
+            pods: vec![pod("p1", "kube-system"), pod("p2", "default")],
+            containers: vec![container("c1", "p1"), container("c2", "p2")],
+            more: false,
+            special_fields: SpecialFields::default(),
+        };
+        plugin.synchronize(&context, sync_req).await.unwrap();
+        match rx.try_recv().unwrap() {
+            MetadataMessage::Add(id, metadata) => {
+                assert_eq!(id, "c2");
+                assert_eq!(metadata.pod_namespace, "default");
+            }
+            _ => panic!("Expected Add message for c2"),
+        }
+        assert!(rx.try_recv().is_err());
+
+        // Start and remove in kube-system: nothing is sent
+        for event in [Event::START_CONTAINER, Event::REMOVE_CONTAINER] {
+            let sc_req = api::StateChangeEvent {
+                pod: MessageField::some(pod("p3", "kube-system")),
+                container: MessageField::some(container("c3", "p3")),
+                event: EnumOrUnknown::new(event),
+                special_fields: SpecialFields::default(),
+            };
+            plugin.state_change(&context, sc_req).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+
+        // Denying a namespace removes the containers already reported from it
+        plugin
+            .set_namespace_filter(NamespaceFilter {
+                allow: vec![],
+                deny: vec!["kube-system".to_string(), "default".to_string()],
+            })
+            .await;
+        assert!(matches!(rx.try_recv().unwrap(), MetadataMessage::Remove(id) if id == "c2"));
+        assert!(rx.try_recv().is_err());
+
+        assert!(NamespaceFilter {
+            allow: vec!["default".to_string()],
+            deny: vec![],
+        }
+        .allows("default"));
+        assert!(!NamespaceFilter {
+            allow: vec!["default".to_string()],
+            deny: vec![],
+        }
+        .allows("kube-system"));
+    }
 }
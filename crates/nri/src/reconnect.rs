@@ -0,0 +1,271 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::api_ttrpc::Plugin;
+use crate::NRI;
+
+/// Default number of reconnection attempts before giving up
+pub const DEFAULT_MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Default delay before the first reconnection attempt
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Default upper bound on the delay between reconnection attempts
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Limits for re-establishing a dropped NRI connection.
+///
+/// Attempts are spaced with exponential backoff starting at `initial_backoff` and
+/// capped at `max_backoff`. After `max_attempts` consecutive failures the caller
+/// gets an error instead of retrying forever against a broken socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectConfig {
+    /// Maximum consecutive attempts (0 disables reconnection)
+    pub max_attempts: u32,
+    /// Delay before the first attempt; doubles after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_RECONNECT_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Delay to wait before the given attempt (1-based)
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let shift = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1u32 << shift)
+            .min(self.max_backoff)
+    }
+}
+
+/// Run `connect` until it succeeds or `config.max_attempts` attempts have failed.
+///
+/// Each attempt is preceded by its backoff delay. Returns the last error, annotated
+/// with the number of attempts, once the limit is reached.
+pub async fn retry_with_backoff<T, F, Fut>(config: &ReconnectConfig, mut connect: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    if config.max_attempts == 0 {
+        return Err(anyhow!("NRI reconnection is disabled (max attempts is 0)"));
+    }
+
+    let mut last_err = None;
+    for attempt in 1..=config.max_attempts {
+        tokio::time::sleep(config.backoff_for(attempt)).await;
+        match connect().await {
+            Ok(v) => return Ok(v),
+            Err(e) => {
+                warn!(
+                    "NRI reconnection attempt {}/{} failed: {}",
+                    attempt, config.max_attempts, e
+                );
+                last_err = Some(e);
+            }
+        }
+    }
+
+    let last_err = last_err.expect("at least one attempt was made");
+    Err(anyhow!(
+        "giving up on NRI connection after {} attempts: {}",
+        config.max_attempts,
+        last_err
+    ))
+}
+
+/// Handle to a plugin connection created by [`NRI::new_with_reconnect`].
+///
+/// The connection is re-established behind this handle, so unlike [`NRI`] it only
+/// offers `close`. Dropping the handle also stops the plugin.
+pub struct ReconnectingNRI {
+    /// Shutdown channel sender for the supervising task
+    shutdown_tx: mpsc::Sender<()>,
+}
+
+impl ReconnectingNRI {
+    /// Stop reconnecting and close the current connection
+    pub async fn close(&self) -> Result<()> {
+        info!("Closing reconnecting NRI connection");
+        let _ = self.shutdown_tx.send(()).await;
+        Ok(())
+    }
+}
+
+/// Spawn the task that keeps `nri` registered, re-dialing with `dial` whenever
+/// its plugin server ends.
+pub(crate) fn spawn_supervisor<P, S, F, Fut>(
+    nri: NRI,
+    join_handle: JoinHandle<Result<()>>,
+    dial: F,
+    plugin: Arc<P>,
+    plugin_name: String,
+    plugin_idx: String,
+    config: ReconnectConfig,
+) -> (ReconnectingNRI, JoinHandle<Result<()>>)
+where
+    P: Plugin + Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<S>> + Send + 'static,
+{
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
+    let handle = tokio::spawn(supervise(
+        nri,
+        join_handle,
+        dial,
+        plugin,
+        plugin_name,
+        plugin_idx,
+        config,
+        shutdown_rx,
+    ));
+    (ReconnectingNRI { shutdown_tx }, handle)
+}
+
+/// Wait for the plugin server to end, then reconnect and re-register with backoff.
+///
+/// Returns `Ok(())` on shutdown, or the error from `retry_with_backoff` once
+/// reconnection gives up.
+#[allow(clippy::too_many_arguments)]
+async fn supervise<P, S, F, Fut>(
+    mut nri: NRI,
+    mut join_handle: JoinHandle<Result<()>>,
+    mut dial: F,
+    plugin: Arc<P>,
+    plugin_name: String,
+    plugin_idx: String,
+    config: ReconnectConfig,
+    mut shutdown_rx: mpsc::Receiver<()>,
+) -> Result<()>
+where
+    P: Plugin + Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<S>> + Send + 'static,
+{
+    loop {
+        tokio::select! {
+            res = &mut join_handle => {
+                match res {
+                    Ok(Ok(())) => warn!("NRI connection for '{}' closed", plugin_name),
+                    Ok(Err(e)) => warn!("NRI connection for '{}' failed: {}", plugin_name, e),
+                    Err(e) => warn!("NRI plugin task for '{}' panicked or was cancelled: {}", plugin_name, e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                let _ = nri.close().await;
+                let _ = join_handle.await;
+                return Ok(());
+            }
+        }
+
+        // The runtime sends Configure and Synchronize again after registration,
+        // so the plugin resubscribes and sees the current pods and containers.
+        let reconnected = tokio::select! {
+            res = retry_with_backoff(&config, || {
+                let stream = dial();
+                let plugin = plugin.clone();
+                let plugin_name = plugin_name.clone();
+                let plugin_idx = plugin_idx.clone();
+                async move {
+                    NRI::new_registered(stream.await?, plugin, &plugin_name, &plugin_idx).await
+                }
+            }) => res?,
+            _ = shutdown_rx.recv() => return Ok(()),
+        };
+        info!("Plugin '{}' reconnected to NRI runtime", plugin_name);
+        (nri, join_handle) = reconnected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_config(max_attempts: u32) -> ReconnectConfig {
+        ReconnectConfig {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_is_capped() {
+        let cfg = ReconnectConfig {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        assert_eq!(cfg.backoff_for(1), Duration::from_millis(100));
+        assert_eq!(cfg.backoff_for(2), Duration::from_millis(200));
+        assert_eq!(cfg.backoff_for(3), Duration::from_millis(400));
+        assert_eq!(cfg.backoff_for(4), Duration::from_millis(500));
+        assert_eq!(cfg.backoff_for(40), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+        let res: Result<()> = retry_with_backoff(&fast_config(3), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(anyhow!("connection refused")) }
+        })
+        .await;
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        let msg = res.unwrap_err().to_string();
+        assert!(msg.contains("after 3 attempts"), "{}", msg);
+        assert!(msg.contains("connection refused"), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn test_succeeds_after_transient_failures() {
+        let attempts = AtomicU32::new(0);
+        let res = retry_with_backoff(&fast_config(5), || {
+            let n = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if n < 3 {
+                    Err(anyhow!("not yet"))
+                } else {
+                    Ok(n)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_zero_attempts_disables_reconnection() {
+        let attempts = AtomicU32::new(0);
+        let res: Result<()> = retry_with_backoff(&fast_config(0), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Policy for sending plugin events on a bounded channel that may be full.
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{SendTimeoutError, TrySendError};
+
+/// What to do with an event when the channel to the consumer is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendPolicy {
+    /// Drop the event immediately.
+    #[default]
+    Drop,
+    /// Wait up to the given duration for capacity, then drop the event.
+    ///
+    /// The wait is asynchronous, so it must not be awaited while holding a
+    /// lock the consumer may need; callers that emit events from synchronous
+    /// code queue them and send from a task instead.
+    BlockWithTimeout(Duration),
+}
+
+impl SendPolicy {
+    /// Send `msg` on `tx` according to the policy.
+    ///
+    /// Returns `TrySendError::Full` with the message if the channel was still
+    /// full when the policy gave up, or `TrySendError::Closed` if the consumer
+    /// is gone.
+    pub async fn send<T>(&self, tx: &mpsc::Sender<T>, msg: T) -> Result<(), TrySendError<T>> {
+        match self {
+            SendPolicy::Drop => tx.try_send(msg),
+            SendPolicy::BlockWithTimeout(timeout) => {
+                tx.send_timeout(msg, *timeout).await.map_err(|e| match e {
+                    SendTimeoutError::Timeout(msg) => TrySendError::Full(msg),
+                    SendTimeoutError::Closed(msg) => TrySendError::Closed(msg),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_drop_policy_fails_on_full_channel() {
+        let (tx, _rx) = mpsc::channel::<u32>(1);
+        SendPolicy::Drop.send(&tx, 1).await.unwrap();
+        assert!(matches!(
+            SendPolicy::Drop.send(&tx, 2).await,
+            Err(TrySendError::Full(2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_times_out() {
+        let (tx, _rx) = mpsc::channel::<u32>(1);
+        let policy = SendPolicy::BlockWithTimeout(Duration::from_millis(20));
+        policy.send(&tx, 1).await.unwrap();
+
+        let start = Instant::now();
+        assert!(matches!(
+            policy.send(&tx, 2).await,
+            Err(TrySendError::Full(2))
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_delivers_once_drained() {
+        let (tx, mut rx) = mpsc::channel::<u32>(1);
+        let policy = SendPolicy::BlockWithTimeout(Duration::from_secs(5));
+        policy.send(&tx, 1).await.unwrap();
+
+        let consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut got = Vec::new();
+            while let Some(v) = rx.recv().await {
+                got.push(v);
+            }
+            got
+        });
+
+        // Waits without blocking the (single-threaded) runtime the consumer runs on
+        policy.send(&tx, 2).await.unwrap();
+        drop(tx);
+        assert_eq!(consumer.await.unwrap(), vec![1, 2]);
+    }
+}
@@ -323,6 +323,31 @@ async fn test_nri_creation() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_register_rejects_invalid_plugin_index() -> Result<()> {
+    for idx in ["ab", "5", "100", "-1", ""] {
+        let (_runtime_stream, plugin_stream) = tokio::io::duplex(1024);
+        let plugin = std::sync::Arc::new(CounterPlugin::new());
+        let (nri, _join_handle) = NRI::new(plugin_stream, plugin, "test-plugin", idx).await?;
+
+        // Fails before any RPC: nothing serves the runtime end
+        let err = timeout(Duration::from_secs(1), nri.register())
+            .await
+            .expect("register should fail without waiting for the runtime")
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("invalid NRI plugin index"),
+            "{}: {}",
+            idx,
+            err
+        );
+        nri.close().await?;
+    }
+
+    nri::validate_plugin_idx("05")?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_counter_plugin_with_nri() -> Result<()> {
     // Create a duplex pipe for communication
@@ -539,3 +564,99 @@ async fn test_nri_connection_error_handling() -> Result<()> {
 
     Ok(())
 }
+
+/// Serve a `MockRuntimeService` on the runtime end of a connection
+async fn start_mock_runtime(
+    runtime_stream: tokio::io::DuplexStream,
+) -> Result<(Mux, MockRuntimeService, tokio::task::JoinHandle<Result<()>>)> {
+    let runtime_mux = Mux::new(runtime_stream);
+    let ttrpc_socket = Socket::new(runtime_mux.open(RUNTIME_SERVICE_CONN).await?);
+
+    let runtime_service = MockRuntimeService::new();
+    let service_map = nri::api_ttrpc::create_runtime(Arc::new(runtime_service.clone()));
+    let mut runtime_server = ttrpc::r#async::Server::new().register_service(service_map);
+    let server_handle = tokio::spawn(async move {
+        runtime_server
+            .start_connected(ttrpc_socket)
+            .await
+            .map_err(|e| anyhow::anyhow!("Server error: {}", e))
+    });
+
+    Ok((runtime_mux, runtime_service, server_handle))
+}
+
+/// Wait until the runtime has seen a registration
+async fn wait_for_registration(runtime_service: &MockRuntimeService) -> Result<()> {
+    timeout(Duration::from_secs(5), async {
+        while !*runtime_service.register_called.lock().await {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("plugin did not register"))
+}
+
+#[tokio::test]
+async fn test_reconnect_after_runtime_restart() -> Result<()> {
+    // Each dial hands out the plugin end of the next queued connection
+    let (stream_tx, stream_rx) = tokio::sync::mpsc::channel::<tokio::io::DuplexStream>(2);
+    let stream_rx = Arc::new(Mutex::new(stream_rx));
+    let dial = move || {
+        let stream_rx = stream_rx.clone();
+        async move {
+            stream_rx
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("no more connections"))
+        }
+    };
+
+    let (runtime_stream, plugin_stream) = tokio::io::duplex(1024);
+    stream_tx.send(plugin_stream).await?;
+    let (runtime_mux, runtime_service, server_handle) = start_mock_runtime(runtime_stream).await?;
+
+    let plugin = Arc::new(CounterPlugin::new());
+    let configure_count = plugin.configure_count.clone();
+    let reconnect = nri::reconnect::ReconnectConfig {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    };
+    let (nri, mut join_handle) =
+        NRI::new_with_reconnect(dial, plugin, "reconnect-plugin", "10", reconnect).await?;
+    wait_for_registration(&runtime_service).await?;
+
+    // Queue the connection of the restarted runtime, then drop the first one
+    let (runtime_stream, plugin_stream) = tokio::io::duplex(1024);
+    stream_tx.send(plugin_stream).await?;
+    let (restarted_mux, mut restarted_service, restarted_handle) =
+        start_mock_runtime(runtime_stream).await?;
+    runtime_mux.shutdown().await?;
+    server_handle.abort();
+
+    // The plugin registers again and answers configure on the new connection
+    wait_for_registration(&restarted_service).await?;
+    assert_eq!(
+        *restarted_service.plugin_name.lock().await,
+        "reconnect-plugin"
+    );
+    let plugin_socket = restarted_mux
+        .open(nri::multiplex::PLUGIN_SERVICE_CONN)
+        .await?;
+    let client = ttrpc::r#async::Client::new(Socket::new(plugin_socket));
+    restarted_service
+        .set_plugin_client(nri::api_ttrpc::PluginClient::new(client))
+        .await;
+    let events = EventMask::from_raw(restarted_service.call_configure().await?.events);
+    assert!(events.is_set(Event::CREATE_CONTAINER));
+    assert_eq!(*configure_count.lock().unwrap(), 1);
+
+    // Closing stops the supervisor without reconnecting again
+    nri.close().await?;
+    timeout(Duration::from_secs(1), &mut join_handle).await???;
+    restarted_handle.abort();
+
+    Ok(())
+}
@@ -2,7 +2,8 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use thiserror::Error;
 
 use crate::{
-    PerfRingError, Reader, ReaderError, SampleHeader, PERF_RECORD_LOST, PERF_RECORD_SAMPLE,
+    lost_record_count, PerfRingError, Reader, ReaderError, SampleHeader, PERF_RECORD_LOST,
+    PERF_RECORD_SAMPLE,
 };
 
 /// Errors that can occur during dispatch operations
@@ -27,6 +28,9 @@ pub struct Stats {
     /// Number of lost message events processed
     pub lost_events_processed: usize,
 
+    /// Number of samples the lost message events reported as dropped
+    pub lost_samples: u64,
+
     /// Number of errors returned from callbacks
     pub callback_errors: usize,
 
@@ -45,6 +49,9 @@ pub struct Dispatcher {
     /// Callbacks for lost sample events
     lost_subscribers: Vec<Subscriber>,
 
+    /// Dropped samples reported by lost events, by ring index
+    lost_samples: Vec<u64>,
+
     /// Statistics counters
     stats: Stats,
 }
@@ -55,6 +62,7 @@ impl Dispatcher {
         Dispatcher {
             sample_subscribers: HashMap::new(),
             lost_subscribers: Vec::new(),
+            lost_samples: Vec::new(),
             stats: Stats::default(),
         }
     }
@@ -64,6 +72,12 @@ impl Dispatcher {
         self.stats
     }
 
+    /// Returns the samples reported as dropped, by ring index. Rings past the
+    /// end of the slice have not reported any.
+    pub fn lost_samples(&self) -> &[u64] {
+        &self.lost_samples
+    }
+
     /// Subscribe to events of a specific message type
     pub fn subscribe<F>(&mut self, message_type: u32, callback: F)
     where
@@ -139,7 +153,13 @@ impl Dispatcher {
                 for subscriber in &mut self.lost_subscribers {
                     subscriber(ring_index, &event_data);
                 }
+                let lost = lost_record_count(&event_data);
+                if self.lost_samples.len() <= ring_index {
+                    self.lost_samples.resize(ring_index + 1, 0);
+                }
+                self.lost_samples[ring_index] += lost;
                 self.stats.lost_events_processed += 1;
+                self.stats.lost_samples += lost;
             }
             _ => {
                 // Unhandled event type, just track as dropped
@@ -373,6 +393,58 @@ mod tests {
         reader.finish().unwrap();
     }
 
+    #[test]
+    fn test_dispatcher_counts_lost_samples() {
+        // Setup test rings and reader
+        let page_size = 4096u64;
+        let n_pages = 2u32;
+        let mut data1 = vec![0u8; (page_size * (1 + u64::from(n_pages))) as usize];
+        let mut data2 = vec![0u8; (page_size * (1 + u64::from(n_pages))) as usize];
+
+        let mut ring1 =
+            unsafe { PerfRing::init_contiguous(&mut data1, n_pages, page_size).unwrap() };
+        let mut ring2 =
+            unsafe { PerfRing::init_contiguous(&mut data2, n_pages, page_size).unwrap() };
+
+        let mut reader = Reader::new();
+        reader
+            .add_ring(unsafe { PerfRing::init_contiguous(&mut data1, n_pages, page_size).unwrap() })
+            .unwrap();
+        reader
+            .add_ring(unsafe { PerfRing::init_contiguous(&mut data2, n_pages, page_size).unwrap() })
+            .unwrap();
+
+        let mut dispatcher = Dispatcher::new();
+
+        // Lost record layout: u64 id, u64 number of lost samples
+        let lost_record = |samples: u64| {
+            let mut data = [0u8; 16];
+            data[8..16].copy_from_slice(&samples.to_ne_bytes());
+            data
+        };
+
+        // Ring 1 reports nothing; ring 2 reports two drops
+        ring1.start_write_batch();
+        let foo_msg = create_test_message(MSG_TYPE_FOO, 100, b"FOO DATA");
+        ring1.write(&foo_msg, PERF_RECORD_SAMPLE).unwrap();
+        ring1.finish_write_batch();
+
+        ring2.start_write_batch();
+        ring2.write(&lost_record(7), PERF_RECORD_LOST).unwrap();
+        ring2.write(&lost_record(5), PERF_RECORD_LOST).unwrap();
+        ring2.finish_write_batch();
+
+        reader.start().unwrap();
+        dispatcher.dispatch_all(&mut reader).unwrap();
+
+        let stats = dispatcher.stats();
+        assert_eq!(stats.lost_events_processed, 2);
+        assert_eq!(stats.lost_samples, 12);
+        assert_eq!(dispatcher.lost_samples(), &[0, 12]);
+
+        reader.finish().unwrap();
+    }
+
     #[test]
     fn test_dispatcher_using_instance_methods() {
         // Setup test rings and reader
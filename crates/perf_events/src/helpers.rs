@@ -237,6 +237,76 @@ pub fn open_perf_counter(
     map: &mut MapMut,
     counter_type: HardwareCounter,
 ) -> Result<(), PerfEventError> {
+    // Open the events
+    open_events(map, &mut hardware_counter_attr(counter_type))
+}
+
+/// Opens a hardware performance counter on the given CPUs only and stores each
+/// file descriptor in the map at its CPU's index. Entries for other CPUs are
+/// left empty.
+///
+/// # Arguments
+///
+/// * `map` - A mutable reference to a libbpf-rs map to store the file descriptors
+/// * `counter_type` - Type of hardware counter to open
+/// * `cpus` - CPUs to open the counter on
+///
+/// # Returns
+///
+/// * `Ok(())` on success
+/// * `Err(PerfEventError)` on failure
+pub fn open_perf_counter_on_cpus(
+    map: &mut MapMut,
+    counter_type: HardwareCounter,
+    cpus: &[usize],
+) -> Result<(), PerfEventError> {
+    let mut attr = hardware_counter_attr(counter_type);
+    let mut fds: Vec<i32> = Vec::with_capacity(cpus.len());
+
+    let close_all = |fds: &[i32]| {
+        for &fd in fds {
+            unsafe {
+                libc::close(fd);
+            }
+        }
+    };
+
+    for &cpu in cpus {
+        let cpu = cpu as i32;
+        let fd = unsafe {
+            sys::perf_event_open(
+                &mut attr,
+                -1, // pid (all threads)
+                cpu,
+                -1, // group_fd
+                sys::bindings::PERF_FLAG_FD_CLOEXEC as u64,
+            )
+        };
+        if fd < 0 {
+            let source = io::Error::last_os_error();
+            close_all(&fds);
+            return Err(PerfEventError::OpenError { cpu, source });
+        }
+        fds.push(fd);
+    }
+
+    for (&cpu, &fd) in cpus.iter().zip(&fds) {
+        let key = (cpu as u32).to_le_bytes();
+        let value = (fd as u32).to_le_bytes();
+        if let Err(err) = map.update(&key, &value, libbpf_rs::MapFlags::ANY) {
+            close_all(&fds);
+            return Err(PerfEventError::MapUpdateError {
+                cpu: cpu as i32,
+                source: err,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Perf event attributes counting `counter_type` with enabled/running times
+fn hardware_counter_attr(counter_type: HardwareCounter) -> sys::bindings::perf_event_attr {
     // Set counter-specific configuration
     let config = match counter_type {
         HardwareCounter::Cycles => sys::bindings::PERF_COUNT_HW_CPU_CYCLES as u64,
@@ -246,17 +316,14 @@ pub fn open_perf_counter(
     };
 
     // Create and configure perf event attributes
-    let mut attr = perf_event_open_sys::bindings::perf_event_attr {
+    perf_event_open_sys::bindings::perf_event_attr {
         size: std::mem::size_of::<sys::bindings::perf_event_attr>() as u32,
         type_: sys::bindings::PERF_TYPE_HARDWARE,
         read_format: (sys::bindings::PERF_FORMAT_TOTAL_TIME_ENABLED
             | sys::bindings::PERF_FORMAT_TOTAL_TIME_RUNNING) as u64,
         config,
         ..Default::default()
-    };
-
-    // Open the events
-    open_events(map, &mut attr)
+    }
 }
 
 /// Enables all perf events stored in the map.
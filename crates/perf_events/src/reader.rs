@@ -93,6 +93,11 @@ impl Reader {
         Ok(())
     }
 
+    /// Returns the number of rings in the collection
+    pub fn num_rings(&self) -> usize {
+        self.rings.len()
+    }
+
     /// Begins a read batch, initializing the heap with available entries
     pub fn start(&mut self) -> Result<(), ReaderError> {
         if self.rings.is_empty() {
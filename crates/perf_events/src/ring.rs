@@ -53,6 +53,14 @@ pub struct PerfEventMmapPage {
 pub const PERF_RECORD_SAMPLE: u32 = 9;
 pub const PERF_RECORD_LOST: u32 = 2;
 
+/// Number of samples a `PERF_RECORD_LOST` record reports as dropped, given the
+/// record data after the header (`u64 id; u64 lost; ...`). Truncated records
+/// count as zero.
+pub fn lost_record_count(data: &[u8]) -> u64 {
+    data.get(8..16)
+        .map_or(0, |b| u64::from_ne_bytes(b.try_into().unwrap()))
+}
+
 /// PerfRing represents a perf ring buffer with shared metadata and data pages
 pub struct PerfRing {
     // Shared metadata page
@@ -18,10 +18,20 @@ use nri::metadata::{ContainerMetadata, MetadataMessage, MetadataPlugin};
 use nri::NRI;
 use nri_resctrl_plugin::{PodResctrlEvent, ResctrlGroupState, ResctrlPlugin, ResctrlPluginConfig};
 
+mod pod_gauges;
+
+pub use pod_gauges::{PodGaugeValues, PodGauges};
+
 /// Default channel capacity for communication with the plugins
 const DEFAULT_CHANNEL_CAPACITY: usize = 256;
 
-/// Create the Arrow schema for resctrl LLC occupancy samples
+/// Default NRI runtime socket path
+const DEFAULT_NRI_SOCKET_PATH: &str = "/var/run/nri/nri.sock";
+
+/// Default NRI plugin index
+const DEFAULT_NRI_PLUGIN_IDX: &str = "10";
+
+/// Create the Arrow schema for per-pod resctrl monitoring samples
 pub fn create_schema() -> SchemaRef {
     Arc::new(Schema::new(vec![
         Field::new("start_timestamp", DataType::Int64, false),
@@ -33,6 +43,10 @@ pub fn create_schema() -> SchemaRef {
         Field::new("total_containers", DataType::Int64, false),
         Field::new("reconciled_containers", DataType::Int64, false),
         Field::new("llc_occupancy_bytes", DataType::Int64, false),
+        Field::new("mbm_total_bytes", DataType::Int64, true),
+        Field::new("mbm_local_bytes", DataType::Int64, true),
+        Field::new("mbm_total_bytes_delta", DataType::Int64, true),
+        Field::new("mbm_local_bytes_delta", DataType::Int64, true),
     ]))
 }
 
@@ -41,6 +55,7 @@ pub fn create_schema() -> SchemaRef {
 pub struct ResctrlCollector {
     resctrl_synced: AtomicBool,
     metadata_synced: AtomicBool,
+    gauges: PodGauges,
 }
 
 impl ResctrlCollector {
@@ -53,6 +68,11 @@ impl ResctrlCollector {
     pub fn ready(&self) -> bool {
         self.resctrl_synced.load(Ordering::Relaxed) && self.metadata_synced.load(Ordering::Relaxed)
     }
+
+    /// Latest per-pod monitoring values, for serving on a metrics endpoint.
+    pub fn gauges(&self) -> &PodGauges {
+        &self.gauges
+    }
 }
 
 #[derive(Default)]
@@ -60,6 +80,8 @@ struct PodState {
     group_path: Option<String>,
     total_containers: usize,
     reconciled_containers: usize,
+    /// Previous sample of the group, for bandwidth deltas
+    last_mon: Option<resctrl::MonData>,
 }
 
 #[derive(Default, Clone)]
@@ -76,7 +98,7 @@ pub(crate) struct ResctrlCollectorState {
     this: Arc<ResctrlCollector>,
     pods: HashMap<String, PodState>,        // keyed by pod_uid
     pod_labels: HashMap<String, PodLabels>, // pod_uid -> labels
-    llc_reader: Box<dyn LlcReader + Send + Sync>,
+    mon_reader: Box<dyn MonDataReader + Send + Sync>,
     schema: SchemaRef,
     batch_sender: mpsc::Sender<RecordBatch>,
     dropped_batches: u64,
@@ -96,14 +118,14 @@ impl ResctrlCollectorState {
             this,
             pods: HashMap::new(),
             pod_labels: HashMap::new(),
-            llc_reader: Box::new(rc),
+            mon_reader: Box::new(rc),
             schema: create_schema(),
             batch_sender,
             dropped_batches: 0,
         }
     }
 
-    /// Handle a periodic sampling tick: read group occupancies and emit a batch.
+    /// Handle a periodic sampling tick: read group monitoring data and emit a batch.
     pub(crate) fn handle_sample_timer(&mut self) {
         let start_ns: i64 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -122,17 +144,18 @@ impl ResctrlCollectorState {
             let mut total_b = Int64Builder::with_capacity(rows_cap);
             let mut reconciled_b = Int64Builder::with_capacity(rows_cap);
             let mut llc_b = Int64Builder::with_capacity(rows_cap);
+            let mut mbm_total_b = Int64Builder::with_capacity(rows_cap);
+            let mut mbm_local_b = Int64Builder::with_capacity(rows_cap);
+            let mut mbm_total_delta_b = Int64Builder::with_capacity(rows_cap);
+            let mut mbm_local_delta_b = Int64Builder::with_capacity(rows_cap);
 
             let mut rows_appended = 0usize;
-            for (uid, pod_state) in self.pods.iter() {
+            for (uid, pod_state) in self.pods.iter_mut() {
                 let Some(group_path) = pod_state.group_path.as_ref() else {
                     continue;
                 };
-                match self
-                    .llc_reader
-                    .llc_occupancy_total_bytes(group_path.as_str())
-                {
-                    Ok(total) => {
+                match self.mon_reader.read_mon_data(group_path.as_str()) {
+                    Ok(mon) => {
                         let labels = self.pod_labels.get(uid);
                         // Per-scan start timestamp and per-measurement read timestamp
                         start_ts_b.append_value(start_ns);
@@ -152,8 +175,30 @@ impl ResctrlCollectorState {
                         grp_b.append_value(group_path.as_str());
                         total_b.append_value(pod_state.total_containers as i64);
                         reconciled_b.append_value(pod_state.reconciled_containers as i64);
-                        llc_b.append_value(total as i64);
+                        llc_b.append_value(mon.llc_occupancy_bytes as i64);
+                        mbm_total_b.append_option(mon.mbm_total_bytes.map(|v| v as i64));
+                        mbm_local_b.append_option(mon.mbm_local_bytes.map(|v| v as i64));
+                        // Deltas are null on the first sample of a group
+                        let delta = pod_state
+                            .last_mon
+                            .map(|prev| mon.delta_since(&prev))
+                            .unwrap_or_default();
+                        mbm_total_delta_b.append_option(delta.mbm_total_bytes.map(|v| v as i64));
+                        mbm_local_delta_b.append_option(delta.mbm_local_bytes.map(|v| v as i64));
+                        pod_state.last_mon = Some(mon);
                         rows_appended += 1;
+
+                        self.this.gauges.set(
+                            uid,
+                            PodGaugeValues {
+                                pod_namespace: labels.map(|l| l.namespace.clone()),
+                                pod_name: labels.map(|l| l.name.clone()),
+                                resctrl_group: group_path.clone(),
+                                llc_occupancy_bytes: mon.llc_occupancy_bytes,
+                                mbm_total_bytes: mon.mbm_total_bytes,
+                                mbm_local_bytes: mon.mbm_local_bytes,
+                            },
+                        );
                     }
                     Err(e) => {
                         debug!("resctrl read failed for {}: {}", group_path, e);
@@ -172,6 +217,10 @@ impl ResctrlCollectorState {
                     Arc::new(total_b.finish()),
                     Arc::new(reconciled_b.finish()),
                     Arc::new(llc_b.finish()),
+                    Arc::new(mbm_total_b.finish()),
+                    Arc::new(mbm_local_b.finish()),
+                    Arc::new(mbm_total_delta_b.finish()),
+                    Arc::new(mbm_local_delta_b.finish()),
                 ];
                 let batch = match RecordBatch::try_new(self.schema.clone(), arrays) {
                     Ok(b) => b,
@@ -231,15 +280,20 @@ impl ResctrlCollectorState {
                 let entry = self.pods.entry(add.pod_uid.clone()).or_default();
                 entry.total_containers = add.total_containers;
                 entry.reconciled_containers = add.reconciled_containers;
-                if let ResctrlGroupState::Exists(p) = add.group_state {
-                    entry.group_path = Some(p);
-                } else {
-                    entry.group_path = None;
+                let group_path = match add.group_state {
+                    ResctrlGroupState::Exists(p) => Some(p),
+                    ResctrlGroupState::Failed | ResctrlGroupState::Observed => None,
+                };
+                // A different group has its own counters
+                if entry.group_path != group_path {
+                    entry.last_mon = None;
                 }
+                entry.group_path = group_path;
             }
             PodResctrlEvent::Removed(r) => {
                 self.pods.remove(&r.pod_uid);
                 self.pod_labels.remove(&r.pod_uid);
+                self.this.gauges.remove(&r.pod_uid);
             }
         }
     }
@@ -283,23 +337,21 @@ impl ResctrlCollectorState {
 }
 
 /// Tiny indirection over resctrl for sampling, to enable hermetic tests.
-pub(crate) trait LlcReader {
-    fn llc_occupancy_total_bytes(&self, group_path: &str) -> anyhow::Result<u64>;
+pub(crate) trait MonDataReader {
+    fn read_mon_data(&self, group_path: &str) -> anyhow::Result<resctrl::MonData>;
 }
 
-impl<P: resctrl::FsProvider> LlcReader for resctrl::Resctrl<P> {
-    fn llc_occupancy_total_bytes(&self, group_path: &str) -> anyhow::Result<u64> {
-        Ok(resctrl::Resctrl::llc_occupancy_total_bytes(
-            self, group_path,
-        )?)
+impl<P: resctrl::FsProvider> MonDataReader for resctrl::Resctrl<P> {
+    fn read_mon_data(&self, group_path: &str) -> anyhow::Result<resctrl::MonData> {
+        Ok(resctrl::Resctrl::read_mon_data(self, group_path)?)
     }
 }
 
 #[cfg(test)]
 impl ResctrlCollectorState {
-    /// Test-only: override the LLC reader with a fake.
-    pub(crate) fn set_llc_reader_for_test(&mut self, reader: Box<dyn LlcReader + Send + Sync>) {
-        self.llc_reader = reader;
+    /// Test-only: override the monitoring data reader with a fake.
+    pub(crate) fn set_mon_reader_for_test(&mut self, reader: Box<dyn MonDataReader + Send + Sync>) {
+        self.mon_reader = reader;
     }
 }
 
@@ -315,6 +367,12 @@ pub struct ResctrlCollectorConfig {
     pub channel_capacity: usize,
     /// resctrl mountpoint (root path)
     pub mountpoint: PathBuf,
+    /// NRI runtime socket path
+    pub nri_socket_path: String,
+    /// NRI plugin index used when registering the plugins
+    pub nri_plugin_idx: String,
+    /// Only report which stale groups startup cleanup would remove
+    pub cleanup_dry_run: bool,
 }
 
 impl Default for ResctrlCollectorConfig {
@@ -325,6 +383,9 @@ impl Default for ResctrlCollectorConfig {
             health_interval: Duration::from_secs(60),
             channel_capacity: DEFAULT_CHANNEL_CAPACITY,
             mountpoint: PathBuf::from("/sys/fs/resctrl"),
+            nri_socket_path: DEFAULT_NRI_SOCKET_PATH.to_string(),
+            nri_plugin_idx: DEFAULT_NRI_PLUGIN_IDX.to_string(),
+            cleanup_dry_run: false,
         }
     }
 }
@@ -337,6 +398,8 @@ impl ResctrlCollectorConfig {
     /// - `RESCTRL_HEALTH_INTERVAL` (humantime)
     /// - `RESCTRL_CHANNEL_CAPACITY` (usize > 0)
     /// - `RESCTRL_MOUNT` (path)
+    /// - `NRI_SOCKET_PATH` (path)
+    /// - `NRI_PLUGIN_IDX` (plugin index string, e.g., "10")
     pub fn from_env() -> Self {
         let mut cfg = Self::default();
         if let Ok(s) = env::var("RESCTRL_SAMPLING_INTERVAL") {
@@ -366,6 +429,16 @@ impl ResctrlCollectorConfig {
                 cfg.mountpoint = PathBuf::from(m);
             }
         }
+        if let Ok(p) = env::var("NRI_SOCKET_PATH") {
+            if !p.is_empty() {
+                cfg.nri_socket_path = p;
+            }
+        }
+        if let Ok(idx) = env::var("NRI_PLUGIN_IDX") {
+            if !idx.is_empty() {
+                cfg.nri_plugin_idx = idx;
+            }
+        }
         cfg
     }
 }
@@ -385,7 +458,10 @@ pub async fn run(
 
     // Create plugins
     let resctrl_plugin = Arc::new(ResctrlPlugin::new(
-        ResctrlPluginConfig::default(),
+        ResctrlPluginConfig {
+            cleanup_dry_run: cfg.cleanup_dry_run,
+            ..Default::default()
+        },
         resctrl_tx,
     ));
     let meta_plugin = Arc::new(MetadataPlugin::new(meta_tx));
@@ -396,13 +472,12 @@ pub async fn run(
     async fn connect_plugin<P: nri::api_ttrpc::Plugin + Send + Sync + 'static>(
         plugin: Arc<P>,
         name: &str,
+        socket_path: &str,
         idx: &str,
         task_tracker: &TaskTracker,
         shutdown: &CancellationToken,
     ) -> Result<Option<NRI>> {
-        let socket_path = std::env::var("NRI_SOCKET_PATH")
-            .unwrap_or_else(|_| "/var/run/nri/nri.sock".to_string());
-        match tokio::net::UnixStream::connect(&socket_path).await {
+        match tokio::net::UnixStream::connect(socket_path).await {
             Ok(stream) => {
                 info!("Connecting {} to NRI at {}", name, socket_path);
                 let (nri, join_handle) = NRI::new(stream, plugin, name, idx).await?;
@@ -432,7 +507,8 @@ pub async fn run(
     let nri_resctrl = connect_plugin(
         resctrl_plugin.clone(),
         "resctrl-plugin",
-        "10",
+        &cfg.nri_socket_path,
+        &cfg.nri_plugin_idx,
         &task_tracker,
         &shutdown,
     )
@@ -440,7 +516,8 @@ pub async fn run(
     let nri_meta = connect_plugin(
         meta_plugin.clone(),
         "metadata-for-resctrl-plugin",
-        "10",
+        &cfg.nri_socket_path,
+        &cfg.nri_plugin_idx,
         &task_tracker,
         &shutdown,
     )
@@ -574,13 +651,26 @@ mod tests {
             Self { map }
         }
     }
-    impl LlcReader for MockLlcReader {
-        fn llc_occupancy_total_bytes(&self, group_path: &str) -> anyhow::Result<u64> {
-            match self.map.get(group_path) {
-                Some(Ok(v)) => Ok(*v),
-                Some(Err(_)) => Err(anyhow::anyhow!("read error")),
-                None => Ok(0),
-            }
+    impl MonDataReader for MockLlcReader {
+        fn read_mon_data(&self, group_path: &str) -> anyhow::Result<resctrl::MonData> {
+            let llc = match self.map.get(group_path) {
+                Some(Ok(v)) => *v,
+                Some(Err(_)) => return Err(anyhow::anyhow!("read error")),
+                None => 0,
+            };
+            Ok(resctrl::MonData {
+                llc_occupancy_bytes: llc,
+                ..Default::default()
+            })
+        }
+    }
+
+    struct MockMonDataReader {
+        map: std::collections::HashMap<String, resctrl::MonData>,
+    }
+    impl MonDataReader for MockMonDataReader {
+        fn read_mon_data(&self, group_path: &str) -> anyhow::Result<resctrl::MonData> {
+            Ok(self.map.get(group_path).copied().unwrap_or_default())
         }
     }
 
@@ -604,6 +694,7 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/sys/fs/resctrl/mon_groups/pod_u1".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         assert!(!this.ready());
         st.handle_metadata_event(MetadataMessage::Add(
@@ -618,6 +709,7 @@ mod tests {
                 pid: None,
                 labels: Default::default(),
                 annotations: Default::default(),
+                ..Default::default()
             }),
         ));
         assert!(this.ready());
@@ -637,6 +729,7 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/g1".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         st.handle_metadata_event(MetadataMessage::Add(
             "c1".into(),
@@ -650,13 +743,14 @@ mod tests {
                 pid: None,
                 labels: Default::default(),
                 annotations: Default::default(),
+                ..Default::default()
             }),
         ));
 
         // Inject fake reader
         let mut map = std::collections::HashMap::new();
         map.insert("/g1".to_string(), Ok(1234u64));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map)));
 
         // Sample
         st.handle_sample_timer();
@@ -719,6 +813,195 @@ mod tests {
         assert_eq!(llc.value(0), 1234);
     }
 
+    // MonData with bandwidth counters fills the MBM columns; missing counters are null.
+    #[tokio::test]
+    async fn l0b_mon_data_bandwidth_columns() {
+        let this = ResctrlCollector::new();
+        let (tx, mut rx) = mpsc::channel(4);
+        let cfg = ResctrlCollectorConfig::default();
+        let mut st = ResctrlCollectorState::new(this.clone(), tx, &cfg);
+
+        for (uid, group) in [("u1", "/g1"), ("u2", "/g2")] {
+            st.handle_resctrl_event(PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+                pod_uid: uid.into(),
+                group_state: ResctrlGroupState::Exists(group.into()),
+                total_containers: 1,
+                reconciled_containers: 1,
+                ..Default::default()
+            }));
+        }
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            "/g1".to_string(),
+            resctrl::MonData {
+                llc_occupancy_bytes: 100,
+                mbm_total_bytes: Some(5000),
+                mbm_local_bytes: Some(3000),
+            },
+        );
+        map.insert(
+            "/g2".to_string(),
+            resctrl::MonData {
+                llc_occupancy_bytes: 200,
+                mbm_total_bytes: None,
+                mbm_local_bytes: None,
+            },
+        );
+        st.set_mon_reader_for_test(Box::new(MockMonDataReader { map }));
+
+        st.handle_sample_timer();
+        let batch = drain_one_record_batch(&mut rx).expect("expected batch");
+        let schema = batch.schema();
+        assert_eq!(schema.fields().len(), 13);
+        assert_eq!(schema.field(9).name(), "mbm_total_bytes");
+        assert!(schema.field(9).is_nullable());
+        assert_eq!(schema.field(10).name(), "mbm_local_bytes");
+        assert!(schema.field(10).is_nullable());
+
+        let uid = batch
+            .column(4)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let llc = batch
+            .column(8)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let mbm_total = batch
+            .column(9)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let mbm_local = batch
+            .column(10)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        for i in 0..batch.num_rows() {
+            match uid.value(i) {
+                "u1" => {
+                    assert_eq!(llc.value(i), 100);
+                    assert_eq!(mbm_total.value(i), 5000);
+                    assert_eq!(mbm_local.value(i), 3000);
+                }
+                "u2" => {
+                    assert_eq!(llc.value(i), 200);
+                    assert!(mbm_total.is_null(i));
+                    assert!(mbm_local.is_null(i));
+                }
+                other => panic!("unexpected pod uid {}", other),
+            }
+        }
+    }
+
+    // Bandwidth deltas are null on the first sample, then follow the counters across a wrap.
+    #[tokio::test]
+    async fn l0b_mon_data_deltas_across_samples() {
+        let this = ResctrlCollector::new();
+        let (tx, mut rx) = mpsc::channel(4);
+        let cfg = ResctrlCollectorConfig::default();
+        let mut st = ResctrlCollectorState::new(this.clone(), tx, &cfg);
+
+        st.handle_resctrl_event(PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+            pod_uid: "u1".into(),
+            group_state: ResctrlGroupState::Exists("/g1".into()),
+            total_containers: 1,
+            reconciled_containers: 1,
+            ..Default::default()
+        }));
+
+        let mut sample = |total: u64, local: u64| {
+            let mut map = std::collections::HashMap::new();
+            map.insert(
+                "/g1".to_string(),
+                resctrl::MonData {
+                    llc_occupancy_bytes: 100,
+                    mbm_total_bytes: Some(total),
+                    mbm_local_bytes: Some(local),
+                },
+            );
+            st.set_mon_reader_for_test(Box::new(MockMonDataReader { map }));
+            st.handle_sample_timer();
+            let batch = drain_one_record_batch(&mut rx).expect("expected batch");
+            let column = |i: usize| {
+                let col = batch
+                    .column(i)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap();
+                (!col.is_null(0)).then_some(col.value(0))
+            };
+            (column(11), column(12))
+        };
+
+        let near_max = u64::MAX - 1199;
+        assert_eq!(sample(near_max, 3000), (None, None));
+        assert_eq!(sample(near_max + 1000, 3100), (Some(1000), Some(100)));
+        // mbm_total wraps past u64::MAX
+        assert_eq!(sample(300, 3500), (Some(500), Some(400)));
+    }
+
+    // A monitoring sample updates the pod's gauges; pod removal clears them.
+    #[tokio::test]
+    async fn l0b_gauges_follow_samples_and_removal() {
+        let this = ResctrlCollector::new();
+        let (tx, _rx) = mpsc::channel(4);
+        let cfg = ResctrlCollectorConfig::default();
+        let mut st = ResctrlCollectorState::new(this.clone(), tx, &cfg);
+
+        st.handle_resctrl_event(PodResctrlEvent::AddOrUpdate(PodResctrlAddOrUpdate {
+            pod_uid: "u1".into(),
+            group_state: ResctrlGroupState::Exists("/g1".into()),
+            total_containers: 1,
+            reconciled_containers: 1,
+            ..Default::default()
+        }));
+        st.handle_metadata_event(MetadataMessage::Add(
+            "c1".into(),
+            Box::new(ContainerMetadata {
+                container_id: "c1".into(),
+                pod_name: "p".into(),
+                pod_namespace: "ns".into(),
+                pod_uid: "u1".into(),
+                container_name: "n".into(),
+                cgroup_path: String::new(),
+                pid: None,
+                labels: Default::default(),
+                annotations: Default::default(),
+                ..Default::default()
+            }),
+        ));
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            "/g1".to_string(),
+            resctrl::MonData {
+                llc_occupancy_bytes: 4096,
+                mbm_total_bytes: Some(5000),
+                mbm_local_bytes: None,
+            },
+        );
+        st.set_mon_reader_for_test(Box::new(MockMonDataReader { map }));
+
+        st.handle_sample_timer();
+        let gauge = this.gauges().get("u1").expect("gauge for u1");
+        assert_eq!(gauge.pod_namespace.as_deref(), Some("ns"));
+        assert_eq!(gauge.pod_name.as_deref(), Some("p"));
+        assert_eq!(gauge.llc_occupancy_bytes, 4096);
+        assert_eq!(gauge.mbm_total_bytes, Some(5000));
+        assert!(this.gauges().render().contains(
+            "resctrl_llc_occupancy_bytes{pod_namespace=\"ns\",pod_name=\"p\",pod_uid=\"u1\",resctrl_group=\"/g1\"} 4096"
+        ));
+
+        st.handle_resctrl_event(PodResctrlEvent::Removed(PodResctrlRemoved {
+            pod_uid: "u1".into(),
+        }));
+        assert!(this.gauges().get("u1").is_none());
+        assert!(!this.gauges().render().contains("u1"));
+    }
+
     // Ensures missing metadata yields null ns/name, then filled after metadata arrives.
     #[tokio::test]
     async fn l0b_missing_metadata_path() {
@@ -732,10 +1015,11 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/g2".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         let mut map = std::collections::HashMap::new();
         map.insert("/g2".to_string(), Ok(42u64));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map)));
         st.handle_sample_timer();
         let batch = drain_one_record_batch(&mut rx).expect("batch");
         let ns = batch
@@ -754,7 +1038,7 @@ mod tests {
         // Now add metadata and sample again
         let mut map2 = std::collections::HashMap::new();
         map2.insert("/g2".to_string(), Ok(10u64));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map2)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map2)));
         st.handle_metadata_event(MetadataMessage::Add(
             "c2".into(),
             Box::new(ContainerMetadata {
@@ -767,6 +1051,7 @@ mod tests {
                 pid: None,
                 labels: Default::default(),
                 annotations: Default::default(),
+                ..Default::default()
             }),
         ));
         st.handle_sample_timer();
@@ -798,10 +1083,11 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/g3".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         let mut map = std::collections::HashMap::new();
         map.insert("/g3".to_string(), Ok(1u64));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map)));
         st.handle_sample_timer();
         assert!(drain_one_record_batch(&mut rx).is_some());
 
@@ -826,10 +1112,11 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/g4".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         let mut map = std::collections::HashMap::new();
         map.insert("/g4".to_string(), Err(()));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map)));
         st.handle_sample_timer();
         // No rows → no batch
         assert!(drain_one_record_batch(&mut rx).is_none());
@@ -848,10 +1135,11 @@ mod tests {
             group_state: ResctrlGroupState::Exists("/g5".into()),
             total_containers: 1,
             reconciled_containers: 1,
+            ..Default::default()
         }));
         let mut map = std::collections::HashMap::new();
         map.insert("/g5".to_string(), Ok(77u64));
-        st.set_llc_reader_for_test(Box::new(MockLlcReader::new(map)));
+        st.set_mon_reader_for_test(Box::new(MockLlcReader::new(map)));
 
         // Two samples without draining → second should be dropped due to capacity=1
         st.handle_sample_timer();
@@ -878,6 +1166,7 @@ mod tests {
                 group_state: ResctrlGroupState::Failed,
                 total_containers: 1,
                 reconciled_containers: 0,
+                ..Default::default()
             },
         ));
         st.handle_resctrl_event(PodResctrlEvent::AddOrUpdate(
@@ -886,6 +1175,7 @@ mod tests {
                 group_state: ResctrlGroupState::Exists("/gB".into()),
                 total_containers: 2,
                 reconciled_containers: 1,
+                ..Default::default()
             },
         ));
         st.handle_resctrl_event(PodResctrlEvent::AddOrUpdate(
@@ -894,6 +1184,7 @@ mod tests {
                 group_state: ResctrlGroupState::Exists("/gC".into()),
                 total_containers: 1,
                 reconciled_containers: 1,
+                ..Default::default()
             },
         ));
 
@@ -915,6 +1206,7 @@ mod tests {
             health_interval: Duration::from_millis(10),
             channel_capacity: 4,
             mountpoint: "/does/not/exist".into(),
+            ..Default::default()
         };
         let jh = tokio::spawn(run(this.clone(), tx, shutdown.clone(), cfg));
         // Advance time twice; after 20ms total, both sample and health
@@ -952,6 +1244,7 @@ mod tests {
                 group_state: ResctrlGroupState::Exists("g1".into()),
                 total_containers: 1,
                 reconciled_containers: 1,
+                ..Default::default()
             }))
             .await
             .unwrap();
@@ -970,6 +1263,7 @@ mod tests {
                     pid: None,
                     labels: Default::default(),
                     annotations: Default::default(),
+                    ..Default::default()
                 }),
             ))
             .await
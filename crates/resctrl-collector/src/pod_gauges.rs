@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+
+/// Latest monitoring sample for one pod, with the labels it is exported under
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PodGaugeValues {
+    pub pod_namespace: Option<String>,
+    pub pod_name: Option<String>,
+    pub resctrl_group: String,
+    pub llc_occupancy_bytes: u64,
+    pub mbm_total_bytes: Option<u64>,
+    pub mbm_local_bytes: Option<u64>,
+}
+
+/// Per-pod resctrl gauges, rendered in the OpenMetrics text format.
+///
+/// Entries are keyed by pod UID, overwritten on every sample and dropped when
+/// the pod is removed so that stale series disappear from the scrape output.
+#[derive(Default)]
+pub struct PodGauges {
+    pods: Mutex<BTreeMap<String, PodGaugeValues>>,
+}
+
+/// (metric name, help text, value accessor)
+type GaugeDef = (
+    &'static str,
+    &'static str,
+    fn(&PodGaugeValues) -> Option<u64>,
+);
+
+const GAUGES: &[GaugeDef] = &[
+    (
+        "resctrl_llc_occupancy_bytes",
+        "LLC occupancy of the pod's resctrl monitoring group",
+        |v| Some(v.llc_occupancy_bytes),
+    ),
+    (
+        "resctrl_mbm_total_bytes",
+        "Total memory bandwidth counter of the pod's resctrl monitoring group",
+        |v| v.mbm_total_bytes,
+    ),
+    (
+        "resctrl_mbm_local_bytes",
+        "Local memory bandwidth counter of the pod's resctrl monitoring group",
+        |v| v.mbm_local_bytes,
+    ),
+];
+
+impl PodGauges {
+    /// Record the latest sample for a pod
+    pub fn set(&self, pod_uid: &str, values: PodGaugeValues) {
+        self.pods
+            .lock()
+            .unwrap()
+            .insert(pod_uid.to_string(), values);
+    }
+
+    /// Drop all series for a pod
+    pub fn remove(&self, pod_uid: &str) {
+        self.pods.lock().unwrap().remove(pod_uid);
+    }
+
+    /// Current sample for a pod, if any
+    pub fn get(&self, pod_uid: &str) -> Option<PodGaugeValues> {
+        self.pods.lock().unwrap().get(pod_uid).cloned()
+    }
+
+    /// Render all gauges in the OpenMetrics text exposition format
+    pub fn render(&self) -> String {
+        let pods = self.pods.lock().unwrap();
+        let mut out = String::new();
+        for (name, help, value) in GAUGES {
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            for (uid, v) in pods.iter() {
+                let Some(value) = value(v) else {
+                    continue;
+                };
+                let _ = writeln!(
+                    out,
+                    "{}{{pod_namespace=\"{}\",pod_name=\"{}\",pod_uid=\"{}\",resctrl_group=\"{}\"}} {}",
+                    name,
+                    escape_label(v.pod_namespace.as_deref().unwrap_or("")),
+                    escape_label(v.pod_name.as_deref().unwrap_or("")),
+                    escape_label(uid),
+                    escape_label(&v.resctrl_group),
+                    value
+                );
+            }
+        }
+        out.push_str("# EOF\n");
+        out
+    }
+}
+
+/// Escape a label value per the OpenMetrics text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_format_and_escaping() {
+        let gauges = PodGauges::default();
+        gauges.set(
+            "u1",
+            PodGaugeValues {
+                pod_namespace: Some("ns".into()),
+                pod_name: Some("we\"ird".into()),
+                resctrl_group: "/g1".into(),
+                llc_occupancy_bytes: 42,
+                mbm_total_bytes: None,
+                mbm_local_bytes: Some(7),
+            },
+        );
+
+        let text = gauges.render();
+        assert!(text.contains("# TYPE resctrl_llc_occupancy_bytes gauge\n"));
+        assert!(text.contains(
+            "resctrl_llc_occupancy_bytes{pod_namespace=\"ns\",pod_name=\"we\\\"ird\",pod_uid=\"u1\",resctrl_group=\"/g1\"} 42\n"
+        ));
+        assert!(text.contains("resctrl_mbm_local_bytes{") && text.contains("} 7\n"));
+        // Unsupported counters produce no series
+        assert!(!text.contains("resctrl_mbm_total_bytes{"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
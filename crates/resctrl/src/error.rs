@@ -22,4 +22,7 @@ pub enum Error {
 
     #[error("no PIDs returned by pid source")]
     EmptyPidSet,
+
+    #[error("invalid group prefix {prefix:?}: {reason}")]
+    InvalidPrefix { prefix: String, reason: String },
 }
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 pub use error::{Error, Result};
 
@@ -13,6 +15,10 @@ pub mod test_utils;
 
 const DEFAULT_ROOT: &str = "/sys/fs/resctrl";
 const DEFAULT_PREFIX: &str = "pod_";
+
+/// Shortest group prefix that cleanup accepts, so a truncated or mistyped
+/// prefix cannot sweep groups created by something else
+pub const MIN_GROUP_PREFIX_LEN: usize = 3;
 const MAX_UID_LEN: usize = 63; // limit UID segment (<64)
 
 #[derive(Clone, Debug)]
@@ -29,6 +35,8 @@ impl AssignmentResult {
 
 #[derive(Clone, Debug)]
 pub struct Config {
+    /// Mount point of the resctrl filesystem all groups are created under
+    /// (e.g. a host bind mount when running in a container)
     pub root: PathBuf,
     pub group_prefix: String,
 }
@@ -121,11 +129,66 @@ impl<P: FsProvider> Resctrl<P> {
         Ok(v.into_iter().map(|r| r.bytes).sum())
     }
 
+    /// Read all monitoring counters for a group, summed across L3 domains.
+    ///
+    /// `llc_occupancy` is required in every domain. The MBM counters
+    /// (`mbm_total_bytes`, `mbm_local_bytes`) are optional: they are `None` when
+    /// no domain exposes a readable value (no MBM support, or "Unavailable").
+    pub fn read_mon_data(&self, group_path: &str) -> Result<MonData> {
+        let group = PathBuf::from(group_path);
+        let mon_data = group.join("mon_data");
+        let domains = self
+            .fs
+            .read_child_dirs(&mon_data)
+            .map_err(|e| map_basic_fs_error(&mon_data, &e))?;
+
+        let mut out = MonData::default();
+        for d in domains {
+            if !d.starts_with("mon_L3_") {
+                continue;
+            }
+            let dpath = mon_data.join(&d);
+            if let Some(v) = self.read_mon_counter(&dpath.join("llc_occupancy"), true)? {
+                out.llc_occupancy_bytes += v;
+            }
+            if let Some(v) = self.read_mon_counter(&dpath.join("mbm_total_bytes"), false)? {
+                *out.mbm_total_bytes.get_or_insert(0) += v;
+            }
+            if let Some(v) = self.read_mon_counter(&dpath.join("mbm_local_bytes"), false)? {
+                *out.mbm_local_bytes.get_or_insert(0) += v;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Read a single monitoring counter file. Returns `None` when an optional
+    /// file is absent or the kernel reports "Unavailable".
+    fn read_mon_counter(&self, path: &Path, required: bool) -> Result<Option<u64>> {
+        if !required && !self.fs.exists(path) {
+            return Ok(None);
+        }
+        let s = self
+            .fs
+            .read_to_string(path)
+            .map_err(|e| map_basic_fs_error(path, &e))?;
+        let s = s.trim();
+        if s == "Unavailable" {
+            return Ok(None);
+        }
+        s.parse::<u64>().map(Some).map_err(|_| Error::Io {
+            path: path.to_path_buf(),
+            source: io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid monitoring counter value",
+            ),
+        })
+    }
+
     // Public API
 
     /// Describe support status of resctrl on this system.
-    /// - mounted: whether resctrl is mounted
-    /// - mount_point: where it is mounted if present
+    /// - mounted: whether resctrl is mounted at the configured root
+    /// - mount_point: the configured root when it is mounted
     /// - writable: whether current process can write to root tasks file
     pub fn detect_support(&self) -> Result<SupportInfo> {
         // Determine mount point by reading /proc/mounts
@@ -139,12 +202,17 @@ impl<P: FsProvider> Resctrl<P> {
             }
         };
 
+        // Only a resctrl mount at the configured root counts
         let mut mount_point: Option<PathBuf> = None;
+        let mut cdp_mount_opt = false;
         for line in mounts.lines() {
             // /proc/mounts format: <src> <target> <fstype> <opts> ...
             let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 3 && parts[2] == "resctrl" {
+            if parts.len() >= 3 && parts[2] == "resctrl" && Path::new(parts[1]) == self.cfg.root {
                 mount_point = Some(PathBuf::from(parts[1]));
+                cdp_mount_opt = parts
+                    .get(3)
+                    .is_some_and(|opts| opts.split(',').any(|o| o == "cdp"));
                 break;
             }
         }
@@ -158,13 +226,26 @@ impl<P: FsProvider> Resctrl<P> {
             false
         };
 
+        // With CDP the kernel exposes L3CODE/L3DATA instead of a single L3 resource
+        let cdp_enabled = match mount_point {
+            Some(ref mp) => cdp_mount_opt || self.has_cdp_info_dirs(mp),
+            None => false,
+        };
+
         Ok(SupportInfo {
             mounted,
             mount_point,
             writable,
+            cdp_enabled,
         })
     }
 
+    /// Whether `<root>/info` exposes the split L3CODE/L3DATA resources used by CDP.
+    fn has_cdp_info_dirs(&self, root: &Path) -> bool {
+        let info = root.join("info");
+        self.fs.exists(&info.join("L3CODE")) && self.fs.exists(&info.join("L3DATA"))
+    }
+
     /// Ensure resctrl is mounted according to the given flag.
     /// - If already mounted, returns Ok(())
     /// - If not mounted and `auto_mount` is false, returns Error::NotMounted
@@ -237,11 +318,153 @@ impl<P: FsProvider> Resctrl<P> {
                 Error::Io { source, .. } if source.kind() == io::ErrorKind::AlreadyExists => {
                     Ok(path.to_string_lossy().into_owned())
                 }
+                // Missing mon_groups means monitoring is unavailable in this layout
+                // (e.g., CDP-only mounts); report it instead of a bare ENOENT.
+                Error::Io { source, .. } if source.kind() == io::ErrorKind::NotFound => {
+                    let cdp = if self.has_cdp_info_dirs(&self.cfg.root) {
+                        " (CDP enabled)"
+                    } else {
+                        ""
+                    };
+                    Err(Error::Unsupported {
+                        source: io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!(
+                                "{} not available{}",
+                                self.cfg.root.join("mon_groups").display(),
+                                cdp
+                            ),
+                        ),
+                    })
+                }
                 other => Err(other),
             },
         }
     }
 
+    /// Create a control group for a pod directly under the resctrl root.
+    ///
+    /// Unlike monitoring groups, control groups have a `schemata` file and can be
+    /// given a cache/bandwidth allocation, but each one consumes a CLOS ID; running
+    /// out of them is reported as `Error::Capacity`. On hosts without allocation
+    /// support (no `schemata` file at the root) the kernel refuses the group,
+    /// which is reported as `Error::Unsupported`.
+    pub fn create_control_group(&self, pod_uid: &str) -> Result<String> {
+        if !self.fs.exists(&self.cfg.root) {
+            return Err(Error::NotMounted {
+                root: self.cfg.root.clone(),
+            });
+        }
+
+        let path = self
+            .cfg
+            .root
+            .join(group_name(&self.cfg.group_prefix, pod_uid));
+        match self.fs.create_dir(&path) {
+            Ok(()) => Ok(path.to_string_lossy().into_owned()),
+            Err(e) => match map_basic_fs_error(&path, &e) {
+                Error::Io { source, .. } if source.kind() == io::ErrorKind::AlreadyExists => {
+                    Ok(path.to_string_lossy().into_owned())
+                }
+                _ if !self.fs.exists(&self.cfg.root.join("schemata")) => {
+                    Err(Error::Unsupported { source: e })
+                }
+                other => Err(other),
+            },
+        }
+    }
+
+    /// Write an allocation to a control group's `schemata` file and verify that
+    /// the kernel applied it.
+    ///
+    /// `schemata` uses the kernel format, one resource per line, e.g.
+    /// `L3:0=ff;1=ff` or `MB:0=50;1=50`. Only the listed resources and domains
+    /// are changed. Groups without a `schemata` file (monitoring groups, or
+    /// kernels without allocation support) yield `Error::Unsupported`; a
+    /// malformed or rejected allocation yields `Error::Io` with
+    /// `ErrorKind::InvalidInput`.
+    pub fn set_schemata(&self, group_path: &str, schemata: &str) -> Result<()> {
+        let path = PathBuf::from(group_path).join("schemata");
+        let requested = parse_schemata(schemata).map_err(|msg| Error::Io {
+            path: path.clone(),
+            source: io::Error::new(io::ErrorKind::InvalidInput, msg),
+        })?;
+        if requested.is_empty() {
+            return Ok(());
+        }
+
+        if !self.fs.exists(&path) {
+            return Err(Error::Unsupported {
+                source: io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "{} not available (monitoring-only group or no allocation support)",
+                        path.display()
+                    ),
+                ),
+            });
+        }
+
+        let data: String = requested.iter().map(|line| format!("{}\n", line)).collect();
+        if let Err(e) = self.fs.write_str(&path, &data) {
+            return Err(match e.raw_os_error() {
+                // The kernel explains rejected schemata in info/last_cmd_status
+                Some(libc::EINVAL) => {
+                    let status = self
+                        .fs
+                        .read_to_string(&self.cfg.root.join("info").join("last_cmd_status"))
+                        .map(|s| s.trim().to_string())
+                        .unwrap_or_default();
+                    Error::Io {
+                        path,
+                        source: io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("schemata rejected by kernel: {}", status),
+                        ),
+                    }
+                }
+                Some(libc::ENOENT) => Error::Unsupported { source: e },
+                _ => map_basic_fs_error(&path, &e),
+            });
+        }
+
+        // Read back and compare every requested domain
+        let current = self
+            .fs
+            .read_to_string(&path)
+            .map_err(|e| map_basic_fs_error(&path, &e))?;
+        let current = parse_schemata(&current).map_err(|msg| Error::Io {
+            path: path.clone(),
+            source: io::Error::new(io::ErrorKind::InvalidData, msg),
+        })?;
+        for line in &requested {
+            for (domain, value) in &line.domains {
+                let applied = current
+                    .iter()
+                    .rev()
+                    .find(|c| c.resource == line.resource)
+                    .and_then(|c| c.domains.iter().find(|(d, _)| d == domain))
+                    .map(|(_, v)| v.as_str());
+                if !applied.is_some_and(|v| schemata_values_equal(v, value)) {
+                    return Err(Error::Io {
+                        path,
+                        source: io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "schemata not applied: {} domain {} is {}, expected {}",
+                                line.resource,
+                                domain,
+                                applied.unwrap_or("missing"),
+                                value
+                            ),
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn delete_group(&self, group_path: &str) -> Result<()> {
         let p = PathBuf::from(group_path);
         match self.fs.remove_dir(&p) {
@@ -336,6 +559,103 @@ impl<P: FsProvider> Resctrl<P> {
         Ok(pids)
     }
 
+    /// List the tasks of every group under the resctrl root, keyed by group path.
+    ///
+    /// Covers control groups at the root, monitoring groups under `<root>/mon_groups`,
+    /// and monitoring groups nested under control groups. The default (root) group and
+    /// reserved directories (`info`, `mon_data`, `mon_groups`) are skipped. Groups that
+    /// disappear while listing are omitted.
+    pub fn all_group_tasks(&self) -> Result<HashMap<String, Vec<i32>>> {
+        let root = &self.cfg.root;
+        let mut groups = Vec::new();
+
+        let root_children = self
+            .fs
+            .read_child_dirs(root)
+            .map_err(|e| map_basic_fs_error(root, &e))?;
+        for name in root_children {
+            if name == "info" || name == "mon_data" || name == "mon_groups" {
+                continue;
+            }
+            let ctrl_group = root.join(&name);
+            let nested = ctrl_group.join("mon_groups");
+            match self.fs.read_child_dirs(&nested) {
+                Ok(children) => groups.extend(children.into_iter().map(|c| nested.join(c))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(map_basic_fs_error(&nested, &e)),
+            }
+            groups.push(ctrl_group);
+        }
+
+        let mon_groups_dir = root.join("mon_groups");
+        let mon_children = self
+            .fs
+            .read_child_dirs(&mon_groups_dir)
+            .map_err(|e| map_basic_fs_error(&mon_groups_dir, &e))?;
+        groups.extend(mon_children.into_iter().map(|c| mon_groups_dir.join(c)));
+
+        self.tasks_by_group(
+            groups
+                .into_iter()
+                .map(|group| group.to_string_lossy().into_owned()),
+        )
+    }
+
+    /// List the tasks of the groups managed by this component, keyed by group path.
+    ///
+    /// Covers the groups returned by `list_groups`: prefixed control groups at the
+    /// root and prefixed monitoring groups under `<root>/mon_groups`. Groups that
+    /// disappear while listing are omitted.
+    pub fn list_all_group_tasks(&self) -> Result<HashMap<String, Vec<i32>>> {
+        self.tasks_by_group(self.list_groups()?)
+    }
+
+    /// List the tasks of each of `groups`, keyed by group path, omitting groups
+    /// that disappear while listing.
+    fn tasks_by_group(
+        &self,
+        groups: impl IntoIterator<Item = String>,
+    ) -> Result<HashMap<String, Vec<i32>>> {
+        let mut out = HashMap::new();
+        for group_path in groups {
+            match self.list_group_tasks(&group_path) {
+                Ok(pids) => {
+                    out.insert(group_path, pids);
+                }
+                Err(Error::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+                    // Group removed concurrently
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(out)
+    }
+
+    /// List the paths of groups whose name starts with the configured prefix.
+    ///
+    /// Covers the same locations as `cleanup_all`: control groups at the root and
+    /// monitoring groups under `<root>/mon_groups`. Paths are returned sorted.
+    pub fn list_groups(&self) -> Result<Vec<String>> {
+        let root = &self.cfg.root;
+        let mon_groups_dir = root.join("mon_groups");
+        let mut groups = Vec::new();
+        for dir in [root, &mon_groups_dir] {
+            let children = self
+                .fs
+                .read_child_dirs(dir)
+                .map_err(|e| map_basic_fs_error(dir, &e))?;
+            groups.extend(
+                children
+                    .into_iter()
+                    .filter(|n| n.starts_with(&self.cfg.group_prefix))
+                    .map(|n| dir.join(n).to_string_lossy().into_owned()),
+            );
+        }
+        groups.sort();
+        Ok(groups)
+    }
+
     /// Return a reference to the underlying filesystem provider.
     pub fn fs_provider(&self) -> &P {
         &self.fs
@@ -355,17 +675,34 @@ impl<P: FsProvider> Resctrl<P> {
     /// - `missing` is the number of desired PIDs still not present in the group after
     ///   the final pass (0 indicates convergence)
     pub fn reconcile_group(
+        &self,
+        group_path: &str,
+        pid_source: impl FnMut() -> Result<Vec<i32>>,
+        max_passes: usize,
+    ) -> Result<AssignmentResult> {
+        self.reconcile_group_until(group_path, pid_source, max_passes, None)
+    }
+
+    /// Like `reconcile_group`, but stops after `max_passes` passes or once
+    /// `deadline` has passed, whichever comes first. At least one pass runs.
+    ///
+    /// When the deadline stops reconciliation after a pass that still had PIDs
+    /// to assign, `pid_source` and the group's tasks are read once more and
+    /// `missing` counts the desired PIDs not in the group, such as PIDs created
+    /// since that pass. A group that converged in the last pass reports 0.
+    pub fn reconcile_group_until(
         &self,
         group_path: &str,
         mut pid_source: impl FnMut() -> Result<Vec<i32>>,
         max_passes: usize,
+        deadline: Option<Instant>,
     ) -> Result<AssignmentResult> {
         use std::collections::HashSet;
 
         let mut total_assigned = 0usize;
         let mut last_desired: HashSet<i32> = HashSet::new();
 
-        for _ in 0..max_passes {
+        for pass in 1..=max_passes {
             // Desired tasks for this pass
             let desired_vec = pid_source()?;
             if desired_vec.is_empty() {
@@ -388,6 +725,14 @@ impl<P: FsProvider> Resctrl<P> {
             let res = self.assign_tasks(group_path, &missing)?;
             total_assigned += res.assigned;
             // Do not treat res.missing as terminal – recompute in next pass
+
+            if pass < max_passes && matches!(deadline, Some(d) if Instant::now() >= d) {
+                let desired: HashSet<i32> = pid_source()?.into_iter().collect();
+                let current: HashSet<i32> =
+                    self.list_group_tasks(group_path)?.into_iter().collect();
+                let still_missing = desired.difference(&current).count();
+                return Ok(AssignmentResult::new(total_assigned, still_missing));
+            }
         }
 
         // After exhausting passes, calculate how many are still missing
@@ -415,11 +760,23 @@ impl<P: FsProvider> Resctrl<P> {
     /// configured group prefix. Only directories are removed; files are ignored.
     ///
     /// Assumes resctrl is mounted. Does not call `ensure_mounted()`.
-    /// Fails if listing the root or `mon_groups` directory fails. Per-entry
-    /// deletion errors are counted in the returned report and the sweep continues.
+    /// Fails with `Error::InvalidPrefix` when the group prefix could match
+    /// unrelated groups (see `validate_group_prefix`), and if listing the root
+    /// or `mon_groups` directory fails. Per-entry deletion errors are counted in
+    /// the returned report and the sweep continues.
     pub fn cleanup_all(&self) -> Result<CleanupReport> {
         cleanup_prefix(&self.fs, &self.cfg.root, &self.cfg.group_prefix)
     }
+
+    /// Report what `cleanup_all()` would remove without deleting anything.
+    ///
+    /// `removed` counts the prefixed groups that would be removed and
+    /// `non_prefix_groups` the groups that would be kept; `removal_failures`
+    /// and `removal_race` are always zero. Fails like `cleanup_all()` when
+    /// listing the root or `mon_groups` directory fails.
+    pub fn cleanup_all_dry_run(&self) -> Result<CleanupReport> {
+        sweep_prefix(&self.fs, &self.cfg.root, &self.cfg.group_prefix, true)
+    }
 }
 
 /// Public helper to cleanup resctrl groups by prefix without a Resctrl instance.
@@ -427,9 +784,42 @@ impl<P: FsProvider> Resctrl<P> {
 /// Removes immediate child directories under `root` (excluding known metadata
 /// dirs) and under `root/mon_groups` whose names start with `prefix`.
 ///
-/// Errors listing the root or mon_groups are returned; per-entry removal errors
-/// are accumulated in the report.
+/// Errors listing the root or mon_groups, and prefixes rejected by
+/// `validate_group_prefix`, are returned; per-entry removal errors are
+/// accumulated in the report.
 pub fn cleanup_prefix<P: FsProvider>(fs: &P, root: &Path, prefix: &str) -> Result<CleanupReport> {
+    sweep_prefix(fs, root, prefix, false)
+}
+
+/// Check that `prefix` can only match groups named by this component: it must
+/// be at least `MIN_GROUP_PREFIX_LEN` characters long and contain no path
+/// separators. Cleanup refuses prefixes that fail this check.
+pub fn validate_group_prefix(prefix: &str) -> Result<()> {
+    let reason = if prefix.is_empty() {
+        "an empty prefix matches every group".to_string()
+    } else if prefix.contains(['/', '\0']) {
+        "must not contain '/' or NUL".to_string()
+    } else if prefix.chars().count() < MIN_GROUP_PREFIX_LEN {
+        format!("must be at least {} characters", MIN_GROUP_PREFIX_LEN)
+    } else {
+        return Ok(());
+    };
+    Err(Error::InvalidPrefix {
+        prefix: prefix.to_string(),
+        reason,
+    })
+}
+
+/// Sweep prefixed groups under `root` and `root/mon_groups`, only counting
+/// them when `dry_run` is set. Refuses prefixes rejected by
+/// `validate_group_prefix`.
+fn sweep_prefix<P: FsProvider>(
+    fs: &P,
+    root: &Path,
+    prefix: &str,
+    dry_run: bool,
+) -> Result<CleanupReport> {
+    validate_group_prefix(prefix)?;
     let mon_groups_dir = root.join("mon_groups");
 
     let mut report = CleanupReport::default();
@@ -442,7 +832,7 @@ pub fn cleanup_prefix<P: FsProvider>(fs: &P, root: &Path, prefix: &str) -> Resul
         .into_iter()
         .filter(|n| n != "info" && n != "mon_data" && n != "mon_groups")
         .collect();
-    report = cleanup_in_dir(fs, root, &root_children, prefix, report)?;
+    report = cleanup_in_dir(fs, root, &root_children, prefix, dry_run, report)?;
 
     // Sweep root-level mon_groups
     let mon_groups_dir_children = fs
@@ -453,6 +843,7 @@ pub fn cleanup_prefix<P: FsProvider>(fs: &P, root: &Path, prefix: &str) -> Resul
         &mon_groups_dir,
         &mon_groups_dir_children,
         prefix,
+        dry_run,
         report,
     )?;
 
@@ -464,10 +855,15 @@ fn cleanup_in_dir<P: FsProvider>(
     parent: &Path,
     child_dirs: &[String],
     prefix: &str,
+    dry_run: bool,
     mut report: CleanupReport,
 ) -> Result<CleanupReport> {
     for name in child_dirs {
         if name.starts_with(prefix) {
+            if dry_run {
+                report.removed += 1;
+                continue;
+            }
             let p = parent.join(name);
             match fs.remove_dir(&p) {
                 Ok(()) => report.removed += 1,
@@ -510,6 +906,71 @@ fn group_name(prefix: &str, pod_uid: &str) -> String {
     format!("{}{}", prefix, sanitize_uid(pod_uid))
 }
 
+/// One resource line of a `schemata` file, e.g. `L3:0=ff;1=ff`
+struct SchemataLine {
+    resource: String,
+    domains: Vec<(String, String)>,
+}
+
+impl fmt::Display for SchemataLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.resource)?;
+        for (i, (domain, value)) in self.domains.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}={}", domain, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse `schemata` text into resource lines, ignoring blank lines and
+/// surrounding whitespace.
+fn parse_schemata(s: &str) -> std::result::Result<Vec<SchemataLine>, String> {
+    let mut lines = Vec::new();
+    for raw in s.lines() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (resource, domains) = line
+            .split_once(':')
+            .ok_or_else(|| format!("missing ':' in schemata line '{}'", line))?;
+        let resource = resource.trim();
+        if resource.is_empty() {
+            return Err(format!("missing resource name in schemata line '{}'", line));
+        }
+        let domains = domains
+            .split(';')
+            .map(|d| {
+                let (id, value) = d
+                    .split_once('=')
+                    .ok_or_else(|| format!("missing '=' in schemata domain '{}'", d))?;
+                let (id, value) = (id.trim(), value.trim());
+                if id.parse::<u32>().is_err() || value.is_empty() {
+                    return Err(format!("invalid schemata domain '{}'", d));
+                }
+                Ok((id.to_string(), value.to_string()))
+            })
+            .collect::<std::result::Result<Vec<_>, String>>()?;
+        lines.push(SchemataLine {
+            resource: resource.to_string(),
+            domains,
+        });
+    }
+    Ok(lines)
+}
+
+/// Compare schemata values as the kernel reports them: cache bit masks may be
+/// echoed back with different zero padding or case.
+fn schemata_values_equal(a: &str, b: &str) -> bool {
+    match (u64::from_str_radix(a, 16), u64::from_str_radix(b, 16)) {
+        (Ok(x), Ok(y)) => x == y,
+        _ => a == b,
+    }
+}
+
 fn map_basic_fs_error(path: &Path, e: &io::Error) -> Error {
     if let Some(code) = e.raw_os_error() {
         match code {
@@ -538,6 +999,59 @@ pub struct SupportInfo {
     pub mounted: bool,
     pub mount_point: Option<PathBuf>,
     pub writable: bool,
+    /// Code/Data Prioritization is active (L3CODE/L3DATA instead of L3)
+    pub cdp_enabled: bool,
+}
+
+/// Monitoring counters for a group, summed across L3 domains
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MonData {
+    pub llc_occupancy_bytes: u64,
+    /// Total memory bandwidth counter; `None` without MBM support
+    pub mbm_total_bytes: Option<u64>,
+    /// Local memory bandwidth counter; `None` without MBM support
+    pub mbm_local_bytes: Option<u64>,
+}
+
+/// Change in the bandwidth counters between two samples of a group
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MonDelta {
+    /// Bytes counted by `mbm_total_bytes`; `None` unless both samples have it
+    pub mbm_total_bytes: Option<u64>,
+    /// Bytes counted by `mbm_local_bytes`; `None` unless both samples have it
+    pub mbm_local_bytes: Option<u64>,
+}
+
+impl MonData {
+    /// Bandwidth counted since `prev`, an earlier sample of the same group.
+    ///
+    /// `llc_occupancy_bytes` is a level rather than a counter and has no delta.
+    /// A counter that went backwards either wrapped or was reset (e.g. the group
+    /// was recreated): see [`counter_delta`].
+    pub fn delta_since(&self, prev: &MonData) -> MonDelta {
+        let delta = |prev: Option<u64>, cur: Option<u64>| Some(counter_delta(prev?, cur?));
+        MonDelta {
+            mbm_total_bytes: delta(prev.mbm_total_bytes, self.mbm_total_bytes),
+            mbm_local_bytes: delta(prev.mbm_local_bytes, self.mbm_local_bytes),
+        }
+    }
+}
+
+/// Increase of a monotonic counter from `prev` to `cur`.
+///
+/// When `cur < prev`, a wrap past `u64::MAX` is assumed if the wrapped distance is
+/// less than half the counter range; otherwise the counter restarted from zero and
+/// `cur` is what it counted since.
+pub fn counter_delta(prev: u64, cur: u64) -> u64 {
+    if cur >= prev {
+        return cur - prev;
+    }
+    let wrapped = cur.wrapping_sub(prev);
+    if wrapped <= u64::MAX / 2 {
+        wrapped
+    } else {
+        cur
+    }
 }
 
 /// Single-domain occupancy reading
@@ -621,6 +1135,32 @@ mod tests {
         assert!(info.mounted);
         assert_eq!(info.mount_point, Some(PathBuf::from("/sys/fs/resctrl")));
         assert!(info.writable);
+        assert!(!info.cdp_enabled);
+    }
+
+    #[test]
+    fn test_detect_support_cdp_enabled() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(Path::new("/sys/fs/resctrl/info"));
+        fs.add_dir(Path::new("/sys/fs/resctrl/info/L3CODE"));
+        fs.add_dir(Path::new("/sys/fs/resctrl/info/L3DATA"));
+        fs.add_dir(Path::new("/sys/fs/resctrl/info/L3_MON"));
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let info = rc.detect_support().expect("detect ok");
+        assert!(info.mounted);
+        assert!(info.cdp_enabled);
+    }
+
+    #[test]
+    fn test_detect_support_cdp_from_mount_options() {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_file(
+            Path::new("/proc/mounts"),
+            "resctrl /sys/fs/resctrl resctrl rw,relatime,cdp 0 0\n",
+        );
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let info = rc.detect_support().expect("detect ok");
+        assert!(info.cdp_enabled);
     }
 
     #[test]
@@ -767,6 +1307,113 @@ mod tests {
         matches_capacity(err);
     }
 
+    #[test]
+    fn test_create_group_without_mon_groups_is_unsupported() {
+        let fs = MockFs::with_premounted_resctrl();
+        let group_path = Path::new("/sys/fs/resctrl/mon_groups/pod_abc");
+        fs.set_enoent_dir(group_path);
+        let rc = Resctrl::with_provider(fs.clone(), Config::default());
+
+        // Monitoring unavailable: mon_groups is missing
+        match rc.create_group("abc").unwrap_err() {
+            Error::Unsupported { source } => {
+                let msg = source.to_string();
+                assert!(msg.contains("mon_groups not available"), "{msg}");
+                assert!(!msg.contains("CDP"), "{msg}");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // CDP-only mounts are called out
+        fs.add_file(Path::new("/sys/fs/resctrl/info/L3CODE"), "");
+        fs.add_file(Path::new("/sys/fs/resctrl/info/L3DATA"), "");
+        match rc.create_group("abc").unwrap_err() {
+            Error::Unsupported { source } => {
+                assert!(source.to_string().contains("(CDP enabled)"), "{source}");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    /// Resctrl root with allocation support: the root group has a schemata file
+    fn allocation_capable_resctrl() -> (MockFs, Resctrl<MockFs>) {
+        let fs = MockFs::with_premounted_resctrl();
+        fs.add_dir(Path::new("/sys/fs/resctrl/mon_groups"));
+        fs.add_file(
+            Path::new("/sys/fs/resctrl/schemata"),
+            "L3:0=fff;1=fff\nMB:0=100;1=100\n",
+        );
+        let rc = Resctrl::with_provider(fs.clone(), Config::default());
+        (fs, rc)
+    }
+
+    #[test]
+    fn test_create_control_group_unsupported_without_allocation() {
+        // Monitoring-only hosts refuse control groups at the root
+        let fs = MockFs::with_premounted_resctrl();
+        fs.set_no_perm_dir(Path::new("/sys/fs/resctrl/pod_abc"));
+        let rc = Resctrl::with_provider(fs.clone(), Config::default());
+        match rc.create_control_group("abc").unwrap_err() {
+            Error::Unsupported { .. } => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // With allocation support the same failure keeps its own error
+        let (fs, rc) = allocation_capable_resctrl();
+        fs.set_no_perm_dir(Path::new("/sys/fs/resctrl/pod_abc"));
+        match rc.create_control_group("abc").unwrap_err() {
+            Error::NoPermission { .. } => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_schemata_on_control_group() {
+        let (fs, rc) = allocation_capable_resctrl();
+        let group = rc.create_control_group("abc").expect("create ok");
+        assert_eq!(group, "/sys/fs/resctrl/pod_abc");
+
+        rc.set_schemata(&group, "L3:0=00f;1=0f0\n MB:0=50 \n")
+            .expect("set schemata");
+        let contents = fs
+            .file_contents(Path::new("/sys/fs/resctrl/pod_abc/schemata"))
+            .unwrap();
+        assert!(contents.contains("L3:0=00f;1=0f0\n"), "{}", contents);
+        assert!(contents.contains("MB:0=50\n"), "{}", contents);
+    }
+
+    #[test]
+    fn test_set_schemata_unsupported_without_schemata_file() {
+        let (_fs, rc) = allocation_capable_resctrl();
+        // Monitoring groups cannot carry an allocation
+        let group = rc.create_group("abc").expect("create ok");
+        match rc.set_schemata(&group, "L3:0=f").unwrap_err() {
+            Error::Unsupported { .. } => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_schemata_rejects_malformed_input() {
+        let (fs, rc) = allocation_capable_resctrl();
+        let group = rc.create_control_group("abc").expect("create ok");
+        let before = fs.file_contents(Path::new("/sys/fs/resctrl/pod_abc/schemata"));
+
+        for bad in ["L3 0=f", ":0=f", "L3:0", "L3:x=f", "L3:0="] {
+            match rc.set_schemata(&group, bad).unwrap_err() {
+                Error::Io { source, .. } => {
+                    assert_eq!(source.kind(), io::ErrorKind::InvalidInput, "{}", bad)
+                }
+                other => panic!("unexpected error for '{}': {:?}", bad, other),
+            }
+        }
+        // Nothing was written
+        assert_eq!(
+            fs.file_contents(Path::new("/sys/fs/resctrl/pod_abc/schemata")),
+            before
+        );
+    }
+
     #[test]
     fn test_delete_group_success() {
         let fs = MockFs::default();
@@ -1231,6 +1878,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_reconcile_group_deadline_bounds_churning_pids() {
+        let fs = MockFs::default();
+        fs.add_file(
+            Path::new("/proc/mounts"),
+            "resctrl /sys/fs/resctrl resctrl rw 0 0\n",
+        );
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root);
+
+        let group_path = root.join("pod_churn");
+        fs.add_dir(&group_path);
+        fs.add_file(&group_path.join("tasks"), "");
+
+        let rc = Resctrl::with_provider(
+            fs,
+            Config {
+                root: root.clone(),
+                group_prefix: "pod_".into(),
+            },
+        );
+
+        use std::cell::RefCell;
+        let calls = RefCell::new(0usize);
+        // Every pass reports a PID the group has not seen yet
+        let pid_source = || -> Result<Vec<i32>> {
+            *calls.borrow_mut() += 1;
+            Ok(vec![1000 + *calls.borrow() as i32])
+        };
+
+        let max_passes = 1_000_000;
+        let start = Instant::now();
+        let deadline = start + std::time::Duration::from_millis(20);
+        let res = rc
+            .reconcile_group_until(
+                group_path.to_str().unwrap(),
+                pid_source,
+                max_passes,
+                Some(deadline),
+            )
+            .expect("reconcile ok");
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        // One PID source read per pass, plus the final check at the deadline
+        let passes = *calls.borrow() - 1;
+        assert!(passes < max_passes, "ran all {} passes", passes);
+        assert_eq!(res.assigned, passes);
+        // A new PID appeared after the last pass, so the result is partial
+        assert_eq!(res.missing, 1);
+
+        // A deadline that has already passed still runs one pass, and a group
+        // that converged in it is not reported partial
+        *calls.borrow_mut() = 0;
+        let res = rc
+            .reconcile_group_until(
+                group_path.to_str().unwrap(),
+                || -> Result<Vec<i32>> {
+                    *calls.borrow_mut() += 1;
+                    Ok(vec![1])
+                },
+                max_passes,
+                Some(Instant::now()),
+            )
+            .expect("reconcile ok");
+        assert_eq!(*calls.borrow(), 2);
+        assert_eq!((res.assigned, res.missing), (1, 0));
+    }
+
     #[test]
     fn test_reconcile_group_handles_forking_processes() {
         let fs = MockFs::default();
@@ -1349,6 +2064,75 @@ mod tests {
         assert!(fs.dir_exists(&root.join("custom_root")));
     }
 
+    #[test]
+    fn test_cleanup_all_dry_run_deletes_nothing() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("pod_u1"));
+        fs.add_dir(&root.join("custom_root"));
+        fs.add_dir(&root.join("info"));
+        fs.add_dir(&root.join("mon_groups"));
+        fs.add_dir(&root.join("mon_groups").join("pod_u2"));
+        fs.add_dir(&root.join("mon_groups").join("pod_u3"));
+        fs.add_dir(&root.join("mon_groups").join("custom"));
+        // Would fail a real removal; not attempted in dry-run
+        fs.set_no_perm_remove_dir(&root.join("mon_groups").join("pod_u3"));
+
+        let rc = Resctrl::with_provider(fs.clone(), Config::default());
+        let rep = rc.cleanup_all_dry_run().expect("dry-run ok");
+        assert_eq!(
+            rep,
+            CleanupReport {
+                removed: 3,
+                removal_failures: 0,
+                removal_race: 0,
+                non_prefix_groups: 2,
+            }
+        );
+        for dir in ["pod_u1", "mon_groups/pod_u2", "mon_groups/pod_u3"] {
+            assert!(fs.dir_exists(&root.join(dir)), "{} was removed", dir);
+        }
+    }
+
+    #[test]
+    fn test_cleanup_refuses_dangerous_prefixes() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("pod_u1"));
+        fs.add_dir(&root.join("other"));
+        fs.add_dir(&root.join("mon_groups"));
+        fs.add_dir(&root.join("mon_groups").join("pod_u2"));
+
+        for prefix in ["", "p", "po", "pod/", "../pod_", "pod_\0"] {
+            let rc = Resctrl::with_provider(
+                fs.clone(),
+                Config {
+                    group_prefix: prefix.into(),
+                    ..Default::default()
+                },
+            );
+            assert!(
+                matches!(rc.cleanup_all(), Err(Error::InvalidPrefix { .. })),
+                "{:?} was accepted",
+                prefix
+            );
+            assert!(matches!(
+                rc.cleanup_all_dry_run(),
+                Err(Error::InvalidPrefix { .. })
+            ));
+            assert!(matches!(
+                cleanup_prefix(&fs, &root, prefix),
+                Err(Error::InvalidPrefix { .. })
+            ));
+        }
+        for dir in ["pod_u1", "other", "mon_groups/pod_u2"] {
+            assert!(fs.dir_exists(&root.join(dir)), "{} was removed", dir);
+        }
+
+        assert!(validate_group_prefix("np_").is_ok());
+        assert!(validate_group_prefix(DEFAULT_PREFIX).is_ok());
+    }
+
     #[test]
     fn test_cleanup_all_failures_and_race() {
         let fs = MockFs::default();
@@ -1516,4 +2300,222 @@ mod tests {
         assert_eq!(v[0].domain_id, "mon_L3_02");
         assert_eq!(v[0].bytes, 42);
     }
+
+    #[test]
+    fn test_read_mon_data_sums_counters() {
+        let fs = MockFs::with_premounted_resctrl();
+        let group = PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uid3");
+        let mon_data = group.join("mon_data");
+        fs.add_dir(&mon_data);
+        let d0 = mon_data.join("mon_L3_00");
+        let d1 = mon_data.join("mon_L3_01");
+        fs.add_dir(&d0);
+        fs.add_dir(&d1);
+        fs.add_file(&d0.join("llc_occupancy"), "100\n");
+        fs.add_file(&d0.join("mbm_total_bytes"), "1000\n");
+        fs.add_file(&d0.join("mbm_local_bytes"), "Unavailable\n");
+        fs.add_file(&d1.join("llc_occupancy"), "200\n");
+        fs.add_file(&d1.join("mbm_total_bytes"), "2000\n");
+
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let md = rc.read_mon_data(group.to_str().unwrap()).expect("read ok");
+        assert_eq!(
+            md,
+            MonData {
+                llc_occupancy_bytes: 300,
+                mbm_total_bytes: Some(3000),
+                mbm_local_bytes: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_mon_data_delta_handles_wrap_and_reset() {
+        let sample = |total: Option<u64>, local: Option<u64>| MonData {
+            llc_occupancy_bytes: 4096,
+            mbm_total_bytes: total,
+            mbm_local_bytes: local,
+        };
+
+        // Counters that moved forward
+        let first = sample(Some(1000), Some(400));
+        let second = sample(Some(1500), Some(400));
+        assert_eq!(
+            second.delta_since(&first),
+            MonDelta {
+                mbm_total_bytes: Some(500),
+                mbm_local_bytes: Some(0),
+            }
+        );
+
+        // Wrap past u64::MAX counts the bytes on both sides of the wrap
+        let before_wrap = sample(Some(u64::MAX - 99), None);
+        let after_wrap = sample(Some(50), Some(10));
+        assert_eq!(
+            after_wrap.delta_since(&before_wrap),
+            MonDelta {
+                mbm_total_bytes: Some(150),
+                mbm_local_bytes: None,
+            }
+        );
+
+        // A reset restarts from zero
+        assert_eq!(counter_delta(1_000_000, 200), 200);
+    }
+
+    #[test]
+    fn test_read_mon_data_requires_llc_occupancy() {
+        let fs = MockFs::with_premounted_resctrl();
+        let group = PathBuf::from("/sys/fs/resctrl/mon_groups/pod_uid4");
+        let d0 = group.join("mon_data").join("mon_L3_00");
+        fs.add_dir(&group.join("mon_data"));
+        fs.add_dir(&d0);
+        fs.add_file(&d0.join("mbm_total_bytes"), "10\n");
+
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let err = rc.read_mon_data(group.to_str().unwrap()).unwrap_err();
+        match err {
+            Error::Io { path, .. } => assert!(path.ends_with("llc_occupancy")),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_groups_filters_by_prefix() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("info"));
+        fs.add_dir(&root.join("pod_ctrl"));
+        fs.add_dir(&root.join("custom_root"));
+        fs.add_dir(&root.join("mon_groups"));
+        fs.add_dir(&root.join("mon_groups").join("pod_b"));
+        fs.add_dir(&root.join("mon_groups").join("pod_a"));
+        fs.add_dir(&root.join("mon_groups").join("custom"));
+
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let groups = rc.list_groups().expect("list ok");
+        assert_eq!(
+            groups,
+            vec![
+                "/sys/fs/resctrl/mon_groups/pod_a".to_string(),
+                "/sys/fs/resctrl/mon_groups/pod_b".to_string(),
+                "/sys/fs/resctrl/pod_ctrl".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_root_is_used_for_all_paths() {
+        let fs = MockFs::default();
+        let root = PathBuf::from("/host/sys/fs/resctrl");
+        let cfg = Config {
+            root: root.clone(),
+            group_prefix: "pod_".into(),
+        };
+        let rc = Resctrl::with_provider(fs.clone(), cfg);
+
+        // A resctrl mount elsewhere does not count as mounted at the root
+        fs.add_file(
+            Path::new("/proc/mounts"),
+            "resctrl /sys/fs/resctrl resctrl rw 0 0\n",
+        );
+        let info = rc.detect_support().expect("detect ok");
+        assert!(!info.mounted);
+        assert_eq!(info.mount_point, None);
+        match rc.ensure_mounted(false).unwrap_err() {
+            Error::NotMounted { root: r } => assert_eq!(r, root),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        match rc.create_group("u1").unwrap_err() {
+            Error::NotMounted { root: r } => assert_eq!(r, root),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // Auto-mount targets the configured root
+        rc.ensure_mounted(true).expect("mount ok");
+        assert!(fs.dir_exists(&root));
+        let info = rc.detect_support().expect("detect ok");
+        assert!(info.mounted);
+        assert_eq!(info.mount_point, Some(root.clone()));
+        assert!(info.writable);
+
+        fs.add_dir(&root.join("mon_groups"));
+        let group = rc.create_group("u1").expect("create ok");
+        assert_eq!(group, "/host/sys/fs/resctrl/mon_groups/pod_u1");
+        let ctrl = rc.create_control_group("u2").expect("create ok");
+        assert_eq!(ctrl, "/host/sys/fs/resctrl/pod_u2");
+        assert_eq!(rc.list_groups().expect("list ok"), vec![group, ctrl]);
+
+        let rep = rc.cleanup_all().expect("cleanup ok");
+        assert_eq!(rep.removed, 2);
+        assert!(!fs.dir_exists(&root.join("mon_groups").join("pod_u1")));
+        assert!(!fs.dir_exists(&root.join("pod_u2")));
+    }
+
+    #[test]
+    fn test_all_group_tasks_maps_each_group() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("info"));
+        fs.add_dir(&root.join("mon_data"));
+        fs.add_file(&root.join("tasks"), "1\n2\n");
+
+        // Monitoring groups under the root
+        fs.add_dir(&root.join("mon_groups"));
+        let mon_a = root.join("mon_groups").join("pod_a");
+        let mon_b = root.join("mon_groups").join("pod_b");
+        fs.add_dir(&mon_a);
+        fs.add_dir(&mon_b);
+        fs.add_file(&mon_a.join("tasks"), "10\n11\n");
+        fs.add_file(&mon_b.join("tasks"), "");
+
+        // Control group with a nested monitoring group
+        let ctrl = root.join("ctrl1");
+        let nested = ctrl.join("mon_groups").join("pod_c");
+        fs.add_dir(&ctrl);
+        fs.add_dir(&ctrl.join("mon_groups"));
+        fs.add_dir(&nested);
+        fs.add_file(&ctrl.join("tasks"), "20\n");
+        fs.add_file(&nested.join("tasks"), "21\n");
+
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let all = rc.all_group_tasks().expect("enumerate ok");
+
+        let key = |p: &Path| p.to_str().unwrap().to_string();
+        let mut expected = HashMap::new();
+        expected.insert(key(&mon_a), vec![10, 11]);
+        expected.insert(key(&mon_b), vec![]);
+        expected.insert(key(&ctrl), vec![20]);
+        expected.insert(key(&nested), vec![21]);
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn test_list_all_group_tasks_covers_prefixed_groups() {
+        let fs = MockFs::with_premounted_resctrl();
+        let root = PathBuf::from("/sys/fs/resctrl");
+        fs.add_dir(&root.join("info"));
+        fs.add_dir(&root.join("mon_groups"));
+
+        let mon = root.join("mon_groups").join("pod_a");
+        let ctrl = root.join("pod_b");
+        fs.add_dir(&mon);
+        fs.add_dir(&ctrl);
+        fs.add_file(&mon.join("tasks"), "10\n11\n");
+        fs.add_file(&ctrl.join("tasks"), "20\n");
+
+        // Groups of other components are not listed
+        let other = root.join("mon_groups").join("other");
+        fs.add_dir(&other);
+        fs.add_file(&other.join("tasks"), "30\n");
+
+        let rc = Resctrl::with_provider(fs, Config::default());
+        let all = rc.list_all_group_tasks().expect("enumerate ok");
+
+        let key = |p: &Path| p.to_str().unwrap().to_string();
+        let mut expected = HashMap::new();
+        expected.insert(key(&mon), vec![10, 11]);
+        expected.insert(key(&ctrl), vec![20]);
+        assert_eq!(all, expected);
+    }
 }
@@ -13,6 +13,8 @@ pub mod mock_fs {
         pub no_perm_files: HashSet<PathBuf>,
         pub no_perm_dirs: HashSet<PathBuf>,
         pub nospace_dirs: HashSet<PathBuf>,
+        // Simulate ENOENT on create_dir for these paths, as for a missing parent
+        pub enoent_dirs: HashSet<PathBuf>,
         pub missing_pids: HashSet<i32>,
         pub mount_err: Option<i32>,
         // Optional overrides for directory listing. If present, returned as-is.
@@ -21,11 +23,17 @@ pub mod mock_fs {
         pub no_perm_remove_dirs: HashSet<PathBuf>,
         // Track create_dir invocations per path
         pub mkdir_calls: HashMap<PathBuf, usize>,
+        // Track read_to_string invocations per path
+        pub read_calls: HashMap<PathBuf, usize>,
     }
 
+    // Called before `remove_dir` of a path, outside the state lock
+    type Hook = Arc<dyn Fn() + Send + Sync>;
+
     #[derive(Clone, Default)]
     pub struct MockFs {
         state: Arc<Mutex<MockFsState>>,
+        remove_dir_hooks: Arc<Mutex<HashMap<PathBuf, Hook>>>,
     }
 
     impl MockFs {
@@ -68,6 +76,11 @@ pub mod mock_fs {
             st.nospace_dirs.insert(p.to_path_buf());
         }
 
+        pub fn set_enoent_dir(&self, p: &Path) {
+            let mut st = self.state.lock().unwrap();
+            st.enoent_dirs.insert(p.to_path_buf());
+        }
+
         pub fn set_mount_err(&self, err: i32) {
             let mut st = self.state.lock().unwrap();
             st.mount_err = Some(err);
@@ -105,12 +118,33 @@ pub mod mock_fs {
             st.child_dir_overrides.insert(parent.to_path_buf(), names);
         }
 
+        /// Run `hook` whenever remove_dir is called for the given path, before the
+        /// removal. The hook may block, e.g. to hold an operation at a known point
+        /// while another thread races with it.
+        pub fn set_remove_dir_hook(&self, p: &Path, hook: impl Fn() + Send + Sync + 'static) {
+            let mut hooks = self.remove_dir_hooks.lock().unwrap();
+            hooks.insert(p.to_path_buf(), Arc::new(hook));
+        }
+
         /// Return number of times create_dir has been called for the given path.
         pub fn mkdir_count(&self, p: &Path) -> usize {
             let st = self.state.lock().unwrap();
             *st.mkdir_calls.get(p).unwrap_or(&0)
         }
 
+        /// Return number of times read_to_string has been called for the given path.
+        pub fn read_count(&self, p: &Path) -> usize {
+            let st = self.state.lock().unwrap();
+            *st.read_calls.get(p).unwrap_or(&0)
+        }
+
+        /// Copy of the files (with their contents) and directories, to check that
+        /// an operation left the filesystem unchanged.
+        pub fn entries(&self) -> (HashMap<PathBuf, String>, HashSet<PathBuf>) {
+            let st = self.state.lock().unwrap();
+            (st.files.clone(), st.dirs.clone())
+        }
+
         /// Convenience: build a MockFs with resctrl pre-mounted at the default root.
         /// Seeds /proc/mounts with a resctrl entry, ensures the root and its tasks file exist.
         pub fn with_premounted_resctrl() -> Self {
@@ -144,6 +178,9 @@ pub mod mock_fs {
             if st.nospace_dirs.contains(p) {
                 return Err(io::Error::from_raw_os_error(libc::ENOSPC));
             }
+            if st.enoent_dirs.contains(p) {
+                return Err(io::Error::from_raw_os_error(libc::ENOENT));
+            }
             if st.dirs.contains(p) {
                 return Err(io::Error::from_raw_os_error(libc::EEXIST));
             }
@@ -152,12 +189,24 @@ pub mod mock_fs {
                 if name.to_string_lossy().starts_with("pod_") {
                     let tasks = p.join("tasks");
                     st.files.entry(tasks).or_default();
+                    // Control groups (outside mon_groups) inherit the parent's
+                    // schemata when allocation is available
+                    let parent = p.parent().unwrap_or(Path::new("/"));
+                    if parent.file_name() != Some(std::ffi::OsStr::new("mon_groups")) {
+                        if let Some(schemata) = st.files.get(&parent.join("schemata")).cloned() {
+                            st.files.insert(p.join("schemata"), schemata);
+                        }
+                    }
                 }
             }
             Ok(())
         }
 
         fn remove_dir(&self, p: &Path) -> io::Result<()> {
+            let hook = self.remove_dir_hooks.lock().unwrap().get(p).cloned();
+            if let Some(hook) = hook {
+                hook();
+            }
             let mut st = self.state.lock().unwrap();
             if st.no_perm_remove_dirs.contains(p) {
                 return Err(io::Error::from_raw_os_error(libc::EACCES));
@@ -202,7 +251,8 @@ pub mod mock_fs {
         }
 
         fn read_to_string(&self, p: &Path) -> io::Result<String> {
-            let st = self.state.lock().unwrap();
+            let mut st = self.state.lock().unwrap();
+            *st.read_calls.entry(p.to_path_buf()).or_insert(0) += 1;
             if st.no_perm_files.contains(p) {
                 return Err(io::Error::from_raw_os_error(libc::EACCES));
             }
@@ -1,17 +1,64 @@
 use anyhow::{Context, Result};
 use arrow_array::{ArrayRef, RecordBatch};
-use arrow_schema::{Field, Schema};
+use arrow_schema::{DataType, Field, Schema};
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use parquet::arrow::ArrowWriter;
 use parquet::basic::Compression;
 use parquet::file::properties::WriterProperties;
+use std::ffi::OsStr;
 use std::fs::File;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use tqdm::pbar;
 
 const READER_BATCH_SIZE: usize = 32 * 1024; // 32k rows per batch
 
+/// Extension of zstd-compressed inputs, e.g. `trace.parquet.zst`
+const ZSTD_EXTENSION: &str = "zst";
+
+/// Open an input file for reading. A `.zst` file is decompressed into an
+/// anonymous temporary file first, so readers that seek (like Parquet's) get the
+/// inner file; other files are opened as they are. Only Parquet is read from
+/// inside a `.zst` wrapper, so anything else (like `trace.arrow.zst`) is
+/// rejected before decompressing it.
+pub fn open_input(path: &Path) -> Result<File> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open input file: {}", path.display()))?;
+    if path.extension() != Some(OsStr::new(ZSTD_EXTENSION)) {
+        return Ok(file);
+    }
+    if unwrapped(path).and_then(Path::extension) != Some(OsStr::new("parquet")) {
+        return Err(anyhow::anyhow!(
+            "Unsupported input file: {} (only Parquet inputs are supported, optionally as .parquet.zst)",
+            path.display()
+        ));
+    }
+
+    let mut inner = tempfile::tempfile().with_context(|| "Failed to create temporary file")?;
+    zstd::stream::copy_decode(BufReader::new(file), &mut inner)
+        .with_context(|| format!("Failed to decompress input file: {}", path.display()))?;
+    inner.seek(SeekFrom::Start(0))?;
+    Ok(inner)
+}
+
+/// `path` without a `.zst` wrapper (`trace.parquet.zst` -> `trace.parquet`)
+fn unwrapped(path: &Path) -> Option<&Path> {
+    if path.extension() == Some(OsStr::new(ZSTD_EXTENSION)) {
+        path.file_stem().map(Path::new)
+    } else {
+        Some(path)
+    }
+}
+
+/// File name of `path` without its extension, also dropping a `.zst` wrapper
+/// (`trace.parquet.zst` -> `trace`)
+pub(crate) fn input_stem(path: &Path) -> Option<&OsStr> {
+    unwrapped(path)?.file_stem()
+}
+
 /// Trait for analysis modules that process record batches and add new columns
 pub trait Analysis {
     /// Process a record batch and return new columns to be added
@@ -20,9 +67,16 @@ pub trait Analysis {
     /// Return the schema for the new columns this analysis adds
     fn new_columns_schema(&self) -> Vec<Arc<Field>>;
 
-    /// Called after all batches have been processed to finalize the analysis
-    fn finalize(&self) -> Result<()> {
-        Ok(())
+    /// Return the input columns (name and type) this analysis reads
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        Vec::new()
+    }
+
+    /// Called after all batches have been processed to finalize the analysis.
+    /// May return an end-of-stream summary (with its own schema), which is written
+    /// next to the output file as `<output>_summary.parquet`.
+    fn finalize(&mut self) -> Result<Option<RecordBatch>> {
+        Ok(None)
     }
 }
 
@@ -37,6 +91,116 @@ impl Analyzer {
         Self { output_filename }
     }
 
+    /// Process every `*.parquet` (or `*.parquet.zst`) file in `dir` concurrently on a pool of worker
+    /// threads, writing `<stem>_<analysis_type>_analysis.parquet` next to each.
+    ///
+    /// Analyses are stateful, so `analysis_factory` creates a fresh one for each
+    /// file from its path and reader. Outputs of earlier runs in `dir`
+    /// (`*_analysis.parquet` and their summaries) are not treated as inputs.
+    /// Every file is attempted and all failures are reported together. Returns
+    /// the output paths, ordered by input file name.
+    pub fn process_directory<A, F>(
+        dir: &Path,
+        analysis_type: &str,
+        analysis_factory: F,
+    ) -> Result<Vec<PathBuf>>
+    where
+        A: Analysis,
+        F: Fn(&Path, &ParquetRecordBatchReaderBuilder<File>) -> Result<A> + Sync,
+    {
+        let inputs = parquet_inputs(dir)?;
+        let outputs = inputs
+            .iter()
+            .map(|input| crate::determine_output_filename(input, None, analysis_type))
+            .collect::<Result<Vec<_>>>()?;
+
+        let next = AtomicUsize::new(0);
+        let errors = Mutex::new(Vec::new());
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(inputs.len());
+        thread::scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(input) = inputs.get(i) else {
+                        break;
+                    };
+                    let result = open_input(input)
+                        .and_then(|file| {
+                            ParquetRecordBatchReaderBuilder::try_new(file)
+                                .with_context(|| "Failed to create Parquet reader builder")
+                        })
+                        .and_then(|builder| {
+                            let analysis = analysis_factory(input, &builder)?;
+                            Analyzer::new(outputs[i].clone())
+                                .process_parquet_file(builder, analysis)
+                        });
+                    if let Err(e) = result {
+                        errors.lock().unwrap().push((i, e));
+                    }
+                });
+            }
+        });
+
+        // Report from this thread once all files are done, in input order
+        let mut errors = errors.into_inner().unwrap();
+        errors.sort_by_key(|(i, _)| *i);
+        let mut failures = Vec::with_capacity(errors.len());
+        let mut errors = errors.into_iter().peekable();
+        for (i, (input, output)) in inputs.iter().zip(&outputs).enumerate() {
+            match errors.next_if(|(failed, _)| *failed == i) {
+                Some((_, e)) => failures.push(format!("{}: {:#}", input.display(), e)),
+                None => println!("Processed {} -> {}", input.display(), output.display()),
+            }
+        }
+        if !failures.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to analyze {} of {} files: {}",
+                failures.len(),
+                inputs.len(),
+                failures.join("; ")
+            ));
+        }
+        Ok(outputs)
+    }
+
+    /// Export the Parquet file `input` as CSV to `output`, with a header row and
+    /// the columns in file order. Batches are streamed, so memory use does not
+    /// grow with the file. Returns the number of rows written.
+    pub fn export_csv(input: &Path, output: &Path) -> Result<usize> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(open_input(input)?)
+            .with_context(|| "Failed to create Parquet reader builder")?;
+        let schema = builder.schema().clone();
+        let reader = builder
+            .with_batch_size(READER_BATCH_SIZE)
+            .build()
+            .with_context(|| "Failed to build Arrow reader")?;
+
+        let output_file = File::create(output)
+            .with_context(|| format!("Failed to create output file: {}", output.display()))?;
+        let mut writer = arrow_csv::Writer::new(BufWriter::new(output_file));
+
+        // The header is written with the first batch; an empty one makes sure
+        // files without rows still get it
+        writer
+            .write(&RecordBatch::new_empty(schema))
+            .with_context(|| "Failed to write CSV header")?;
+        let mut rows = 0;
+        for batch in reader {
+            let batch = batch.with_context(|| "Failed to read record batch")?;
+            writer
+                .write(&batch)
+                .with_context(|| "Failed to write CSV rows")?;
+            rows += batch.num_rows();
+        }
+        writer
+            .into_inner()
+            .flush()
+            .with_context(|| format!("Failed to flush output file: {}", output.display()))?;
+        Ok(rows)
+    }
+
     /// Process a Parquet file with the given analysis
     pub fn process_parquet_file<A: Analysis>(
         &self,
@@ -45,6 +209,9 @@ impl Analyzer {
     ) -> Result<()> {
         let input_schema = builder.schema().clone();
 
+        // Fail before reading any batch if the input lacks required columns
+        validate_input_schema(&input_schema, &analysis.required_columns())?;
+
         // Calculate total rows from metadata
         let total_rows: usize = builder
             .metadata()
@@ -97,9 +264,50 @@ impl Analyzer {
         progress_bar.close()?;
         writer.close().with_context(|| "Failed to close writer")?;
 
-        // Finalize the analysis
-        analysis.finalize()?;
+        // Finalize the analysis, writing its summary if it has one
+        if let Some(summary) = analysis.finalize()? {
+            self.write_summary(&summary)?;
+        }
+
+        Ok(())
+    }
+
+    /// Path of the summary file: the output file name with a `_summary` suffix
+    fn summary_filename(&self) -> PathBuf {
+        let stem = self
+            .output_filename
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.output_filename
+            .with_file_name(format!("{}_summary.parquet", stem))
+    }
+
+    /// Write the end-of-stream summary batch returned by `Analysis::finalize`
+    fn write_summary(&self, summary: &RecordBatch) -> Result<()> {
+        let summary_filename = self.summary_filename();
+        println!(
+            "Writing analysis summary to: {}",
+            summary_filename.display()
+        );
 
+        let file = File::create(&summary_filename).with_context(|| {
+            format!(
+                "Failed to create summary file: {}",
+                summary_filename.display()
+            )
+        })?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(file, summary.schema(), Some(props))
+            .with_context(|| "Failed to create summary writer")?;
+        writer
+            .write(summary)
+            .with_context(|| "Failed to write summary batch")?;
+        writer
+            .close()
+            .with_context(|| "Failed to close summary writer")?;
         Ok(())
     }
 
@@ -135,3 +343,278 @@ impl Analyzer {
             .with_context(|| "Failed to create output record batch")
     }
 }
+
+/// Parquet files directly in `dir`, sorted by name, skipping analysis outputs
+fn parquet_inputs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read input directory: {}", dir.display()))?;
+
+    let mut inputs = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_parquet = unwrapped(&path)
+            .and_then(Path::extension)
+            .is_some_and(|ext| ext == "parquet");
+        let is_output = input_stem(&path)
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.ends_with("_analysis") || stem.ends_with("_analysis_summary"));
+        if is_parquet && !is_output && path.is_file() {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// Check that `schema` contains every required column with the expected type.
+/// Reports all missing or mismatched columns in a single error.
+fn validate_input_schema(schema: &Schema, required: &[(&str, DataType)]) -> Result<()> {
+    let mut problems = Vec::new();
+    for (name, expected) in required {
+        match schema.field_with_name(name) {
+            Ok(field) if field.data_type() == expected => {}
+            Ok(field) => problems.push(format!(
+                "column '{}' has type {}, expected {}",
+                name,
+                field.data_type(),
+                expected
+            )),
+            Err(_) => problems.push(format!("missing column '{}' ({})", name, expected)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Input schema is incompatible with analysis: {}",
+            problems.join("; ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_input_schema_reports_all_problems() {
+        let schema = Schema::new(vec![
+            Field::new("timestamp", DataType::Int64, false),
+            Field::new("cpu_id", DataType::Int64, false),
+        ]);
+        let required = [
+            ("timestamp", DataType::Int64),
+            ("cpu_id", DataType::Int32),
+            ("pid", DataType::Int32),
+        ];
+
+        let err = validate_input_schema(&schema, &required)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("column 'cpu_id' has type Int64, expected Int32"));
+        assert!(err.contains("missing column 'pid'"));
+        assert!(!err.contains("'timestamp'"));
+    }
+
+    #[test]
+    fn test_validate_input_schema_ok() {
+        let schema = Schema::new(vec![Field::new("timestamp", DataType::Int64, false)]);
+        assert!(validate_input_schema(&schema, &[("timestamp", DataType::Int64)]).is_ok());
+    }
+
+    /// Counts rows and reports the total as its summary
+    struct RowCountAnalysis {
+        rows: i64,
+    }
+
+    impl Analysis for RowCountAnalysis {
+        fn process_record_batch(&mut self, batch: &RecordBatch) -> Result<Vec<ArrayRef>> {
+            self.rows += batch.num_rows() as i64;
+            Ok(vec![Arc::new(arrow_array::Int64Array::from(vec![
+                self.rows;
+                batch
+                    .num_rows(
+                    )
+            ]))])
+        }
+
+        fn new_columns_schema(&self) -> Vec<Arc<Field>> {
+            vec![Arc::new(Field::new("rows_so_far", DataType::Int64, false))]
+        }
+
+        fn finalize(&mut self) -> Result<Option<RecordBatch>> {
+            let schema = Schema::new(vec![Field::new("total_rows", DataType::Int64, false)]);
+            let batch = RecordBatch::try_new(
+                Arc::new(schema),
+                vec![Arc::new(arrow_array::Int64Array::from(vec![self.rows]))],
+            )?;
+            Ok(Some(batch))
+        }
+    }
+
+    /// Write a Parquet file with a single `timestamp` column
+    fn write_timestamps(path: &Path, timestamps: &[i64]) {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "timestamp",
+            DataType::Int64,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(arrow_array::Int64Array::from(timestamps.to_vec()))],
+        )
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    /// Read a whole Parquet file into batches
+    fn read_batches(path: &Path) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_export_csv_writes_header_and_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let input = dir.join("trace.parquet");
+        let output = dir.join("trace.csv");
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Int64, false),
+            Field::new("process_name", DataType::Utf8, true),
+            Field::new("cpu_id", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(arrow_array::Int64Array::from(vec![100, 200])),
+                Arc::new(arrow_array::StringArray::from(vec![Some("app"), None])),
+                Arc::new(arrow_array::Int32Array::from(vec![3, 7])),
+            ],
+        )
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&input).unwrap(), schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        assert_eq!(Analyzer::export_csv(&input, &output).unwrap(), 2);
+
+        let csv = std::fs::read_to_string(&output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            ["timestamp,process_name,cpu_id", "100,app,3", "200,,7"]
+        );
+
+        // A file without rows still gets the header
+        write_timestamps(&input, &[]);
+        assert_eq!(Analyzer::export_csv(&input, &output).unwrap(), 0);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "timestamp\n");
+    }
+
+    #[test]
+    fn test_finalize_summary_is_written() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let input = dir.join("trace.parquet");
+        let output = dir.join("trace_rowcount_analysis.parquet");
+
+        write_timestamps(&input, &[1, 2, 3]);
+
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&input).unwrap()).unwrap();
+        Analyzer::new(output.clone())
+            .process_parquet_file(builder, RowCountAnalysis { rows: 0 })
+            .unwrap();
+
+        let summary_path = dir.join("trace_rowcount_analysis_summary.parquet");
+        let summary = read_batches(&summary_path);
+        assert_eq!(summary.len(), 1);
+        let totals = summary[0]
+            .column_by_name("total_rows")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<arrow_array::Int64Array>()
+            .unwrap();
+        assert_eq!(totals.value(0), 3);
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn test_zstd_wrapped_parquet_is_read_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let plain = dir.join("trace.parquet");
+        write_timestamps(&plain, &[10, 20, 30]);
+        let compressed = dir.join("trace.parquet.zst");
+        let bytes = zstd::encode_all(File::open(&plain).unwrap(), 3).unwrap();
+        std::fs::write(&compressed, bytes).unwrap();
+
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(open_input(&compressed).unwrap()).unwrap();
+        let batches: Vec<RecordBatch> = builder
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches, read_batches(&plain));
+
+        // Outputs are named after the inner file, and plain files are unchanged
+        assert_eq!(input_stem(&compressed), Some(OsStr::new("trace")));
+        assert_eq!(input_stem(&plain), Some(OsStr::new("trace")));
+        assert_eq!(parquet_inputs(dir).unwrap(), vec![plain, compressed]);
+    }
+
+    #[test]
+    fn test_zstd_wrapped_ipc_is_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("trace.arrow.zst");
+        std::fs::write(&path, zstd::encode_all(&b"not parquet"[..], 3).unwrap()).unwrap();
+
+        let err = open_input(&path).unwrap_err().to_string();
+        assert!(err.contains("only Parquet inputs are supported"), "{}", err);
+        assert!(parquet_inputs(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_process_directory_analyzes_each_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        write_timestamps(&dir.join("node-a.parquet"), &[1, 2, 3]);
+        write_timestamps(&dir.join("node-b.parquet"), &[4, 5]);
+        std::fs::write(dir.join("notes.txt"), "not a trace").unwrap();
+
+        let expected = vec![
+            dir.join("node-a_rowcount_analysis.parquet"),
+            dir.join("node-b_rowcount_analysis.parquet"),
+        ];
+        for _ in 0..2 {
+            // The second run must not pick up the first run's outputs
+            let outputs = Analyzer::process_directory(dir, "rowcount", |_, _| {
+                Ok(RowCountAnalysis { rows: 0 })
+            })
+            .unwrap();
+            assert_eq!(outputs, expected);
+        }
+
+        // Each file got its own analysis instance
+        for (output, rows) in expected.iter().zip([3, 2]) {
+            let batches = read_batches(output);
+            let counts = batches[0]
+                .column_by_name("rows_so_far")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<arrow_array::Int64Array>()
+                .unwrap();
+            assert_eq!(counts.values().to_vec(), vec![rows; rows as usize]);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use arrow_array::{Array, ArrayRef, Float64Array, Int64Array, RecordBatch};
+use arrow_schema::{DataType, Field};
+use std::sync::Arc;
+
+use crate::analyzer::Analysis;
+
+/// Per-row cache efficiency metrics derived from the hardware counter columns
+pub struct CacheEfficiencyAnalysis;
+
+impl CacheEfficiencyAnalysis {
+    pub fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    fn int64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a Int64Array> {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| anyhow::anyhow!("{} column not found", name))?
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| anyhow::anyhow!("{} column is not Int64Array", name))
+    }
+
+    /// Divide two counters, yielding None for null inputs or a zero denominator
+    fn ratio(numerator: &Int64Array, denominator: &Int64Array, i: usize) -> Option<f64> {
+        if numerator.is_null(i) || denominator.is_null(i) || denominator.value(i) == 0 {
+            return None;
+        }
+        Some(numerator.value(i) as f64 / denominator.value(i) as f64)
+    }
+}
+
+impl Analysis for CacheEfficiencyAnalysis {
+    fn process_record_batch(&mut self, batch: &RecordBatch) -> Result<Vec<ArrayRef>> {
+        let num_rows = batch.num_rows();
+
+        // Extract required columns
+        let cycles = Self::int64_column(batch, "cycles")?;
+        let instructions = Self::int64_column(batch, "instructions")?;
+        let llc_misses = Self::int64_column(batch, "llc_misses")?;
+        let cache_references = Self::int64_column(batch, "cache_references")?;
+
+        let mut llc_miss_rate = Vec::with_capacity(num_rows);
+        let mut ipc = Vec::with_capacity(num_rows);
+
+        for i in 0..num_rows {
+            llc_miss_rate.push(Self::ratio(llc_misses, cache_references, i));
+            ipc.push(Self::ratio(instructions, cycles, i));
+        }
+
+        Ok(vec![
+            Arc::new(Float64Array::from(llc_miss_rate)),
+            Arc::new(Float64Array::from(ipc)),
+        ])
+    }
+
+    fn new_columns_schema(&self) -> Vec<Arc<Field>> {
+        vec![
+            Arc::new(Field::new("llc_miss_rate", DataType::Float64, true)),
+            Arc::new(Field::new("ipc", DataType::Float64, true)),
+        ]
+    }
+
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        vec![
+            ("cycles", DataType::Int64),
+            ("instructions", DataType::Int64),
+            ("llc_misses", DataType::Int64),
+            ("cache_references", DataType::Int64),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_schema::Schema;
+
+    fn create_test_batch(
+        cycles: Vec<i64>,
+        instructions: Vec<i64>,
+        llc_misses: Vec<i64>,
+        cache_references: Vec<i64>,
+    ) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Arc::new(Field::new("cycles", DataType::Int64, false)),
+            Arc::new(Field::new("instructions", DataType::Int64, false)),
+            Arc::new(Field::new("llc_misses", DataType::Int64, false)),
+            Arc::new(Field::new("cache_references", DataType::Int64, false)),
+        ]);
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(cycles)),
+                Arc::new(Int64Array::from(instructions)),
+                Arc::new(Int64Array::from(llc_misses)),
+                Arc::new(Int64Array::from(cache_references)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ratios_and_divide_by_zero() {
+        let mut analysis = CacheEfficiencyAnalysis::new().unwrap();
+        let batch = create_test_batch(
+            vec![2000, 0, 1000],
+            vec![1000, 500, 3000],
+            vec![25, 10, 0],
+            vec![100, 0, 50],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        assert_eq!(new_columns.len(), analysis.new_columns_schema().len());
+
+        let llc_miss_rate = new_columns[0]
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        let ipc = new_columns[1]
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+
+        // Row 0: both ratios defined
+        assert!((llc_miss_rate.value(0) - 0.25).abs() < f64::EPSILON);
+        assert!((ipc.value(0) - 0.5).abs() < f64::EPSILON);
+
+        // Row 1: zero cache_references and zero cycles produce nulls
+        assert!(llc_miss_rate.is_null(1));
+        assert!(ipc.is_null(1));
+
+        // Row 2: zero numerator is a valid 0.0 ratio
+        assert_eq!(llc_miss_rate.value(2), 0.0);
+        assert!((ipc.value(2) - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_missing_column_errors() {
+        let mut analysis = CacheEfficiencyAnalysis::new().unwrap();
+        let schema = Schema::new(vec![Arc::new(Field::new("cycles", DataType::Int64, false))]);
+        let batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(Int64Array::from(vec![1]))])
+                .unwrap();
+
+        let result = analysis.process_record_batch(&batch);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("instructions column not found"));
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use arrow_array::{Array, ArrayRef, Float64Array, RecordBatch};
+use arrow_array::{Array, ArrayRef, BooleanArray, Float64Array, RecordBatch};
 use arrow_schema::{DataType, Field};
 use std::collections::HashMap;
 use std::fs::File;
@@ -101,19 +101,24 @@ impl CpuTimeCounter {
         self.current_thread_count -= 1;
     }
 
-    /// Update aggregate CPU time with elapsed time since last update
-    pub fn update(&mut self, timestamp: u64) {
+    /// Update aggregate CPU time with elapsed time since last update.
+    /// Returns false, leaving the counter unchanged, if `timestamp` is before the
+    /// last update.
+    pub fn update(&mut self, timestamp: u64) -> bool {
         if self.last_update_timestamp > 0 {
             if timestamp < self.last_update_timestamp {
-                panic!(
-                    "Timestamp regression detected: {} < {}",
-                    timestamp, self.last_update_timestamp
-                );
+                return false;
             }
             let elapsed_time = timestamp - self.last_update_timestamp;
             self.aggregate_cpu_time += elapsed_time * (self.current_thread_count as u64);
         }
         self.last_update_timestamp = timestamp;
+        true
+    }
+
+    /// Whether `timestamp` is before the last update, so `update` would reject it
+    pub fn precedes_last_update(&self, timestamp: u64) -> bool {
+        self.last_update_timestamp > 0 && timestamp < self.last_update_timestamp
     }
 
     /// Get current aggregate CPU time in nanoseconds
@@ -143,6 +148,10 @@ impl PerCpuState {
 }
 
 /// Main concurrency analysis processor
+///
+/// Works on trace-mode rows, where `pid` and `next_tgid` are both process (thread
+/// group) IDs, so "same process" compares whole processes. The collector's
+/// `--granularity thread` only changes timeslot rows and leaves these intact.
 pub struct ConcurrencyAnalysis {
     num_cpus: usize,
 
@@ -186,7 +195,10 @@ impl ConcurrencyAnalysis {
         pid == 0
     }
 
-    /// Process a single event
+    /// Process a single event. Returns the average total and same-process thread
+    /// counts, and whether the timestamp went backward on this CPU or for one of the
+    /// counters involved. Such rows leave all state unchanged, including the thread
+    /// counts of a context switch, and their averages are 0.
     fn process_event(
         &mut self,
         timestamp: u64,
@@ -194,7 +206,25 @@ impl ConcurrencyAnalysis {
         cpu_id: usize,
         is_context_switch: bool,
         next_tgid: Option<u32>,
-    ) -> Result<(f64, f64)> {
+    ) -> Result<(f64, f64, bool)> {
+        if is_context_switch && next_tgid.is_none() {
+            return Err(anyhow::anyhow!("next_tgid is null for context switch"));
+        }
+
+        // Skip rows that any of the counters they would update has moved past
+        let precedes_pid_counter = |pid: u32| {
+            self.per_pid_counters
+                .get(&pid)
+                .is_some_and(|counter| counter.precedes_last_update(timestamp))
+        };
+        let regression = timestamp < self.per_cpu_state[cpu_id].last_timestamp
+            || self.total_counter.precedes_last_update(timestamp)
+            || precedes_pid_counter(pid)
+            || (is_context_switch && next_tgid.is_some_and(precedes_pid_counter));
+        if regression {
+            return Ok((0.0, 0.0, true));
+        }
+
         // Get or create current PID counter entry
         let current_pid_counter = self
             .per_pid_counters
@@ -215,10 +245,7 @@ impl ConcurrencyAnalysis {
         let end_same_process_cpu_time = current_pid_counter.get_ns();
 
         // Handle context switches - only increment/decrement counters on context switches
-        if is_context_switch {
-            let next_pid =
-                next_tgid.expect("next_tgid should always be present on context switches");
-
+        if let Some(next_pid) = next_tgid.filter(|_| is_context_switch) {
             // Identify kernel threads for counter management
             let is_kernel = Self::is_kernel(pid);
             let context_switch_count = self.per_cpu_state[cpu_id].context_switch_count;
@@ -268,9 +295,16 @@ impl ConcurrencyAnalysis {
         };
 
         let next_tgid_same_process_cpu_time = if let Some(next_tgid) = next_tgid {
+            // On context switches the counter was added above; other rows should not
+            // carry a next_tgid
             self.per_pid_counters
                 .get(&next_tgid)
-                .expect("on context switch, we add a counter for next_tgid if it doesn't exist)")
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "next_tgid {} set on a row that is not a context switch",
+                        next_tgid
+                    )
+                })?
                 .get_ns()
         } else {
             end_same_process_cpu_time
@@ -282,7 +316,7 @@ impl ConcurrencyAnalysis {
         self.per_cpu_state[cpu_id].last_timestamp = timestamp;
 
         // Return computed concurrency metrics
-        Ok((avg_total_threads, avg_same_process_threads))
+        Ok((avg_total_threads, avg_same_process_threads, false))
     }
 }
 
@@ -345,6 +379,7 @@ impl Analysis for ConcurrencyAnalysis {
         // Prepare output arrays for concurrency metrics
         let mut avg_total_threads = Vec::with_capacity(num_rows);
         let mut avg_same_process_threads = Vec::with_capacity(num_rows);
+        let mut timestamp_regression = Vec::with_capacity(num_rows);
 
         // Process each row
         for i in 0..num_rows {
@@ -365,14 +400,16 @@ impl Analysis for ConcurrencyAnalysis {
                 return Err(anyhow::anyhow!("Invalid CPU ID: {}", cpu_id));
             }
 
-            let (avg_total, avg_same_process) =
-                self.process_event(timestamp, pid, cpu_id, is_context_switch, next_tgid)?;
+            let (avg_total, avg_same_process, regression) = self
+                .process_event(timestamp, pid, cpu_id, is_context_switch, next_tgid)
+                .with_context(|| format!("at row {} (cpu_id {})", i, cpu_id))?;
 
             avg_total_threads.push(avg_total);
             avg_same_process_threads.push(avg_same_process);
+            timestamp_regression.push(regression);
 
             // Calculate CPI and update statistics if we have valid data
-            if instructions > 0 && cycles > 0 {
+            if !regression && instructions > 0 && cycles > 0 {
                 let cpi = cycles as f64 / instructions as f64;
                 let process_name_key = process_name.to_string();
 
@@ -397,6 +434,7 @@ impl Analysis for ConcurrencyAnalysis {
         Ok(vec![
             Arc::new(Float64Array::from(avg_total_threads)),
             Arc::new(Float64Array::from(avg_same_process_threads)),
+            Arc::new(BooleanArray::from(timestamp_regression)),
         ])
     }
 
@@ -408,10 +446,24 @@ impl Analysis for ConcurrencyAnalysis {
                 DataType::Float64,
                 false,
             )),
+            Arc::new(Field::new("timestamp_regression", DataType::Boolean, false)),
         ]
     }
 
-    fn finalize(&self) -> Result<()> {
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        vec![
+            ("timestamp", DataType::Int64),
+            ("pid", DataType::Int32),
+            ("cpu_id", DataType::Int32),
+            ("is_context_switch", DataType::Boolean),
+            ("next_tgid", DataType::Int32),
+            ("instructions", DataType::Int64),
+            ("cycles", DataType::Int64),
+            ("process_name", DataType::Utf8),
+        ]
+    }
+
+    fn finalize(&mut self) -> Result<Option<RecordBatch>> {
         // Export CSV files if paths are set
         if let Some(total_path) = &self.total_csv_path {
             println!("Exporting total concurrency statistics to: {}", total_path);
@@ -426,7 +478,7 @@ impl Analysis for ConcurrencyAnalysis {
             self.export_same_process_concurrency_csv(same_process_path)?;
         }
 
-        Ok(())
+        Ok(None)
     }
 }
 
@@ -465,3 +517,122 @@ impl ConcurrencyAnalysis {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Int32Array, Int64Array, StringArray};
+    use arrow_schema::Schema;
+
+    fn create_test_batch(
+        timestamps: Vec<i64>,
+        cpu_ids: Vec<i32>,
+        is_context_switches: Vec<bool>,
+        next_tgids: Vec<Option<i32>>,
+    ) -> RecordBatch {
+        let num_rows = cpu_ids.len();
+        let schema = Schema::new(vec![
+            Arc::new(Field::new("timestamp", DataType::Int64, false)),
+            Arc::new(Field::new("pid", DataType::Int32, false)),
+            Arc::new(Field::new("cpu_id", DataType::Int32, false)),
+            Arc::new(Field::new("is_context_switch", DataType::Boolean, false)),
+            Arc::new(Field::new("next_tgid", DataType::Int32, true)),
+            Arc::new(Field::new("instructions", DataType::Int64, false)),
+            Arc::new(Field::new("cycles", DataType::Int64, false)),
+            Arc::new(Field::new("process_name", DataType::Utf8, false)),
+        ]);
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(timestamps)),
+                Arc::new(Int32Array::from(vec![100; num_rows])),
+                Arc::new(Int32Array::from(cpu_ids)),
+                Arc::new(BooleanArray::from(is_context_switches)),
+                Arc::new(Int32Array::from(next_tgids)),
+                Arc::new(Int64Array::from(vec![1000; num_rows])),
+                Arc::new(Int64Array::from(vec![2000; num_rows])),
+                Arc::new(StringArray::from(vec!["proc"; num_rows])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_context_switch_without_next_tgid_is_an_error() {
+        let mut analysis = ConcurrencyAnalysis::new(4).unwrap();
+        let batch = create_test_batch(
+            vec![1000, 2000],
+            vec![0, 2],
+            vec![true, true],
+            vec![Some(200), None],
+        );
+
+        let err = analysis.process_record_batch(&batch).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("row 1"), "{}", msg);
+        assert!(msg.contains("cpu_id 2"), "{}", msg);
+        assert!(msg.contains("next_tgid is null"), "{}", msg);
+    }
+
+    #[test]
+    fn test_next_tgid_on_non_context_switch_is_an_error() {
+        let mut analysis = ConcurrencyAnalysis::new(4).unwrap();
+        let batch = create_test_batch(vec![1000], vec![1], vec![false], vec![Some(300)]);
+
+        let err = analysis.process_record_batch(&batch).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("row 0 (cpu_id 1)"), "{}", msg);
+        assert!(msg.contains("next_tgid 300"), "{}", msg);
+    }
+
+    #[test]
+    fn test_timestamp_regression_is_flagged_and_skipped() {
+        let mut analysis = ConcurrencyAnalysis::new(2).unwrap();
+        // CPU 0 goes back from 3000 to 2500, then continues at 4000
+        let batch = create_test_batch(
+            vec![1000, 3000, 2500, 4000],
+            vec![0, 0, 0, 0],
+            vec![true, false, false, false],
+            vec![Some(100), None, None, None],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        assert_eq!(new_columns.len(), analysis.new_columns_schema().len());
+        let avg_total = new_columns[0]
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        let regression = new_columns[2]
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap();
+
+        let flags: Vec<bool> = (0..4).map(|i| regression.value(i)).collect();
+        assert_eq!(flags, vec![false, false, true, false]);
+        assert_eq!(avg_total.value(2), 0.0);
+
+        // Processing continues: one thread ran the whole time since 1000
+        assert!((avg_total.value(1) - 1.0).abs() < f64::EPSILON);
+        assert!((avg_total.value(3) - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_regressed_context_switch_leaves_thread_counts() {
+        let mut analysis = ConcurrencyAnalysis::new(2).unwrap();
+        // CPU 1 switches to process 300 at 3000; CPU 0 then reports a switch back at
+        // 2000, before the counters' last update
+        analysis.process_event(1000, 0, 0, true, Some(100)).unwrap();
+        analysis.process_event(3000, 0, 1, true, Some(300)).unwrap();
+        let (avg_total, avg_same, regression) = analysis
+            .process_event(2000, 100, 0, true, Some(200))
+            .unwrap();
+
+        assert!(regression);
+        assert_eq!((avg_total, avg_same), (0.0, 0.0));
+        assert_eq!(analysis.total_counter.current_thread_count, 2);
+        assert_eq!(analysis.per_pid_counters[&100].current_thread_count, 1);
+        assert!(!analysis.per_pid_counters.contains_key(&200));
+        assert_eq!(analysis.per_cpu_state[0].context_switch_count, 1);
+    }
+}
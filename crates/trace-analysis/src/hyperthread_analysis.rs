@@ -1,6 +1,10 @@
 use anyhow::Result;
 use arrow_array::{Array, ArrayRef, BooleanArray, Int32Array, Int64Array, RecordBatch};
-use arrow_schema::{DataType, Field};
+use arrow_schema::{DataType, Field, Schema};
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::analyzer::Analysis;
@@ -12,6 +16,10 @@ struct CpuState {
     ns_peer_same_process: i64,
     ns_peer_different_process: i64,
     ns_peer_kernel: i64,
+    // Counters already reported on this CPU's rows
+    reported_same_process: i64,
+    reported_different_process: i64,
+    reported_kernel: i64,
 }
 
 impl CpuState {
@@ -22,37 +30,156 @@ impl CpuState {
             ns_peer_same_process: 0,
             ns_peer_different_process: 0,
             ns_peer_kernel: 0,
+            reported_same_process: 0,
+            reported_different_process: 0,
+            reported_kernel: 0,
         }
     }
 
     fn reset_counters(&mut self) {
+        self.reported_same_process += self.ns_peer_same_process;
+        self.reported_different_process += self.ns_peer_different_process;
+        self.reported_kernel += self.ns_peer_kernel;
         self.ns_peer_same_process = 0;
         self.ns_peer_different_process = 0;
         self.ns_peer_kernel = 0;
     }
 }
 
+/// Peer map assuming CPU `i` pairs with `i + num_cpus/2` (split-half enumeration).
+/// A single CPU has no sibling.
+fn split_half_peers(num_cpus: usize) -> Vec<Option<usize>> {
+    if num_cpus < 2 {
+        return vec![None; num_cpus];
+    }
+    (0..num_cpus)
+        .map(|cpu_id| {
+            if cpu_id < num_cpus / 2 {
+                Some(cpu_id + num_cpus / 2)
+            } else {
+                Some(cpu_id - num_cpus / 2)
+            }
+        })
+        .collect()
+}
+
+/// Parse a kernel CPU list such as "0,4" or "0-1,8-9"
+fn parse_cpu_list(list: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("Invalid CPU list '{}'", list.trim()))
+        };
+        match part.split_once('-') {
+            Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+            None => cpus.push(parse(part)?),
+        }
+    }
+    Ok(cpus)
+}
+
+/// Build a peer map for `num_cpus` CPUs from a sysfs CPU directory (normally
+/// `/sys/devices/system/cpu`), using each `cpuN/topology/thread_siblings_list`.
+///
+/// CPUs without a sibling, or without a topology entry (e.g. offline), map to `None`.
+pub fn peers_from_sysfs(cpu_dir: &Path, num_cpus: usize) -> Result<Vec<Option<usize>>> {
+    let mut peers = Vec::with_capacity(num_cpus);
+    for cpu_id in 0..num_cpus {
+        let path = cpu_dir
+            .join(format!("cpu{}", cpu_id))
+            .join("topology/thread_siblings_list");
+        let siblings = match fs::read_to_string(&path) {
+            Ok(s) => parse_cpu_list(&s)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(anyhow::anyhow!("Failed to read {}: {}", path.display(), e));
+            }
+        };
+        peers.push(siblings.into_iter().find(|&s| s != cpu_id && s < num_cpus));
+    }
+    Ok(peers)
+}
+
 pub struct HyperthreadAnalysis {
     num_cpus: usize,
     cpu_states: Vec<CpuState>,
+    // Hyperthread sibling of each CPU (None = no sibling)
+    peers: Vec<Option<usize>>,
+    // Only CPUs in this range get peer columns computed (None = all CPUs)
+    cpu_range: Option<RangeInclusive<usize>>,
+    // No CPU has a sibling (SMT disabled), so no time is shared with a peer
+    smt_disabled: bool,
 }
 
 impl HyperthreadAnalysis {
+    /// Create an analysis assuming CPU `i` is the sibling of `i + num_cpus/2`.
     pub fn new(num_cpus: usize) -> Result<Self> {
-        let cpu_states = vec![CpuState::new(); num_cpus];
+        Self::with_topology(split_half_peers(num_cpus))
+    }
 
+    /// Create an analysis with an explicit peer map: `peers[i]` is the hyperthread
+    /// sibling of CPU `i`, or `None` if it has none. The number of CPUs is `peers.len()`.
+    ///
+    /// If no CPU has a sibling, SMT is taken to be disabled and rows get zero peer
+    /// columns instead of nulls.
+    pub fn with_topology(peers: Vec<Option<usize>>) -> Result<Self> {
+        let num_cpus = peers.len();
+        for (cpu_id, peer) in peers.iter().enumerate() {
+            if let Some(peer) = *peer {
+                if peer >= num_cpus || peer == cpu_id {
+                    return Err(anyhow::anyhow!(
+                        "Invalid hyperthread peer {} for CPU {} ({} CPUs)",
+                        peer,
+                        cpu_id,
+                        num_cpus
+                    ));
+                }
+            }
+        }
+
+        let smt_disabled = peers.iter().all(Option::is_none);
         Ok(Self {
             num_cpus,
-            cpu_states,
+            cpu_states: vec![CpuState::new(); num_cpus],
+            peers,
+            cpu_range: None,
+            smt_disabled,
         })
     }
 
-    fn get_hyperthread_peer(&self, cpu_id: usize) -> usize {
-        if cpu_id < self.num_cpus / 2 {
-            cpu_id + self.num_cpus / 2
-        } else {
-            cpu_id - self.num_cpus / 2
+    /// Restrict peer computation to CPUs within `range`. Rows for CPUs outside the
+    /// range are passed through with null peer columns.
+    pub fn with_cpu_range(mut self, range: RangeInclusive<usize>) -> Result<Self> {
+        if range.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid CPU range: {}-{}",
+                range.start(),
+                range.end()
+            ));
+        }
+        // The end is at least the start, so this also bounds the start
+        if *range.end() >= self.num_cpus {
+            return Err(anyhow::anyhow!(
+                "CPU range {}-{} is out of bounds for {} CPUs",
+                range.start(),
+                range.end(),
+                self.num_cpus
+            ));
         }
+        self.cpu_range = Some(range);
+        Ok(self)
+    }
+
+    fn in_range(&self, cpu_id: usize) -> bool {
+        self.cpu_range
+            .as_ref()
+            .is_none_or(|range| range.contains(&cpu_id))
+    }
+
+    fn get_hyperthread_peer(&self, cpu_id: usize) -> Option<usize> {
+        self.peers[cpu_id]
     }
 
     fn update_hyperthread(&mut self, cpu_a: usize, cpu_b: usize, event_timestamp: i64) {
@@ -161,20 +288,44 @@ impl Analysis for HyperthreadAnalysis {
                 return Err(anyhow::anyhow!("Invalid CPU ID: {}", cpu_id));
             }
 
-            let peer_cpu = self.get_hyperthread_peer(cpu_id);
+            let cpu_in_range = self.in_range(cpu_id);
+
+            // Without SMT no CPU shares its core, so in-range rows get zero peer time
+            if self.smt_disabled {
+                let zero = cpu_in_range.then_some(0);
+                ns_peer_same_process.push(zero);
+                ns_peer_different_process.push(zero);
+                ns_peer_kernel.push(zero);
+                continue;
+            }
+
+            // Rows outside the configured range, or on CPUs without a sibling, get null
+            // peer columns. If the peer is in range we still track this CPU's state so the
+            // peer's counters stay accurate.
+            let peer_cpu = match self.get_hyperthread_peer(cpu_id) {
+                Some(peer_cpu) if cpu_in_range || self.in_range(peer_cpu) => peer_cpu,
+                _ => {
+                    ns_peer_same_process.push(None);
+                    ns_peer_different_process.push(None);
+                    ns_peer_kernel.push(None);
+                    continue;
+                }
+            };
 
             // Update hyperthread counters
             self.update_hyperthread(cpu_id, peer_cpu, timestamp);
 
-            // Get current counter values
-            let same_process = self.cpu_states[cpu_id].ns_peer_same_process;
-            let different_process = self.cpu_states[cpu_id].ns_peer_different_process;
-            let kernel = self.cpu_states[cpu_id].ns_peer_kernel;
-
-            // Store counter values
-            ns_peer_same_process.push(same_process);
-            ns_peer_different_process.push(different_process);
-            ns_peer_kernel.push(kernel);
+            if cpu_in_range {
+                // Store current counter values
+                ns_peer_same_process.push(Some(self.cpu_states[cpu_id].ns_peer_same_process));
+                ns_peer_different_process
+                    .push(Some(self.cpu_states[cpu_id].ns_peer_different_process));
+                ns_peer_kernel.push(Some(self.cpu_states[cpu_id].ns_peer_kernel));
+            } else {
+                ns_peer_same_process.push(None);
+                ns_peer_different_process.push(None);
+                ns_peer_kernel.push(None);
+            }
 
             // Update CPU state for context switches
             if is_context_switch {
@@ -202,15 +353,61 @@ impl Analysis for HyperthreadAnalysis {
 
     fn new_columns_schema(&self) -> Vec<Arc<Field>> {
         vec![
-            Arc::new(Field::new("ns_peer_same_process", DataType::Int64, false)),
+            Arc::new(Field::new("ns_peer_same_process", DataType::Int64, true)),
             Arc::new(Field::new(
                 "ns_peer_different_process",
                 DataType::Int64,
-                false,
+                true,
             )),
-            Arc::new(Field::new("ns_peer_kernel", DataType::Int64, false)),
+            Arc::new(Field::new("ns_peer_kernel", DataType::Int64, true)),
+        ]
+    }
+
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        vec![
+            ("timestamp", DataType::Int64),
+            ("cpu_id", DataType::Int32),
+            ("is_context_switch", DataType::Boolean),
+            ("next_tgid", DataType::Int32),
         ]
     }
+
+    /// Per-CPU totals over the whole trace, including time accumulated from peer
+    /// events after the CPU's last row, which no row reports
+    fn finalize(&mut self) -> Result<Option<RecordBatch>> {
+        let cpus: Vec<usize> = (0..self.num_cpus)
+            .filter(|&cpu_id| {
+                self.in_range(cpu_id)
+                    && (self.smt_disabled || self.get_hyperthread_peer(cpu_id).is_some())
+            })
+            .collect();
+        let states: Vec<&CpuState> = cpus
+            .iter()
+            .map(|&cpu_id| &self.cpu_states[cpu_id])
+            .collect();
+
+        let schema = Schema::new(vec![
+            Field::new("cpu_id", DataType::Int32, false),
+            Field::new("total_ns_peer_same_process", DataType::Int64, false),
+            Field::new("total_ns_peer_different_process", DataType::Int64, false),
+            Field::new("total_ns_peer_kernel", DataType::Int64, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int32Array::from_iter_values(cpus.iter().map(|&c| c as i32))),
+            Arc::new(Int64Array::from_iter_values(
+                states
+                    .iter()
+                    .map(|s| s.reported_same_process + s.ns_peer_same_process),
+            )),
+            Arc::new(Int64Array::from_iter_values(states.iter().map(|s| {
+                s.reported_different_process + s.ns_peer_different_process
+            }))),
+            Arc::new(Int64Array::from_iter_values(
+                states.iter().map(|s| s.reported_kernel + s.ns_peer_kernel),
+            )),
+        ];
+        Ok(Some(RecordBatch::try_new(Arc::new(schema), columns)?))
+    }
 }
 
 #[cfg(test)]
@@ -387,6 +584,50 @@ mod tests {
         assert_eq!(kernel_col.value(5), 4000);
     }
 
+    #[test]
+    fn test_finalize_reports_totals_with_trailing_counters() {
+        let mut analysis = HyperthreadAnalysis::new(4).unwrap();
+
+        // Same sequence as test_hyperthread_counter_logic
+        let batch = create_test_batch(
+            vec![1000, 2000, 3000, 4000, 6000, 10000],
+            vec![0, 2, 0, 2, 0, 2],
+            vec![true, true, true, true, true, true],
+            vec![Some(100), Some(200), Some(100), Some(0), Some(0), Some(0)],
+        );
+        analysis.process_record_batch(&batch).unwrap();
+
+        let summary = analysis.finalize().unwrap().unwrap();
+        let column = |name: &str| {
+            summary
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .values()
+                .to_vec()
+        };
+        let cpu_ids = summary
+            .column_by_name("cpu_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap()
+            .values()
+            .to_vec();
+        assert_eq!(cpu_ids, vec![0, 1, 2, 3]);
+
+        // CPU 0's rows reported 2000ns different process and 2000ns kernel; the
+        // 4000ns of kernel time from CPU 2's last event only shows up in the summary
+        assert_eq!(column("total_ns_peer_same_process"), vec![0, 0, 0, 0]);
+        assert_eq!(
+            column("total_ns_peer_different_process"),
+            vec![2000, 0, 4000, 0]
+        );
+        assert_eq!(column("total_ns_peer_kernel"), vec![6000, 0, 4000, 0]);
+    }
+
     #[test]
     fn test_same_process_detection() {
         let mut analysis = HyperthreadAnalysis::new(4).unwrap();
@@ -464,4 +705,180 @@ mod tests {
         let result = analysis.process_record_batch(&batch);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_cpu_range_filter_nulls_out_of_range_cpus() {
+        // 8 CPUs: peers are (0,4), (1,5), (2,6), (3,7). Restrict to CPUs 0-1.
+        let mut analysis = HyperthreadAnalysis::new(8)
+            .unwrap()
+            .with_cpu_range(0..=1)
+            .unwrap();
+
+        let batch = create_test_batch(
+            vec![1000, 2000, 3000, 4000, 5000],
+            vec![0, 4, 0, 2, 6],
+            vec![true, true, true, true, true],
+            vec![Some(100), Some(200), Some(100), Some(300), Some(300)],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        let same_process_col = new_columns[0]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let different_process_col = new_columns[1]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let kernel_col = new_columns[2]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+
+        // CPU 0 is in range and still sees its (out-of-range) peer CPU 4 running PID 200
+        for row in [0, 2] {
+            assert!(!same_process_col.is_null(row));
+            assert!(!different_process_col.is_null(row));
+            assert!(!kernel_col.is_null(row));
+        }
+        assert_eq!(different_process_col.value(2), 1000);
+        assert_eq!(same_process_col.value(2), 0);
+        assert_eq!(kernel_col.value(2), 0);
+
+        // CPUs 4, 2 and 6 are outside the range: all peer columns are null
+        for row in [1, 3, 4] {
+            assert!(same_process_col.is_null(row));
+            assert!(different_process_col.is_null(row));
+            assert!(kernel_col.is_null(row));
+        }
+    }
+
+    #[test]
+    fn test_cpu_range_rejects_out_of_bounds_start() {
+        let result = HyperthreadAnalysis::new(4).unwrap().with_cpu_range(4..=7);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cpu_range_rejects_out_of_bounds_end() {
+        let result = HyperthreadAnalysis::new(8)
+            .unwrap()
+            .with_cpu_range(0..=9999);
+        assert!(result.is_err());
+        assert!(HyperthreadAnalysis::new(8)
+            .unwrap()
+            .with_cpu_range(0..=7)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_with_topology_adjacent_siblings() {
+        // Siblings are enumerated adjacently: (0,1), (2,3). Split-half pairing
+        // would instead pair (0,2) and (1,3).
+        let mut analysis =
+            HyperthreadAnalysis::with_topology(vec![Some(1), Some(0), Some(3), Some(2)]).unwrap();
+
+        let batch = create_test_batch(
+            vec![1000, 2000, 3000, 4000, 5000],
+            vec![0, 1, 0, 2, 2],
+            vec![true, true, true, true, true],
+            vec![Some(100), Some(100), Some(100), Some(300), Some(300)],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        let same_process_col = new_columns[0]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        let different_process_col = new_columns[1]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+
+        // Row 2 (CPU 0): peer CPU 1 ran the same process 2000-3000
+        assert_eq!(same_process_col.value(2), 1000);
+        assert_eq!(different_process_col.value(2), 0);
+
+        // Row 4 (CPU 2): peer CPU 3 has never been seen, so nothing is attributed
+        // (split-half pairing would have used CPU 0 instead)
+        assert_eq!(same_process_col.value(4), 0);
+        assert_eq!(different_process_col.value(4), 0);
+    }
+
+    #[test]
+    fn test_with_topology_cpu_without_sibling_is_null() {
+        let mut analysis =
+            HyperthreadAnalysis::with_topology(vec![Some(1), Some(0), None]).unwrap();
+
+        let batch = create_test_batch(
+            vec![1000, 2000],
+            vec![2, 0],
+            vec![true, true],
+            vec![Some(100), Some(200)],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        assert!(new_columns[0].is_null(0));
+        assert!(!new_columns[0].is_null(1));
+    }
+
+    #[test]
+    fn test_no_smt_topology_reports_zero_peer_time() {
+        let mut analysis = HyperthreadAnalysis::with_topology(vec![None; 4]).unwrap();
+        // Split-half pairing would attribute CPU 2's process to CPU 0
+        let batch = create_test_batch(
+            vec![1000, 2000, 3000, 4000, 5000],
+            vec![0, 2, 0, 2, 1],
+            vec![true, true, true, false, true],
+            vec![Some(100), Some(200), Some(0), None, Some(300)],
+        );
+
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        for column in &new_columns {
+            let column = column.as_any().downcast_ref::<Int64Array>().unwrap();
+            assert_eq!(column.null_count(), 0);
+            assert!(column.values().iter().all(|&ns| ns == 0));
+        }
+
+        let summary = analysis.finalize().unwrap().unwrap();
+        assert_eq!(summary.num_rows(), 4);
+        for name in [
+            "total_ns_peer_same_process",
+            "total_ns_peer_different_process",
+            "total_ns_peer_kernel",
+        ] {
+            let column = summary
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            assert!(column.values().iter().all(|&ns| ns == 0));
+        }
+
+        // A single CPU has no sibling to pair with
+        assert!(HyperthreadAnalysis::new(1).unwrap().smt_disabled);
+    }
+
+    #[test]
+    fn test_with_topology_rejects_invalid_peers() {
+        assert!(HyperthreadAnalysis::with_topology(vec![Some(1), Some(5)]).is_err());
+        assert!(HyperthreadAnalysis::with_topology(vec![Some(0), None]).is_err());
+    }
+
+    #[test]
+    fn test_peers_from_sysfs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let siblings = ["0-1", "0-1", "2,6", "3"];
+        for (cpu_id, list) in siblings.iter().enumerate() {
+            let topo = dir.join(format!("cpu{}", cpu_id)).join("topology");
+            fs::create_dir_all(&topo).unwrap();
+            fs::write(topo.join("thread_siblings_list"), format!("{}\n", list)).unwrap();
+        }
+
+        // CPU 4 has no topology entry; CPU 2's sibling 6 is beyond num_cpus
+        let peers = peers_from_sysfs(dir, 5).unwrap();
+        assert_eq!(peers, vec![Some(1), Some(0), None, None, None]);
+    }
 }
@@ -0,0 +1,272 @@
+//! Analyses of collector trace files. Each analysis reads a Parquet trace, adds
+//! columns to every row, and writes the result next to the input file.
+
+use anyhow::{Context, Result};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+mod analyzer;
+mod cache_efficiency_analysis;
+mod concurrency_analysis;
+mod hyperthread_analysis;
+mod migration_analysis;
+mod monotonicity_analysis;
+
+use analyzer::Analyzer;
+use cache_efficiency_analysis::CacheEfficiencyAnalysis;
+use concurrency_analysis::ConcurrencyAnalysis;
+use hyperthread_analysis::HyperthreadAnalysis;
+use migration_analysis::MigrationAnalysis;
+use monotonicity_analysis::MonotonicityAnalysis;
+
+/// What to analyze and where to write the results
+#[derive(Debug, Clone)]
+pub struct AnalyzeOptions {
+    /// Input Parquet trace file, or a directory whose `*.parquet` files are
+    /// analyzed concurrently
+    pub filename: PathBuf,
+    /// Output file prefix (defaults to base name of input file; not allowed
+    /// for a directory)
+    pub output_prefix: Option<String>,
+    /// Analysis type: "concurrency", "hyperthread", "monotonicity",
+    /// "cache-efficiency" ("cache" for short) or "migration", or "csv" to export
+    /// the input file as CSV
+    pub analysis_type: String,
+    /// Restrict hyperthread peer analysis to a CPU range, e.g. "0-15"
+    pub cpu_range: Option<String>,
+    /// Sysfs CPU directory to read hyperthread siblings from
+    pub cpu_topology: Option<PathBuf>,
+}
+
+/// Run the analysis selected in `opts` over its input file or directory
+pub fn run(opts: &AnalyzeOptions) -> Result<()> {
+    let analysis_type = match opts.analysis_type.as_str() {
+        "cache" => "cache-efficiency",
+        other => other,
+    };
+
+    if analysis_type == "csv" {
+        return export_csv(opts);
+    }
+    if opts.filename.is_dir() {
+        return run_directory(opts, analysis_type);
+    }
+
+    // Open the input Parquet file, decompressing a .zst wrapper
+    let file = analyzer::open_input(&opts.filename)?;
+
+    // Create ParquetRecordBatchReaderBuilder to access metadata
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| "Failed to create Parquet reader builder")?;
+
+    let num_cpus = num_cpus(&builder)?;
+
+    // Determine output filename based on analysis type
+    let output_filename =
+        determine_output_filename(&opts.filename, opts.output_prefix.as_deref(), analysis_type)?;
+
+    println!(
+        "Processing {} CPUs with {} analysis, output to: {}",
+        num_cpus,
+        analysis_type,
+        output_filename.display()
+    );
+
+    // Create analyzer
+    let analyzer = Analyzer::new(output_filename);
+    let input = opts.filename.as_path();
+    let output_prefix = opts.output_prefix.as_deref();
+
+    match analysis_type {
+        "concurrency" => analyzer.process_parquet_file(
+            builder,
+            new_concurrency_analysis(input, output_prefix, num_cpus)?,
+        )?,
+        "hyperthread" => {
+            analyzer.process_parquet_file(builder, new_hyperthread_analysis(opts, num_cpus)?)?
+        }
+        "monotonicity" => analyzer
+            .process_parquet_file(builder, new_monotonicity_analysis(input, output_prefix)?)?,
+        "cache-efficiency" => {
+            analyzer.process_parquet_file(builder, CacheEfficiencyAnalysis::new()?)?
+        }
+        "migration" => analyzer.process_parquet_file(builder, MigrationAnalysis::new()?)?,
+        _ => return Err(invalid_analysis_type(analysis_type)),
+    }
+
+    println!("Analysis complete!");
+
+    Ok(())
+}
+
+/// Run the analysis over every Parquet file in the `opts.filename` directory
+fn run_directory(opts: &AnalyzeOptions, analysis_type: &str) -> Result<()> {
+    if opts.output_prefix.is_some() {
+        return Err(anyhow::anyhow!(
+            "An output prefix cannot be used with a directory input"
+        ));
+    }
+
+    let dir = opts.filename.as_path();
+    let outputs = match analysis_type {
+        "concurrency" => Analyzer::process_directory(dir, analysis_type, |input, builder| {
+            new_concurrency_analysis(input, None, num_cpus(builder)?)
+        })?,
+        "hyperthread" => Analyzer::process_directory(dir, analysis_type, |_, builder| {
+            new_hyperthread_analysis(opts, num_cpus(builder)?)
+        })?,
+        "monotonicity" => Analyzer::process_directory(dir, analysis_type, |input, _| {
+            new_monotonicity_analysis(input, None)
+        })?,
+        "cache-efficiency" => {
+            Analyzer::process_directory(dir, analysis_type, |_, _| CacheEfficiencyAnalysis::new())?
+        }
+        "migration" => {
+            Analyzer::process_directory(dir, analysis_type, |_, _| MigrationAnalysis::new())?
+        }
+        _ => return Err(invalid_analysis_type(analysis_type)),
+    };
+
+    println!("Analysis complete! Analyzed {} files", outputs.len());
+
+    Ok(())
+}
+
+/// Export the input Parquet file as `<prefix>_export.csv` next to it
+fn export_csv(opts: &AnalyzeOptions) -> Result<()> {
+    if opts.filename.is_dir() {
+        return Err(anyhow::anyhow!(
+            "CSV export takes a single Parquet file, not a directory"
+        ));
+    }
+    let output =
+        determine_csv_output_filename(&opts.filename, opts.output_prefix.as_deref(), "export")?;
+    println!("Exporting CSV to: {}", output.display());
+    let rows = Analyzer::export_csv(&opts.filename, &output)?;
+    println!("Export complete! Wrote {} rows", rows);
+    Ok(())
+}
+
+/// Number of CPUs recorded in the trace's `num_cpus` key-value metadata
+fn num_cpus(builder: &ParquetRecordBatchReaderBuilder<File>) -> Result<usize> {
+    let metadata = builder.metadata();
+    let file_metadata = metadata.file_metadata();
+    let key_value_metadata = file_metadata
+        .key_value_metadata()
+        .ok_or_else(|| anyhow::anyhow!("No key-value metadata found in Parquet file"))?;
+
+    key_value_metadata
+        .iter()
+        .find(|kv| kv.key == "num_cpus")
+        .ok_or_else(|| anyhow::anyhow!("num_cpus not found in metadata"))?
+        .value
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("num_cpus value is empty"))?
+        .parse::<usize>()
+        .with_context(|| "Failed to parse num_cpus as integer")
+}
+
+/// Concurrency analysis writing its CSVs next to `input`
+fn new_concurrency_analysis(
+    input: &Path,
+    output_prefix: Option<&str>,
+    num_cpus: usize,
+) -> Result<ConcurrencyAnalysis> {
+    let mut analysis = ConcurrencyAnalysis::new(num_cpus)?;
+
+    // Set CSV output paths
+    let total_csv_path = determine_csv_output_filename(input, output_prefix, "total_concurrency")?;
+    let same_process_csv_path =
+        determine_csv_output_filename(input, output_prefix, "same_process_concurrency")?;
+    analysis.set_csv_paths(
+        total_csv_path.to_string_lossy().to_string(),
+        same_process_csv_path.to_string_lossy().to_string(),
+    );
+    Ok(analysis)
+}
+
+/// Hyperthread analysis with the topology and CPU range from `opts`
+fn new_hyperthread_analysis(opts: &AnalyzeOptions, num_cpus: usize) -> Result<HyperthreadAnalysis> {
+    let mut analysis = match opts.cpu_topology.as_deref() {
+        Some(cpu_dir) => HyperthreadAnalysis::with_topology(
+            hyperthread_analysis::peers_from_sysfs(cpu_dir, num_cpus)?,
+        )?,
+        None => HyperthreadAnalysis::new(num_cpus)?,
+    };
+    if let Some(cpu_range) = opts.cpu_range.as_deref() {
+        analysis = analysis.with_cpu_range(parse_cpu_range(cpu_range)?)?;
+    }
+    Ok(analysis)
+}
+
+/// Monotonicity analysis writing its CSV next to `input`
+fn new_monotonicity_analysis(
+    input: &Path,
+    output_prefix: Option<&str>,
+) -> Result<MonotonicityAnalysis> {
+    let csv_output = determine_csv_output_filename(input, output_prefix, "monotonicity_analysis")?;
+    MonotonicityAnalysis::new(csv_output)
+}
+
+fn invalid_analysis_type(analysis_type: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Invalid analysis type: {}. Must be 'concurrency', 'hyperthread', 'monotonicity', 'cache-efficiency', 'migration', or 'csv'",
+        analysis_type
+    )
+}
+
+fn determine_output_filename(
+    input_path: &Path,
+    output_prefix: Option<&str>,
+    analysis_type: &str,
+) -> Result<PathBuf> {
+    let base_name = analyzer::input_stem(input_path)
+        .ok_or_else(|| anyhow::anyhow!("Invalid input filename"))?
+        .to_string_lossy();
+
+    let prefix = output_prefix.unwrap_or(&base_name);
+    let output_filename = format!("{}_{}_analysis.parquet", prefix, analysis_type);
+
+    if let Some(parent) = input_path.parent() {
+        Ok(parent.join(output_filename))
+    } else {
+        Ok(PathBuf::from(output_filename))
+    }
+}
+
+fn determine_csv_output_filename(
+    input_path: &Path,
+    output_prefix: Option<&str>,
+    suffix: &str,
+) -> Result<PathBuf> {
+    let base_name = analyzer::input_stem(input_path)
+        .ok_or_else(|| anyhow::anyhow!("Invalid input filename"))?
+        .to_string_lossy();
+
+    let prefix = output_prefix.unwrap_or(&base_name);
+    let output_filename = format!("{}_{}.csv", prefix, suffix);
+
+    if let Some(parent) = input_path.parent() {
+        Ok(parent.join(output_filename))
+    } else {
+        Ok(PathBuf::from(output_filename))
+    }
+}
+
+/// Parse a CPU range of the form "START-END" (inclusive) or a single CPU "N"
+fn parse_cpu_range(spec: &str) -> Result<RangeInclusive<usize>> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .with_context(|| format!("Invalid CPU number in range '{}'", spec))
+    };
+    match spec.split_once('-') {
+        Some((start, end)) => Ok(parse(start)?..=parse(end)?),
+        None => {
+            let cpu = parse(spec)?;
+            Ok(cpu..=cpu)
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use arrow_array::{Array, ArrayRef, BooleanArray, Int32Array, Int64Array, RecordBatch};
+use arrow_schema::{DataType, Field};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::analyzer::Analysis;
+
+/// Flags context switches where a pid leaves a different CPU than it last left.
+///
+/// Each context switch row (`is_context_switch`) ends a run of `pid` on
+/// `cpu_id`, and is one observation of it; comparing consecutive runs counts
+/// each migration once, however many samples the runs hold. Other rows are
+/// samples within a run: they are never a migration and carry the pid's count
+/// so far. A pid's first run is not a migration. The idle task (pid 0) runs on
+/// every CPU at once, so it never migrates. Rows with a null `pid` or `cpu_id`
+/// are not observations.
+pub struct MigrationAnalysis {
+    /// CPU of the last run and migrations so far, per pid
+    pids: HashMap<i32, (i32, i64)>,
+}
+
+impl MigrationAnalysis {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            pids: HashMap::new(),
+        })
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a T> {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| anyhow::anyhow!("{} column not found", name))?
+            .as_any()
+            .downcast_ref::<T>()
+            .ok_or_else(|| anyhow::anyhow!("{} column has an unexpected type", name))
+    }
+}
+
+impl Analysis for MigrationAnalysis {
+    fn process_record_batch(&mut self, batch: &RecordBatch) -> Result<Vec<ArrayRef>> {
+        let num_rows = batch.num_rows();
+
+        let pid_col = Self::column::<Int32Array>(batch, "pid")?;
+        let cpu_col = Self::column::<Int32Array>(batch, "cpu_id")?;
+        let switch_col = Self::column::<BooleanArray>(batch, "is_context_switch")?;
+
+        let mut migrated = Vec::with_capacity(num_rows);
+        let mut migration_count = Vec::with_capacity(num_rows);
+
+        for i in 0..num_rows {
+            if pid_col.is_null(i) || cpu_col.is_null(i) || pid_col.value(i) == 0 {
+                migrated.push(false);
+                migration_count.push(0);
+                continue;
+            }
+            let pid = pid_col.value(i);
+            let cpu = cpu_col.value(i);
+
+            if switch_col.is_null(i) || !switch_col.value(i) {
+                migrated.push(false);
+                migration_count.push(self.pids.get(&pid).map_or(0, |(_, count)| *count));
+                continue;
+            }
+
+            let (last_cpu, count) = self.pids.entry(pid).or_insert((cpu, 0));
+            let moved = *last_cpu != cpu;
+            if moved {
+                *count += 1;
+            }
+            *last_cpu = cpu;
+
+            migrated.push(moved);
+            migration_count.push(*count);
+        }
+
+        Ok(vec![
+            Arc::new(BooleanArray::from(migrated)),
+            Arc::new(Int64Array::from(migration_count)),
+        ])
+    }
+
+    fn new_columns_schema(&self) -> Vec<Arc<Field>> {
+        vec![
+            Arc::new(Field::new("migrated", DataType::Boolean, false)),
+            Arc::new(Field::new("migration_count", DataType::Int64, false)),
+        ]
+    }
+
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        vec![
+            ("pid", DataType::Int32),
+            ("cpu_id", DataType::Int32),
+            ("is_context_switch", DataType::Boolean),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_schema::Schema;
+
+    /// Batch of context switch rows, or samples where `switches` is false
+    fn create_test_batch(
+        pids: Vec<Option<i32>>,
+        cpus: Vec<i32>,
+        switches: Vec<bool>,
+    ) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Arc::new(Field::new("pid", DataType::Int32, true)),
+            Arc::new(Field::new("cpu_id", DataType::Int32, false)),
+            Arc::new(Field::new("is_context_switch", DataType::Boolean, false)),
+        ]);
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(pids)),
+                Arc::new(Int32Array::from(cpus)),
+                Arc::new(BooleanArray::from(switches)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn results(columns: &[ArrayRef]) -> (Vec<bool>, Vec<i64>) {
+        let migrated = columns[0].as_any().downcast_ref::<BooleanArray>().unwrap();
+        let count = columns[1].as_any().downcast_ref::<Int64Array>().unwrap();
+        (
+            (0..migrated.len()).map(|i| migrated.value(i)).collect(),
+            count.values().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_pid_bouncing_between_cpus() {
+        let mut analysis = MigrationAnalysis::new().unwrap();
+
+        // pid 100 bounces between CPUs 0 and 1; pid 200 stays on CPU 2; the idle
+        // task shows up on several CPUs
+        let pids = vec![100, 200, 100, 0, 100, 0, 100, 200];
+        let batch = create_test_batch(
+            pids.into_iter().map(Some).collect(),
+            vec![0, 2, 1, 0, 1, 1, 0, 2],
+            vec![true; 8],
+        );
+        let new_columns = analysis.process_record_batch(&batch).unwrap();
+        assert_eq!(new_columns.len(), analysis.new_columns_schema().len());
+        let (migrated, count) = results(&new_columns);
+        assert_eq!(
+            migrated,
+            vec![false, false, true, false, false, false, true, false]
+        );
+        assert_eq!(count, vec![0, 0, 1, 0, 1, 0, 2, 0]);
+
+        // The last CPU carries over into the next batch
+        let batch = create_test_batch(vec![Some(100), Some(100)], vec![0, 1], vec![true, true]);
+        let (migrated, count) = results(&analysis.process_record_batch(&batch).unwrap());
+        assert_eq!(migrated, vec![false, true]);
+        assert_eq!(count, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_only_context_switches_are_observations() {
+        let mut analysis = MigrationAnalysis::new().unwrap();
+
+        // Samples of pid 100 on CPU 1 are not migrations; its switch out of CPU 1
+        // is. A null pid is not an observation, rather than the idle task.
+        let batch = create_test_batch(
+            vec![Some(100), Some(100), None, Some(100), Some(100)],
+            vec![0, 1, 3, 1, 1],
+            vec![true, false, true, false, true],
+        );
+        let (migrated, count) = results(&analysis.process_record_batch(&batch).unwrap());
+        assert_eq!(migrated, vec![false, false, false, false, true]);
+        assert_eq!(count, vec![0, 0, 0, 0, 1]);
+        assert_eq!(analysis.pids.len(), 1);
+
+        // A sample of a pid not yet seen switching does not start tracking it
+        let batch = create_test_batch(vec![Some(200), Some(200)], vec![0, 1], vec![false, true]);
+        let (migrated, count) = results(&analysis.process_record_batch(&batch).unwrap());
+        assert_eq!(migrated, vec![false, false]);
+        assert_eq!(count, vec![0, 0]);
+    }
+}
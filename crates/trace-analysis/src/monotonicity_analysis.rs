@@ -1,6 +1,6 @@
 use anyhow::Result;
 use arrow_array::{ArrayRef, Int64Array, RecordBatch};
-use arrow_schema::Field;
+use arrow_schema::{DataType, Field};
 use std::fs::File;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -102,4 +102,8 @@ impl Analysis for MonotonicityAnalysis {
         // Return empty vector since we don't add any columns
         vec![]
     }
+
+    fn required_columns(&self) -> Vec<(&str, DataType)> {
+        vec![("timestamp", DataType::Int64)]
+    }
 }